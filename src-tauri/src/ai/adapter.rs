@@ -1,85 +1,242 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
 use anyhow::Result;
-use genai::chat::{ChatMessage, ChatRequest};
-use genai::Client;
-use serde_json::Value;
-use crate::settings::api_keys::ConfigDirs;
-use crate::ai::gemini;
+use chrono::Utc;
+
+use crate::ai::history::{self, StoredChatMessage, HISTORY_SHARE};
+use crate::ai::providers::{AiProvider, ChatMessage, ModelInfo, Provider};
+use crate::ai::{context, gemini};
+use crate::database::adding::DB;
+use crate::database::read::DBReader;
+use crate::settings::api_keys::{ApiKeys, ConfigDirs};
+
+// Listed models by provider endpoint and key: (cache key, fetched at, models)
+static MODEL_CACHE: Mutex<Vec<(String, i64, Vec<ModelInfo>)>> = Mutex::new(Vec::new());
 
-pub async fn ask_ai(question: &str) -> Result<String> {
+/// Answers a question about the app's data with the configured provider. In a
+/// chat session the earlier turns are sent along and the exchange is saved.
+pub async fn ask_ai(question: &str, session_id: Option<i64>) -> Result<String> {
+    let provider = configured_provider()?;
+    let messages = question_messages(question, session_id).await?;
+
+    let answer = provider.chat(&messages).await?;
+    if let Some(session_id) = session_id {
+        save_exchange(session_id, question, &answer)?;
+    }
+    Ok(answer)
+}
+
+/// The provider selected in the saved settings.
+pub fn configured_provider() -> Result<Provider> {
     let config = ConfigDirs::read_config().map_err(|e| anyhow::anyhow!(e.to_string()))?;
-    let api_keys = config.api_keys;
-
-    match api_keys.ai_provider.as_str() {
-        "openai" => {
-            if api_keys.openai_api_key == "CHANGE_ME" || api_keys.openai_api_key.is_empty() {
-                return Err(anyhow::anyhow!("OpenAI API Key not configured"));
-            }
-
-            // Set the environment variable for genai to pick up
-            std::env::set_var("OPENAI_API_KEY", &api_keys.openai_api_key);
-            let client = Client::default();
-
-            let chat_req = ChatRequest::new(vec![
-                ChatMessage::user(question),
-            ]);
-
-            let model = if api_keys.openai_model.is_empty() {
-                "gpt-4o".to_string()
-            } else {
-                api_keys.openai_model
-            };
-
-            let response = client.exec_chat(&model, chat_req, None).await?;
-            Ok(response.content.into_texts().join(""))
-        }
-        _ => {
-            // Default to Gemini
-            // We need to wrap the Gemini response (which returns a generic Value) into a String
-            // The existing ask_gemini returns a Result<Value, GeminiError>
-            // We'll convert it here.
-            
-            // Note: ask_gemini expects the question. It handles the context building internally.
-            // However, the existing ask_gemini returns a JSON Value.
-            // If it's a simple chat, we might want to extract the "answer" field or return the whole thing.
-            // The command `ask_gemini_command` does this extraction.
-            // Let's reuse that logic or call ask_gemini directly.
-            
-            let response = gemini::ask_gemini(question).await
-                .map_err(|e| anyhow::anyhow!(e.to_string()))?;
-
-            if let Some(answer) = response.get("answer").and_then(|v| v.as_str()) {
-                Ok(answer.to_string())
-            } else {
-                 // If it's a plain text response wrapped in our fix, it might be in "answer" too.
-                 // If it's raw JSON, return stringified.
-                if let Some(ans) = response.get("answer").and_then(|v| v.as_str()) {
-                    Ok(ans.to_string())
-                } else {
-                    Ok(response.to_string())
-                }
-            }
+    Ok(Provider::from_api_keys(&config.api_keys)?)
+}
+
+/// The app's data relevant to `question` as a system prompt, then the session's
+/// earlier turns that fit in part of the budget, then `question`.
+pub async fn question_messages(
+    question: &str,
+    session_id: Option<i64>,
+) -> Result<Vec<ChatMessage>> {
+    let config = ConfigDirs::read_config().map_err(|e| anyhow::anyhow!(e.to_string()))?;
+    let budget = context_budget(&config.api_keys).await?;
+    let stored = match session_id {
+        Some(session_id) => chat_history(session_id)?,
+        None => Vec::new(),
+    };
+    let history = history::history_messages(&stored, (budget as f64 * HISTORY_SHARE) as usize);
+    let history_tokens: usize = history
+        .iter()
+        .map(|message| context::estimate_tokens(&message.content))
+        .sum();
+
+    let app_data = gemini::get_application_data(question, budget - history_tokens)
+        .await
+        .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+    let system_prompt = format!(
+        "Given the following application data: {}, output the information in the best way possible to answer the questions. Only the posts and comments most relevant to the question are listed; \"not_included\" summarises the rest. Be as thorough as possible and provide URLs when needed.",
+        serde_json::to_string(&app_data)?
+    );
+
+    let mut messages = vec![ChatMessage::system(system_prompt)];
+    messages.extend(history);
+    messages.push(ChatMessage::user(question));
+    Ok(messages)
+}
+
+// Turns saved in a session; an unknown id is an error, not an empty history
+fn chat_history(session_id: i64) -> Result<Vec<StoredChatMessage>> {
+    let reader = DBReader::new();
+    if reader.get_chat_session(session_id)?.is_none() {
+        anyhow::bail!("Chat session {} not found", session_id);
+    }
+    Ok(reader.get_chat_messages(session_id)?)
+}
+
+/// Adds a question and its answer to a chat session.
+pub fn save_exchange(session_id: i64, question: &str, answer: &str) -> Result<()> {
+    let mut db = DB::new()?;
+    db.add_chat_exchange(session_id, question, answer, Utc::now().timestamp())?;
+    Ok(())
+}
+
+// Tokens of app data the configured model gets, from its listed context window when known
+async fn context_budget(api_keys: &ApiKeys) -> Result<usize> {
+    let provider = Provider::from_api_keys(api_keys)?;
+    let listed = get_available_models(api_keys.clone(), &api_keys.ai_provider, "", false)
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("Could not list models, guessing the context window: {}", e);
+            Vec::new()
+        });
+    let window = context::context_window(provider.model(), &listed);
+
+    Ok(context::token_budget(window, api_keys.ai_context_tokens))
+}
+
+/// Models offered by `provider`, using `api_key` instead of the saved key when given.
+///
+/// Lists are reused for `model_cache_minutes` unless `refresh` is set.
+pub async fn get_available_models(
+    mut api_keys: ApiKeys,
+    provider: &str,
+    api_key: &str,
+    refresh: bool,
+) -> Result<Vec<ModelInfo>> {
+    let provider = provider.trim().to_lowercase();
+    api_keys.ai_provider = provider.clone();
+    let key = provider_key_mut(&mut api_keys, &provider);
+    if !api_key.trim().is_empty() {
+        *key = api_key.to_string();
+    }
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+
+    let provider = Provider::from_api_keys(&api_keys)?;
+    let cache_key = format!("{} {:x}", provider.endpoint(), hasher.finish());
+    let now = Utc::now().timestamp();
+    let ttl = api_keys.model_cache_minutes.max(0) * 60;
+
+    if !refresh {
+        let cache = MODEL_CACHE.lock().unwrap();
+        if let Some((_, _, models)) = cache
+            .iter()
+            .find(|(key, fetched_at, _)| *key == cache_key && now - fetched_at < ttl)
+        {
+            return Ok(models.clone());
         }
     }
+
+    let models = provider.list_models().await?;
+    let mut cache = MODEL_CACHE.lock().unwrap();
+    // Expired lists of other endpoints and keys go too, not just the one being replaced
+    cache.retain(|(key, fetched_at, _)| *key != cache_key && now - fetched_at < ttl);
+    cache.push((cache_key, now, models.clone()));
+    Ok(models)
 }
 
-pub async fn get_available_models(provider: &str, api_key: &str) -> Result<Vec<String>> {
+// The settings field with the API key of `provider`
+fn provider_key_mut<'a>(api_keys: &'a mut ApiKeys, provider: &str) -> &'a mut String {
     match provider {
-        "openai" => {
-             // genai doesn't seem to have a simple "list models" that works across all providers easily 
-             // or it might be provider specific.
-             // For now, let's return a static list of popular OpenAI models to avoid complexity
-             // as listing models often requires specific endpoints.
-             // We can improve this later if needed.
-             Ok(vec![
-                 "gpt-4o".to_string(),
-                 "gpt-4-turbo".to_string(),
-                 "gpt-4".to_string(),
-                 "gpt-3.5-turbo".to_string(),
-             ])
-        }
-        _ => {
-            gemini::get_available_models(api_key).await
-                .map_err(|e| anyhow::anyhow!(e.to_string()))
-        }
+        "openai" => &mut api_keys.openai_api_key,
+        "anthropic" | "claude" => &mut api_keys.anthropic_api_key,
+        "local" | "ollama" | "llamacpp" | "llama.cpp" => &mut api_keys.local_ai_api_key,
+        _ => &mut api_keys.gemini_api_key,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::mock_reddit::MockReddit;
+
+    #[tokio::test]
+    async fn model_lists_are_cached_per_endpoint_until_refreshed() {
+        let server = MockReddit::start(|_| {
+            (
+                200,
+                r#"{"data":[
+                    {"id":"qwen2.5:14b","meta":{"n_ctx_train":32768}},
+                    {"id":"nomic-embed-text"}
+                ]}"#
+                .to_string(),
+            )
+        })
+        .await;
+        let api_keys = ApiKeys {
+            local_ai_base_url: server.base_url.clone(),
+            ..ApiKeys::default()
+        };
+
+        let models = get_available_models(api_keys.clone(), "local", "", false)
+            .await
+            .unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].id, "qwen2.5:14b");
+        assert_eq!(models[0].context_window, Some(32768));
+
+        get_available_models(api_keys.clone(), "local", "", false)
+            .await
+            .unwrap();
+        assert_eq!(server.requests().len(), 1);
+
+        // Another key may see other models
+        get_available_models(api_keys.clone(), "local", "other-key", false)
+            .await
+            .unwrap();
+        get_available_models(api_keys, "local", "", true)
+            .await
+            .unwrap();
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn provider_names_are_normalised_before_picking_the_key() {
+        let server = MockReddit::start(|_| (200, r#"{"data":[{"id":"gpt-4o"}]}"#.to_string())).await;
+        let api_keys = ApiKeys {
+            openai_base_url: server.base_url.clone(),
+            ..ApiKeys::default()
+        };
+
+        get_available_models(api_keys, " OpenAI ", "sk-test", true)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            server.requests()[0].header("authorization"),
+            Some("Bearer sk-test")
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_lists_of_other_endpoints_are_dropped() {
+        let server = MockReddit::start(|_| (200, r#"{"data":[{"id":"gpt-4o"}]}"#.to_string())).await;
+        MODEL_CACHE
+            .lock()
+            .unwrap()
+            .push(("http://gone.invalid 0".to_string(), 0, Vec::new()));
+
+        get_available_models(
+            ApiKeys {
+                openai_base_url: server.base_url.clone(),
+                ..ApiKeys::default()
+            },
+            "openai",
+            "sk-test",
+            false,
+        )
+        .await
+        .unwrap();
+
+        let keys: Vec<String> = MODEL_CACHE
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, _, _)| key.clone())
+            .collect();
+        assert!(!keys.iter().any(|key| key == "http://gone.invalid 0"));
+        assert!(keys.iter().any(|key| key.contains(&server.base_url)));
     }
 }
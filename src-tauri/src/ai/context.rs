@@ -0,0 +1,482 @@
+// Fits the app's data into the model's context window. Posts and comments are
+// ranked against the question with BM25 and packed most relevant first (then
+// newest first) until the token budget is spent. Whatever doesn't fit is
+// described by a short summary instead of being dropped silently.
+use std::collections::{HashMap, HashSet};
+
+use chrono::DateTime;
+use serde_json::{json, Value};
+
+use crate::ai::gemini::CommandInfo;
+use crate::ai::providers::ModelInfo;
+use crate::database::adding::{CommentDataWrapper, PostDataWrapper};
+use crate::models::topics::tokenize;
+
+// Share of the context window kept free for the question and the answer
+const RESERVED_SHARE: f64 = 0.25;
+// Even million-token models get at most this much; more data rarely helps an answer
+const MAX_PROMPT_TOKENS: usize = 100_000;
+const MIN_PROMPT_TOKENS: usize = 1_000;
+// Most of the budget the log and the command list may take
+const LOG_SHARE: f64 = 0.1;
+const COMMANDS_SHARE: f64 = 0.15;
+// Kept back for the summary of what was left out
+const SUMMARY_TOKENS: usize = 400;
+// Longer selftext and comment bodies are cut
+const MAX_TEXT_CHARS: usize = 1_500;
+const SUMMARY_SUBREDDITS: usize = 10;
+const SUMMARY_TERMS: usize = 15;
+// BM25 term frequency saturation and length normalisation
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+// Context windows by model id prefix, for providers that don't report them.
+// Checked in order, so longer prefixes come first.
+const KNOWN_WINDOWS: &[(&str, usize)] = &[
+    ("gemini-1.0", 32_768),
+    ("gemini-pro", 32_768),
+    ("gemini", 1_048_576),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-5", 400_000),
+    ("gpt-4", 8_192),
+    ("gpt-3.5", 16_385),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4", 200_000),
+    ("claude", 200_000),
+];
+// Local servers often run with a small context
+const DEFAULT_WINDOW: usize = 8_192;
+
+/// Everything `ask_gemini` can draw on.
+pub struct ApplicationData {
+    pub posts: Vec<PostDataWrapper>,
+    pub comments: Vec<CommentDataWrapper>,
+    pub searched_posts: Vec<PostDataWrapper>,
+    pub log: String,
+    pub commands: Vec<CommandInfo>,
+}
+
+enum Item<'a> {
+    Post(&'a PostDataWrapper),
+    SearchedPost(&'a PostDataWrapper),
+    Comment(&'a CommentDataWrapper),
+}
+
+impl Item<'_> {
+    fn text(&self) -> String {
+        match self {
+            Item::Post(post) | Item::SearchedPost(post) => {
+                format!("{}\n{}", post.title, post.selftext.as_deref().unwrap_or(""))
+            }
+            Item::Comment(comment) => format!("{}\n{}", comment.post_title, comment.body),
+        }
+    }
+
+    fn timestamp(&self) -> i64 {
+        match self {
+            Item::Post(post) | Item::SearchedPost(post) => post.timestamp,
+            Item::Comment(comment) => comment.timestamp,
+        }
+    }
+
+    fn subreddit(&self) -> &str {
+        match self {
+            Item::Post(post) | Item::SearchedPost(post) => &post.subreddit,
+            Item::Comment(comment) => &comment.subreddit,
+        }
+    }
+
+    // The fields worth showing the model, with long text cut
+    fn to_json(&self) -> Value {
+        match self {
+            Item::Post(post) | Item::SearchedPost(post) => json!({
+                "id": post.id,
+                "title": post.title,
+                "selftext": truncate(post.selftext.as_deref().unwrap_or("")),
+                "subreddit": post.subreddit,
+                "author": post.author,
+                "url": post.url,
+                "permalink": post.permalink,
+                "formatted_date": post.formatted_date,
+                "score": post.score,
+                "num_comments": post.num_comments,
+                "intent": post.intent,
+                "relevance_score": post.relevance_score,
+                "engaged": post.engaged,
+                "assignee": post.assignee,
+                "notes": post.notes,
+            }),
+            Item::Comment(comment) => json!({
+                "id": comment.id,
+                "post_id": comment.post_id,
+                "post_title": comment.post_title,
+                "body": truncate(&comment.body),
+                "author": comment.author,
+                "subreddit": comment.subreddit,
+                "permalink": comment.permalink,
+                "formatted_date": comment.formatted_date,
+                "score": comment.score,
+                "sentiment": comment.sentiment,
+                "engaged": comment.engaged,
+                "assignee": comment.assignee,
+            }),
+        }
+    }
+}
+
+/// Rough token count: about four characters per token in English text.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Context window of `model`, as listed by the provider or else guessed from its id.
+pub fn context_window(model: &str, listed: &[ModelInfo]) -> usize {
+    let reported = listed
+        .iter()
+        .find(|info| info.id == model)
+        .and_then(|info| info.context_window)
+        .filter(|window| *window > 0);
+    if let Some(window) = reported {
+        return window as usize;
+    }
+
+    let model = model.to_lowercase();
+    KNOWN_WINDOWS
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map(|(_, window)| *window)
+        .unwrap_or(DEFAULT_WINDOW)
+}
+
+/// Tokens the app's data may take in the prompt; `configured` wins when above 0.
+pub fn token_budget(context_window: usize, configured: i64) -> usize {
+    if configured > 0 {
+        return configured as usize;
+    }
+    let usable = (context_window as f64 * (1.0 - RESERVED_SHARE)) as usize;
+    usable.clamp(MIN_PROMPT_TOKENS, MAX_PROMPT_TOKENS)
+}
+
+/// The posts and comments most relevant to `question`, a summary of the rest,
+/// the end of the log and the command list, within about `budget` tokens.
+pub fn pack_application_data(question: &str, data: &ApplicationData, budget: usize) -> Value {
+    let available_commands =
+        commands_within(&data.commands, (budget as f64 * COMMANDS_SHARE) as usize);
+    let application_log = log_tail(&data.log, (budget as f64 * LOG_SHARE) as usize);
+    let mut remaining = budget
+        .saturating_sub(estimate_tokens(&available_commands.to_string()))
+        .saturating_sub(estimate_tokens(&application_log))
+        .saturating_sub(SUMMARY_TOKENS);
+
+    let items: Vec<Item> = data
+        .posts
+        .iter()
+        .map(Item::Post)
+        .chain(data.searched_posts.iter().map(Item::SearchedPost))
+        .chain(data.comments.iter().map(Item::Comment))
+        .collect();
+
+    let mut relevant_posts = Vec::new();
+    let mut relevant_searched_posts = Vec::new();
+    let mut relevant_comments = Vec::new();
+    let mut omitted = Vec::new();
+    for i in rank(question, &items) {
+        let item = &items[i];
+        let value = item.to_json();
+        // +1 for the separating comma
+        let tokens = estimate_tokens(&value.to_string()) + 1;
+        if tokens > remaining {
+            omitted.push(item);
+            continue;
+        }
+        remaining -= tokens;
+        match item {
+            Item::Post(_) => relevant_posts.push(value),
+            Item::SearchedPost(_) => relevant_searched_posts.push(value),
+            Item::Comment(_) => relevant_comments.push(value),
+        }
+    }
+
+    json!({
+        "relevant_posts": relevant_posts,
+        "relevant_searched_posts": relevant_searched_posts,
+        "relevant_comments": relevant_comments,
+        "not_included": summarize(&omitted),
+        "application_log": application_log,
+        "available_commands": available_commands,
+    })
+}
+
+// Item indices by BM25 score against the question, ties (and questions
+// without useful terms) newest first
+fn rank(question: &str, items: &[Item]) -> Vec<usize> {
+    let terms: HashSet<String> = tokenize(question).iter().map(|t| stem(t)).collect();
+
+    let mut lengths = Vec::with_capacity(items.len());
+    let mut frequencies: Vec<HashMap<&str, usize>> = Vec::with_capacity(items.len());
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    for item in items {
+        let tokens = tokenize(&item.text());
+        lengths.push(tokens.len() as f64);
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for token in &tokens {
+            if let Some(term) = terms.get(&stem(token)) {
+                *counts.entry(term.as_str()).or_insert(0) += 1;
+            }
+        }
+        for term in counts.keys() {
+            *document_frequency.entry(term).or_insert(0) += 1;
+        }
+        frequencies.push(counts);
+    }
+
+    let n = items.len() as f64;
+    let average_length = (lengths.iter().sum::<f64>() / n.max(1.0)).max(1.0);
+    let scores: Vec<f64> = frequencies
+        .iter()
+        .zip(&lengths)
+        .map(|(counts, length)| {
+            counts
+                .iter()
+                .map(|(term, tf)| {
+                    let df = document_frequency[term] as f64;
+                    let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+                    let tf = *tf as f64;
+                    idf * tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * length / average_length))
+                })
+                .sum()
+        })
+        .collect();
+
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.sort_by(|a, b| {
+        scores[*b]
+            .total_cmp(&scores[*a])
+            .then_with(|| items[*b].timestamp().cmp(&items[*a].timestamp()))
+    });
+    order
+}
+
+// Folds simple plurals so "leads" matches "lead"
+fn stem(token: &str) -> String {
+    match token.strip_suffix('s') {
+        Some(stem) if stem.len() > 2 && !stem.ends_with('s') => stem.to_string(),
+        _ => token.to_string(),
+    }
+}
+
+// Counts, subreddits, dates and common terms of what didn't fit
+fn summarize(omitted: &[&Item]) -> Value {
+    let mut posts = 0;
+    let mut searched_posts = 0;
+    let mut comments = 0;
+    let mut subreddits: HashMap<&str, usize> = HashMap::new();
+    let mut terms: HashMap<String, usize> = HashMap::new();
+    for item in omitted {
+        match item {
+            Item::Post(_) => posts += 1,
+            Item::SearchedPost(_) => searched_posts += 1,
+            Item::Comment(_) => comments += 1,
+        }
+        *subreddits.entry(item.subreddit()).or_insert(0) += 1;
+        let unique: HashSet<String> = tokenize(&item.text()).into_iter().collect();
+        for term in unique {
+            *terms.entry(term).or_insert(0) += 1;
+        }
+    }
+
+    let mut subreddits: Vec<(&str, usize)> = subreddits.into_iter().collect();
+    subreddits.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    subreddits.truncate(SUMMARY_SUBREDDITS);
+    let mut terms: Vec<(String, usize)> = terms.into_iter().filter(|(_, n)| *n > 1).collect();
+    terms.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    terms.truncate(SUMMARY_TERMS);
+
+    let date = |timestamp: Option<i64>| {
+        timestamp
+            .and_then(|t| DateTime::from_timestamp(t, 0))
+            .map(|d| d.format("%Y-%m-%d").to_string())
+    };
+
+    json!({
+        "posts": posts,
+        "searched_posts": searched_posts,
+        "comments": comments,
+        "top_subreddits": subreddits
+            .iter()
+            .map(|(subreddit, count)| json!({ "subreddit": subreddit, "count": count }))
+            .collect::<Vec<_>>(),
+        "common_terms": terms.into_iter().map(|(term, _)| term).collect::<Vec<_>>(),
+        "oldest": date(omitted.iter().map(|item| item.timestamp()).min()),
+        "newest": date(omitted.iter().map(|item| item.timestamp()).max()),
+    })
+}
+
+// The full catalogue, or names and descriptions only when it is too long
+fn commands_within(commands: &[CommandInfo], budget: usize) -> Value {
+    let full = json!(commands);
+    if estimate_tokens(&full.to_string()) <= budget {
+        return full;
+    }
+    let short: Vec<Value> = commands
+        .iter()
+        .map(|command| json!({ "name": command.name, "description": command.description }))
+        .collect();
+    let short = json!(short);
+    if estimate_tokens(&short.to_string()) <= budget {
+        return short;
+    }
+    json!(commands
+        .iter()
+        .map(|command| &command.name)
+        .collect::<Vec<_>>())
+}
+
+// The newest log lines that fit in `budget`
+fn log_tail(log: &str, budget: usize) -> String {
+    let mut used = 0;
+    let mut lines: Vec<&str> = Vec::new();
+    for line in log.lines().rev() {
+        used += estimate_tokens(line) + 1;
+        if used > budget {
+            break;
+        }
+        lines.push(line);
+    }
+    lines.reverse();
+    lines.join("\n")
+}
+
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_TEXT_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(id: i64, subreddit: &str, title: &str, body: &str, timestamp: i64) -> PostDataWrapper {
+        PostDataWrapper {
+            subreddit: subreddit.to_string(),
+            selftext: Some(body.to_string()),
+            timestamp,
+            ..PostDataWrapper::sample(id, title)
+        }
+    }
+
+    fn data(posts: Vec<PostDataWrapper>) -> ApplicationData {
+        ApplicationData {
+            posts,
+            comments: Vec::new(),
+            searched_posts: Vec::new(),
+            log: String::new(),
+            commands: Vec::new(),
+        }
+    }
+
+    fn ids(packed: &Value) -> Vec<i64> {
+        packed["relevant_posts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|post| post["id"].as_i64().unwrap())
+            .collect()
+    }
+
+    fn padding() -> String {
+        "Long story about the weekend and the weather. ".repeat(20)
+    }
+
+    #[test]
+    fn matching_posts_win_when_the_budget_is_tight() {
+        let mut posts: Vec<PostDataWrapper> = (1..=30)
+            .map(|i| post(i, "cats", "Photos of my cat", &padding(), 1_000 + i))
+            .collect();
+        posts.push(post(
+            100,
+            "shopify",
+            "Inventory sync app for Shopify?",
+            &padding(),
+            10,
+        ));
+        posts.push(post(
+            101,
+            "ecommerce",
+            "Which inventory tool do you use",
+            &padding(),
+            5,
+        ));
+
+        let packed = pack_application_data("Who needs an inventory app?", &data(posts), 1_500);
+
+        let included = ids(&packed);
+        assert_eq!(&included[..2], &[100, 101]);
+        assert!(included.len() < 32);
+        assert_eq!(packed["not_included"]["posts"], 32 - included.len());
+        assert_eq!(
+            packed["not_included"]["top_subreddits"][0]["subreddit"],
+            "cats"
+        );
+    }
+
+    #[test]
+    fn packed_data_stays_within_the_budget() {
+        let posts = (1..=200)
+            .map(|i| {
+                post(
+                    i,
+                    "smallbusiness",
+                    &format!("Question {}", i),
+                    &padding(),
+                    i,
+                )
+            })
+            .collect();
+        let mut data = data(posts);
+        data.log = "Searched r/smallbusiness\n".repeat(500);
+
+        for budget in [1_000, 5_000, 20_000] {
+            let packed = pack_application_data("anything new?", &data, budget);
+            assert!(estimate_tokens(&packed.to_string()) <= budget);
+            // Without matching terms the newest posts go first
+            assert_eq!(ids(&packed)[0], 200);
+        }
+    }
+
+    #[test]
+    fn plurals_match_singular_terms() {
+        let posts = vec![
+            post(1, "sales", "Looking for a CRM", "", 2),
+            post(2, "sales", "Where do you find good leads", "", 1),
+        ];
+
+        let packed = pack_application_data("lead", &data(posts), 10_000);
+
+        assert_eq!(ids(&packed), vec![2, 1]);
+    }
+
+    #[test]
+    fn budget_follows_the_model_window() {
+        let listed = vec![ModelInfo {
+            id: "qwen2.5:14b".to_string(),
+            display_name: None,
+            context_window: Some(32_768),
+            max_output_tokens: None,
+            supports_json: None,
+        }];
+
+        assert_eq!(context_window("qwen2.5:14b", &listed), 32_768);
+        assert_eq!(context_window("llama3.1", &listed), DEFAULT_WINDOW);
+        assert_eq!(context_window("gemini-pro", &[]), 32_768);
+        assert_eq!(context_window("gemini-1.5-flash", &[]), 1_048_576);
+        assert_eq!(token_budget(32_768, 0), 24_576);
+        assert_eq!(token_budget(1_048_576, 0), MAX_PROMPT_TOKENS);
+        assert_eq!(token_budget(1_048_576, 4_000), 4_000);
+    }
+}
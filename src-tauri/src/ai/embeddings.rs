@@ -0,0 +1,380 @@
+// Semantic search over saved posts and their comments. Each post and comment is
+// embedded once by the configured embedder and stored with a hash of the text
+// it was made from, so re-indexing only sends new or edited items. Searches
+// compare vectors by cosine similarity and return posts, reached either
+// directly or through one of their comments.
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::ai::providers::{AiError, Embedder};
+use crate::database::adding::{CommentDataWrapper, PostDataWrapper};
+
+pub const POST: &str = "post";
+pub const COMMENT: &str = "comment";
+
+/// Texts sent to the embedder per request.
+pub const BATCH_SIZE: usize = 64;
+pub const DEFAULT_MATCHES: usize = 20;
+// Embedding models take a few thousand tokens at most; the start carries the meaning
+const MAX_TEXT_CHARS: usize = 2_000;
+
+#[derive(Debug, Clone)]
+pub struct StoredEmbedding {
+    // POST or COMMENT
+    pub kind: String,
+    // Post row id or comment id
+    pub item_id: String,
+    pub model: String,
+    pub content_hash: String,
+    pub vector: Vec<f32>,
+    pub created_at: i64,
+}
+
+/// A post or comment whose embedding is missing or out of date.
+#[derive(Debug, Clone)]
+pub struct PendingEmbedding {
+    pub kind: &'static str,
+    pub item_id: String,
+    pub text: String,
+    pub content_hash: String,
+}
+
+#[derive(Debug, Default)]
+pub struct IndexPlan {
+    pub pending: Vec<PendingEmbedding>,
+    // (kind, item id) of embeddings whose post or comment is gone
+    pub stale: Vec<(String, String)>,
+    pub unchanged: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct IndexStats {
+    pub embedded: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SemanticMatch {
+    pub post: PostDataWrapper,
+    pub similarity: f64,
+    // Set when the post was reached through this comment rather than its own text
+    pub comment: Option<CommentDataWrapper>,
+}
+
+/// What needs embedding with `model` so the index covers `posts` and `comments`.
+pub fn plan_index(
+    posts: &[PostDataWrapper],
+    comments: &[CommentDataWrapper],
+    stored: &[StoredEmbedding],
+    model: &str,
+) -> IndexPlan {
+    let existing: HashMap<(&str, &str), &StoredEmbedding> = stored
+        .iter()
+        .map(|embedding| {
+            (
+                (embedding.kind.as_str(), embedding.item_id.as_str()),
+                embedding,
+            )
+        })
+        .collect();
+
+    let items = posts
+        .iter()
+        .map(|post| (POST, post.id.to_string(), post_text(post)))
+        .chain(
+            comments
+                .iter()
+                .map(|comment| (COMMENT, comment.id.clone(), comment.body.trim().to_string())),
+        )
+        .filter(|(_, _, text)| !text.is_empty());
+
+    let mut plan = IndexPlan::default();
+    let mut current: Vec<(&str, String)> = Vec::new();
+    for (kind, item_id, text) in items {
+        let text = truncate(&text);
+        let content_hash = content_hash(model, &text);
+        let up_to_date = existing
+            .get(&(kind, item_id.as_str()))
+            .is_some_and(|embedding| embedding.content_hash == content_hash);
+        if up_to_date {
+            plan.unchanged += 1;
+        } else {
+            plan.pending.push(PendingEmbedding {
+                kind,
+                item_id: item_id.clone(),
+                text,
+                content_hash,
+            });
+        }
+        current.push((kind, item_id));
+    }
+
+    let current: HashSet<(&str, &str)> = current
+        .iter()
+        .map(|(kind, item_id)| (*kind, item_id.as_str()))
+        .collect();
+    plan.stale = stored
+        .iter()
+        .filter(|embedding| {
+            !current.contains(&(embedding.kind.as_str(), embedding.item_id.as_str()))
+        })
+        .map(|embedding| (embedding.kind.clone(), embedding.item_id.clone()))
+        .collect();
+    plan
+}
+
+/// Embeds one batch of pending items.
+pub async fn embed_batch(
+    embedder: &Embedder,
+    batch: &[PendingEmbedding],
+    now: i64,
+) -> Result<Vec<StoredEmbedding>, AiError> {
+    let texts: Vec<String> = batch.iter().map(|pending| pending.text.clone()).collect();
+    let vectors = embedder.embed(&texts).await?;
+
+    Ok(batch
+        .iter()
+        .zip(vectors)
+        .map(|(pending, vector)| StoredEmbedding {
+            kind: pending.kind.to_string(),
+            item_id: pending.item_id.clone(),
+            model: embedder.model().to_string(),
+            content_hash: pending.content_hash.clone(),
+            vector,
+            created_at: now,
+        })
+        .collect())
+}
+
+/// Posts closest to `query`, each once with its best similarity.
+/// Only embeddings made with `model` are compared; `exclude` leaves out one post.
+pub fn rank_posts(
+    query: &[f32],
+    stored: &[StoredEmbedding],
+    model: &str,
+    posts: Vec<PostDataWrapper>,
+    comments: Vec<CommentDataWrapper>,
+    exclude: Option<i64>,
+    limit: usize,
+) -> Vec<SemanticMatch> {
+    let post_index: HashMap<String, usize> = posts
+        .iter()
+        .enumerate()
+        .map(|(i, post)| (post.id.to_string(), i))
+        .collect();
+    // Comments are stored against the base36 post id
+    let by_name: HashMap<&str, usize> = posts
+        .iter()
+        .enumerate()
+        .map(|(i, post)| (post.name.trim_start_matches("t3_"), i))
+        .collect();
+    let comment_index: HashMap<&str, usize> = comments
+        .iter()
+        .enumerate()
+        .map(|(i, comment)| (comment.id.as_str(), i))
+        .collect();
+
+    // Post index to (similarity, index of the comment it was reached through)
+    let mut best: HashMap<usize, (f64, Option<usize>)> = HashMap::new();
+    for embedding in stored {
+        if embedding.model != model || embedding.vector.len() != query.len() {
+            continue;
+        }
+        let (post, comment) = if embedding.kind == POST {
+            match post_index.get(&embedding.item_id) {
+                Some(i) => (*i, None),
+                None => continue,
+            }
+        } else {
+            let Some(c) = comment_index.get(embedding.item_id.as_str()) else {
+                continue;
+            };
+            match by_name.get(comments[*c].post_id.as_str()) {
+                Some(i) => (*i, Some(*c)),
+                None => continue,
+            }
+        };
+        if exclude == Some(posts[post].id) {
+            continue;
+        }
+
+        let similarity = cosine(query, &embedding.vector);
+        let entry = best.entry(post).or_insert((f64::MIN, None));
+        if similarity > entry.0 {
+            *entry = (similarity, comment);
+        }
+    }
+
+    let mut ranked: Vec<(usize, f64, Option<usize>)> = best
+        .into_iter()
+        .map(|(post, (similarity, comment))| (post, similarity, comment))
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.1.total_cmp(&a.1)
+            .then_with(|| posts[b.0].timestamp.cmp(&posts[a.0].timestamp))
+    });
+    ranked.truncate(limit);
+
+    let mut posts: Vec<Option<PostDataWrapper>> = posts.into_iter().map(Some).collect();
+    let mut comments: Vec<Option<CommentDataWrapper>> = comments.into_iter().map(Some).collect();
+    ranked
+        .into_iter()
+        .filter_map(|(post, similarity, comment)| {
+            Some(SemanticMatch {
+                post: posts[post].take()?,
+                similarity,
+                // A comment leads to one post only, so it is taken at most once
+                comment: comment.and_then(|c| comments[c].take()),
+            })
+        })
+        .collect()
+}
+
+pub fn cosine(a: &[f32], b: &[f32]) -> f64 {
+    let mut dot = 0.0;
+    let mut norm_a = 0.0;
+    let mut norm_b = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        let (x, y) = (*x as f64, *y as f64);
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+/// Little-endian f32s, as stored in the `vector` BLOB column.
+pub fn vector_to_bytes(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+pub fn vector_from_bytes(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+fn post_text(post: &PostDataWrapper) -> String {
+    format!("{}\n{}", post.title, post.selftext.as_deref().unwrap_or(""))
+        .trim()
+        .to_string()
+}
+
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_TEXT_CHARS) {
+        Some((end, _)) => text[..end].to_string(),
+        None => text.to_string(),
+    }
+}
+
+// FNV-1a of the model and text. Stored in the database, so unlike
+// DefaultHasher it must not change between Rust releases.
+fn content_hash(model: &str, text: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in model.bytes().chain([0]).chain(text.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::adding::base36;
+
+    fn post(id: i64, title: &str) -> PostDataWrapper {
+        PostDataWrapper {
+            timestamp: id,
+            ..PostDataWrapper::sample(id, title)
+        }
+    }
+
+    fn comment(id: &str, post_id: i64, body: &str) -> CommentDataWrapper {
+        CommentDataWrapper::sample(id, &base36(post_id), body)
+    }
+
+    fn stored(kind: &str, item_id: &str, model: &str, vector: Vec<f32>) -> StoredEmbedding {
+        StoredEmbedding {
+            kind: kind.to_string(),
+            item_id: item_id.to_string(),
+            model: model.to_string(),
+            content_hash: String::new(),
+            vector,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn only_new_and_edited_items_are_embedded_again() {
+        let posts = vec![post(1, "Inventory app?"), post(2, "Payroll help")];
+        let comments = vec![
+            comment("c1", 1, "Try a spreadsheet"),
+            comment("c2", 1, "  "),
+        ];
+        let first = plan_index(&posts, &comments, &[], "embed-1");
+        assert_eq!(first.pending.len(), 3);
+
+        let mut index: Vec<StoredEmbedding> = first
+            .pending
+            .iter()
+            .map(|pending| StoredEmbedding {
+                content_hash: pending.content_hash.clone(),
+                ..stored(pending.kind, &pending.item_id, "embed-1", vec![1.0])
+            })
+            .collect();
+        index.push(stored(POST, "99", "embed-1", vec![1.0]));
+
+        let edited = vec![post(1, "Inventory app?"), post(2, "Payroll and tax help")];
+        let plan = plan_index(&edited, &comments, &index, "embed-1");
+        assert_eq!(plan.unchanged, 2);
+        assert_eq!(plan.pending.len(), 1);
+        assert_eq!(plan.pending[0].item_id, "2");
+        assert_eq!(plan.stale, vec![(POST.to_string(), "99".to_string())]);
+
+        // A new model makes every vector incomparable
+        let plan = plan_index(&edited, &comments, &index, "embed-2");
+        assert_eq!(plan.pending.len(), 3);
+    }
+
+    #[test]
+    fn posts_are_reached_through_their_best_comment() {
+        let posts = || vec![post(1, "Anyone sell on Etsy?"), post(2, "Tax deadline")];
+        let comments = || vec![comment("c1", 1, "I track stock in a spreadsheet")];
+        let index = vec![
+            stored(POST, "1", "m", vec![0.0, 1.0]),
+            stored(COMMENT, "c1", "m", vec![0.9, 0.1]),
+            stored(POST, "2", "m", vec![0.1, 0.9]),
+            // Other model, never compared
+            stored(POST, "2", "other", vec![1.0, 0.0]),
+        ];
+
+        let matches = rank_posts(&[1.0, 0.0], &index, "m", posts(), comments(), None, 10);
+
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].post.id, 1);
+        assert_eq!(matches[0].comment.as_ref().unwrap().id, "c1");
+        assert!(matches[0].similarity > 0.99);
+        assert_eq!(matches[1].post.id, 2);
+        assert!(matches[1].comment.is_none());
+
+        let similar = rank_posts(&[1.0, 0.0], &index, "m", posts(), comments(), Some(1), 10);
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].post.id, 2);
+    }
+
+    #[test]
+    fn vectors_survive_the_blob_round_trip() {
+        let vector = vec![0.25, -1.5, 3.0e-7];
+
+        assert_eq!(vector_from_bytes(&vector_to_bytes(&vector)), vector);
+        assert!((cosine(&vector, &vector) - 1.0).abs() < 1e-9);
+        assert_eq!(cosine(&vector, &[0.0, 0.0, 0.0]), 0.0);
+    }
+}
@@ -1,7 +1,6 @@
 use anyhow::Result;
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde_json::Value;
 use std::fmt;
 use std::io::Write;
 use std::sync::Arc;
@@ -10,8 +9,12 @@ use std::thread;
 use std::time::Duration;
 
 use crate::{database, settings};
+use crate::ai::context::{self, ApplicationData};
+use crate::ai::providers::{AiProvider, ChatMessage, Provider};
+use crate::ai::structured::{self, LeadBatch};
 use crate::exports::excel;
 use crate::database::read::DBReader;
+use crate::models::leads::find_local_leads;
 use crate::settings::api_keys::ApiKeys; // Import ApiKeys for command descriptions
 use directories::BaseDirs;
 #[derive(Debug)]
@@ -34,7 +37,7 @@ impl fmt::Display for GeminiError {
         match self {
             GeminiError::DatabaseError(e) => write!(f, "Database error: {}", e),
             GeminiError::ConfigError(e) => write!(f, "Configuration error: {}", e),
-            GeminiError::GeminiApiError(e) => write!(f, "Gemini API error: {}", e),
+            GeminiError::GeminiApiError(e) => write!(f, "AI request error: {}", e),
             GeminiError::JsonParsingError(e) => write!(f, "JSON parsing error: {}", e),
         }
     }
@@ -43,46 +46,6 @@ impl fmt::Display for GeminiError {
 // Implement Error trait for GeminiError
 impl std::error::Error for GeminiError {}
 
-#[derive(Debug, Serialize, Deserialize)]
-struct GeminiModel {
-    name: String,
-    #[serde(rename = "displayName")]
-    display_name: String,
-    #[serde(rename = "supportedGenerationMethods")]
-    supported_generation_methods: Vec<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct ListModelsResponse {
-    models: Vec<GeminiModel>,
-}
-
-pub async fn get_available_models(api_key: &str) -> Result<Vec<String>, GeminiError> {
-    let client = Client::new();
-    let url = format!("https://generativelanguage.googleapis.com/v1beta/models?key={}", api_key);
-
-    let response = client.get(&url)
-        .send()
-        .await
-        .map_err(|e| GeminiError::GeminiApiError(format!("Failed to fetch models: {}", e)))?;
-
-    if !response.status().is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(GeminiError::GeminiApiError(format!("API Error: {}", error_text)));
-    }
-
-    let list_response: ListModelsResponse = response.json()
-        .await
-        .map_err(|e| GeminiError::JsonParsingError(format!("Failed to parse models list: {}", e)))?;
-
-    let models = list_response.models.into_iter()
-        .filter(|m| m.supported_generation_methods.contains(&"generateContent".to_string()))
-        .map(|m| m.name.replace("models/", ""))
-        .collect();
-
-    Ok(models)
-}
-
 pub async fn read_application_log() -> Result<String, GeminiError> {
     let base_dirs = BaseDirs::new().ok_or(GeminiError::ConfigError(
         "Failed to get base directories for log file".to_string(),
@@ -119,12 +82,27 @@ pub async fn get_available_commands() -> Vec<CommandInfo> {
     vec![
         CommandInfo {
             name: "get_reddit_results".to_string(),
-            description: "Fetches Reddit posts based on a query and a list of sort types (e.g., 'hot', 'new', 'top').".to_string(),
+            description: "Fetches Reddit posts based on a query and a list of sort types (e.g., 'hot', 'new', 'top'), applying the configured content filters. Returns the posts and counts of what was filtered out.".to_string(),
             parameters: vec![
                 CommandParameter { name: "sortTypes".to_string(), r#type: "Vec<String>".to_string(), description: "A list of sort types (e.g., ['hot', 'new', 'top'])".to_string() },
                 CommandParameter { name: "query".to_string(), r#type: "String".to_string(), description: "The search query or subreddit (e.g., 'rust' or 'r/rust')".to_string() },
             ],
         },
+        CommandInfo {
+            name: "run_brand_monitoring_command".to_string(),
+            description: "Searches Reddit for every branded and competitor keyword and tags the posts with the brands they mention.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "sort_types".to_string(), r#type: "Vec<String>".to_string(), description: "A list of sort types (e.g., ['new', 'relevance'])".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "get_brand_mention_counts_command".to_string(),
+            description: "Counts brand mentions per brand and period for share-of-voice reporting.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "period".to_string(), r#type: "String".to_string(), description: "'day', 'week' or 'month'".to_string() },
+                CommandParameter { name: "since".to_string(), r#type: "Option<i64>".to_string(), description: "Only count mentions after this unix timestamp.".to_string() },
+            ],
+        },
         CommandInfo {
             name: "get_recent_posts".to_string(),
             description: "Retrieves a limited number of the most recent posts from the database.".to_string(),
@@ -178,6 +156,20 @@ pub async fn get_available_commands() -> Vec<CommandInfo> {
             description: "Retrieves all comments currently stored in the database.".to_string(),
             parameters: vec![],
         },
+        CommandInfo {
+            name: "get_posts_by_sentiment_command".to_string(),
+            description: "Retrieves saved posts with the given locally detected sentiment, most negative first.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "sentiment".to_string(), r#type: "String".to_string(), description: "positive, neutral or negative.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "get_comments_by_sentiment_command".to_string(),
+            description: "Retrieves stored comments with the given locally detected sentiment, most negative first.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "sentiment".to_string(), r#type: "String".to_string(), description: "positive, neutral or negative.".to_string() },
+            ],
+        },
         CommandInfo {
             name: "get_reddit_config_command".to_string(),
             description: "Retrieves the current Reddit API configuration settings.".to_string(),
@@ -185,9 +177,19 @@ pub async fn get_available_commands() -> Vec<CommandInfo> {
         },
         CommandInfo {
             name: "get_gemini_models_command".to_string(),
-            description: "Retrieves a list of available Gemini AI models.".to_string(),
+            description: "Retrieves the models offered by the configured AI provider (Gemini, OpenAI, Anthropic or a local server).".to_string(),
+            parameters: vec![
+                CommandParameter { name: "api_key".to_string(), r#type: "String".to_string(), description: "The API key for the provider; empty to use the saved one.".to_string() },
+                CommandParameter { name: "provider".to_string(), r#type: "Option<String>".to_string(), description: "gemini, openai, anthropic or local; defaults to the saved provider.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "get_ai_models_command".to_string(),
+            description: "Lists the AI provider's models with context window, output limit and JSON mode support where the provider reports them. Results are cached for model_cache_minutes.".to_string(),
             parameters: vec![
-                CommandParameter { name: "api_key".to_string(), r#type: "String".to_string(), description: "The API key for the Gemini service.".to_string() },
+                CommandParameter { name: "provider".to_string(), r#type: "Option<String>".to_string(), description: "gemini, openai, anthropic or local; defaults to the saved provider.".to_string() },
+                CommandParameter { name: "api_key".to_string(), r#type: "Option<String>".to_string(), description: "Key to use instead of the saved one.".to_string() },
+                CommandParameter { name: "refresh".to_string(), r#type: "Option<bool>".to_string(), description: "Ignore the cached list.".to_string() },
             ],
         },
         CommandInfo {
@@ -264,17 +266,246 @@ pub async fn get_available_commands() -> Vec<CommandInfo> {
                 CommandParameter { name: "text".to_string(), r#type: "String".to_string(), description: "The content of the comment.".to_string() },
             ],
         },
+        CommandInfo {
+            name: "reddit_whoami_command".to_string(),
+            description: "Reports the logged-in Reddit username, granted scopes and when the access token expires.".to_string(),
+            parameters: vec![],
+        },
+        CommandInfo {
+            name: "find_leads_command".to_string(),
+            description: "Finds saved posts whose title, body or comments match the lead keywords, using the AND/OR match setting, without calling the AI. generate_ai_leads_command grades the same matches with the AI.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "profile_authors".to_string(), r#type: "Option<bool>".to_string(), description: "Fetch missing author profiles from Reddit so bots and throwaways can be ranked last.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "generate_ai_leads_command".to_string(),
+            description: "Has the AI grade the posts matching the lead keywords, exports the valid leads to Excel and returns them with the records that failed validation.".to_string(),
+            parameters: vec![],
+        },
+        CommandInfo {
+            name: "count_leads_by_subreddit_command".to_string(),
+            description: "Counts the saved posts matching the lead keywords in each subreddit, most leads first.".to_string(),
+            parameters: vec![],
+        },
+        CommandInfo {
+            name: "get_duplicate_clusters_command".to_string(),
+            description: "Groups saved posts and current results that are crossposts or near-duplicates of the same question across subreddits.".to_string(),
+            parameters: vec![],
+        },
+        CommandInfo {
+            name: "cluster_topics_command".to_string(),
+            description: "Groups saved posts into labelled topics by the words in their titles, bodies and comments, replacing the previous topics.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "k".to_string(), r#type: "Option<usize>".to_string(), description: "Number of topics; picked from the number of posts when omitted.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "get_topics_command".to_string(),
+            description: "Lists the topics from the last clustering run with their top terms and number of posts.".to_string(),
+            parameters: vec![],
+        },
+        CommandInfo {
+            name: "get_posts_by_topic_command".to_string(),
+            description: "Returns the saved posts in a topic, most representative first.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "topic_id".to_string(), r#type: "i64".to_string(), description: "Topic id from get_topics_command.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "index_embeddings_command".to_string(),
+            description: "Computes embeddings for saved posts and comments that are new or edited since the last run, using embedding_provider (or ai_provider) and embedding_model. Returns how many were embedded, unchanged and removed.".to_string(),
+            parameters: vec![],
+        },
+        CommandInfo {
+            name: "semantic_search_command".to_string(),
+            description: "Finds saved posts by meaning rather than keywords, e.g. 'need something to track stock' finds inventory posts. Each result has the post, its cosine similarity and the comment it matched through, if any.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "query".to_string(), r#type: "String".to_string(), description: "What to look for, in plain words.".to_string() },
+                CommandParameter { name: "limit".to_string(), r#type: "Option<usize>".to_string(), description: "Maximum results, 20 by default.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "find_similar_leads_command".to_string(),
+            description: "Returns the saved posts closest in meaning to a given post, from the embeddings index.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "post_id".to_string(), r#type: "i64".to_string(), description: "Row id of the post to compare against.".to_string() },
+                CommandParameter { name: "limit".to_string(), r#type: "Option<usize>".to_string(), description: "Maximum results, 20 by default.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "discover_subreddits_command".to_string(),
+            description: "Searches Reddit for subreddits about a topic and returns their subscribers, active users, description and NSFW flag.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "query".to_string(), r#type: "String".to_string(), description: "Topic or name to search for.".to_string() },
+                CommandParameter { name: "include_nsfw".to_string(), r#type: "Option<bool>".to_string(), description: "Also return NSFW subreddits.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "get_saved_subreddits_command".to_string(),
+            description: "Lists the saved subreddits with their notes, lead score weight and cached rules.".to_string(),
+            parameters: vec![],
+        },
+        CommandInfo {
+            name: "get_subreddit_rules_command".to_string(),
+            description: "Returns a subreddit's rules and its self-promotion policy; check these before replying.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "subreddit".to_string(), r#type: "String".to_string(), description: "The subreddit name, with or without r/.".to_string() },
+                CommandParameter { name: "refresh".to_string(), r#type: "Option<bool>".to_string(), description: "Ignore the cached rules.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "get_author_profile_command".to_string(),
+            description: "Returns a Redditor's karma, account age, active subreddits and prior keyword mentions, cached in the authors table.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "username".to_string(), r#type: "String".to_string(), description: "The Reddit username, with or without u/.".to_string() },
+                CommandParameter { name: "refresh".to_string(), r#type: "Option<bool>".to_string(), description: "Ignore the cache and fetch from Reddit.".to_string() },
+            ],
+        },
         CommandInfo {
             name: "ask_gemini_command".to_string(),
-            description: "Invokes the Gemini AI with a given question.".to_string(),
+            description: "Asks the configured AI provider a question about the application data.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "question".to_string(), r#type: "String".to_string(), description: "The question to ask the AI.".to_string() },
+                CommandParameter { name: "session_id".to_string(), r#type: "Option<i64>".to_string(), description: "Chat session to continue; its earlier turns are sent and the exchange is saved.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "ask_gemini_stream_command".to_string(),
+            description: "Like ask_gemini_command, but sends the answer in pieces as 'ai-stream' events tagged with the request id.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "request_id".to_string(), r#type: "String".to_string(), description: "Id chosen by the caller to match events and cancel the request.".to_string() },
+                CommandParameter { name: "question".to_string(), r#type: "String".to_string(), description: "The question to ask the AI.".to_string() },
+                CommandParameter { name: "session_id".to_string(), r#type: "Option<i64>".to_string(), description: "Chat session to continue; the finished answer is saved to it.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "generate_reply_stream_command".to_string(),
+            description: "Drafts a reply to a Reddit post, sending it in pieces as 'ai-stream' events tagged with the request id.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "request_id".to_string(), r#type: "String".to_string(), description: "Id chosen by the caller to match events and cancel the request.".to_string() },
+                CommandParameter { name: "post_title".to_string(), r#type: "String".to_string(), description: "Title of the post to reply to.".to_string() },
+                CommandParameter { name: "post_body".to_string(), r#type: "String".to_string(), description: "Body of the post to reply to.".to_string() },
+                CommandParameter { name: "tone".to_string(), r#type: "Option<String>".to_string(), description: "Tone preset from get_reply_tones_command; the default tone when omitted.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "draft_replies_command".to_string(),
+            description: "Drafts several replies to a stored post using its top comments, the subreddit rules and the product facts, each with a self-promotion risk score.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "post_id".to_string(), r#type: "i64".to_string(), description: "Id of the post to reply to.".to_string() },
+                CommandParameter { name: "tone".to_string(), r#type: "Option<String>".to_string(), description: "Tone preset from get_reply_tones_command; the default tone when omitted.".to_string() },
+                CommandParameter { name: "variants".to_string(), r#type: "Option<usize>".to_string(), description: "Number of drafts, 1 to 5.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "get_reply_tones_command".to_string(),
+            description: "Lists the tone presets for reply drafts.".to_string(),
+            parameters: vec![],
+        },
+        CommandInfo {
+            name: "start_reply_queue_command".to_string(),
+            description: "Drafts replies for many stored posts in the background, a few at a time, saving them for review and reporting progress as reply-queue events.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "job_id".to_string(), r#type: "String".to_string(), description: "Id that tags the progress events of this job.".to_string() },
+                CommandParameter { name: "post_ids".to_string(), r#type: "Vec<i64>".to_string(), description: "Ids of the posts to reply to.".to_string() },
+                CommandParameter { name: "tone".to_string(), r#type: "Option<String>".to_string(), description: "Tone preset from get_reply_tones_command; the default tone when omitted.".to_string() },
+                CommandParameter { name: "variants".to_string(), r#type: "Option<usize>".to_string(), description: "Drafts per post, 1 to 5.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "cancel_reply_queue_command".to_string(),
+            description: "Stops a reply queue job; drafts already saved are kept.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "job_id".to_string(), r#type: "String".to_string(), description: "The id passed when the job was started.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "get_reply_drafts_command".to_string(),
+            description: "Lists saved reply drafts, optionally only one status or one post.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "status".to_string(), r#type: "Option<String>".to_string(), description: "draft, approved, posted or rejected.".to_string() },
+                CommandParameter { name: "post_id".to_string(), r#type: "Option<i64>".to_string(), description: "Only drafts for this post.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "update_reply_draft_command".to_string(),
+            description: "Changes the review status or the text of a saved reply draft.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "id".to_string(), r#type: "i64".to_string(), description: "Id of the draft.".to_string() },
+                CommandParameter { name: "status".to_string(), r#type: "Option<String>".to_string(), description: "draft, approved, posted or rejected.".to_string() },
+                CommandParameter { name: "text".to_string(), r#type: "Option<String>".to_string(), description: "New reply text.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "cancel_ai_stream_command".to_string(),
+            description: "Stops a streaming AI request started with a request id.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "request_id".to_string(), r#type: "String".to_string(), description: "The id passed when the stream was started.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "create_chat_session_command".to_string(),
+            description: "Starts a saved chat session for ask_gemini_command.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "title".to_string(), r#type: "Option<String>".to_string(), description: "Session title; left empty it becomes the first question.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "list_chat_sessions_command".to_string(),
+            description: "Lists saved chat sessions with their message counts, most recently used first.".to_string(),
+            parameters: vec![],
+        },
+        CommandInfo {
+            name: "get_chat_messages_command".to_string(),
+            description: "Returns the messages of a chat session, oldest first.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "session_id".to_string(), r#type: "i64".to_string(), description: "The chat session.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "rename_chat_session_command".to_string(),
+            description: "Changes the title of a chat session.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "session_id".to_string(), r#type: "i64".to_string(), description: "The chat session.".to_string() },
+                CommandParameter { name: "title".to_string(), r#type: "String".to_string(), description: "The new title.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "delete_chat_session_command".to_string(),
+            description: "Deletes a chat session and its messages.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "session_id".to_string(), r#type: "i64".to_string(), description: "The chat session.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "export_chat_session_command".to_string(),
+            description: "Saves a chat session to the Reddit_data folder on the desktop and returns the file path.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "session_id".to_string(), r#type: "i64".to_string(), description: "The chat session.".to_string() },
+                CommandParameter { name: "format".to_string(), r#type: "Option<String>".to_string(), description: "markdown (default) or json.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "ask_ai_with_tools_command".to_string(),
+            description: "Answers a question with the AI calling read-only commands (post search, comments, lead counts, topics, semantic search) itself. Notes, assignee and engaged changes come back as pending actions instead of running.".to_string(),
             parameters: vec![
-                CommandParameter { name: "question".to_string(), r#type: "String".to_string(), description: "The question to ask the Gemini AI.".to_string() },
+                CommandParameter { name: "question".to_string(), r#type: "String".to_string(), description: "The question or request.".to_string() },
+            ],
+        },
+        CommandInfo {
+            name: "confirm_ai_actions_command".to_string(),
+            description: "Runs the pending actions the user approved, declines the others and continues the answer.".to_string(),
+            parameters: vec![
+                CommandParameter { name: "conversation_id".to_string(), r#type: "String".to_string(), description: "conversation_id from ask_ai_with_tools_command.".to_string() },
+                CommandParameter { name: "approved".to_string(), r#type: "Vec<String>".to_string(), description: "Ids of the pending actions to run.".to_string() },
             ],
         },
     ]
 }
 
-pub async fn get_all_application_data() -> Result<Value, GeminiError> {
+/// The app's data most relevant to `question`, packed into about `token_budget` tokens.
+pub async fn get_application_data(question: &str, token_budget: usize) -> Result<Value, GeminiError> {
     let db_reader = DBReader::new();
 
     let all_posts = db_reader.get_all_posts()
@@ -287,157 +518,25 @@ pub async fn get_all_application_data() -> Result<Value, GeminiError> {
     let application_log = read_application_log().await?;
     let available_commands = get_available_commands().await;
 
-    let combined_data = json!({
-        "all_posts": all_posts,
-        "all_comments": all_comments,
-        "all_searched_posts": all_searched_posts,
-        "application_log": application_log,
-        "available_commands": available_commands,
+    let data = ApplicationData {
+        posts: all_posts,
+        comments: all_comments,
+        searched_posts: all_searched_posts,
+        log: application_log,
+        commands: available_commands,
         // Add other data sources here as they are integrated
-    });
-
-    Ok(combined_data)
-}
-
-pub async fn ask_gemini(question: &str) -> Result<Value, GeminiError> {
-    // Get all application data
-    let all_app_data = get_all_application_data().await?;
-
-    // Convert data to JSON string
-    let json_all_app_data = serde_json::to_string(&all_app_data).map_err(|e| {
-        GeminiError::DatabaseError(format!("Failed to serialize all application data to JSON: {}", e))
-    })?;
-
-    // Get API key from configuration
-    let config = settings::api_keys::ConfigDirs::read_config()
-        .map_err(|e| GeminiError::ConfigError(e.to_string()))?;
-    let api_key = config.api_keys.gemini_api_key;
-    let model = config.api_keys.gemini_model;
-
-    let client = Client::new();
-    let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-        model, api_key
-    );
-
-    let mut attempts = 0;
-    let max_attempts = 2;
-    let mut last_error = None;
-
-    while attempts < max_attempts {
-        attempts += 1;
-
-        // Create system prompt
-        let system_prompt = format!(
-            "Given the following application data: {}, output the information in the best way possible to answer the questions. Be as thorough as possible and provide URLs when needed.",
-            json_all_app_data
-        );
-
-        log::debug!("Attempt {} - System prompt: {}", attempts, system_prompt);
-
-        // SPINNER SECTION
-        let running = Arc::new(AtomicBool::new(true));
-        let running_clone = running.clone();
-
-        let spinner_handle = thread::spawn(move || {
-            let spinner_chars = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
-            let mut i = 0;
-            while running_clone.load(Ordering::Relaxed) {
-                print!("\r{} Thinking... ", spinner_chars[i]);
-                std::io::stdout().flush().unwrap();
-                i = (i + 1) % spinner_chars.len();
-                thread::sleep(Duration::from_millis(100));
-            }
-            print!("\r{}", " ".repeat(20));
-            print!("\r");
-            std::io::stdout().flush().unwrap();
-        });
-
-        // Make API request
-        // We combine system prompt and question into one message for simplicity and compatibility
-        let combined_text = format!("{}\n\nUser Question: {}", system_prompt, question);
-        let request_body = json!({
-            "contents": [{
-                "parts": [{"text": combined_text}]
-            }]
-        });
-
-        let response = client.post(&url)
-            .json(&request_body)
-            .send()
-            .await;
-
-        // Stop the spinner
-        running.store(false, Ordering::Relaxed);
-        let _ = spinner_handle.join();
-
-        match response {
-            Ok(resp) => {
-                if !resp.status().is_success() {
-                     let error_text = resp.text().await.unwrap_or_default();
-                     last_error = Some(GeminiError::GeminiApiError(format!("API Error: {}", error_text)));
-                     continue;
-                }
-
-                let response_json: Value = match resp.json().await {
-                    Ok(v) => v,
-                    Err(e) => {
-                        last_error = Some(GeminiError::JsonParsingError(format!("Failed to parse API response: {}", e)));
-                        continue;
-                    }
-                };
-
-                // Extract text from Gemini response structure
-                let text_response = response_json["candidates"][0]["content"]["parts"][0]["text"]
-                    .as_str()
-                    .unwrap_or("")
-                    .to_string();
-
-                log::debug!("Raw Gemini API response: {}", text_response);
-                let trimmed_response = text_response.trim();
-
-                // Try to extract JSON from markdown code blocks if present
-                let json_str = if trimmed_response.starts_with("```json") {
-                    trimmed_response
-                        .trim_start_matches("```json")
-                        .trim_end_matches("```")
-                        .trim()
-                } else if trimmed_response.starts_with("```") {
-                    trimmed_response
-                        .trim_start_matches("```")
-                        .trim_end_matches("```")
-                        .trim()
-                } else {
-                    trimmed_response
-                };
-
-                log::debug!("Processed JSON string: {}", json_str);
-
-                match serde_json::from_str(json_str) {
-                    Ok(data) => return Ok(data),
-                    Err(_) => {
-                        // If it's not JSON, treat it as a plain text answer
-                        // The prompt doesn't strictly enforce JSON, so this is expected for general questions
-                        return Ok(json!({ "answer": text_response }));
-                    }
-                }
-            }
-            Err(e) => {
-                last_error = Some(GeminiError::GeminiApiError(format!("Failed to send request: {}", e)));
-            }
-        }
-    }
+    };
 
-    Err(last_error.unwrap_or(GeminiError::GeminiApiError(
-        "Unknown error after multiple attempts".to_string(),
-    )))
+    Ok(context::pack_application_data(question, &data, token_budget))
 }
 
-pub async fn gemini_generate_leads() -> Result<(), GeminiError> {
+/// Has the AI pick and grade leads among the posts matching the lead keywords,
+/// exports the valid ones to Excel and returns them with the records that failed validation.
+pub async fn gemini_generate_leads() -> Result<LeadBatch, GeminiError> {
     let settings = settings::api_keys::ConfigDirs::read_config()
         .map_err(|e| GeminiError::ConfigError(e.to_string()))?;
 
-    let question_vec = settings.api_keys.lead_keywords;
+    let question_vec = &settings.api_keys.lead_keywords;
     if question_vec.is_empty() {
         return Err(GeminiError::ConfigError(
             "No lead keywords found in configuration file. Add default Keywords to match with reddit data and export leads".to_string(),
@@ -447,17 +546,27 @@ pub async fn gemini_generate_leads() -> Result<(), GeminiError> {
     let keywords = question_vec.join(" OR ");
     println!("Matching Keywords: {}", &keywords);
 
+    // Keyword matching picks the candidates locally; the AI only refines them
     let db = database::adding::DB::new()
         .map_err(|e| GeminiError::DatabaseError(format!("Failed to connect to DB: {}", e)))?;
 
     let posts = db.get_db_results()
         .map_err(|e| GeminiError::DatabaseError(format!("Failed to get posts: {}", e)))?;
+    let comments = DBReader::new().get_all_comments()
+        .map_err(|e| GeminiError::DatabaseError(format!("Failed to get comments: {}", e)))?;
+
+    let leads = find_local_leads(&settings.api_keys, posts, comments);
+    if leads.is_empty() {
+        println!("No saved posts match the lead keywords, nothing to refine.");
+        return Ok(LeadBatch::default());
+    }
+    println!("{} posts match the lead keywords locally", leads.len());
 
+    let mut reddits = Vec::new();
     let mut all_comments = Vec::new();
-    for post in &posts {
-        if let Ok(comments) = db.get_post_comments(&post.id.to_string()) {
-            all_comments.extend(comments);
-        }
+    for lead in leads {
+        reddits.push(lead.post);
+        all_comments.extend(lead.matching_comments);
     }
 
     let sentiments = settings.api_keys.sentiment.join(" OR ");
@@ -470,160 +579,68 @@ pub async fn gemini_generate_leads() -> Result<(), GeminiError> {
         2. The post's sentiment OR the overall sentiment of its comments should match one of: {}.
         3. Return ONLY posts that are likely to be leads or business opportunities for inventory management.
 
-        For each matching post, format the result as a JSON object with these fields:
-        - title: the post title
-        - url: the post URL
-        - formatted_date: the post date
-        - relevance: HIGH if it's a strong lead, MEDIUM if potential, LOW if uncertain
-        - subreddit: the subreddit name
-        - sentiment: the detected sentiment of the post
-        - top_comments: an array of up to 3 most relevant comments that match the criteria
-        - comment_sentiment: the overall sentiment of the matching comments
+        Record each matching post in \"leads\" with up to 3 of its most relevant comments.
         ",
         keywords, match_operator, sentiments
     );
 
-    let db = database::adding::DB::new()
-        .map_err(|e| GeminiError::DatabaseError(format!("Failed to connect to DB: {}", e)))?;
-
-    let reddits = db.get_db_results()
-        .map_err(|e| GeminiError::DatabaseError(format!("Failed to get DB results: {}", e)))?;
-
-    let json_reddits = serde_json::to_string(&reddits).map_err(|e| {
-        GeminiError::DatabaseError(format!("Failed to serialize DB data to JSON: {}", e))
-    })?;
-
-    let api_key = settings.api_keys.gemini_api_key;
-    let model = settings.api_keys.gemini_model;
+    let provider = Provider::from_api_keys(&settings.api_keys)
+        .map_err(|e| GeminiError::ConfigError(e.to_string()))?;
 
-    let client = Client::new();
-    let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-        model, api_key
+    let combined_data = serde_json::json!({
+        "posts": reddits,
+        "comments": all_comments
+    });
+    let system_prompt = format!(
+        "You are a lead generation AI analyzing posts and comments. Analyze this data: {}",
+        serde_json::to_string(&combined_data).map_err(|e| {
+            GeminiError::DatabaseError(format!("Failed to serialize DB data to JSON: {}", e))
+        })?
     );
+    log::debug!("System prompt: {}", system_prompt);
 
-    let mut attempts = 0;
-    let max_attempts = 2;
-    let mut last_error = None;
-
-    while attempts < max_attempts {
-        attempts += 1;
-
-        let system_prompt = if attempts > 1 {
-            format!(
-                "You are a lead generation AI. Analyze the following data strictly: {}\n\n        REQUIREMENTS:\n        1. Return ONLY a valid JSON array of objects\n        2. Each object MUST have these fields:\n           - formatted_date: post date (YYYY-MM-DD)\n           - title: exact post title\n           - url: full post URL\n           - relevance: HIGH, MEDIUM, or LOW based on lead quality\n           - subreddit: subreddit name\n           - sentiment: detected sentiment (positive, negative, neutral)\n           - engagement_score: HIGH/MEDIUM/LOW\n\n        Follow these rules:\n        - Use proper JSON format with double quotes\n        - No text outside the JSON\n        - No markdown code blocks\n        - ONLY include posts matching the query criteria",
-                json_reddits
-            )
-        } else {
-            let combined_data = serde_json::json!({
-                "posts": reddits,
-                "comments": all_comments
-            });
-
-            format!(
-                "You are a lead generation AI analyzing posts and comments. Analyze this data: {}\n\n                STRICT OUTPUT REQUIREMENTS:\n                1. Return ONLY a valid JSON array of objects\n                2. Each object MUST have:\n                   - formatted_date: post date (YYYY-MM-DD)\n                   - title: exact post title\n                   - url: full post URL\n                   - relevance: HIGH/MEDIUM/LOW for lead quality\n                   - subreddit: subreddit name\n                   - sentiment: detected sentiment\n                   - top_comments: array of up to 3 most relevant comments, each with 'author', 'text', and 'sentiment' fields.\n                   - comment_sentiment: overall comment sentiment\n                   - engagement_score: HIGH/MEDIUM/LOW based on interaction\n
-                NO text outside JSON. NO markdown blocks.",
-                serde_json::to_string(&combined_data).unwrap_or_default()
-            )
-        };
-
-        log::debug!("Attempt {} - System prompt: {}", attempts, system_prompt);
-
-        // SPINNER SECTION
-        let running = Arc::new(AtomicBool::new(true));
-        let running_clone = running.clone();
-
-        let spinner_handle = thread::spawn(move || {
-            let spinner_chars = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
-            let mut i = 0;
-            while running_clone.load(Ordering::Relaxed) {
-                print!("\r{} Thinking... ", spinner_chars[i]);
-                std::io::stdout().flush().unwrap();
-                i = (i + 1) % spinner_chars.len();
-                thread::sleep(Duration::from_millis(100));
-            }
-            print!("\r{}", " ".repeat(20));
-            print!("\r");
+    // SPINNER SECTION
+    let running = Arc::new(AtomicBool::new(true));
+    let running_clone = running.clone();
+
+    let spinner_handle = thread::spawn(move || {
+        let spinner_chars = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+        let mut i = 0;
+        while running_clone.load(Ordering::Relaxed) {
+            print!("\r{} Thinking... ", spinner_chars[i]);
             std::io::stdout().flush().unwrap();
-        });
-
-        let combined_text = format!("{}\n\n{}", system_prompt, question);
-        let request_body = json!({
-            "contents": [{
-                "parts": [{"text": combined_text}]
-            }]
-        });
-
-        let response = client.post(&url)
-            .json(&request_body)
-            .send()
-            .await;
-
-        // Stop the spinner
-        running.store(false, Ordering::Relaxed);
-        let _ = spinner_handle.join();
-
-        match response {
-            Ok(resp) => {
-                if !resp.status().is_success() {
-                     let error_text = resp.text().await.unwrap_or_default();
-                     last_error = Some(GeminiError::GeminiApiError(format!("API Error: {}", error_text)));
-                     continue;
-                }
-
-                let response_json: Value = match resp.json().await {
-                    Ok(v) => v,
-                    Err(e) => {
-                        last_error = Some(GeminiError::JsonParsingError(format!("Failed to parse API response: {}", e)));
-                        continue;
-                    }
-                };
-
-                // Extract text from Gemini response structure
-                let text_response = response_json["candidates"][0]["content"]["parts"][0]["text"]
-                    .as_str()
-                    .unwrap_or("")
-                    .to_string();
-
-                log::debug!("Raw Gemini API response: {}", text_response);
-                let trimmed_response = text_response.trim();
-
-                // Try to extract JSON from markdown code blocks if present
-                let json_str = if trimmed_response.starts_with("```json") {
-                    trimmed_response
-                        .trim_start_matches("```json")
-                        .trim_end_matches("```")
-                        .trim()
-                } else if trimmed_response.starts_with("```") {
-                    trimmed_response
-                        .trim_start_matches("```")
-                        .trim_end_matches("```")
-                        .trim()
-                } else {
-                    trimmed_response
-                };
-
-                log::debug!("Processed JSON string: {}", json_str);
-
-                excel::export_gemini_to_excel(json_str).expect("Failed to export gemini leads to excel");
-
-                match serde_json::from_str::<Value>(json_str) {
-                    Ok(_) => return Ok(()),
-                    Err(e) => {
-                        last_error = Some(GeminiError::JsonParsingError(format!(
-                            "Failed to parse JSON from API response: {}. Response was: {}",
-                            e, text_response
-                        )));
-                    }
-                }
-            }
-            Err(e) => {
-                last_error = Some(GeminiError::GeminiApiError(format!("Failed to send request: {}", e)));
-            }
+            i = (i + 1) % spinner_chars.len();
+            thread::sleep(Duration::from_millis(100));
         }
+        print!("\r{}", " ".repeat(20));
+        print!("\r");
+        std::io::stdout().flush().unwrap();
+    });
+
+    let messages = [ChatMessage::system(system_prompt), ChatMessage::user(question)];
+    let response = provider
+        .chat_json(&messages, "leads", &structured::lead_schema())
+        .await;
+
+    // Stop the spinner
+    running.store(false, Ordering::Relaxed);
+    let _ = spinner_handle.join();
+
+    let reply = response.map_err(|e| GeminiError::GeminiApiError(e.to_string()))?;
+    log::debug!("Raw AI response: {}", reply);
+
+    let batch = structured::validate_leads(reply);
+    for error in &batch.errors {
+        eprintln!("Lead {} failed validation: {}", error.index, error.message);
     }
+    println!(
+        "{} leads from the AI, {} invalid",
+        batch.leads.len(),
+        batch.errors.len()
+    );
+
+    excel::export_gemini_to_excel(&batch)
+        .map_err(|e| GeminiError::JsonParsingError(format!("Failed to export leads to Excel: {}", e)))?;
 
-    Err(last_error.unwrap_or(GeminiError::GeminiApiError(
-        "Unknown error after multiple attempts".to_string(),
-    )))
+    Ok(batch)
 }
@@ -0,0 +1,134 @@
+// Saved AI conversations. Each question asked in a session is sent with the
+// turns before it, newest first until the history budget runs out.
+use serde::{Deserialize, Serialize};
+
+use crate::ai::context::estimate_tokens;
+use crate::ai::providers::ChatMessage;
+
+// Share of the prompt budget prior turns may take; the app data gets the rest
+pub const HISTORY_SHARE: f64 = 0.5;
+// Characters of the first question used as the title of an untitled session
+const TITLE_CHARS: usize = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatSession {
+    pub id: i64,
+    pub title: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub message_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredChatMessage {
+    pub id: i64,
+    pub session_id: i64,
+    // "user" or "assistant"
+    pub role: String,
+    pub content: String,
+    pub created_at: i64,
+}
+
+/// The latest turns of `stored` that fit in `budget` tokens, oldest first.
+/// Whole question/answer pairs are kept so the model never sees a lone answer.
+pub fn history_messages(stored: &[StoredChatMessage], budget: usize) -> Vec<ChatMessage> {
+    let mut kept = Vec::new();
+    let mut used = 0;
+    for pair in stored.chunks(2).rev() {
+        let tokens: usize = pair
+            .iter()
+            .map(|message| estimate_tokens(&message.content))
+            .sum();
+        if used + tokens > budget {
+            break;
+        }
+        used += tokens;
+        kept.extend(pair.iter().rev());
+    }
+
+    kept.into_iter()
+        .rev()
+        .map(|message| match message.role.as_str() {
+            "assistant" => ChatMessage::assistant(message.content.as_str()),
+            _ => ChatMessage::user(message.content.as_str()),
+        })
+        .collect()
+}
+
+/// Title for a session from its first question.
+pub fn title_from_question(question: &str) -> String {
+    let question = question.split_whitespace().collect::<Vec<_>>().join(" ");
+    if question.chars().count() <= TITLE_CHARS {
+        return question;
+    }
+    // One more character shows whether the cut falls between words
+    let cut: String = question.chars().take(TITLE_CHARS + 1).collect();
+    let cut = cut.rsplit_once(' ').map(|(head, _)| head).unwrap_or(&cut);
+    format!("{}…", cut.trim_end())
+}
+
+/// The conversation as Markdown, one heading per turn.
+pub fn to_markdown(session: &ChatSession, messages: &[StoredChatMessage]) -> String {
+    let mut markdown = format!("# {}\n", session.title);
+    for message in messages {
+        let speaker = if message.role == "assistant" {
+            "Assistant"
+        } else {
+            "You"
+        };
+        let date = chrono::DateTime::from_timestamp(message.created_at, 0)
+            .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        markdown.push_str(&format!(
+            "\n## {} ({})\n\n{}\n",
+            speaker,
+            date,
+            message.content.trim()
+        ));
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::providers::ChatRole;
+
+    fn message(id: i64, role: &str, content: &str) -> StoredChatMessage {
+        StoredChatMessage {
+            id,
+            session_id: 1,
+            role: role.to_string(),
+            content: content.to_string(),
+            created_at: 1_700_000_000 + id,
+        }
+    }
+
+    #[test]
+    fn only_the_latest_pairs_that_fit_are_sent() {
+        let stored = vec![
+            message(1, "user", &"old question ".repeat(40)),
+            message(2, "assistant", &"old answer ".repeat(40)),
+            message(3, "user", "Which subreddit has the most leads?"),
+            message(4, "assistant", "r/smallbusiness, with 12."),
+        ];
+
+        let history = history_messages(&stored, 30);
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].role, ChatRole::User);
+        assert_eq!(history[0].content, "Which subreddit has the most leads?");
+        assert_eq!(history[1].role, ChatRole::Assistant);
+        assert_eq!(history_messages(&stored, 10_000).len(), 4);
+        assert!(history_messages(&stored, 5).is_empty());
+    }
+
+    #[test]
+    fn long_questions_are_cut_at_a_word_for_titles() {
+        assert_eq!(title_from_question("  Any   leads today? "), "Any leads today?");
+        let title = title_from_question(
+            "Which of the posts saved this week ask for an inventory tool that works offline?",
+        );
+        assert_eq!(title, "Which of the posts saved this week ask for an inventory tool…");
+    }
+}
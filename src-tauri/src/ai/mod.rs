@@ -1,2 +1,11 @@
 pub mod gemini;
 pub mod adapter;
+pub mod context;
+pub mod embeddings;
+pub mod history;
+pub mod providers;
+pub mod replies;
+pub mod reply_queue;
+pub mod stream;
+pub mod structured;
+pub mod tools;
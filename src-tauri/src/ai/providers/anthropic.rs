@@ -0,0 +1,283 @@
+// Anthropic Messages API. System messages go in the top-level `system` field.
+use reqwest::{Client, RequestBuilder};
+use serde_json::{json, Value};
+
+use super::{
+    check_status, read_events, AiError, AiProvider, ChatMessage, ChatRole, ModelInfo, ToolCall,
+    ToolSpec,
+};
+
+const API_VERSION: &str = "2023-06-01";
+const MAX_TOKENS: u32 = 4096;
+
+pub struct AnthropicProvider {
+    client: Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+impl AnthropicProvider {
+    pub fn new(base_url: &str, api_key: &str, model: &str) -> Self {
+        AnthropicProvider {
+            client: Client::new(),
+            base_url: base_url.trim().trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn request(
+        &self,
+        messages: &[ChatMessage],
+        stream: bool,
+        tools: &[ToolSpec],
+        forced_tool: Option<&str>,
+    ) -> RequestBuilder {
+        let system: Vec<&str> = messages
+            .iter()
+            .filter(|message| message.role == ChatRole::System)
+            .map(|message| message.content.as_str())
+            .collect();
+
+        let mut body = json!({
+            "model": self.model,
+            "max_tokens": MAX_TOKENS,
+            "messages": turns(messages),
+            "stream": stream,
+        });
+        if !system.is_empty() {
+            body["system"] = json!(system.join("\n\n"));
+        }
+        if !tools.is_empty() {
+            body["tools"] = tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "input_schema": tool.parameters,
+                    })
+                })
+                .collect();
+        }
+        if let Some(name) = forced_tool {
+            body["tool_choice"] = json!({ "type": "tool", "name": name });
+        }
+
+        self.authorize(self.client.post(format!("{}/v1/messages", self.base_url)))
+            .json(&body)
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        request
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+    }
+}
+
+// Conversation turns without system messages. Tool calls become tool_use blocks
+// and their results tool_result blocks in a user turn, one turn per batch.
+fn turns(messages: &[ChatMessage]) -> Vec<Value> {
+    let mut turns: Vec<Value> = Vec::new();
+    for message in messages {
+        match message.role {
+            ChatRole::System => {}
+            ChatRole::User => turns.push(json!({ "role": "user", "content": message.content })),
+            ChatRole::Assistant if message.tool_calls.is_empty() => {
+                turns.push(json!({ "role": "assistant", "content": message.content }))
+            }
+            ChatRole::Assistant => {
+                let mut blocks = Vec::new();
+                if !message.content.is_empty() {
+                    blocks.push(json!({ "type": "text", "text": message.content }));
+                }
+                for call in &message.tool_calls {
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call.id,
+                        "name": call.name,
+                        "input": call.arguments,
+                    }));
+                }
+                turns.push(json!({ "role": "assistant", "content": blocks }));
+            }
+            ChatRole::Tool => {
+                let block = json!({
+                    "type": "tool_result",
+                    "tool_use_id": message.tool_call_id,
+                    "content": message.content,
+                });
+                match turns.last_mut() {
+                    Some(last) if last["role"] == "user" && last["content"].is_array() => {
+                        last["content"].as_array_mut().unwrap().push(block)
+                    }
+                    _ => turns.push(json!({ "role": "user", "content": [block] })),
+                }
+            }
+        }
+    }
+    turns
+}
+
+impl AiProvider for AnthropicProvider {
+    async fn chat(&self, messages: &[ChatMessage]) -> Result<String, AiError> {
+        let response = check_status(self.request(messages, false, &[], None).send().await?).await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AiError::ResponseError(e.to_string()))?;
+
+        let blocks = body["content"]
+            .as_array()
+            .ok_or_else(|| AiError::ResponseError(format!("No content in response: {}", body)))?;
+        Ok(blocks
+            .iter()
+            .filter(|block| block["type"] == "text")
+            .filter_map(|block| block["text"].as_str())
+            .collect())
+    }
+
+    async fn chat_stream<F>(
+        &self,
+        messages: &[ChatMessage],
+        mut on_chunk: F,
+    ) -> Result<String, AiError>
+    where
+        F: FnMut(&str) + Send,
+    {
+        let response = check_status(self.request(messages, true, &[], None).send().await?).await?;
+
+        let mut reply = String::new();
+        read_events(response, |data| {
+            let event: Value =
+                serde_json::from_str(data).map_err(|e| AiError::ResponseError(e.to_string()))?;
+            match event["type"].as_str() {
+                Some("content_block_delta") => {
+                    if let Some(text) = event["delta"]["text"].as_str() {
+                        reply.push_str(text);
+                        on_chunk(text);
+                    }
+                    Ok(true)
+                }
+                Some("message_stop") => Ok(false),
+                Some("error") => Err(AiError::ApiError(
+                    event["error"]["message"]
+                        .as_str()
+                        .unwrap_or("stream error")
+                        .to_string(),
+                )),
+                _ => Ok(true),
+            }
+        })
+        .await?;
+
+        Ok(reply)
+    }
+
+    async fn chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+    ) -> Result<ChatMessage, AiError> {
+        let response =
+            check_status(self.request(messages, false, tools, None).send().await?).await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AiError::ResponseError(e.to_string()))?;
+
+        let blocks = body["content"]
+            .as_array()
+            .ok_or_else(|| AiError::ResponseError(format!("No content in response: {}", body)))?;
+        let mut reply = ChatMessage::assistant(
+            blocks
+                .iter()
+                .filter(|block| block["type"] == "text")
+                .filter_map(|block| block["text"].as_str())
+                .collect::<String>(),
+        );
+        reply.tool_calls = blocks
+            .iter()
+            .filter(|block| block["type"] == "tool_use")
+            .map(|block| ToolCall {
+                id: block["id"].as_str().unwrap_or_default().to_string(),
+                name: block["name"].as_str().unwrap_or_default().to_string(),
+                arguments: block["input"].clone(),
+                signature: None,
+            })
+            .collect();
+        Ok(reply)
+    }
+
+    // No JSON mode, so the reply is a forced call to a tool taking `schema`
+    async fn chat_json(
+        &self,
+        messages: &[ChatMessage],
+        name: &str,
+        schema: &Value,
+    ) -> Result<Value, AiError> {
+        let tool = ToolSpec {
+            name: name.to_string(),
+            description: "Record the answer.".to_string(),
+            parameters: schema.clone(),
+        };
+        let response = check_status(
+            self.request(messages, false, &[tool], Some(name))
+                .send()
+                .await?,
+        )
+        .await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AiError::ResponseError(e.to_string()))?;
+
+        body["content"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|block| block["type"] == "tool_use")
+            .map(|block| block["input"].clone())
+            .ok_or_else(|| AiError::ResponseError(format!("No tool use in response: {}", body)))
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, AiError> {
+        let response = check_status(
+            self.authorize(self.client.get(format!("{}/v1/models", self.base_url)))
+                .query(&[("limit", "100")])
+                .send()
+                .await?,
+        )
+        .await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AiError::ResponseError(e.to_string()))?;
+
+        // Newest first, as the API returns them
+        Ok(body["data"]
+            .as_array()
+            .map(|models| {
+                models
+                    .iter()
+                    .filter_map(|model| {
+                        let mut info = ModelInfo::new(model["id"].as_str()?);
+                        info.display_name = model["display_name"].as_str().map(str::to_string);
+                        info.context_window = model["max_input_tokens"].as_i64();
+                        info.max_output_tokens = model["max_tokens"].as_i64();
+                        Some(info)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
@@ -0,0 +1,343 @@
+// Gemini generateContent REST API. System messages are folded into the first
+// user turn, as older models such as gemini-pro reject systemInstruction.
+use std::collections::HashMap;
+
+use reqwest::{Client, RequestBuilder};
+use serde_json::{json, Value};
+
+use super::{
+    check_status, read_events, vector, AiError, AiProvider, ChatMessage, ChatRole, ModelInfo,
+    ToolCall, ToolSpec,
+};
+
+pub struct GeminiProvider {
+    client: Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+impl GeminiProvider {
+    pub fn new(base_url: &str, api_key: &str, model: &str) -> Self {
+        GeminiProvider {
+            client: Client::new(),
+            base_url: base_url.trim().trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            model: model.trim_start_matches("models/").to_string(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn request(
+        &self,
+        messages: &[ChatMessage],
+        method: &str,
+        tools: &[ToolSpec],
+        schema: Option<&Value>,
+    ) -> RequestBuilder {
+        let mut system = String::new();
+        let mut contents: Vec<Value> = Vec::new();
+        // Function responses are matched to calls by name, Gemini has no call ids
+        let mut call_names: HashMap<&str, &str> = HashMap::new();
+        for message in messages {
+            match message.role {
+                ChatRole::System => {
+                    system.push_str(&message.content);
+                    system.push_str("\n\n");
+                }
+                ChatRole::User => {
+                    let text = format!("{}{}", std::mem::take(&mut system), message.content);
+                    contents.push(json!({ "role": "user", "parts": [{ "text": text }] }));
+                }
+                ChatRole::Assistant => {
+                    let mut parts = Vec::new();
+                    if !message.content.is_empty() || message.tool_calls.is_empty() {
+                        parts.push(json!({ "text": message.content }));
+                    }
+                    for call in &message.tool_calls {
+                        call_names.insert(&call.id, &call.name);
+                        let mut part = json!({
+                            "functionCall": { "name": call.name, "args": call.arguments },
+                        });
+                        if let Some(signature) = &call.signature {
+                            part["thoughtSignature"] = json!(signature);
+                        }
+                        parts.push(part);
+                    }
+                    contents.push(json!({ "role": "model", "parts": parts }));
+                }
+                ChatRole::Tool => {
+                    let id = message.tool_call_id.as_deref().unwrap_or_default();
+                    // The response has to be an object
+                    let result = serde_json::from_str(&message.content)
+                        .unwrap_or_else(|_| Value::String(message.content.clone()));
+                    let part = json!({
+                        "functionResponse": {
+                            "name": call_names.get(id).copied().unwrap_or(id),
+                            "response": { "result": result },
+                        },
+                    });
+                    // All responses to one turn's calls go back together
+                    match contents.last_mut() {
+                        Some(last)
+                            if last["role"] == "user"
+                                && last["parts"][0]["functionResponse"].is_object() =>
+                        {
+                            last["parts"].as_array_mut().unwrap().push(part)
+                        }
+                        _ => contents.push(json!({ "role": "user", "parts": [part] })),
+                    }
+                }
+            }
+        }
+        // Only instructions, no question
+        if !system.is_empty() {
+            contents.push(json!({ "role": "user", "parts": [{ "text": system.trim_end() }] }));
+        }
+
+        let mut body = json!({ "contents": contents });
+        if !tools.is_empty() {
+            let declarations: Vec<Value> = tools
+                .iter()
+                .map(|tool| {
+                    let mut declaration =
+                        json!({ "name": tool.name, "description": tool.description });
+                    // An empty parameters object is rejected
+                    if tool.parameters["properties"]
+                        .as_object()
+                        .is_some_and(|properties| !properties.is_empty())
+                    {
+                        declaration["parameters"] = tool.parameters.clone();
+                    }
+                    declaration
+                })
+                .collect();
+            body["tools"] = json!([{ "functionDeclarations": declarations }]);
+        }
+        if let Some(schema) = schema {
+            body["generationConfig"] = json!({
+                "responseMimeType": "application/json",
+                "responseSchema": schema,
+            });
+        }
+
+        self.client
+            .post(format!(
+                "{}/models/{}:{}",
+                self.base_url, self.model, method
+            ))
+            .query(&[("key", self.api_key.as_str())])
+            .json(&body)
+    }
+
+    /// One vector per text, with the model as an embedding model.
+    pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AiError> {
+        let requests: Vec<Value> = texts
+            .iter()
+            .map(|text| {
+                json!({
+                    "model": format!("models/{}", self.model),
+                    "content": { "parts": [{ "text": text }] },
+                })
+            })
+            .collect();
+        let response = check_status(
+            self.client
+                .post(format!(
+                    "{}/models/{}:batchEmbedContents",
+                    self.base_url, self.model
+                ))
+                .query(&[("key", self.api_key.as_str())])
+                .json(&json!({ "requests": requests }))
+                .send()
+                .await?,
+        )
+        .await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AiError::ResponseError(e.to_string()))?;
+
+        let vectors: Vec<Vec<f32>> = body["embeddings"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|embedding| vector(&embedding["values"]))
+            .collect();
+        if vectors.len() != texts.len() {
+            return Err(AiError::ResponseError(format!(
+                "Expected {} embeddings, got {}",
+                texts.len(),
+                vectors.len()
+            )));
+        }
+        Ok(vectors)
+    }
+}
+
+// Text of the first candidate
+fn candidate_text(body: &Value) -> Option<String> {
+    let parts = body["candidates"][0]["content"]["parts"].as_array()?;
+    Some(
+        parts
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect(),
+    )
+}
+
+impl AiProvider for GeminiProvider {
+    async fn chat(&self, messages: &[ChatMessage]) -> Result<String, AiError> {
+        let response = check_status(
+            self.request(messages, "generateContent", &[], None)
+                .send()
+                .await?,
+        )
+        .await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AiError::ResponseError(e.to_string()))?;
+
+        candidate_text(&body)
+            .ok_or_else(|| AiError::ResponseError(format!("No candidates in response: {}", body)))
+    }
+
+    async fn chat_stream<F>(
+        &self,
+        messages: &[ChatMessage],
+        mut on_chunk: F,
+    ) -> Result<String, AiError>
+    where
+        F: FnMut(&str) + Send,
+    {
+        let response = check_status(
+            self.request(messages, "streamGenerateContent", &[], None)
+                .query(&[("alt", "sse")])
+                .send()
+                .await?,
+        )
+        .await?;
+
+        let mut reply = String::new();
+        read_events(response, |data| {
+            let event: Value =
+                serde_json::from_str(data).map_err(|e| AiError::ResponseError(e.to_string()))?;
+            if let Some(text) = candidate_text(&event).filter(|text| !text.is_empty()) {
+                reply.push_str(&text);
+                on_chunk(&text);
+            }
+            Ok(true)
+        })
+        .await?;
+
+        Ok(reply)
+    }
+
+    async fn chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+    ) -> Result<ChatMessage, AiError> {
+        let response = check_status(
+            self.request(messages, "generateContent", tools, None)
+                .send()
+                .await?,
+        )
+        .await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AiError::ResponseError(e.to_string()))?;
+
+        let parts = body["candidates"][0]["content"]["parts"]
+            .as_array()
+            .ok_or_else(|| {
+                AiError::ResponseError(format!("No candidates in response: {}", body))
+            })?;
+        let mut reply = ChatMessage::assistant(candidate_text(&body).unwrap_or_default());
+        reply.tool_calls = parts
+            .iter()
+            .filter(|part| part["functionCall"].is_object())
+            .enumerate()
+            .map(|(i, part)| ToolCall {
+                id: format!("call_{}", i),
+                name: part["functionCall"]["name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                arguments: part["functionCall"]["args"].clone(),
+                signature: part["thoughtSignature"].as_str().map(str::to_string),
+            })
+            .collect();
+        Ok(reply)
+    }
+
+    async fn chat_json(
+        &self,
+        messages: &[ChatMessage],
+        _name: &str,
+        schema: &Value,
+    ) -> Result<Value, AiError> {
+        let response = check_status(
+            self.request(messages, "generateContent", &[], Some(schema))
+                .send()
+                .await?,
+        )
+        .await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AiError::ResponseError(e.to_string()))?;
+
+        let text = candidate_text(&body).ok_or_else(|| {
+            AiError::ResponseError(format!("No candidates in response: {}", body))
+        })?;
+        serde_json::from_str(&text)
+            .map_err(|e| AiError::ResponseError(format!("Invalid JSON in reply: {}", e)))
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, AiError> {
+        let response = check_status(
+            self.client
+                .get(format!("{}/models", self.base_url))
+                .query(&[("key", self.api_key.as_str()), ("pageSize", "1000")])
+                .send()
+                .await?,
+        )
+        .await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AiError::ResponseError(e.to_string()))?;
+
+        Ok(body["models"]
+            .as_array()
+            .map(|models| {
+                models
+                    .iter()
+                    .filter(|model| {
+                        model["supportedGenerationMethods"]
+                            .as_array()
+                            .is_some_and(|methods| methods.iter().any(|m| m == "generateContent"))
+                    })
+                    .filter_map(|model| {
+                        let name = model["name"].as_str()?;
+                        let mut info = ModelInfo::new(name.trim_start_matches("models/"));
+                        info.display_name = model["displayName"].as_str().map(str::to_string);
+                        info.context_window = model["inputTokenLimit"].as_i64();
+                        info.max_output_tokens = model["outputTokenLimit"].as_i64();
+                        Some(info)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
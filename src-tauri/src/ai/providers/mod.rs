@@ -0,0 +1,417 @@
+// One interface over the AI backends. `ai_provider` picks the backend:
+// "gemini" (default), "openai", "anthropic", or "local" for any
+// OpenAI-compatible server such as Ollama or llama.cpp, so everything can run
+// on the user's machine.
+use std::fmt;
+use std::future::Future;
+
+use reqwest::Response;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::settings::api_keys::ApiKeys;
+
+mod anthropic;
+mod gemini;
+mod openai;
+
+#[cfg(test)]
+mod provider_tests;
+
+pub use anthropic::AnthropicProvider;
+pub use gemini::GeminiProvider;
+pub use openai::OpenAiProvider;
+
+#[derive(Debug)]
+pub enum AiError {
+    ConfigError(String),
+    RequestError(String),
+    ApiError(String),
+    ResponseError(String),
+}
+
+impl fmt::Display for AiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AiError::ConfigError(e) => write!(f, "Configuration error: {}", e),
+            AiError::RequestError(e) => write!(f, "Request failed: {}", e),
+            AiError::ApiError(e) => write!(f, "AI API error: {}", e),
+            AiError::ResponseError(e) => write!(f, "Unexpected AI response: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AiError {}
+
+impl From<reqwest::Error> for AiError {
+    fn from(e: reqwest::Error) -> Self {
+        AiError::RequestError(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+    // The result of a tool call, sent back to the model
+    Tool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+    // Tools the assistant asked to run in this turn
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    // For Tool messages, the call they answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatMessage {
+    fn new(role: ChatRole, content: impl Into<String>) -> Self {
+        ChatMessage {
+            role,
+            content: content.into(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        ChatMessage::new(ChatRole::System, content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        ChatMessage::new(ChatRole::User, content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        ChatMessage::new(ChatRole::Assistant, content)
+    }
+
+    pub fn tool_result(call_id: &str, content: impl Into<String>) -> Self {
+        ChatMessage {
+            tool_call_id: Some(call_id.to_string()),
+            ..ChatMessage::new(ChatRole::Tool, content)
+        }
+    }
+}
+
+/// A function the model may call, with its parameters as a JSON schema.
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+    // Opaque data some providers need echoed back with the call (Gemini thought signatures)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+// A model as listed by the provider. Capabilities are None when the API doesn't say.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    pub display_name: Option<String>,
+    // Input tokens the model accepts
+    pub context_window: Option<i64>,
+    pub max_output_tokens: Option<i64>,
+    // Whether a JSON response format can be requested
+    pub supports_json: Option<bool>,
+}
+
+impl ModelInfo {
+    fn new(id: &str) -> Self {
+        ModelInfo {
+            id: id.to_string(),
+            display_name: None,
+            context_window: None,
+            max_output_tokens: None,
+            supports_json: None,
+        }
+    }
+}
+
+pub trait AiProvider {
+    /// Sends the conversation and returns the complete reply.
+    fn chat(
+        &self,
+        messages: &[ChatMessage],
+    ) -> impl Future<Output = Result<String, AiError>> + Send;
+
+    /// Like `chat`, but hands each piece of the reply to `on_chunk` as it arrives.
+    fn chat_stream<F>(
+        &self,
+        messages: &[ChatMessage],
+        on_chunk: F,
+    ) -> impl Future<Output = Result<String, AiError>> + Send
+    where
+        F: FnMut(&str) + Send;
+
+    /// Like `chat`, but the reply may ask to run some of `tools` in its `tool_calls`.
+    fn chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+    ) -> impl Future<Output = Result<ChatMessage, AiError>> + Send;
+
+    /// Asks for a reply matching `schema`, which describes an object, using the
+    /// provider's structured output mode, and returns it parsed.
+    fn chat_json(
+        &self,
+        messages: &[ChatMessage],
+        name: &str,
+        schema: &Value,
+    ) -> impl Future<Output = Result<Value, AiError>> + Send;
+
+    /// Models that can be used for chat.
+    fn list_models(&self) -> impl Future<Output = Result<Vec<ModelInfo>, AiError>> + Send;
+}
+
+/// The backend selected in the settings.
+pub enum Provider {
+    Gemini(GeminiProvider),
+    OpenAi(OpenAiProvider),
+    Anthropic(AnthropicProvider),
+}
+
+impl Provider {
+    /// Where the models come from, to tell cached model lists apart.
+    pub fn endpoint(&self) -> String {
+        match self {
+            Provider::Gemini(provider) => format!("gemini {}", provider.base_url()),
+            Provider::OpenAi(provider) => format!("openai {}", provider.base_url()),
+            Provider::Anthropic(provider) => format!("anthropic {}", provider.base_url()),
+        }
+    }
+
+    /// The model requests are sent to.
+    pub fn model(&self) -> &str {
+        match self {
+            Provider::Gemini(provider) => provider.model(),
+            Provider::OpenAi(provider) => provider.model(),
+            Provider::Anthropic(provider) => provider.model(),
+        }
+    }
+
+    pub fn from_api_keys(api_keys: &ApiKeys) -> Result<Self, AiError> {
+        match api_keys.ai_provider.trim().to_lowercase().as_str() {
+            "openai" => Ok(Provider::OpenAi(OpenAiProvider::new(
+                &api_keys.openai_base_url,
+                required_key(&api_keys.openai_api_key, "OpenAI")?,
+                or_default(&api_keys.openai_model, "gpt-4o"),
+            ))),
+            "anthropic" | "claude" => Ok(Provider::Anthropic(AnthropicProvider::new(
+                &api_keys.anthropic_base_url,
+                required_key(&api_keys.anthropic_api_key, "Anthropic")?,
+                or_default(&api_keys.anthropic_model, "claude-sonnet-4-5"),
+            ))),
+            // Local servers usually don't check the key
+            "local" | "ollama" | "llamacpp" | "llama.cpp" => {
+                if api_keys.local_ai_model.trim().is_empty() {
+                    return Err(AiError::ConfigError(
+                        "No model configured for the local AI server".to_string(),
+                    ));
+                }
+                Ok(Provider::OpenAi(OpenAiProvider::new(
+                    &api_keys.local_ai_base_url,
+                    api_keys.local_ai_api_key.trim(),
+                    api_keys.local_ai_model.trim(),
+                )))
+            }
+            _ => Ok(Provider::Gemini(GeminiProvider::new(
+                &api_keys.gemini_base_url,
+                required_key(&api_keys.gemini_api_key, "Gemini")?,
+                or_default(&api_keys.gemini_model, "gemini-pro"),
+            ))),
+        }
+    }
+}
+
+/// The backend that turns text into vectors for semantic search. Anthropic has
+/// no embeddings API, so `embedding_provider` can point elsewhere.
+pub enum Embedder {
+    Gemini(GeminiProvider),
+    OpenAi(OpenAiProvider),
+}
+
+impl Embedder {
+    pub fn from_api_keys(api_keys: &ApiKeys) -> Result<Self, AiError> {
+        let provider = if api_keys.embedding_provider.trim().is_empty() {
+            &api_keys.ai_provider
+        } else {
+            &api_keys.embedding_provider
+        };
+        let model = &api_keys.embedding_model;
+        match provider.trim().to_lowercase().as_str() {
+            "openai" => Ok(Embedder::OpenAi(OpenAiProvider::new(
+                &api_keys.openai_base_url,
+                required_key(&api_keys.openai_api_key, "OpenAI")?,
+                or_default(model, "text-embedding-3-small"),
+            ))),
+            "anthropic" | "claude" => Err(AiError::ConfigError(
+                "Anthropic has no embeddings API, set embedding_provider to gemini, openai or local"
+                    .to_string(),
+            )),
+            "local" | "ollama" | "llamacpp" | "llama.cpp" => Ok(Embedder::OpenAi(
+                OpenAiProvider::new(
+                    &api_keys.local_ai_base_url,
+                    api_keys.local_ai_api_key.trim(),
+                    or_default(model, "nomic-embed-text"),
+                ),
+            )),
+            _ => Ok(Embedder::Gemini(GeminiProvider::new(
+                &api_keys.gemini_base_url,
+                required_key(&api_keys.gemini_api_key, "Gemini")?,
+                or_default(model, "text-embedding-004"),
+            ))),
+        }
+    }
+
+    /// Vectors from different models can't be compared, so each is stored with it.
+    pub fn model(&self) -> &str {
+        match self {
+            Embedder::Gemini(provider) => provider.model(),
+            Embedder::OpenAi(provider) => provider.model(),
+        }
+    }
+
+    pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AiError> {
+        match self {
+            Embedder::Gemini(provider) => provider.embed(texts).await,
+            Embedder::OpenAi(provider) => provider.embed(texts).await,
+        }
+    }
+}
+
+impl AiProvider for Provider {
+    async fn chat(&self, messages: &[ChatMessage]) -> Result<String, AiError> {
+        match self {
+            Provider::Gemini(provider) => provider.chat(messages).await,
+            Provider::OpenAi(provider) => provider.chat(messages).await,
+            Provider::Anthropic(provider) => provider.chat(messages).await,
+        }
+    }
+
+    async fn chat_stream<F>(&self, messages: &[ChatMessage], on_chunk: F) -> Result<String, AiError>
+    where
+        F: FnMut(&str) + Send,
+    {
+        match self {
+            Provider::Gemini(provider) => provider.chat_stream(messages, on_chunk).await,
+            Provider::OpenAi(provider) => provider.chat_stream(messages, on_chunk).await,
+            Provider::Anthropic(provider) => provider.chat_stream(messages, on_chunk).await,
+        }
+    }
+
+    async fn chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+    ) -> Result<ChatMessage, AiError> {
+        match self {
+            Provider::Gemini(provider) => provider.chat_with_tools(messages, tools).await,
+            Provider::OpenAi(provider) => provider.chat_with_tools(messages, tools).await,
+            Provider::Anthropic(provider) => provider.chat_with_tools(messages, tools).await,
+        }
+    }
+
+    async fn chat_json(
+        &self,
+        messages: &[ChatMessage],
+        name: &str,
+        schema: &Value,
+    ) -> Result<Value, AiError> {
+        match self {
+            Provider::Gemini(provider) => provider.chat_json(messages, name, schema).await,
+            Provider::OpenAi(provider) => provider.chat_json(messages, name, schema).await,
+            Provider::Anthropic(provider) => provider.chat_json(messages, name, schema).await,
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, AiError> {
+        match self {
+            Provider::Gemini(provider) => provider.list_models().await,
+            Provider::OpenAi(provider) => provider.list_models().await,
+            Provider::Anthropic(provider) => provider.list_models().await,
+        }
+    }
+}
+
+fn required_key<'a>(key: &'a str, provider: &str) -> Result<&'a str, AiError> {
+    let key = key.trim();
+    if key.is_empty() || key == "CHANGE_ME" {
+        return Err(AiError::ConfigError(format!(
+            "{} API Key not configured",
+            provider
+        )));
+    }
+    Ok(key)
+}
+
+fn or_default<'a>(model: &'a str, default: &'a str) -> &'a str {
+    let model = model.trim();
+    if model.is_empty() {
+        default
+    } else {
+        model
+    }
+}
+
+// A JSON array of numbers as an embedding
+fn vector(value: &Value) -> Option<Vec<f32>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|x| x.as_f64().map(|x| x as f32))
+        .collect()
+}
+
+// Turns a non-2xx response into an ApiError carrying the body
+async fn check_status(response: Response) -> Result<Response, AiError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(AiError::ApiError(format!("{}: {}", status, body.trim())))
+}
+
+// Calls `on_data` with the payload of each `data:` line of a server-sent event
+// stream until it returns false or the stream ends
+async fn read_events<F>(mut response: Response, mut on_data: F) -> Result<(), AiError>
+where
+    F: FnMut(&str) -> Result<bool, AiError>,
+{
+    let mut buffer: Vec<u8> = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        buffer.extend_from_slice(&chunk);
+        while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(data) = line.trim_end().strip_prefix("data:") {
+                if !on_data(data.trim_start())? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    // A last event without a trailing newline
+    let line = String::from_utf8_lossy(&buffer);
+    if let Some(data) = line.trim_end().strip_prefix("data:") {
+        on_data(data.trim_start())?;
+    }
+    Ok(())
+}
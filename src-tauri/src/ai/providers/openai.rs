@@ -0,0 +1,313 @@
+// OpenAI chat completions. Ollama, llama.cpp and other local servers expose
+// the same API under their own base URL, so they use this backend too.
+use reqwest::{Client, RequestBuilder};
+use serde_json::{json, Value};
+
+use super::{
+    check_status, read_events, vector, AiError, AiProvider, ChatMessage, ChatRole, ModelInfo,
+    ToolCall, ToolSpec,
+};
+
+// Ids with these are embedding, audio, image or moderation models
+const NON_CHAT_MODELS: &[&str] = &[
+    "embed",
+    "whisper",
+    "tts",
+    "dall-e",
+    "moderation",
+    "transcribe",
+    "image",
+    "davinci",
+    "babbage",
+];
+
+pub struct OpenAiProvider {
+    client: Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+impl OpenAiProvider {
+    pub fn new(base_url: &str, api_key: &str, model: &str) -> Self {
+        OpenAiProvider {
+            client: Client::new(),
+            base_url: base_url.trim().trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn request(
+        &self,
+        messages: &[ChatMessage],
+        stream: bool,
+        tools: &[ToolSpec],
+        schema: Option<(&str, &Value)>,
+    ) -> RequestBuilder {
+        let mut body = json!({
+            "model": self.model,
+            "messages": messages.iter().map(wire_message).collect::<Vec<_>>(),
+            "stream": stream,
+        });
+        if !tools.is_empty() {
+            body["tools"] = tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "type": "function",
+                        "function": {
+                            "name": tool.name,
+                            "description": tool.description,
+                            "parameters": tool.parameters,
+                        },
+                    })
+                })
+                .collect();
+        }
+        // Not strict: strict mode requires every property and rejects optional ones
+        if let Some((name, schema)) = schema {
+            body["response_format"] = json!({
+                "type": "json_schema",
+                "json_schema": { "name": name, "schema": schema, "strict": false },
+            });
+        }
+        self.authorize(
+            self.client
+                .post(format!("{}/chat/completions", self.base_url))
+                .json(&body),
+        )
+    }
+
+    // Keyless local servers get no Authorization header at all
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        if self.api_key.is_empty() {
+            request
+        } else {
+            request.bearer_auth(&self.api_key)
+        }
+    }
+
+    /// One vector per text, with the model as an embedding model.
+    pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AiError> {
+        let body = json!({ "model": self.model, "input": texts });
+        let response = check_status(
+            self.authorize(
+                self.client
+                    .post(format!("{}/embeddings", self.base_url))
+                    .json(&body),
+            )
+            .send()
+            .await?,
+        )
+        .await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AiError::ResponseError(e.to_string()))?;
+
+        // Entries carry the index of their input and aren't guaranteed to be in order
+        let mut data: Vec<&Value> = body["data"].as_array().into_iter().flatten().collect();
+        data.sort_by_key(|entry| entry["index"].as_u64().unwrap_or(0));
+        let vectors: Vec<Vec<f32>> = data
+            .iter()
+            .filter_map(|entry| vector(&entry["embedding"]))
+            .collect();
+        if vectors.len() != texts.len() {
+            return Err(AiError::ResponseError(format!(
+                "Expected {} embeddings, got {}",
+                texts.len(),
+                vectors.len()
+            )));
+        }
+        Ok(vectors)
+    }
+}
+
+impl AiProvider for OpenAiProvider {
+    async fn chat(&self, messages: &[ChatMessage]) -> Result<String, AiError> {
+        let response = check_status(self.request(messages, false, &[], None).send().await?).await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AiError::ResponseError(e.to_string()))?;
+
+        body["choices"][0]["message"]["content"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| AiError::ResponseError(format!("No message in response: {}", body)))
+    }
+
+    async fn chat_stream<F>(
+        &self,
+        messages: &[ChatMessage],
+        mut on_chunk: F,
+    ) -> Result<String, AiError>
+    where
+        F: FnMut(&str) + Send,
+    {
+        let response = check_status(self.request(messages, true, &[], None).send().await?).await?;
+
+        let mut reply = String::new();
+        read_events(response, |data| {
+            if data == "[DONE]" {
+                return Ok(false);
+            }
+            let event: Value =
+                serde_json::from_str(data).map_err(|e| AiError::ResponseError(e.to_string()))?;
+            if let Some(text) = event["choices"][0]["delta"]["content"].as_str() {
+                reply.push_str(text);
+                on_chunk(text);
+            }
+            Ok(true)
+        })
+        .await?;
+
+        Ok(reply)
+    }
+
+    async fn chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+    ) -> Result<ChatMessage, AiError> {
+        let response =
+            check_status(self.request(messages, false, tools, None).send().await?).await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AiError::ResponseError(e.to_string()))?;
+
+        let message = &body["choices"][0]["message"];
+        if message.is_null() {
+            return Err(AiError::ResponseError(format!(
+                "No message in response: {}",
+                body
+            )));
+        }
+        let mut reply = ChatMessage::assistant(message["content"].as_str().unwrap_or(""));
+        reply.tool_calls = message["tool_calls"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|call| {
+                // Arguments arrive as a JSON string; a malformed one is passed on for the tool to reject
+                let arguments = call["function"]["arguments"].as_str().unwrap_or("{}");
+                ToolCall {
+                    id: call["id"].as_str().unwrap_or_default().to_string(),
+                    name: call["function"]["name"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    arguments: serde_json::from_str(arguments)
+                        .unwrap_or_else(|_| Value::String(arguments.to_string())),
+                    signature: None,
+                }
+            })
+            .collect();
+        Ok(reply)
+    }
+
+    async fn chat_json(
+        &self,
+        messages: &[ChatMessage],
+        name: &str,
+        schema: &Value,
+    ) -> Result<Value, AiError> {
+        let response = check_status(
+            self.request(messages, false, &[], Some((name, schema)))
+                .send()
+                .await?,
+        )
+        .await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AiError::ResponseError(e.to_string()))?;
+
+        let content = body["choices"][0]["message"]["content"]
+            .as_str()
+            .ok_or_else(|| AiError::ResponseError(format!("No message in response: {}", body)))?;
+        serde_json::from_str(content)
+            .map_err(|e| AiError::ResponseError(format!("Invalid JSON in reply: {}", e)))
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, AiError> {
+        let response = check_status(
+            self.authorize(self.client.get(format!("{}/models", self.base_url)))
+                .send()
+                .await?,
+        )
+        .await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AiError::ResponseError(e.to_string()))?;
+
+        let mut models: Vec<ModelInfo> = body["data"]
+            .as_array()
+            .map(|models| models.iter().filter_map(model_info).collect())
+            .unwrap_or_default();
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(models)
+    }
+}
+
+// Tool calls and results in the chat completions format
+fn wire_message(message: &ChatMessage) -> Value {
+    match message.role {
+        ChatRole::Tool => json!({
+            "role": "tool",
+            "tool_call_id": message.tool_call_id,
+            "content": message.content,
+        }),
+        ChatRole::Assistant if !message.tool_calls.is_empty() => json!({
+            "role": "assistant",
+            "content": message.content,
+            "tool_calls": message
+                .tool_calls
+                .iter()
+                .map(|call| {
+                    json!({
+                        "id": call.id,
+                        "type": "function",
+                        "function": { "name": call.name, "arguments": call.arguments.to_string() },
+                    })
+                })
+                .collect::<Vec<_>>(),
+        }),
+        _ => json!({ "role": message.role, "content": message.content }),
+    }
+}
+
+// OpenAI itself only returns ids; OpenRouter, vLLM and llama.cpp add limits and parameters
+fn model_info(model: &Value) -> Option<ModelInfo> {
+    let id = model["id"].as_str()?;
+    let lower = id.to_lowercase();
+    if NON_CHAT_MODELS.iter().any(|marker| lower.contains(marker)) {
+        return None;
+    }
+
+    let mut info = ModelInfo::new(id);
+    info.display_name = model["name"].as_str().map(str::to_string);
+    info.context_window = model["context_length"]
+        .as_i64()
+        .or_else(|| model["context_window"].as_i64())
+        .or_else(|| model["max_model_len"].as_i64())
+        .or_else(|| model["meta"]["n_ctx_train"].as_i64());
+    info.max_output_tokens = model["top_provider"]["max_completion_tokens"].as_i64();
+    info.supports_json = model["supported_parameters"].as_array().map(|parameters| {
+        parameters
+            .iter()
+            .any(|p| p == "response_format" || p == "structured_outputs")
+    });
+    Some(info)
+}
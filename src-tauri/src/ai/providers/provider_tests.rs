@@ -0,0 +1,506 @@
+// Provider requests and response parsing against the local mock server
+use serde_json::Value;
+
+use super::{AiError, AiProvider, ChatMessage, Embedder, Provider, ToolCall, ToolSpec};
+use crate::models::mock_reddit::MockReddit;
+use crate::settings::api_keys::ApiKeys;
+
+fn conversation() -> Vec<ChatMessage> {
+    vec![
+        ChatMessage::system("Answer briefly."),
+        ChatMessage::user("Hi"),
+        ChatMessage::assistant("Hello!"),
+        ChatMessage::user("Any leads today?"),
+    ]
+}
+
+fn body(server: &MockReddit) -> Value {
+    serde_json::from_str(&server.requests()[0].body).unwrap()
+}
+
+#[tokio::test]
+async fn openai_sends_roles_and_reads_the_message() {
+    let server = MockReddit::start(|_| {
+        (
+            200,
+            r#"{"choices":[{"message":{"role":"assistant","content":"Two new leads."}}]}"#
+                .to_string(),
+        )
+    })
+    .await;
+    let api_keys = ApiKeys {
+        ai_provider: "openai".to_string(),
+        openai_api_key: "sk-test".to_string(),
+        openai_base_url: format!("{}/v1/", server.base_url),
+        ..ApiKeys::default()
+    };
+
+    let answer = Provider::from_api_keys(&api_keys)
+        .unwrap()
+        .chat(&conversation())
+        .await
+        .unwrap();
+
+    assert_eq!(answer, "Two new leads.");
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/chat/completions");
+    assert_eq!(request.header("authorization"), Some("Bearer sk-test"));
+    let body = body(&server);
+    assert_eq!(body["model"], "gpt-4o");
+    assert_eq!(body["messages"][0]["role"], "system");
+    assert_eq!(body["messages"][2]["role"], "assistant");
+}
+
+#[tokio::test]
+async fn local_server_streams_without_an_api_key() {
+    let server = MockReddit::start(|_| {
+        (
+            200,
+            [
+                r#"data: {"choices":[{"delta":{"role":"assistant"}}]}"#,
+                r#"data: {"choices":[{"delta":{"content":"Two "}}]}"#,
+                r#"data: {"choices":[{"delta":{"content":"leads."}}]}"#,
+                "data: [DONE]",
+                "",
+            ]
+            .join("\n\n"),
+        )
+    })
+    .await;
+    let api_keys = ApiKeys {
+        ai_provider: "ollama".to_string(),
+        local_ai_base_url: format!("{}/v1", server.base_url),
+        local_ai_model: "llama3.1".to_string(),
+        ..ApiKeys::default()
+    };
+
+    let mut chunks = Vec::new();
+    let answer = Provider::from_api_keys(&api_keys)
+        .unwrap()
+        .chat_stream(&conversation(), |chunk| chunks.push(chunk.to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(answer, "Two leads.");
+    assert_eq!(chunks, vec!["Two ", "leads."]);
+    assert_eq!(server.requests()[0].header("authorization"), None);
+    assert_eq!(body(&server)["stream"], true);
+    assert_eq!(body(&server)["model"], "llama3.1");
+}
+
+#[tokio::test]
+async fn anthropic_moves_system_prompt_and_joins_text_blocks() {
+    let server = MockReddit::start(|_| {
+        (
+            200,
+            r#"{"content":[{"type":"text","text":"Two "},{"type":"text","text":"leads."}]}"#
+                .to_string(),
+        )
+    })
+    .await;
+    let api_keys = ApiKeys {
+        ai_provider: "anthropic".to_string(),
+        anthropic_api_key: "ant-key".to_string(),
+        anthropic_base_url: server.base_url.clone(),
+        ..ApiKeys::default()
+    };
+
+    let answer = Provider::from_api_keys(&api_keys)
+        .unwrap()
+        .chat(&conversation())
+        .await
+        .unwrap();
+
+    assert_eq!(answer, "Two leads.");
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/messages");
+    assert_eq!(request.header("x-api-key"), Some("ant-key"));
+    assert!(request.header("anthropic-version").is_some());
+    let body = body(&server);
+    assert_eq!(body["system"], "Answer briefly.");
+    assert_eq!(body["messages"].as_array().unwrap().len(), 3);
+    assert_eq!(body["messages"][0]["role"], "user");
+}
+
+#[tokio::test]
+async fn anthropic_stream_reads_text_deltas() {
+    let server = MockReddit::start(|_| {
+        (
+            200,
+            [
+                "event: message_start\ndata: {\"type\":\"message_start\"}",
+                "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"delta\":{\"type\":\"text_delta\",\"text\":\"Two \"}}",
+                "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"delta\":{\"type\":\"text_delta\",\"text\":\"leads.\"}}",
+                "event: message_stop\ndata: {\"type\":\"message_stop\"}",
+                "",
+            ]
+            .join("\n\n"),
+        )
+    })
+    .await;
+    let api_keys = ApiKeys {
+        ai_provider: "anthropic".to_string(),
+        anthropic_api_key: "ant-key".to_string(),
+        anthropic_base_url: server.base_url.clone(),
+        ..ApiKeys::default()
+    };
+
+    let answer = Provider::from_api_keys(&api_keys)
+        .unwrap()
+        .chat_stream(&conversation(), |_| {})
+        .await
+        .unwrap();
+
+    assert_eq!(answer, "Two leads.");
+}
+
+#[tokio::test]
+async fn gemini_folds_system_prompt_into_the_first_turn() {
+    let server = MockReddit::start(|request| {
+        if request.path.ends_with("/models") {
+            (
+                200,
+                r#"{"models":[
+                    {"name":"models/gemini-1.5-flash","inputTokenLimit":1048576,"supportedGenerationMethods":["generateContent"]},
+                    {"name":"models/text-embedding-004","supportedGenerationMethods":["embedContent"]}
+                ]}"#
+                .to_string(),
+            )
+        } else {
+            (200, r#"{"candidates":[{"content":{"parts":[{"text":"Two leads."}]}}]}"#.to_string())
+        }
+    })
+    .await;
+    let api_keys = ApiKeys {
+        gemini_api_key: "g-key".to_string(),
+        gemini_model: "gemini-1.5-flash".to_string(),
+        gemini_base_url: server.base_url.clone(),
+        ..ApiKeys::default()
+    };
+    let provider = Provider::from_api_keys(&api_keys).unwrap();
+
+    assert_eq!(provider.chat(&conversation()).await.unwrap(), "Two leads.");
+    let models = provider.list_models().await.unwrap();
+    assert_eq!(models.len(), 1);
+    assert_eq!(models[0].id, "gemini-1.5-flash");
+    assert_eq!(models[0].context_window, Some(1048576));
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/models/gemini-1.5-flash:generateContent");
+    assert_eq!(request.query["key"], "g-key");
+    let body = body(&server);
+    assert_eq!(
+        body["contents"][0]["parts"][0]["text"],
+        "Answer briefly.\n\nHi"
+    );
+    assert_eq!(body["contents"][1]["role"], "model");
+}
+
+#[tokio::test]
+async fn api_errors_carry_status_and_body() {
+    let server =
+        MockReddit::start(|_| (401, r#"{"error":{"message":"bad key"}}"#.to_string())).await;
+    let api_keys = ApiKeys {
+        ai_provider: "openai".to_string(),
+        openai_api_key: "sk-wrong".to_string(),
+        openai_base_url: server.base_url.clone(),
+        ..ApiKeys::default()
+    };
+
+    let error = Provider::from_api_keys(&api_keys)
+        .unwrap()
+        .list_models()
+        .await
+        .unwrap_err();
+
+    match error {
+        AiError::ApiError(message) => {
+            assert!(message.contains("401") && message.contains("bad key"))
+        }
+        other => panic!("unexpected error: {}", other),
+    }
+}
+
+fn notes_tool() -> ToolSpec {
+    ToolSpec {
+        name: "update_post_notes".to_string(),
+        description: "Updates notes.".to_string(),
+        parameters: serde_json::json!({
+            "type": "object",
+            "properties": { "id": { "type": "integer" } },
+            "required": ["id"],
+        }),
+    }
+}
+
+// A conversation where the assistant already called two tools
+fn after_tool_calls() -> Vec<ChatMessage> {
+    let mut assistant = ChatMessage::assistant("");
+    assistant.tool_calls = ["a", "b"]
+        .iter()
+        .map(|id| ToolCall {
+            id: id.to_string(),
+            name: "update_post_notes".to_string(),
+            arguments: serde_json::json!({ "id": 7 }),
+            signature: None,
+        })
+        .collect();
+    vec![
+        ChatMessage::system("Answer briefly."),
+        ChatMessage::user("Note it"),
+        assistant,
+        ChatMessage::tool_result("a", r#"{"ok":true}"#),
+        ChatMessage::tool_result("b", r#"{"ok":true}"#),
+    ]
+}
+
+#[tokio::test]
+async fn anthropic_tool_use_round_trip() {
+    let server = MockReddit::start(|_| {
+        (
+            200,
+            r#"{"content":[
+                {"type":"text","text":"Updating."},
+                {"type":"tool_use","id":"toolu_1","name":"update_post_notes","input":{"id":7}}
+            ]}"#
+            .to_string(),
+        )
+    })
+    .await;
+    let provider = Provider::from_api_keys(&ApiKeys {
+        ai_provider: "anthropic".to_string(),
+        anthropic_api_key: "ant-key".to_string(),
+        anthropic_base_url: server.base_url.clone(),
+        ..ApiKeys::default()
+    })
+    .unwrap();
+
+    let reply = provider
+        .chat_with_tools(&after_tool_calls(), &[notes_tool()])
+        .await
+        .unwrap();
+
+    assert_eq!(reply.content, "Updating.");
+    assert_eq!(reply.tool_calls[0].id, "toolu_1");
+    assert_eq!(reply.tool_calls[0].arguments["id"], 7);
+    let body = body(&server);
+    assert_eq!(body["tools"][0]["input_schema"]["required"][0], "id");
+    let messages = body["messages"].as_array().unwrap();
+    // Both results share the user turn after the calls
+    assert_eq!(messages.len(), 3);
+    assert_eq!(messages[1]["content"][1]["type"], "tool_use");
+    assert_eq!(messages[2]["role"], "user");
+    assert_eq!(messages[2]["content"][1]["tool_use_id"], "b");
+}
+
+#[tokio::test]
+async fn gemini_function_calls_round_trip() {
+    let server = MockReddit::start(|_| {
+        (
+            200,
+            r#"{"candidates":[{"content":{"parts":[
+                {"functionCall":{"name":"update_post_notes","args":{"id":7}},"thoughtSignature":"sig"}
+            ]}}]}"#
+                .to_string(),
+        )
+    })
+    .await;
+    let provider = Provider::from_api_keys(&ApiKeys {
+        gemini_api_key: "g-key".to_string(),
+        gemini_base_url: server.base_url.clone(),
+        ..ApiKeys::default()
+    })
+    .unwrap();
+
+    let reply = provider
+        .chat_with_tools(&after_tool_calls(), &[notes_tool()])
+        .await
+        .unwrap();
+
+    assert_eq!(reply.content, "");
+    assert_eq!(reply.tool_calls[0].name, "update_post_notes");
+    assert_eq!(reply.tool_calls[0].signature.as_deref(), Some("sig"));
+    let body = body(&server);
+    assert_eq!(
+        body["tools"][0]["functionDeclarations"][0]["name"],
+        "update_post_notes"
+    );
+    let contents = body["contents"].as_array().unwrap();
+    assert_eq!(contents.len(), 3);
+    assert_eq!(
+        contents[1]["parts"][0]["functionCall"]["name"],
+        "update_post_notes"
+    );
+    let responses = contents[2]["parts"].as_array().unwrap();
+    assert_eq!(responses.len(), 2);
+    assert_eq!(
+        responses[0]["functionResponse"]["name"],
+        "update_post_notes"
+    );
+    assert_eq!(
+        responses[0]["functionResponse"]["response"]["result"]["ok"],
+        true
+    );
+}
+
+fn answer_schema() -> Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": { "answer": { "type": "string" } },
+        "required": ["answer"],
+    })
+}
+
+#[tokio::test]
+async fn openai_json_replies_follow_the_schema() {
+    let server = MockReddit::start(|_| {
+        (
+            200,
+            r#"{"choices":[{"message":{"content":"{\"answer\":\"Two\"}"}}]}"#.to_string(),
+        )
+    })
+    .await;
+    let provider = Provider::from_api_keys(&ApiKeys {
+        ai_provider: "openai".to_string(),
+        openai_api_key: "sk-test".to_string(),
+        openai_base_url: server.base_url.clone(),
+        ..ApiKeys::default()
+    })
+    .unwrap();
+
+    let reply = provider
+        .chat_json(&conversation(), "answer", &answer_schema())
+        .await
+        .unwrap();
+
+    assert_eq!(reply["answer"], "Two");
+    let body = body(&server);
+    assert_eq!(body["response_format"]["type"], "json_schema");
+    assert_eq!(body["response_format"]["json_schema"]["name"], "answer");
+    assert_eq!(
+        body["response_format"]["json_schema"]["schema"]["required"][0],
+        "answer"
+    );
+}
+
+#[tokio::test]
+async fn anthropic_json_replies_come_from_a_forced_tool() {
+    let server = MockReddit::start(|_| {
+        (
+            200,
+            r#"{"content":[{"type":"tool_use","id":"toolu_1","name":"answer","input":{"answer":"Two"}}]}"#
+                .to_string(),
+        )
+    })
+    .await;
+    let provider = Provider::from_api_keys(&ApiKeys {
+        ai_provider: "anthropic".to_string(),
+        anthropic_api_key: "ant-key".to_string(),
+        anthropic_base_url: server.base_url.clone(),
+        ..ApiKeys::default()
+    })
+    .unwrap();
+
+    let reply = provider
+        .chat_json(&conversation(), "answer", &answer_schema())
+        .await
+        .unwrap();
+
+    assert_eq!(reply["answer"], "Two");
+    let body = body(&server);
+    assert_eq!(body["tool_choice"]["name"], "answer");
+    assert_eq!(body["tools"][0]["input_schema"]["type"], "object");
+}
+
+#[tokio::test]
+async fn local_embeddings_are_returned_in_input_order() {
+    let server = MockReddit::start(|_| {
+        (
+            200,
+            r#"{"data":[
+                {"index":1,"embedding":[0.0,1.0]},
+                {"index":0,"embedding":[1.0,0.0]}
+            ]}"#
+            .to_string(),
+        )
+    })
+    .await;
+    let embedder = Embedder::from_api_keys(&ApiKeys {
+        ai_provider: "anthropic".to_string(),
+        embedding_provider: "local".to_string(),
+        local_ai_base_url: server.base_url.clone(),
+        ..ApiKeys::default()
+    })
+    .unwrap();
+
+    let vectors = embedder
+        .embed(&["first".to_string(), "second".to_string()])
+        .await
+        .unwrap();
+
+    assert_eq!(vectors, vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+    assert_eq!(server.requests()[0].path, "/embeddings");
+    let body = body(&server);
+    assert_eq!(body["model"], "nomic-embed-text");
+    assert_eq!(body["input"][1], "second");
+}
+
+#[tokio::test]
+async fn gemini_embeds_in_one_batch_request() {
+    let server = MockReddit::start(|_| {
+        (
+            200,
+            r#"{"embeddings":[{"values":[0.5,0.5]},{"values":[0.1,0.9]}]}"#.to_string(),
+        )
+    })
+    .await;
+    let embedder = Embedder::from_api_keys(&ApiKeys {
+        gemini_api_key: "g-key".to_string(),
+        gemini_base_url: server.base_url.clone(),
+        ..ApiKeys::default()
+    })
+    .unwrap();
+
+    let vectors = embedder
+        .embed(&["first".to_string(), "second".to_string()])
+        .await
+        .unwrap();
+
+    assert_eq!(vectors.len(), 2);
+    let request = &server.requests()[0];
+    assert_eq!(
+        request.path,
+        "/models/text-embedding-004:batchEmbedContents"
+    );
+    assert_eq!(
+        body(&server)["requests"][0]["model"],
+        "models/text-embedding-004"
+    );
+}
+
+#[test]
+fn anthropic_cannot_embed() {
+    let error = Embedder::from_api_keys(&ApiKeys {
+        ai_provider: "anthropic".to_string(),
+        anthropic_api_key: "ant-key".to_string(),
+        ..ApiKeys::default()
+    })
+    .err()
+    .unwrap();
+
+    assert!(error.to_string().contains("embedding_provider"));
+}
+
+#[test]
+fn missing_keys_are_reported_before_any_request() {
+    let error = Provider::from_api_keys(&ApiKeys {
+        ai_provider: "anthropic".to_string(),
+        ..ApiKeys::default()
+    })
+    .err()
+    .unwrap();
+
+    assert!(error
+        .to_string()
+        .contains("Anthropic API Key not configured"));
+}
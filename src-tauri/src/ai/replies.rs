@@ -0,0 +1,449 @@
+// Reply drafts for a Reddit thread. The prompt carries the post, its top comments,
+// the subreddit's rules and our product facts. Each draft gets a self-promotion
+// risk computed here rather than by the model, so the score is explainable.
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::ai::providers::ChatMessage;
+use crate::database::adding::CommentDataWrapper;
+use crate::models::intent::KeywordMatcher;
+
+// Drafts per request at most, whatever the settings ask for
+const MAX_VARIANTS: usize = 5;
+// Characters of the post body and of each comment sent to the model
+const MAX_BODY_CHARS: usize = 4000;
+const MAX_COMMENT_CHARS: usize = 600;
+
+// Phrases that read like an ad in a Reddit comment
+const PROMOTION_PHRASES: &[&str] = &[
+    "check out",
+    "sign up",
+    "free trial",
+    "discount",
+    "promo code",
+    "use code",
+    "dm me",
+    "link in bio",
+    "our product",
+    "our app",
+    "our tool",
+    "we built",
+    "i built",
+    "try it",
+];
+// Phrases that disclose who is writing
+const DISCLOSURE_PHRASES: &[&str] = &[
+    "disclosure",
+    "disclaimer",
+    "i work on",
+    "i work for",
+    "i'm the founder",
+    "i am the founder",
+    "i'm one of the",
+    "i made",
+    "i built",
+    "we built",
+    "our team",
+];
+
+const BUILT_IN_TONES: &[(&str, &str)] = &[
+    ("helpful", "Answer the question directly and practically. Only mention our product where it clearly solves the problem."),
+    ("expert", "Write as an experienced practitioner: concrete steps, trade-offs and numbers, no filler."),
+    ("casual", "Write like a fellow redditor: short, friendly, plain words and no marketing language."),
+    ("founder", "Write as someone who works on our product. Lead with useful advice and say openly that you work on it before mentioning it."),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TonePreset {
+    pub name: String,
+    pub instructions: String,
+}
+
+// [api_keys.replies] in settings.toml. reply_preamble stays the base persona;
+// the tone adds to it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReplySettings {
+    // Facts the drafts may state about our product, e.g. "Free for up to 100 SKUs"
+    pub product_facts: Vec<String>,
+    // Extra tones; one named like a built-in tone replaces it
+    pub tones: Vec<TonePreset>,
+    pub default_tone: String,
+    pub variants: usize,
+    // Highest scored comments of the thread sent along with the post
+    pub max_comments: usize,
+    // Posts the reply queue drafts at the same time
+    pub concurrency: usize,
+}
+
+impl Default for ReplySettings {
+    fn default() -> Self {
+        ReplySettings {
+            product_facts: Vec::new(),
+            tones: Vec::new(),
+            default_tone: "helpful".to_string(),
+            variants: 3,
+            max_comments: 5,
+            concurrency: 3,
+        }
+    }
+}
+
+impl ReplySettings {
+    /// Built-in tones, then configured ones, with configured ones winning on name.
+    pub fn tone_presets(&self) -> Vec<TonePreset> {
+        let mut presets: Vec<TonePreset> = BUILT_IN_TONES
+            .iter()
+            .filter(|(name, _)| !self.tones.iter().any(|t| t.name.eq_ignore_ascii_case(name)))
+            .map(|(name, instructions)| TonePreset {
+                name: name.to_string(),
+                instructions: instructions.to_string(),
+            })
+            .collect();
+        presets.extend(self.tones.iter().cloned());
+        presets
+    }
+
+    /// The tone called `name`, or the default tone.
+    pub fn tone(&self, name: Option<&str>) -> Result<TonePreset, String> {
+        let name = name
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or(&self.default_tone)
+            .trim();
+        let presets = self.tone_presets();
+        presets
+            .iter()
+            .find(|preset| preset.name.eq_ignore_ascii_case(name))
+            .cloned()
+            .ok_or_else(|| {
+                let names: Vec<&str> = presets.iter().map(|p| p.name.as_str()).collect();
+                format!(
+                    "Unknown tone {:?}, expected one of: {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+
+    /// Variants to ask for: `requested` or the configured number, 1 to 5.
+    pub fn variant_count(&self, requested: Option<usize>) -> usize {
+        requested.unwrap_or(self.variants).clamp(1, MAX_VARIANTS)
+    }
+}
+
+// What the model sees of the thread
+#[derive(Debug, Default)]
+pub struct ThreadContext {
+    pub title: String,
+    pub body: String,
+    pub subreddit: String,
+    pub comments: Vec<CommentDataWrapper>,
+    // Numbered rule list and the rules about self-promotion, empty when unknown
+    pub rules: String,
+    pub self_promotion: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplyDraft {
+    pub text: String,
+    // How this variant differs from the others, in the model's words
+    pub approach: String,
+    // 0 (reads as plain help) to 1 (reads as an ad)
+    pub promotion_risk: f64,
+    // low, medium or high
+    pub risk_level: String,
+    pub risk_reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplyDrafts {
+    pub tone: String,
+    pub drafts: Vec<ReplyDraft>,
+}
+
+/// The `max` highest scored comments that still have a body.
+pub fn top_comments(comments: Vec<CommentDataWrapper>, max: usize) -> Vec<CommentDataWrapper> {
+    let mut comments: Vec<CommentDataWrapper> = comments
+        .into_iter()
+        .filter(|comment| {
+            let body = comment.body.trim();
+            !body.is_empty() && body != "[deleted]" && body != "[removed]"
+        })
+        .collect();
+    comments.sort_by_key(|comment| std::cmp::Reverse(comment.score));
+    comments.truncate(max);
+    comments
+}
+
+/// Prompt for `variants` drafts in `tone`. Product facts are the only claims
+/// about the product the model is allowed to make.
+pub fn reply_messages(
+    preamble: &str,
+    settings: &ReplySettings,
+    tone: &TonePreset,
+    thread: &ThreadContext,
+    variants: usize,
+) -> Vec<ChatMessage> {
+    let mut system = format!("{}\n\nTone: {}", preamble.trim(), tone.instructions);
+    if settings.product_facts.is_empty() {
+        system.push_str("\n\nDon't mention any product or company.");
+    } else {
+        system.push_str(
+            "\n\nFacts about our product. Only state these, and only where they help the poster:",
+        );
+        for fact in &settings.product_facts {
+            system.push_str(&format!("\n- {}", fact.trim()));
+        }
+    }
+    if !thread.rules.trim().is_empty() {
+        system.push_str(&format!(
+            "\n\nRules of r/{}, which the reply must follow:\n{}",
+            thread.subreddit, thread.rules
+        ));
+    }
+    if !thread.self_promotion.trim().is_empty() {
+        system.push_str(&format!(
+            "\n\nThe subreddit's rules on self-promotion: {}",
+            thread.self_promotion
+        ));
+    }
+    if variants > 1 {
+        system.push_str(&format!(
+            "\n\nWrite {} different replies, each taking a different approach.",
+            variants
+        ));
+    }
+    system.push_str("\n\nDon't repeat what the comments already say. Plain text, no headings.");
+
+    let mut thread_text = format!("Title: {}", thread.title);
+    if !thread.subreddit.is_empty() {
+        thread_text = format!("r/{}\n\n{}", thread.subreddit, thread_text);
+    }
+    if !thread.body.trim().is_empty() {
+        thread_text.push_str(&format!(
+            "\n\nContent:\n{}",
+            truncate(&thread.body, MAX_BODY_CHARS)
+        ));
+    }
+    if !thread.comments.is_empty() {
+        thread_text.push_str("\n\nTop comments:");
+        for comment in &thread.comments {
+            thread_text.push_str(&format!(
+                "\n- u/{} ({} points): {}",
+                comment.author,
+                comment.score,
+                truncate(&comment.body, MAX_COMMENT_CHARS)
+            ));
+        }
+    }
+
+    vec![ChatMessage::system(system), ChatMessage::user(thread_text)]
+}
+
+/// JSON schema of a reply, `{"replies": [{"text", "approach"}]}`.
+pub fn reply_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "replies": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "text": { "type": "string", "description": "The reply, ready to post" },
+                        "approach": { "type": "string", "description": "A few words on how this reply differs from the others" },
+                    },
+                    "required": ["text", "approach"],
+                },
+            },
+        },
+        "required": ["replies"],
+    })
+}
+
+/// Drafts from the model's reply, each with its promotion risk.
+pub fn drafts_from_reply(
+    reply: &Value,
+    brands: &[String],
+    self_promotion: &str,
+) -> Vec<ReplyDraft> {
+    let brands = KeywordMatcher::new(brands);
+    reply["replies"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|candidate| {
+            let text = candidate["text"].as_str()?.trim();
+            if text.is_empty() {
+                return None;
+            }
+            let (risk, reasons) = promotion_risk(text, &brands, self_promotion);
+            Some(ReplyDraft {
+                text: text.to_string(),
+                approach: candidate["approach"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                promotion_risk: risk,
+                risk_level: risk_level(risk).to_string(),
+                risk_reasons: reasons,
+            })
+        })
+        .collect()
+}
+
+/// How much `text` reads as self-promotion, 0 to 1, with the reasons.
+pub fn promotion_risk(
+    text: &str,
+    brands: &KeywordMatcher,
+    self_promotion: &str,
+) -> (f64, Vec<String>) {
+    let lower = text.to_lowercase();
+    let mut risk: f64 = 0.0;
+    let mut reasons = Vec::new();
+
+    let mentioned = brands.matching(text, None);
+    if let Some(brand) = mentioned.first() {
+        risk += 0.3;
+        reasons.push(format!("Mentions {}", brand));
+        if !DISCLOSURE_PHRASES
+            .iter()
+            .any(|phrase| lower.contains(phrase))
+        {
+            risk += 0.1;
+            reasons.push("Doesn't say the writer is affiliated".to_string());
+        }
+    }
+
+    let has_link =
+        lower.contains("http://") || lower.contains("https://") || lower.contains("www.");
+    if has_link {
+        risk += 0.25;
+        reasons.push("Contains a link".to_string());
+    }
+
+    let phrases: Vec<&str> = PROMOTION_PHRASES
+        .iter()
+        .filter(|phrase| lower.contains(*phrase))
+        .copied()
+        .collect();
+    if !phrases.is_empty() {
+        risk += (0.1 * phrases.len() as f64).min(0.3);
+        reasons.push(format!("Sounds like an ad: {}", phrases.join(", ")));
+    }
+
+    if !self_promotion.trim().is_empty() && (!mentioned.is_empty() || has_link) {
+        risk += 0.2;
+        reasons.push("The subreddit restricts self-promotion".to_string());
+    }
+
+    (risk.min(1.0), reasons)
+}
+
+fn risk_level(risk: f64) -> &'static str {
+    if risk < 0.3 {
+        "low"
+    } else if risk < 0.6 {
+        "medium"
+    } else {
+        "high"
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    let text = text.trim();
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    format!("{}…", text.chars().take(max_chars).collect::<String>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(body: &str, score: i32) -> CommentDataWrapper {
+        CommentDataWrapper {
+            score,
+            ..CommentDataWrapper::sample(&format!("c{}", score), "abc", body)
+        }
+    }
+
+    #[test]
+    fn configured_tones_replace_built_in_ones() {
+        let settings = ReplySettings {
+            tones: vec![TonePreset {
+                name: "Casual".to_string(),
+                instructions: "Keep it to two sentences.".to_string(),
+            }],
+            ..ReplySettings::default()
+        };
+
+        assert_eq!(settings.tone(None).unwrap().name, "helpful");
+        assert_eq!(
+            settings.tone(Some("casual")).unwrap().instructions,
+            "Keep it to two sentences."
+        );
+        assert!(settings
+            .tone(Some("pirate"))
+            .unwrap_err()
+            .contains("founder"));
+        assert_eq!(settings.variant_count(Some(12)), 5);
+    }
+
+    #[test]
+    fn prompt_carries_comments_rules_and_facts() {
+        let settings = ReplySettings {
+            product_facts: vec!["Free for up to 100 SKUs".to_string()],
+            ..ReplySettings::default()
+        };
+        let thread = ThreadContext {
+            title: "How do you track stock?".to_string(),
+            subreddit: "smallbusiness".to_string(),
+            comments: top_comments(
+                vec![
+                    comment("Spreadsheets", 2),
+                    comment("[deleted]", 50),
+                    comment("A barcode scanner helped", 9),
+                ],
+                1,
+            ),
+            rules: "1. No spam".to_string(),
+            ..ThreadContext::default()
+        };
+
+        let messages = reply_messages(
+            "Be kind.",
+            &settings,
+            &settings.tone(None).unwrap(),
+            &thread,
+            3,
+        );
+
+        assert!(messages[0].content.contains("Free for up to 100 SKUs"));
+        assert!(messages[0].content.contains("1. No spam"));
+        assert!(messages[0].content.contains("Write 3 different replies"));
+        assert!(messages[1].content.contains("A barcode scanner helped"));
+        assert!(!messages[1].content.contains("Spreadsheets"));
+    }
+
+    #[test]
+    fn promotional_drafts_score_higher() {
+        let reply = json!({ "replies": [
+            { "text": "Start with a weekly count of your top 20 items.", "approach": "process" },
+            { "text": "Check out StockPilot at https://stockpilot.io, free trial!", "approach": "product" },
+            { "text": "  ", "approach": "empty" },
+        ]});
+
+        let drafts = drafts_from_reply(&reply, &["StockPilot".to_string()], "No promotion");
+
+        assert_eq!(drafts.len(), 2);
+        assert_eq!(drafts[0].promotion_risk, 0.0);
+        assert_eq!(drafts[0].risk_level, "low");
+        assert_eq!(drafts[1].risk_level, "high");
+        assert_eq!(drafts[1].promotion_risk, 1.0);
+        assert!(drafts[1]
+            .risk_reasons
+            .contains(&"Mentions StockPilot".to_string()));
+    }
+}
@@ -0,0 +1,390 @@
+// Drafts replies for many posts in the background. A few posts are drafted at
+// once, progress goes out as `reply-queue` events tagged with the caller's job
+// id, and the drafts are kept in the reply_drafts table for review.
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use tokio::task::JoinSet;
+
+use crate::ai::replies::ReplyDraft;
+
+/// Tauri event every `QueueEvent` is emitted on.
+pub const REPLY_QUEUE_EVENT: &str = "reply-queue";
+
+/// Review states of a stored draft; new drafts are "draft".
+pub const REPLY_STATUSES: &[&str] = &["draft", "approved", "posted", "rejected"];
+
+// Posts drafted at the same time at most, whatever the settings ask for
+const MAX_CONCURRENCY: usize = 8;
+
+// Jobs still running, with the sender that cancels each one
+static JOBS: Mutex<Vec<(String, oneshot::Sender<()>)>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredReplyDraft {
+    pub id: i64,
+    pub post_id: i64,
+    // The queue job that wrote it
+    pub job_id: String,
+    pub tone: String,
+    pub text: String,
+    pub approach: String,
+    pub promotion_risk: f64,
+    pub risk_level: String,
+    pub risk_reasons: Vec<String>,
+    pub status: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl StoredReplyDraft {
+    /// A new draft for `post_id`; the id is set when it's saved.
+    pub fn new(post_id: i64, job_id: &str, tone: &str, draft: ReplyDraft, now: i64) -> Self {
+        StoredReplyDraft {
+            id: 0,
+            post_id,
+            job_id: job_id.to_string(),
+            tone: tone.to_string(),
+            text: draft.text,
+            approach: draft.approach,
+            promotion_risk: draft.promotion_risk,
+            risk_level: draft.risk_level,
+            risk_reasons: draft.risk_reasons,
+            status: "draft".to_string(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum QueueEvent {
+    Started {
+        job_id: String,
+        total: usize,
+    },
+    // `drafts` replies were saved for the post
+    Drafted {
+        job_id: String,
+        post_id: i64,
+        drafts: usize,
+        done: usize,
+        total: usize,
+    },
+    Failed {
+        job_id: String,
+        post_id: i64,
+        message: String,
+        done: usize,
+        total: usize,
+    },
+    Finished {
+        job_id: String,
+        drafted: usize,
+        failed: usize,
+    },
+    // Posts not started or still running when cancelled get no event
+    Cancelled {
+        job_id: String,
+        drafted: usize,
+        failed: usize,
+    },
+}
+
+/// Starts drafting for `post_ids` in the background, `concurrency` posts at a time.
+/// `draft` drafts and saves one post, returning how many drafts it saved.
+pub fn start<D, F, E>(
+    job_id: &str,
+    post_ids: Vec<i64>,
+    concurrency: usize,
+    draft: D,
+    emit: E,
+) -> Result<(), String>
+where
+    D: Fn(i64) -> F + Send + 'static,
+    F: Future<Output = Result<usize, String>> + Send + 'static,
+    E: Fn(QueueEvent) + Send + 'static,
+{
+    let (cancel, cancelled) = oneshot::channel();
+    {
+        let mut jobs = JOBS.lock().unwrap();
+        if jobs.iter().any(|(id, _)| id == job_id) {
+            return Err(format!("Reply job {} is already running", job_id));
+        }
+        jobs.push((job_id.to_string(), cancel));
+    }
+
+    tokio::spawn(run(
+        job_id.to_string(),
+        post_ids,
+        concurrency,
+        draft,
+        cancelled,
+        emit,
+    ));
+    Ok(())
+}
+
+/// Stops a running job; false when nothing with that id is running.
+pub fn cancel(job_id: &str) -> bool {
+    let cancel = {
+        let mut jobs = JOBS.lock().unwrap();
+        jobs.iter()
+            .position(|(id, _)| id == job_id)
+            .map(|i| jobs.remove(i).1)
+    };
+
+    match cancel {
+        Some(cancel) => cancel.send(()).is_ok(),
+        None => false,
+    }
+}
+
+async fn run<D, F, E>(
+    job_id: String,
+    mut post_ids: Vec<i64>,
+    concurrency: usize,
+    draft: D,
+    mut cancelled: oneshot::Receiver<()>,
+    emit: E,
+) where
+    D: Fn(i64) -> F,
+    F: Future<Output = Result<usize, String>> + Send + 'static,
+    E: Fn(QueueEvent),
+{
+    // Each post once, in the order given
+    let mut seen = Vec::new();
+    post_ids.retain(|id| {
+        let new = !seen.contains(id);
+        seen.push(*id);
+        new
+    });
+    let total = post_ids.len();
+    emit(QueueEvent::Started {
+        job_id: job_id.clone(),
+        total,
+    });
+
+    let concurrency = concurrency.clamp(1, MAX_CONCURRENCY);
+    let mut pending = post_ids.into_iter();
+    let mut running = JoinSet::new();
+    // Post of each running task, so a task that panics can still be reported
+    let mut task_posts = HashMap::new();
+    let (mut drafted, mut failed) = (0, 0);
+    let mut was_cancelled = false;
+
+    loop {
+        while running.len() < concurrency {
+            match pending.next() {
+                Some(post_id) => {
+                    let task = running.spawn(draft(post_id));
+                    task_posts.insert(task.id(), post_id);
+                }
+                None => break,
+            }
+        }
+
+        let joined = tokio::select! {
+            joined = running.join_next_with_id() => joined,
+            _ = &mut cancelled => {
+                running.shutdown().await;
+                was_cancelled = true;
+                break;
+            }
+        };
+        let (post_id, result) = match joined {
+            Some(Ok((task, result))) => (task_posts.remove(&task).unwrap_or_default(), result),
+            Some(Err(e)) => {
+                eprintln!("Reply drafting task of job {} failed: {}", job_id, e);
+                (
+                    task_posts.remove(&e.id()).unwrap_or_default(),
+                    Err(format!("Drafting stopped unexpectedly: {}", e)),
+                )
+            }
+            None => break,
+        };
+
+        let done = drafted + failed + 1;
+        match result {
+            Ok(drafts) => {
+                drafted += 1;
+                emit(QueueEvent::Drafted {
+                    job_id: job_id.clone(),
+                    post_id,
+                    drafts,
+                    done,
+                    total,
+                });
+            }
+            Err(message) => {
+                failed += 1;
+                emit(QueueEvent::Failed {
+                    job_id: job_id.clone(),
+                    post_id,
+                    message,
+                    done,
+                    total,
+                });
+            }
+        }
+    }
+
+    JOBS.lock().unwrap().retain(|(id, _)| *id != job_id);
+    emit(if was_cancelled {
+        QueueEvent::Cancelled {
+            job_id,
+            drafted,
+            failed,
+        }
+    } else {
+        QueueEvent::Finished {
+            job_id,
+            drafted,
+            failed,
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    // Drafts after a pause, failing for negative ids, and records the peak concurrency
+    fn slow_drafter(
+        pause: Duration,
+        peak: Arc<AtomicUsize>,
+    ) -> impl Fn(i64) -> std::pin::Pin<Box<dyn Future<Output = Result<usize, String>> + Send>> {
+        let running = Arc::new(AtomicUsize::new(0));
+        move |post_id| {
+            let running = running.clone();
+            let peak = peak.clone();
+            Box::pin(async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(pause).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                if post_id < 0 {
+                    Err(format!("Post {} not found", post_id))
+                } else {
+                    Ok(3)
+                }
+            })
+        }
+    }
+
+    async fn events_until_end(events: &mut mpsc::UnboundedReceiver<QueueEvent>) -> Vec<QueueEvent> {
+        let mut received = Vec::new();
+        while let Some(event) = events.recv().await {
+            let end = matches!(
+                event,
+                QueueEvent::Finished { .. } | QueueEvent::Cancelled { .. }
+            );
+            received.push(event);
+            if end {
+                break;
+            }
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn posts_are_drafted_a_few_at_a_time() {
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (sender, mut events) = mpsc::unbounded_channel();
+
+        start(
+            "bulk-1",
+            vec![1, 2, -3, 4, 2, 5],
+            2,
+            slow_drafter(Duration::from_millis(20), peak.clone()),
+            move |event| sender.send(event).unwrap(),
+        )
+        .unwrap();
+        let received = events_until_end(&mut events).await;
+
+        assert_eq!(
+            received[0],
+            QueueEvent::Started {
+                job_id: "bulk-1".to_string(),
+                total: 5
+            }
+        );
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert!(received
+            .iter()
+            .any(|event| matches!(event, QueueEvent::Failed { post_id: -3, .. })));
+        assert_eq!(
+            received.last().unwrap(),
+            &QueueEvent::Finished {
+                job_id: "bulk-1".to_string(),
+                drafted: 4,
+                failed: 1
+            }
+        );
+        assert!(!cancel("bulk-1"));
+    }
+
+    #[tokio::test]
+    async fn cancelled_jobs_start_no_more_posts() {
+        let (sender, mut events) = mpsc::unbounded_channel();
+
+        start(
+            "bulk-2",
+            (1..=20).collect(),
+            1,
+            slow_drafter(Duration::from_millis(50), Arc::new(AtomicUsize::new(0))),
+            move |event| sender.send(event).unwrap(),
+        )
+        .unwrap();
+        assert!(start("bulk-2", vec![1], 1, |_| async { Ok(0) }, |_| {}).is_err());
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        assert!(cancel("bulk-2"));
+        let received = events_until_end(&mut events).await;
+
+        match received.last().unwrap() {
+            QueueEvent::Cancelled { drafted, .. } => assert!(*drafted < 20),
+            other => panic!("expected a cancellation, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn panicked_posts_are_reported_as_failed() {
+        let (sender, mut events) = mpsc::unbounded_channel();
+
+        start(
+            "bulk-3",
+            vec![1, 2],
+            2,
+            |post_id| async move {
+                if post_id == 2 {
+                    panic!("drafter bug");
+                }
+                Ok(1)
+            },
+            move |event| sender.send(event).unwrap(),
+        )
+        .unwrap();
+        let received = events_until_end(&mut events).await;
+
+        assert!(received.iter().any(|event| matches!(
+            event,
+            QueueEvent::Failed { post_id: 2, .. }
+        )));
+        assert_eq!(
+            received.last().unwrap(),
+            &QueueEvent::Finished {
+                job_id: "bulk-3".to_string(),
+                drafted: 1,
+                failed: 1
+            }
+        );
+    }
+}
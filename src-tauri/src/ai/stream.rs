@@ -0,0 +1,208 @@
+// Streams AI replies to the frontend piece by piece. The caller picks a request
+// id, listens for `ai-stream` events carrying that id and can stop the request
+// with `cancel`, which drops the HTTP request mid-reply.
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tokio::sync::oneshot;
+
+use crate::ai::providers::{AiError, AiProvider, ChatMessage, Provider};
+
+/// Tauri event every `StreamEvent` is emitted on.
+pub const AI_STREAM_EVENT: &str = "ai-stream";
+
+// Streams still running, with the sender that cancels each one
+static IN_FLIGHT: Mutex<Vec<(String, oneshot::Sender<()>)>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum StreamEvent {
+    Chunk { request_id: String, text: String },
+    // The whole reply, once the provider has finished
+    Done { request_id: String, text: String },
+    Error { request_id: String, message: String },
+    Cancelled { request_id: String },
+}
+
+/// Starts streaming the reply to `messages` in the background, passing every event to `emit`.
+pub fn start<E>(
+    request_id: &str,
+    provider: Provider,
+    messages: Vec<ChatMessage>,
+    emit: E,
+) -> Result<(), AiError>
+where
+    E: Fn(StreamEvent) + Clone + Send + 'static,
+{
+    let (cancel, cancelled) = oneshot::channel();
+    {
+        let mut in_flight = IN_FLIGHT.lock().unwrap();
+        if in_flight.iter().any(|(id, _)| id == request_id) {
+            return Err(AiError::ConfigError(format!(
+                "Request {} is already streaming",
+                request_id
+            )));
+        }
+        in_flight.push((request_id.to_string(), cancel));
+    }
+
+    tokio::spawn(run(
+        request_id.to_string(),
+        provider,
+        messages,
+        cancelled,
+        emit,
+    ));
+    Ok(())
+}
+
+/// Stops a running stream; false when nothing with that id is running.
+pub fn cancel(request_id: &str) -> bool {
+    let cancel = {
+        let mut in_flight = IN_FLIGHT.lock().unwrap();
+        in_flight
+            .iter()
+            .position(|(id, _)| id == request_id)
+            .map(|i| in_flight.remove(i).1)
+    };
+
+    match cancel {
+        Some(cancel) => cancel.send(()).is_ok(),
+        None => false,
+    }
+}
+
+async fn run<E>(
+    request_id: String,
+    provider: Provider,
+    messages: Vec<ChatMessage>,
+    cancelled: oneshot::Receiver<()>,
+    emit: E,
+) where
+    E: Fn(StreamEvent) + Clone + Send + 'static,
+{
+    let on_chunk = {
+        let emit = emit.clone();
+        let request_id = request_id.clone();
+        move |text: &str| {
+            emit(StreamEvent::Chunk {
+                request_id: request_id.clone(),
+                text: text.to_string(),
+            })
+        }
+    };
+
+    let event = tokio::select! {
+        result = provider.chat_stream(&messages, on_chunk) => match result {
+            Ok(text) => StreamEvent::Done { request_id: request_id.clone(), text },
+            Err(e) => StreamEvent::Error { request_id: request_id.clone(), message: e.to_string() },
+        },
+        _ = cancelled => StreamEvent::Cancelled { request_id: request_id.clone() },
+    };
+
+    IN_FLIGHT.lock().unwrap().retain(|(id, _)| *id != request_id);
+    emit(event);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::mock_reddit::MockReddit;
+    use crate::settings::api_keys::ApiKeys;
+    use tokio::sync::mpsc;
+
+    fn local_provider(base_url: &str) -> Provider {
+        Provider::from_api_keys(&ApiKeys {
+            ai_provider: "local".to_string(),
+            local_ai_base_url: base_url.to_string(),
+            ..ApiKeys::default()
+        })
+        .unwrap()
+    }
+
+    fn events() -> (
+        impl Fn(StreamEvent) + Clone + Send + 'static,
+        mpsc::UnboundedReceiver<StreamEvent>,
+    ) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (
+            move |event| {
+                let _ = sender.send(event);
+            },
+            receiver,
+        )
+    }
+
+    #[tokio::test]
+    async fn chunks_are_emitted_before_the_full_reply() {
+        let server = MockReddit::start(|_| {
+            (
+                200,
+                [
+                    r#"data: {"choices":[{"delta":{"content":"Hel"}}]}"#,
+                    r#"data: {"choices":[{"delta":{"content":"lo"}}]}"#,
+                    "data: [DONE]",
+                    "",
+                ]
+                .join("\n\n"),
+            )
+        })
+        .await;
+        let (emit, mut received) = events();
+
+        start(
+            "stream-1",
+            local_provider(&server.base_url),
+            vec![ChatMessage::user("Hi")],
+            emit,
+        )
+        .unwrap();
+
+        let chunk = |text: &str| StreamEvent::Chunk {
+            request_id: "stream-1".to_string(),
+            text: text.to_string(),
+        };
+        assert_eq!(received.recv().await, Some(chunk("Hel")));
+        assert_eq!(received.recv().await, Some(chunk("lo")));
+        assert_eq!(
+            received.recv().await,
+            Some(StreamEvent::Done {
+                request_id: "stream-1".to_string(),
+                text: "Hello".to_string(),
+            })
+        );
+        assert!(!cancel("stream-1"));
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_a_request_that_never_answers() {
+        // Accepts connections and never responds
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                open.push(stream);
+            }
+        });
+        let (emit, mut received) = events();
+
+        start(
+            "stream-2",
+            local_provider(&base_url),
+            vec![ChatMessage::user("Hi")],
+            emit.clone(),
+        )
+        .unwrap();
+        assert!(start("stream-2", local_provider(&base_url), Vec::new(), emit).is_err());
+
+        assert!(cancel("stream-2"));
+        assert_eq!(
+            received.recv().await,
+            Some(StreamEvent::Cancelled {
+                request_id: "stream-2".to_string(),
+            })
+        );
+        assert!(!cancel("stream-2"));
+    }
+}
@@ -0,0 +1,244 @@
+// Leads returned by the AI as typed records. The schema is sent with the request
+// so providers answer in JSON, and each record is checked on its own: a bad one
+// is reported next to the good ones rather than failing or vanishing.
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Level {
+    #[serde(alias = "high", alias = "High")]
+    High,
+    #[serde(alias = "medium", alias = "Medium")]
+    Medium,
+    #[serde(alias = "low", alias = "Low")]
+    Low,
+}
+
+impl Level {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::High => "HIGH",
+            Level::Medium => "MEDIUM",
+            Level::Low => "LOW",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiLeadComment {
+    pub author: String,
+    pub text: String,
+    #[serde(default)]
+    pub sentiment: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiLead {
+    pub title: String,
+    pub url: String,
+    // YYYY-MM-DD
+    pub formatted_date: String,
+    pub relevance: Level,
+    pub subreddit: String,
+    pub sentiment: String,
+    #[serde(default)]
+    pub engagement_score: Option<Level>,
+    #[serde(default)]
+    pub top_comments: Vec<AiLeadComment>,
+    #[serde(default)]
+    pub comment_sentiment: Option<String>,
+}
+
+// A record that didn't make it into `leads`, with why and what was sent
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordError {
+    pub index: usize,
+    pub message: String,
+    pub record: Value,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LeadBatch {
+    pub leads: Vec<AiLead>,
+    pub errors: Vec<RecordError>,
+}
+
+/// JSON schema of a reply, `{"leads": [...]}`. Only keywords that OpenAI, Gemini
+/// and Anthropic all accept are used.
+pub fn lead_schema() -> Value {
+    let level = |description: &str| {
+        json!({ "type": "string", "enum": ["HIGH", "MEDIUM", "LOW"], "description": description })
+    };
+    json!({
+        "type": "object",
+        "properties": {
+            "leads": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "title": { "type": "string", "description": "Exact post title" },
+                        "url": { "type": "string", "description": "Full post URL" },
+                        "formatted_date": { "type": "string", "description": "Post date, YYYY-MM-DD" },
+                        "relevance": level("Lead quality: HIGH strong lead, MEDIUM potential, LOW uncertain"),
+                        "subreddit": { "type": "string" },
+                        "sentiment": { "type": "string", "description": "positive, negative or neutral" },
+                        "engagement_score": level("Interaction on the post"),
+                        "top_comments": {
+                            "type": "array",
+                            "description": "Up to 3 most relevant comments",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "author": { "type": "string" },
+                                    "text": { "type": "string" },
+                                    "sentiment": { "type": "string" },
+                                },
+                                "required": ["author", "text"],
+                            },
+                        },
+                        "comment_sentiment": { "type": "string", "description": "Overall sentiment of the comments" },
+                    },
+                    "required": ["title", "url", "formatted_date", "relevance", "subreddit", "sentiment"],
+                },
+            },
+        },
+        "required": ["leads"],
+    })
+}
+
+/// Splits a reply into valid leads and per-record errors. Takes `{"leads": [...]}`,
+/// a bare array or a single lead.
+pub fn validate_leads(reply: Value) -> LeadBatch {
+    let records = match reply {
+        Value::Object(mut object) if object.contains_key("leads") => {
+            match object.remove("leads") {
+                Some(Value::Array(records)) => records,
+                Some(other) => {
+                    return LeadBatch {
+                        leads: Vec::new(),
+                        errors: vec![RecordError {
+                            index: 0,
+                            message: "\"leads\" is not an array".to_string(),
+                            record: other,
+                        }],
+                    }
+                }
+                None => Vec::new(),
+            }
+        }
+        Value::Array(records) => records,
+        other => vec![other],
+    };
+
+    let mut batch = LeadBatch::default();
+    for (index, record) in records.into_iter().enumerate() {
+        let checked = serde_json::from_value::<AiLead>(record.clone())
+            .map_err(|e| e.to_string())
+            .and_then(|lead| check_lead(&lead).map(|_| lead));
+        match checked {
+            Ok(lead) => batch.leads.push(lead),
+            Err(message) => batch.errors.push(RecordError {
+                index,
+                message,
+                record,
+            }),
+        }
+    }
+    batch
+}
+
+/// Like `validate_leads`, for a reply that is still text. Text that isn't JSON
+/// at all becomes a single error.
+pub fn parse_leads(text: &str) -> LeadBatch {
+    let trimmed = text.trim();
+    let json_str = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .map(|inner| inner.trim_end_matches("```").trim())
+        .unwrap_or(trimmed);
+
+    match serde_json::from_str(json_str) {
+        Ok(reply) => validate_leads(reply),
+        Err(e) => LeadBatch {
+            leads: Vec::new(),
+            errors: vec![RecordError {
+                index: 0,
+                message: format!("Reply is not JSON: {}", e),
+                record: Value::String(text.to_string()),
+            }],
+        },
+    }
+}
+
+// Values the schema can't express
+fn check_lead(lead: &AiLead) -> Result<(), String> {
+    if lead.title.trim().is_empty() {
+        return Err("title is empty".to_string());
+    }
+    if !lead.url.starts_with("http://") && !lead.url.starts_with("https://") {
+        return Err(format!("url is not a link: {:?}", lead.url));
+    }
+    let date = lead.formatted_date.get(..10).unwrap_or(&lead.formatted_date);
+    if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+        return Err(format!(
+            "formatted_date is not YYYY-MM-DD: {:?}",
+            lead.formatted_date
+        ));
+    }
+    if lead.subreddit.trim().is_empty() {
+        return Err("subreddit is empty".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lead(title: &str, date: &str) -> Value {
+        json!({
+            "title": title,
+            "url": "https://reddit.com/r/smallbusiness/comments/abc",
+            "formatted_date": date,
+            "relevance": "high",
+            "subreddit": "smallbusiness",
+            "sentiment": "negative",
+            "top_comments": [{ "author": "a", "text": "Same problem here" }],
+        })
+    }
+
+    #[test]
+    fn invalid_records_are_reported_by_index() {
+        let reply = json!({ "leads": [
+            lead("Spreadsheets can't keep up with stock", "2024-03-01"),
+            lead("", "2024-03-01"),
+            lead("Looking for inventory software", "March 1st"),
+            { "title": "No url" },
+        ]});
+
+        let batch = validate_leads(reply);
+
+        assert_eq!(batch.leads.len(), 1);
+        assert_eq!(batch.leads[0].relevance, Level::High);
+        assert_eq!(batch.leads[0].top_comments[0].sentiment, None);
+        let indices: Vec<usize> = batch.errors.iter().map(|e| e.index).collect();
+        assert_eq!(indices, vec![1, 2, 3]);
+        assert!(batch.errors[1].message.contains("formatted_date"));
+        assert!(batch.errors[2].message.contains("missing field"));
+        assert_eq!(batch.errors[2].record["title"], "No url");
+    }
+
+    #[test]
+    fn text_replies_are_parsed_or_reported() {
+        let fenced = format!("```json\n[{}]\n```", lead("Stock tracking", "2024-03-01"));
+        assert_eq!(parse_leads(&fenced).leads.len(), 1);
+
+        let batch = parse_leads("Here are the leads I found!");
+        assert!(batch.leads.is_empty());
+        assert_eq!(batch.errors.len(), 1);
+        assert!(batch.errors[0].message.starts_with("Reply is not JSON"));
+    }
+}
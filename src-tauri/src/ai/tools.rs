@@ -0,0 +1,453 @@
+// Lets the assistant call the app's own commands through the provider's function
+// calling. Tool definitions are built from the `get_available_commands`
+// catalogue. Read-only commands run as soon as the model asks for them; commands
+// that change saved data are held until the user confirms them, and the
+// conversation then carries on from where it stopped.
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::ai::gemini::{get_available_commands, CommandInfo, CommandParameter};
+use crate::ai::providers::{AiError, AiProvider, ChatMessage, Provider, ToolCall, ToolSpec};
+use crate::commands;
+
+// Commands the assistant may run on its own
+const READ_ONLY_TOOLS: &[&str] = &[
+    "get_recent_posts",
+    "get_posts_by_sort_type",
+    "search_posts",
+    "get_posts_by_subreddit",
+    "get_posts_by_sentiment_command",
+    "get_comments_by_sentiment_command",
+    "get_brand_mention_counts_command",
+    "count_leads_by_subreddit_command",
+    "get_topics_command",
+    "get_posts_by_topic_command",
+    "semantic_search_command",
+    "find_similar_leads_command",
+    "get_saved_subreddits_command",
+];
+// Commands that change saved data and only run once the user confirms them
+const WRITE_TOOLS: &[&str] = &[
+    // Fetches from Reddit and saves the comments
+    "get_post_comments_command",
+    "update_post_notes",
+    "update_post_assignee",
+    "update_post_engaged_status",
+];
+
+// Model turns per question before giving up
+const MAX_ROUNDS: usize = 8;
+// Longer result lists are cut; the model can narrow its query instead
+const MAX_RESULT_ITEMS: usize = 25;
+// Conversations waiting for confirmation; the oldest are dropped first
+const MAX_PENDING: usize = 20;
+
+const SYSTEM_PROMPT: &str = "You help the user find and manage sales leads saved from Reddit. Use the tools to look up posts, comments and leads instead of guessing, and include post URLs in answers. Tools that change data (fetching comments, notes, assignee, engaged status) only run after the user confirms them, so only call them when the user asked for that change.";
+
+static PENDING: Mutex<Vec<PendingConversation>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+struct PendingConversation {
+    id: String,
+    messages: Vec<ChatMessage>,
+    // Write calls of the last turn, waiting for the user
+    calls: Vec<ToolCall>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolReply {
+    pub conversation_id: String,
+    // None while actions wait for confirmation
+    pub answer: Option<String>,
+    pub pending_actions: Vec<ToolCall>,
+    // Tools run for this reply, in order
+    pub actions_taken: Vec<ToolCall>,
+}
+
+/// Tool definitions for the catalogue commands the assistant may use.
+pub fn tool_specs(catalogue: &[CommandInfo]) -> Vec<ToolSpec> {
+    catalogue
+        .iter()
+        .filter(|command| is_read_only(&command.name) || is_write(&command.name))
+        .map(|command| {
+            let mut description = command.description.clone();
+            if is_write(&command.name) {
+                description.push_str(" Runs only after the user confirms it.");
+            }
+            ToolSpec {
+                name: command.name.clone(),
+                description,
+                parameters: parameter_schema(&command.parameters),
+            }
+        })
+        .collect()
+}
+
+/// Starts a conversation about `question`.
+pub async fn ask(provider: &Provider, question: &str) -> Result<ToolReply, AiError> {
+    let tools = tool_specs(&get_available_commands().await);
+    let conversation_id = format!("tools-{}", NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    let messages = vec![
+        ChatMessage::system(SYSTEM_PROMPT),
+        ChatMessage::user(question),
+    ];
+
+    converse(provider, conversation_id, messages, &tools, Vec::new()).await
+}
+
+/// Runs the `approved` pending actions of a conversation, declines the rest and carries on.
+pub async fn confirm(
+    provider: &Provider,
+    conversation_id: &str,
+    approved: &[String],
+) -> Result<ToolReply, AiError> {
+    let pending = {
+        let mut pending = PENDING.lock().unwrap();
+        pending
+            .iter()
+            .position(|conversation| conversation.id == conversation_id)
+            .map(|i| pending.remove(i))
+    }
+    .ok_or_else(|| {
+        AiError::ConfigError(format!(
+            "No actions are waiting for confirmation in {}",
+            conversation_id
+        ))
+    })?;
+
+    let mut messages = pending.messages;
+    let mut actions_taken = Vec::new();
+    for call in pending.calls {
+        let result = if approved.contains(&call.id) {
+            println!("Running confirmed AI action {}", call.name);
+            actions_taken.push(call.clone());
+            run_tool(&call).await
+        } else {
+            Err("The user declined this action".to_string())
+        };
+        messages.push(tool_message(&call, result));
+    }
+
+    let tools = tool_specs(&get_available_commands().await);
+    converse(
+        provider,
+        conversation_id.to_string(),
+        messages,
+        &tools,
+        actions_taken,
+    )
+    .await
+}
+
+async fn converse(
+    provider: &Provider,
+    conversation_id: String,
+    mut messages: Vec<ChatMessage>,
+    tools: &[ToolSpec],
+    mut actions_taken: Vec<ToolCall>,
+) -> Result<ToolReply, AiError> {
+    for _ in 0..MAX_ROUNDS {
+        let reply = provider.chat_with_tools(&messages, tools).await?;
+        let calls = reply.tool_calls.clone();
+        let answer = reply.content.clone();
+        messages.push(reply);
+        if calls.is_empty() {
+            return Ok(ToolReply {
+                conversation_id,
+                answer: Some(answer),
+                pending_actions: Vec::new(),
+                actions_taken,
+            });
+        }
+
+        let mut waiting = Vec::new();
+        for call in calls {
+            if is_write(&call.name) {
+                waiting.push(call);
+                continue;
+            }
+            let result = if is_read_only(&call.name) {
+                actions_taken.push(call.clone());
+                run_tool(&call).await
+            } else {
+                Err(format!("Unknown tool {}", call.name))
+            };
+            messages.push(tool_message(&call, result));
+        }
+
+        if !waiting.is_empty() {
+            let mut pending = PENDING.lock().unwrap();
+            pending.retain(|conversation| conversation.id != conversation_id);
+            if pending.len() >= MAX_PENDING {
+                pending.remove(0);
+            }
+            pending.push(PendingConversation {
+                id: conversation_id.clone(),
+                messages,
+                calls: waiting.clone(),
+            });
+            return Ok(ToolReply {
+                conversation_id,
+                answer: None,
+                pending_actions: waiting,
+                actions_taken,
+            });
+        }
+    }
+
+    Err(AiError::ResponseError(format!(
+        "No answer after {} rounds of tool calls",
+        MAX_ROUNDS
+    )))
+}
+
+fn is_read_only(name: &str) -> bool {
+    READ_ONLY_TOOLS.contains(&name)
+}
+
+fn is_write(name: &str) -> bool {
+    WRITE_TOOLS.contains(&name)
+}
+
+fn tool_message(call: &ToolCall, result: Result<Value, String>) -> ChatMessage {
+    let content = match result {
+        Ok(value) => value.to_string(),
+        Err(e) => json!({ "error": e }).to_string(),
+    };
+    ChatMessage::tool_result(&call.id, content)
+}
+
+// JSON schema of a command's parameters; Option<T> parameters are not required
+fn parameter_schema(parameters: &[CommandParameter]) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for parameter in parameters {
+        let inner = parameter
+            .r#type
+            .strip_prefix("Option<")
+            .and_then(|inner| inner.strip_suffix('>'));
+        let mut schema = json_type(inner.unwrap_or(&parameter.r#type));
+        schema["description"] = json!(parameter.description);
+        properties.insert(parameter.name.clone(), schema);
+        if inner.is_none() {
+            required.push(parameter.name.clone());
+        }
+    }
+
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+fn json_type(rust_type: &str) -> Value {
+    match rust_type {
+        "String" => json!({ "type": "string" }),
+        "i64" | "i32" | "usize" => json!({ "type": "integer" }),
+        "f64" => json!({ "type": "number" }),
+        "bool" => json!({ "type": "boolean" }),
+        "Vec<String>" => json!({ "type": "array", "items": { "type": "string" } }),
+        _ => json!({ "type": "object" }),
+    }
+}
+
+async fn run_tool(call: &ToolCall) -> Result<Value, String> {
+    let args = &call.arguments;
+    match call.name.as_str() {
+        "get_recent_posts" => result(commands::get_recent_posts(integer(args, "limit")?)),
+        "get_posts_by_sort_type" => {
+            result(commands::get_posts_by_sort_type(string(args, "sort_type")?))
+        }
+        "search_posts" => result(commands::search_posts(string(args, "search_term")?)),
+        "get_posts_by_subreddit" => {
+            result(commands::get_posts_by_subreddit(string(args, "subreddit")?))
+        }
+        "get_post_comments_command" => result(
+            commands::get_post_comments_command(
+                string(args, "url")?,
+                string(args, "title")?,
+                string(args, "sort_type")?,
+                string(args, "subreddit")?,
+                None,
+            )
+            .await,
+        ),
+        "get_posts_by_sentiment_command" => result(commands::get_posts_by_sentiment_command(
+            string(args, "sentiment")?,
+        )),
+        "get_comments_by_sentiment_command" => result(commands::get_comments_by_sentiment_command(
+            string(args, "sentiment")?,
+        )),
+        "get_brand_mention_counts_command" => result(commands::get_brand_mention_counts_command(
+            string(args, "period")?,
+            optional_integer(args, "since"),
+        )),
+        "count_leads_by_subreddit_command" => result(commands::count_leads_by_subreddit_command()),
+        "get_topics_command" => result(commands::get_topics_command()),
+        "get_posts_by_topic_command" => result(commands::get_posts_by_topic_command(integer(
+            args, "topic_id",
+        )?)),
+        "semantic_search_command" => result(
+            commands::semantic_search_command(string(args, "query")?, optional_count(args)).await,
+        ),
+        "find_similar_leads_command" => result(commands::find_similar_leads_command(
+            integer(args, "post_id")?,
+            optional_count(args),
+        )),
+        "get_saved_subreddits_command" => result(commands::get_saved_subreddits_command()),
+        "update_post_notes" => result(commands::update_post_notes(
+            integer(args, "id")?,
+            string(args, "notes")?,
+        )),
+        "update_post_assignee" => result(
+            commands::update_post_assignee(
+                integer(args, "id")?,
+                string(args, "assignee")?,
+                string(args, "title")?,
+            )
+            .await,
+        ),
+        "update_post_engaged_status" => result(commands::update_post_engaged_status(
+            integer(args, "id")?,
+            integer(args, "engaged")?,
+        )),
+        other => Err(format!("Unknown tool {}", other)),
+    }
+}
+
+// Serializes a command result, cutting long lists
+fn result<T: Serialize>(result: Result<T, String>) -> Result<Value, String> {
+    let value = serde_json::to_value(result?).map_err(|e| e.to_string())?;
+    Ok(match value {
+        Value::Null => json!({ "ok": true }),
+        Value::Array(items) if items.len() > MAX_RESULT_ITEMS => json!({
+            "total": items.len(),
+            "items": items.into_iter().take(MAX_RESULT_ITEMS).collect::<Vec<_>>(),
+            "truncated": true,
+        }),
+        value => value,
+    })
+}
+
+fn string(args: &Value, name: &str) -> Result<String, String> {
+    args[name]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| format!("Missing text argument {}", name))
+}
+
+fn integer(args: &Value, name: &str) -> Result<i64, String> {
+    optional_integer(args, name).ok_or_else(|| format!("Missing number argument {}", name))
+}
+
+// Models sometimes send numbers as floats or strings
+fn optional_integer(args: &Value, name: &str) -> Option<i64> {
+    let value = &args[name];
+    value
+        .as_i64()
+        .or_else(|| value.as_f64().map(|n| n as i64))
+        .or_else(|| value.as_str()?.trim().parse().ok())
+}
+
+fn optional_count(args: &Value) -> Option<usize> {
+    optional_integer(args, "limit").map(|n| n.max(0) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::mock_reddit::MockReddit;
+    use crate::settings::api_keys::ApiKeys;
+    use std::sync::atomic::AtomicUsize;
+
+    fn body(server: &MockReddit, i: usize) -> Value {
+        serde_json::from_str(&server.requests()[i].body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn specs_cover_allowed_commands_only() {
+        let specs = tool_specs(&get_available_commands().await);
+        let names: Vec<&str> = specs.iter().map(|spec| spec.name.as_str()).collect();
+
+        assert_eq!(names.len(), READ_ONLY_TOOLS.len() + WRITE_TOOLS.len());
+        assert!(!names.contains(&"clear_saved_reddits"));
+
+        let counts = specs
+            .iter()
+            .find(|spec| spec.name == "get_brand_mention_counts_command")
+            .unwrap();
+        assert_eq!(counts.parameters["properties"]["since"]["type"], "integer");
+        assert_eq!(counts.parameters["required"], json!(["period"]));
+
+        let notes = specs
+            .iter()
+            .find(|spec| spec.name == "update_post_notes")
+            .unwrap();
+        assert!(notes.description.contains("confirms"));
+    }
+
+    #[tokio::test]
+    async fn write_calls_wait_for_confirmation() {
+        let turn = AtomicUsize::new(0);
+        let server = MockReddit::start(move |_| {
+            let reply = match turn.fetch_add(1, Ordering::SeqCst) {
+                0 => json!({ "choices": [{ "message": { "content": null, "tool_calls": [
+                    { "id": "a", "type": "function", "function": {
+                        "name": "update_post_notes", "arguments": "{\"id\":7,\"notes\":\"Call back\"}" } },
+                    { "id": "b", "type": "function", "function": {
+                        "name": "drop_all_tables", "arguments": "{}" } }
+                ] } }] }),
+                _ => json!({ "choices": [{ "message": { "content": "Left the notes alone." } }] }),
+            };
+            (200, reply.to_string())
+        })
+        .await;
+        let provider = Provider::from_api_keys(&ApiKeys {
+            ai_provider: "local".to_string(),
+            local_ai_base_url: server.base_url.clone(),
+            ..ApiKeys::default()
+        })
+        .unwrap();
+
+        let reply = ask(&provider, "Add a note to post 7").await.unwrap();
+
+        assert_eq!(reply.answer, None);
+        assert_eq!(reply.pending_actions.len(), 1);
+        assert_eq!(reply.pending_actions[0].arguments["notes"], "Call back");
+        // Unknown tools are answered with an error, not run
+        assert!(reply.actions_taken.is_empty());
+        let request = body(&server, 0);
+        assert!(request["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|tool| tool["function"]["name"] == "update_post_notes"));
+
+        let reply = confirm(&provider, &reply.conversation_id, &[])
+            .await
+            .unwrap();
+
+        assert_eq!(reply.answer.as_deref(), Some("Left the notes alone."));
+        assert!(reply.actions_taken.is_empty());
+        let messages = body(&server, 1)["messages"].clone();
+        assert_eq!(messages[2]["tool_calls"][0]["id"], "a");
+        assert_eq!(messages[3]["tool_call_id"], "b");
+        assert!(messages[3]["content"]
+            .as_str()
+            .unwrap()
+            .contains("Unknown tool"));
+        assert_eq!(messages[4]["tool_call_id"], "a");
+        assert!(messages[4]["content"]
+            .as_str()
+            .unwrap()
+            .contains("declined"));
+
+        assert!(confirm(&provider, &reply.conversation_id, &[])
+            .await
+            .is_err());
+    }
+}
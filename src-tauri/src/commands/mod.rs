@@ -1,9 +1,32 @@
-use crate::database::adding::{CommentDataWrapper, PostDataWrapper};
+use crate::ai::embeddings::{self, IndexStats, SemanticMatch, StoredEmbedding};
+use crate::ai::history::{ChatSession, StoredChatMessage};
+use crate::ai::providers::{AiProvider, ChatMessage, Embedder, ModelInfo};
+use crate::ai::replies::{self, ReplyDrafts, ThreadContext, TonePreset};
+use crate::ai::reply_queue::{self, QueueEvent, StoredReplyDraft};
+use crate::ai::stream::{self, StreamEvent};
+use crate::ai::structured::LeadBatch;
+use crate::ai::tools::{self, ToolReply};
+use crate::database::adding::{BrandMentionCount, CommentDataWrapper, PostDataWrapper};
 use crate::database::read::DBReader;
-use crate::models::search::{self, get_access_token, get_subreddit_posts, search_subreddit_posts};
+use crate::models::authors::{fetch_author_profile, AuthorProfile};
+use crate::models::brands::{brand_query, split_tags, BrandMonitorReport};
+use crate::models::duplicates::{cluster_ids, find_clusters, DuplicateCluster};
+use crate::models::lead_score::LeadScorer;
+use crate::models::leads::{count_by_subreddit, find_local_leads, LocalLead, SubredditLeadCount};
+use crate::models::sentiment::Sentiment;
+use crate::models::subreddits::{
+    discover_subreddits, fetch_subreddit_rules, SavedSubreddit, SubredditInfo, SubredditRules,
+    RULES_CACHE_SECONDS,
+};
+use crate::models::topics::{cluster_topics, post_documents, Topic};
+use crate::models::search::{
+    self, get_access_token, get_subreddit_posts, search_subreddit_posts, SearchResponse,
+};
 use crate::settings::api_keys;
 use crate::settings::api_keys::AppConfig;
 use crate::{actions, database};
+use std::collections::{HashMap, HashSet};
+use tauri::Emitter;
 
 // Define a custom error type for better error handling
 #[derive(Debug)]
@@ -23,8 +46,7 @@ impl From<reqwest::Error> for RedditError {
 pub async fn get_reddit_results(
     sortTypes: Vec<String>, // Changed parameter name
     query: String,
-) -> Result<Vec<PostDataWrapper>, String> {
-    // Changed return type
+) -> Result<SearchResponse, String> {
     println!(
         "Querying Reddit for: '{}' with sortTypes: {:?}",
         query, sortTypes
@@ -36,62 +58,152 @@ pub async fn get_reddit_results(
         AppConfig::default()
     });
 
-    let api_keys = config.api_keys;
-    let client_id = api_keys.reddit_api_id;
-    let client_secret = api_keys.reddit_api_secret;
+    let api_keys = with_saved_subreddit_weights(config.api_keys);
+    let token = app_token(&api_keys).await?;
+
+    // Clear the current search results ONCE before populating with new filtered results
+    database::adding::DB::clear_current_search_results().unwrap();
+
+    let scorer = LeadScorer::new(&api_keys, chrono::Utc::now().timestamp());
+    let mut unique_posts_map: HashMap<i64, PostDataWrapper> = HashMap::new();
+    collect_posts(&api_keys, &token, &scorer, &query, &sortTypes, &mut unique_posts_map).await;
+
+    let all_fetched_posts: Vec<PostDataWrapper> = unique_posts_map.into_values().collect();
+
+    // Drop deleted, NSFW, low quality etc. posts before they reach the database
+    let (mut all_fetched_posts, filtered) = api_keys
+        .content_filters
+        .apply(all_fetched_posts, chrono::Utc::now().timestamp());
+    if filtered.total > 0 {
+        println!("Filtered out {} posts: {:?}", filtered.total, filtered);
+    }
+
+    store_search_results(&api_keys, &mut all_fetched_posts);
+
+    println!("Total posts added to database: {}", all_fetched_posts.len());
+    Ok(SearchResponse {
+        posts: all_fetched_posts,
+        filtered,
+    })
+}
+
+// Searches Reddit for each of our brands and competitors and tags what it finds
+#[tauri::command]
+pub async fn run_brand_monitoring_command(
+    sort_types: Vec<String>,
+) -> Result<BrandMonitorReport, String> {
+    let config = api_keys::ConfigDirs::read_config().map_err(|e| e.to_string())?;
+    let api_keys = with_saved_subreddit_weights(config.api_keys);
+
+    let brands = api_keys.all_brand_keywords();
+    if brands.is_empty() {
+        return Err("No branded or competitor keywords configured. Add them in settings first.".into());
+    }
+
+    let token = app_token(&api_keys).await?;
+
+    let scorer = LeadScorer::new(&api_keys, chrono::Utc::now().timestamp());
+    let mut unique_posts_map: HashMap<i64, PostDataWrapper> = HashMap::new();
+    for brand in &brands {
+        println!("Monitoring brand: {}", brand);
+        collect_posts(&api_keys, &token, &scorer, &brand_query(brand), &sort_types, &mut unique_posts_map).await;
+    }
+
+    // Reddit search is fuzzy, keep only posts that really mention one of the brands
+    let posts: Vec<PostDataWrapper> = unique_posts_map
+        .into_values()
+        .filter(|post| !post.brand_mentions.is_empty())
+        .collect();
+
+    let (posts, filtered) = api_keys
+        .content_filters
+        .apply(posts, chrono::Utc::now().timestamp());
+
+    let mut mentions: HashMap<String, usize> = HashMap::new();
+    for post in &posts {
+        for brand in split_tags(&post.brand_mentions) {
+            *mentions.entry(brand.to_string()).or_default() += 1;
+        }
+    }
+
+    // Only the mention history is updated; the current search results are left alone
+    let mut db = database::adding::DB::new().map_err(|e| e.to_string())?;
+    db.record_post_brand_mentions(&posts)
+        .map_err(|e| e.to_string())?;
+
+    println!("Brand monitoring found {} posts: {:?}", posts.len(), mentions);
+    Ok(BrandMonitorReport {
+        posts,
+        mentions,
+        filtered,
+    })
+}
+
+// Mention counts per brand and period ("day", "week" or "month") for share of voice
+#[tauri::command]
+pub fn get_brand_mention_counts_command(
+    period: String,
+    since: Option<i64>,
+) -> Result<Vec<BrandMentionCount>, String> {
+    let reader = DBReader::new();
+    reader
+        .get_brand_mention_counts(&period, since.unwrap_or(0))
+        .map_err(|e| e.to_string())
+}
 
-    // Get token
-    let token = match get_access_token(client_id, client_secret).await {
-        Ok(t) if !t.is_empty() => t,
+async fn app_token(api_keys: &api_keys::ApiKeys) -> Result<String, String> {
+    match get_access_token(api_keys).await {
+        Ok(t) if !t.is_empty() => Ok(t),
         Ok(_) => {
             eprintln!("Empty access token received");
-            return Err("Reddit API returned an empty token. Please check your Client ID and Secret in settings.".into());
+            Err("Reddit API returned an empty token. Please check your Client ID and Secret in settings.".into())
         }
         Err(e) => {
             eprintln!("Failed to retrieve access token: {:?}", e);
-            return Err(format!("Reddit Authentication Failed: {}. Please check your API credentials in settings.", e));
+            Err(format!("Reddit Authentication Failed: {}. Please check your API credentials in settings.", e))
         }
-    };
-
-    // Clear the current search results ONCE before populating with new filtered results
-    database::adding::DB::clear_current_search_results().unwrap();
+    }
+}
 
-    let mut unique_posts_map: std::collections::HashMap<i64, PostDataWrapper> =
-        std::collections::HashMap::new();
+// Lead scoring also uses the weights set on saved subreddits
+fn with_saved_subreddit_weights(mut api_keys: api_keys::ApiKeys) -> api_keys::ApiKeys {
+    match DBReader::new().get_subreddit_weights() {
+        Ok(weights) => api_keys.lead_score.apply_saved_weights(weights),
+        Err(e) => eprintln!("Failed to read subreddit weights: {}", e),
+    }
+    api_keys
+}
 
+// Runs `query` once per sort type and merges the results by post id
+async fn collect_posts(
+    api_keys: &api_keys::ApiKeys,
+    token: &str,
+    scorer: &LeadScorer,
+    query: &str,
+    sort_types: &[String],
+    unique_posts_map: &mut HashMap<i64, PostDataWrapper>,
+) {
     // Query Reddit for each sort type - ONE REQUEST PER SORT TYPE
-    for sort_type in sortTypes {
-        // Use sortTypes here
+    for sort_type in sort_types {
         println!("Querying with sort type: {}", sort_type);
 
-        let posts_for_this_sort: Vec<PostDataWrapper>;
-
         // if query contains "r/" then it's a subreddit search
-        if query.starts_with("r/") {
-            let result = get_subreddit_posts(&token, &query, &sort_type).await;
-            match result {
-                Ok(posts) => {
-                    println!("Found {} posts for sort type: {}", posts.len(), sort_type);
-                    posts_for_this_sort = posts;
-                }
-                Err(e) => {
-                    eprintln!("Failed to fetch {} posts: {:?}", sort_type, e);
-                    continue;
-                }
-            };
+        let result = if query.starts_with("r/") {
+            get_subreddit_posts(api_keys, token, query, sort_type, scorer).await
         } else {
-            let result = search_subreddit_posts(&token, &query, &sort_type).await;
-            match result {
-                Ok(posts) => {
-                    println!("Found {} posts for sort type: {}", posts.len(), sort_type);
-                    posts_for_this_sort = posts;
-                }
-                Err(e) => {
-                    eprintln!("Failed to fetch {} posts: {:?}", sort_type, e);
-                    continue;
-                }
-            };
-        }
+            search_subreddit_posts(api_keys, token, query, sort_type, scorer).await
+        };
+
+        let posts_for_this_sort = match result {
+            Ok(posts) => {
+                println!("Found {} posts for sort type: {}", posts.len(), sort_type);
+                posts
+            }
+            Err(e) => {
+                eprintln!("Failed to fetch {} posts: {:?}", sort_type, e);
+                continue;
+            }
+        };
 
         // Merge logic
         for mut post in posts_for_this_sort {
@@ -99,7 +211,7 @@ pub async fn get_reddit_results(
                 Some(existing_post) => {
                     // Append sort_type if not already present
                     // We check purely string containment for simplicity given "hot", "new", "top" don't overlap as substrings
-                    if !existing_post.sort_type.contains(&sort_type) {
+                    if !existing_post.sort_type.contains(sort_type.as_str()) {
                         existing_post.sort_type =
                             format!("{},{}", existing_post.sort_type, sort_type);
                     }
@@ -112,29 +224,250 @@ pub async fn get_reddit_results(
             }
         }
     }
+}
 
-    let all_fetched_posts: Vec<PostDataWrapper> = unique_posts_map.into_values().collect();
+// Saves to the subreddit_search table so results persist for the view
+fn store_search_results(api_keys: &api_keys::ApiKeys, posts: &mut [PostDataWrapper]) {
+    if posts.is_empty() {
+        return;
+    }
+
+    tag_duplicates(api_keys, posts);
 
-    // HANDLE DB CREATION
     let mut db = database::adding::DB::new().unwrap();
+    match db.replace_current_results(posts) {
+        Ok(_) => {
+            println!(
+                "Successfully added {} merged unique posts to subreddit_search database",
+                posts.len()
+            );
+        }
+        Err(e) => {
+            eprintln!("Failed to save posts to database: {}", e);
+        }
+    }
 
-    if !all_fetched_posts.is_empty() {
-        // Save to subreddit_search table so it persists for the view
-        match db.replace_current_results(&all_fetched_posts) {
-            Ok(_) => {
-                println!(
-                    "Successfully added {} merged unique posts to subreddit_search database",
-                    all_fetched_posts.len()
-                );
-            }
-            Err(e) => {
-                eprintln!("Failed to save posts to database: {}", e);
-            }
+    if let Err(e) = db.record_post_brand_mentions(posts) {
+        eprintln!("Failed to record brand mentions: {}", e);
+    }
+}
+
+// Sets cluster_id on new results that repeat each other or a saved post,
+// and on the saved posts they repeat
+fn tag_duplicates(api_keys: &api_keys::ApiKeys, posts: &mut [PostDataWrapper]) {
+    let saved = DBReader::new().get_all_posts().unwrap_or_else(|e| {
+        eprintln!("Failed to read saved posts for duplicate detection: {}", e);
+        Vec::new()
+    });
+
+    let clusters = {
+        let new_ids: HashSet<i64> = posts.iter().map(|post| post.id).collect();
+        let new: Vec<&PostDataWrapper> = posts.iter().collect();
+        let saved: Vec<&PostDataWrapper> =
+            saved.iter().filter(|post| !new_ids.contains(&post.id)).collect();
+        cluster_ids(&new, &saved, api_keys.duplicate_similarity)
+    };
+
+    for post in posts.iter_mut() {
+        post.cluster_id = clusters.get(&post.id).cloned().unwrap_or_default();
+    }
+
+    let saved_changes: Vec<(i64, String)> = saved
+        .iter()
+        .filter_map(|post| {
+            clusters
+                .get(&post.id)
+                .filter(|cluster_id| **cluster_id != post.cluster_id)
+                .map(|cluster_id| (post.id, cluster_id.clone()))
+        })
+        .collect();
+    if !saved_changes.is_empty() {
+        if let Err(e) =
+            database::adding::DB::new().and_then(|mut db| db.set_cluster_ids(&saved_changes))
+        {
+            eprintln!("Failed to update duplicate clusters: {}", e);
         }
     }
+}
 
-    println!("Total posts added to database: {}", all_fetched_posts.len());
-    Ok(all_fetched_posts) // Return the fetched posts
+// Crossposts and near-duplicate posts among saved posts and current results
+#[tauri::command]
+pub fn get_duplicate_clusters_command() -> Result<Vec<DuplicateCluster>, String> {
+    let config = api_keys::ConfigDirs::read_config().map_err(|e| e.to_string())?;
+
+    let reader = DBReader::new();
+    let mut posts = reader.get_all_posts().map_err(|e| e.to_string())?;
+    let saved_ids: HashSet<i64> = posts.iter().map(|post| post.id).collect();
+    posts.extend(
+        reader
+            .get_all_searched_posts()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .filter(|post| !saved_ids.contains(&post.id)),
+    );
+
+    let clusters = find_clusters(posts, config.api_keys.duplicate_similarity);
+
+    let ids: Vec<(i64, String)> = clusters
+        .iter()
+        .flat_map(|cluster| cluster.posts.iter().map(|post| (post.id, cluster.id.clone())))
+        .collect();
+    let mut db = database::adding::DB::new().map_err(|e| e.to_string())?;
+    db.update_cluster_ids(&ids).map_err(|e| e.to_string())?;
+
+    Ok(clusters)
+}
+
+// Groups saved posts into topics by their text and comments; `k` defaults to about sqrt(posts / 2)
+#[tauri::command]
+pub fn cluster_topics_command(k: Option<usize>) -> Result<Vec<Topic>, String> {
+    let reader = DBReader::new();
+    let posts = reader.get_all_posts().map_err(|e| e.to_string())?;
+    let comments = reader.get_all_comments().map_err(|e| e.to_string())?;
+
+    let documents = post_documents(&posts, &comments);
+    let model = cluster_topics(&documents, k, chrono::Utc::now().timestamp());
+    println!(
+        "Clustered {} of {} saved posts into {} topics",
+        model.assignments.len(),
+        posts.len(),
+        model.topics.len()
+    );
+
+    let mut db = database::adding::DB::new().map_err(|e| e.to_string())?;
+    db.replace_topics(&model).map_err(|e| e.to_string())?;
+
+    Ok(model.topics)
+}
+
+#[tauri::command]
+pub fn get_topics_command() -> Result<Vec<Topic>, String> {
+    DBReader::new().get_topics().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_posts_by_topic_command(topic_id: i64) -> Result<Vec<PostDataWrapper>, String> {
+    DBReader::new()
+        .get_posts_by_topic(topic_id)
+        .map_err(|e| e.to_string())
+}
+
+// Embeds saved posts and comments that are new or edited since the last run
+#[tauri::command]
+pub async fn index_embeddings_command() -> Result<IndexStats, String> {
+    let config = api_keys::ConfigDirs::read_config().map_err(|e| e.to_string())?;
+    let embedder = Embedder::from_api_keys(&config.api_keys).map_err(|e| e.to_string())?;
+    let (posts, comments, stored) = semantic_index()?;
+    let plan = embeddings::plan_index(&posts, &comments, &stored, embedder.model());
+
+    let mut db = database::adding::DB::new().map_err(|e| e.to_string())?;
+    db.delete_embeddings(&plan.stale)
+        .map_err(|e| e.to_string())?;
+    let now = chrono::Utc::now().timestamp();
+    // Saved per batch, so an interrupted run keeps what it already paid for
+    for batch in plan.pending.chunks(embeddings::BATCH_SIZE) {
+        let embedded = embeddings::embed_batch(&embedder, batch, now)
+            .await
+            .map_err(|e| e.to_string())?;
+        db.save_embeddings(&embedded).map_err(|e| e.to_string())?;
+    }
+    println!(
+        "Embedded {} posts and comments with {} ({} unchanged, {} removed)",
+        plan.pending.len(),
+        embedder.model(),
+        plan.unchanged,
+        plan.stale.len()
+    );
+
+    Ok(IndexStats {
+        embedded: plan.pending.len(),
+        unchanged: plan.unchanged,
+        removed: plan.stale.len(),
+    })
+}
+
+// Saved posts closest in meaning to `query`, matched on their text or their comments
+#[tauri::command]
+pub async fn semantic_search_command(
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<SemanticMatch>, String> {
+    let config = api_keys::ConfigDirs::read_config().map_err(|e| e.to_string())?;
+    let embedder = Embedder::from_api_keys(&config.api_keys).map_err(|e| e.to_string())?;
+    let (posts, comments, stored) = semantic_index()?;
+    if !stored.iter().any(|embedding| embedding.model == embedder.model()) {
+        return Err(format!(
+            "Nothing is embedded with {} yet, run index_embeddings_command first",
+            embedder.model()
+        ));
+    }
+
+    let query_vector = embedder
+        .embed(&[query])
+        .await
+        .map_err(|e| e.to_string())?
+        .pop()
+        .ok_or("The embedder returned no vector")?;
+
+    Ok(embeddings::rank_posts(
+        &query_vector,
+        &stored,
+        embedder.model(),
+        posts,
+        comments,
+        None,
+        limit.unwrap_or(embeddings::DEFAULT_MATCHES),
+    ))
+}
+
+// Saved posts closest in meaning to the post with row id `post_id`
+#[tauri::command]
+pub fn find_similar_leads_command(
+    post_id: i64,
+    limit: Option<usize>,
+) -> Result<Vec<SemanticMatch>, String> {
+    let config = api_keys::ConfigDirs::read_config().map_err(|e| e.to_string())?;
+    let embedder = Embedder::from_api_keys(&config.api_keys).map_err(|e| e.to_string())?;
+    let (posts, comments, stored) = semantic_index()?;
+
+    let item_id = post_id.to_string();
+    let vector = stored
+        .iter()
+        .find(|embedding| {
+            embedding.kind == embeddings::POST
+                && embedding.item_id == item_id
+                && embedding.model == embedder.model()
+        })
+        .map(|embedding| embedding.vector.clone())
+        .ok_or(format!(
+            "Post {} has no embedding yet, run index_embeddings_command first",
+            post_id
+        ))?;
+
+    Ok(embeddings::rank_posts(
+        &vector,
+        &stored,
+        embedder.model(),
+        posts,
+        comments,
+        Some(post_id),
+        limit.unwrap_or(embeddings::DEFAULT_MATCHES),
+    ))
+}
+
+// Saved posts, their comments and the stored embeddings
+type SemanticIndex = (
+    Vec<PostDataWrapper>,
+    Vec<CommentDataWrapper>,
+    Vec<StoredEmbedding>,
+);
+
+fn semantic_index() -> Result<SemanticIndex, String> {
+    let reader = DBReader::new();
+    let posts = reader.get_all_posts().map_err(|e| e.to_string())?;
+    let comments = reader.get_all_comments().map_err(|e| e.to_string())?;
+    let stored = reader.get_embeddings().map_err(|e| e.to_string())?;
+    Ok((posts, comments, stored))
 }
 
 #[tauri::command]
@@ -179,9 +512,14 @@ pub fn get_all_searched_posts() -> Result<Vec<PostDataWrapper>, String> {
 }
 
 #[tauri::command]
-pub fn save_single_reddit_command(post: PostDataWrapper) -> Result<PostDataWrapper, String> {
+pub fn save_single_reddit_command(mut post: PostDataWrapper) -> Result<PostDataWrapper, String> {
     let db = database::adding::DB::new().unwrap();
 
+    // Scored here rather than trusted from the client, which may be older or an import
+    let tone = crate::models::sentiment::analyze_post(&post.title, post.selftext.as_deref());
+    post.sentiment = tone.sentiment.as_str().to_string();
+    post.sentiment_score = tone.score;
+
     db.save_single_reddit(&post).unwrap();
     Ok(post)
 }
@@ -225,6 +563,36 @@ pub fn get_all_comments_command() -> Result<Vec<CommentDataWrapper>, String> {
     reader.get_all_comments().map_err(|e| e.to_string())
 }
 
+// Saved posts / comments with the given sentiment, most negative first
+#[tauri::command]
+pub fn get_posts_by_sentiment_command(sentiment: String) -> Result<Vec<PostDataWrapper>, String> {
+    let sentiment = parse_sentiment(&sentiment)?;
+    let reader = DBReader::new();
+    reader
+        .get_posts_by_sentiment(sentiment.as_str())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_comments_by_sentiment_command(
+    sentiment: String,
+) -> Result<Vec<CommentDataWrapper>, String> {
+    let sentiment = parse_sentiment(&sentiment)?;
+    let reader = DBReader::new();
+    reader
+        .get_comments_by_sentiment(sentiment.as_str())
+        .map_err(|e| e.to_string())
+}
+
+fn parse_sentiment(value: &str) -> Result<Sentiment, String> {
+    Sentiment::parse(value).ok_or_else(|| {
+        format!(
+            "Unknown sentiment '{}', expected positive, neutral or negative",
+            value
+        )
+    })
+}
+
 // CLEAR THE COMMENTS TABLE
 #[tauri::command]
 pub fn clear_comments_command() -> Result<String, String> {
@@ -260,6 +628,13 @@ pub async fn update_post_assignee(id: i64, assignee: String, title: String) -> R
     let db = database::adding::DB::new().map_err(|e| e.to_string())?;
     db.update_post_assignee(id, &assignee)
         .map_err(|e| e.to_string())?;
+    // One person handles every copy of the same question
+    let siblings = db
+        .update_cluster_assignee(id, &assignee)
+        .map_err(|e| e.to_string())?;
+    if siblings > 0 {
+        println!("Also assigned {} duplicate posts of {}", siblings, id);
+    }
 
     if !assignee.is_empty() {
         // Send email in background or await it
@@ -296,18 +671,33 @@ pub fn get_reddit_config_command() -> Result<api_keys::ApiKeys, String> {
     Ok(config.api_keys)
 }
 
+// Name kept for the frontend; lists models of `provider`, or of the saved provider when omitted
 #[tauri::command]
-pub async fn get_gemini_models_command(api_key: String) -> Result<Vec<String>, String> {
-    // This command name is kept for frontend compatibility, but it now supports generic providers
-    // We need to read the config to know the provider, but the frontend passes the API key directly for the *current* provider being configured.
-    // Ideally, the frontend should pass the provider too.
-    // For now, let's assume if this is called, we want models for the *currently selected* provider in settings, 
-    // OR we can update the command signature.
-    // Let's read the config to get the provider.
+pub async fn get_gemini_models_command(
+    api_key: String,
+    provider: Option<String>,
+) -> Result<Vec<String>, String> {
+    let models = get_ai_models_command(provider, Some(api_key), None).await?;
+    Ok(models.into_iter().map(|model| model.id).collect())
+}
+
+// Models with their context window and JSON support where the provider reports them
+#[tauri::command]
+pub async fn get_ai_models_command(
+    provider: Option<String>,
+    api_key: Option<String>,
+    refresh: Option<bool>,
+) -> Result<Vec<ModelInfo>, String> {
     let config = api_keys::ConfigDirs::read_config().map_err(|e| e.to_string())?;
-    crate::ai::adapter::get_available_models(&config.api_keys.ai_provider, &api_key)
-        .await
-        .map_err(|e| e.to_string())
+    let provider = provider.unwrap_or_else(|| config.api_keys.ai_provider.clone());
+    crate::ai::adapter::get_available_models(
+        config.api_keys,
+        &provider,
+        api_key.as_deref().unwrap_or(""),
+        refresh.unwrap_or(false),
+    )
+    .await
+    .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -324,6 +714,8 @@ pub fn update_reddit_config_command(mut new_api_keys: api_keys::ApiKeys) -> Resu
     {
         println!("Reddit App Credentials changed, clearing old auth tokens.");
         new_api_keys.reddit_access_token = String::new();
+        new_api_keys.reddit_access_token_expires_at = 0;
+        new_api_keys.reddit_token_scope = String::new();
         new_api_keys.reddit_refresh_token = String::new();
     }
 
@@ -337,32 +729,226 @@ pub async fn submit_reddit_comment_command(
     parent_id: String,
     text: String,
 ) -> Result<CommentDataWrapper, String> {
+    // Reuses the stored access token and only refreshes when it expired or got rejected
+    crate::models::auth::with_user_token(|api_keys, token| {
+        let parent_id = parent_id.clone();
+        let text = text.clone();
+        async move { search::post_comment(&api_keys, &token, &parent_id, &text).await }
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn reddit_whoami_command() -> Result<crate::models::auth::RedditIdentity, String> {
+    crate::models::auth::whoami().await
+}
+
+// Saved posts matching lead_keywords (AND/OR per match_keyword); generate_ai_leads_command
+// has the AI grade the same matches
+#[tauri::command]
+pub async fn find_leads_command(
+    profile_authors: Option<bool>,
+) -> Result<Vec<LocalLead>, String> {
     let config = api_keys::ConfigDirs::read_config().map_err(|e| e.to_string())?;
     let api_keys = config.api_keys;
 
-    let token = if !api_keys.reddit_refresh_token.is_empty() {
-        crate::models::auth::refresh_access_token(
-            &api_keys.reddit_api_id,
-            &api_keys.reddit_api_secret,
-            &api_keys.reddit_refresh_token,
-        )
+    let reader = DBReader::new();
+    let posts = reader.get_all_posts().map_err(|e| e.to_string())?;
+    let comments = reader.get_all_comments().map_err(|e| e.to_string())?;
+    let mut leads = find_local_leads(&api_keys, posts, comments);
+
+    // Attach cached author profiles, fetching missing or stale ones when asked to
+    let now = chrono::Utc::now().timestamp();
+    let mut profiles: HashMap<String, Option<AuthorProfile>> = HashMap::new();
+    for lead in &leads {
+        let author = &lead.post.author;
+        if author != "[deleted]" && !profiles.contains_key(author) {
+            profiles.insert(author.clone(), cached_author_profile(author));
+        }
+    }
+    if profile_authors.unwrap_or(false) {
+        let stale: Vec<String> = profiles
+            .iter()
+            .filter(|(_, profile)| !profile.as_ref().is_some_and(|p| p.is_fresh(&api_keys, now)))
+            .map(|(author, _)| author.clone())
+            .collect();
+        if !stale.is_empty() {
+            let token = app_token(&api_keys).await?;
+            for author in stale {
+                match fetch_and_cache_author(&api_keys, &token, &author, now).await {
+                    Ok(profile) => {
+                        profiles.insert(author, Some(profile));
+                    }
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+        }
+    }
+    for lead in &mut leads {
+        lead.author = profiles.get(&lead.post.author).cloned().flatten();
+    }
+    // Bots and throwaways go last, the order is otherwise kept
+    leads.sort_by_key(|lead| lead.author.as_ref().is_some_and(AuthorProfile::is_suspect));
+
+    Ok(leads)
+}
+
+// Leads graded by the AI, with the records it returned that failed validation
+#[tauri::command]
+pub async fn generate_ai_leads_command() -> Result<LeadBatch, String> {
+    crate::ai::gemini::gemini_generate_leads()
         .await
-        .map_err(|e| e.to_string())?
-    } else {
-        // Fallback or Error
-        return Err("Please login with Reddit in Settings first.".to_string());
-    };
+        .map_err(|e| e.to_string())
+}
 
-    let comment = search::post_comment(&token, &parent_id, &text)
+// How many saved posts match the lead keywords in each subreddit
+#[tauri::command]
+pub fn count_leads_by_subreddit_command() -> Result<Vec<SubredditLeadCount>, String> {
+    let config = api_keys::ConfigDirs::read_config().map_err(|e| e.to_string())?;
+    let reader = DBReader::new();
+    let posts = reader.get_all_posts().map_err(|e| e.to_string())?;
+    let comments = reader.get_all_comments().map_err(|e| e.to_string())?;
+
+    let leads = find_local_leads(&config.api_keys, posts, comments);
+    Ok(count_by_subreddit(&leads))
+}
+
+// Subreddits whose name or description match `query`, with size and activity
+#[tauri::command]
+pub async fn discover_subreddits_command(
+    query: String,
+    include_nsfw: Option<bool>,
+) -> Result<Vec<SubredditInfo>, String> {
+    let config = api_keys::ConfigDirs::read_config().map_err(|e| e.to_string())?;
+    let token = app_token(&config.api_keys).await?;
+
+    discover_subreddits(&config.api_keys, &token, &query, include_nsfw.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn save_subreddit_command(subreddit: SubredditInfo) -> Result<(), String> {
+    let db = database::adding::DB::new().map_err(|e| e.to_string())?;
+    db.save_subreddit(&subreddit).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_saved_subreddits_command() -> Result<Vec<SavedSubreddit>, String> {
+    let reader = DBReader::new();
+    reader.get_saved_subreddits().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_subreddit_notes_command(name: String, notes: String) -> Result<(), String> {
+    let db = database::adding::DB::new().map_err(|e| e.to_string())?;
+    db.update_subreddit_notes(&name, &notes)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_subreddit_weight_command(name: String, weight: f64) -> Result<(), String> {
+    if !(weight.is_finite() && weight >= 0.0) {
+        return Err(format!("Invalid subreddit weight: {}", weight));
+    }
+    let db = database::adding::DB::new().map_err(|e| e.to_string())?;
+    db.update_subreddit_weight(&name, weight)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn remove_saved_subreddit_command(name: String) -> Result<(), String> {
+    let db = database::adding::DB::new().map_err(|e| e.to_string())?;
+    db.remove_subreddit(&name).map_err(|e| e.to_string())
+}
+
+// Rules and self-promotion policy, cached on saved subreddits for a week
+#[tauri::command]
+pub async fn get_subreddit_rules_command(
+    subreddit: String,
+    refresh: Option<bool>,
+) -> Result<SubredditRules, String> {
+    let config = api_keys::ConfigDirs::read_config().map_err(|e| e.to_string())?;
+    let name = subreddit.trim().trim_start_matches("r/").to_string();
+    let now = chrono::Utc::now().timestamp();
+
+    if !refresh.unwrap_or(false) {
+        let cached = database::adding::DB::new()
+            .and_then(|db| db.get_subreddit_rules(&name))
+            .map_err(|e| e.to_string())?;
+        if let Some(cached) = cached {
+            if now - cached.fetched_at < RULES_CACHE_SECONDS {
+                return Ok(cached);
+            }
+        }
+    }
+
+    let token = app_token(&config.api_keys).await?;
+    let rules = fetch_subreddit_rules(&config.api_keys, &token, &name)
         .await
         .map_err(|e| e.to_string())?;
+    let rules = SubredditRules::new(&name, &rules, now);
+
+    if let Err(e) = database::adding::DB::new().and_then(|db| db.update_subreddit_rules(&rules)) {
+        eprintln!("Failed to cache rules of r/{}: {}", name, e);
+    }
 
-    Ok(comment)
+    Ok(rules)
 }
+
+// Karma, account age, active subreddits and keyword history of a Redditor
 #[tauri::command]
-pub async fn ask_gemini_command(question: String) -> Result<String, String> {
+pub async fn get_author_profile_command(
+    username: String,
+    refresh: Option<bool>,
+) -> Result<AuthorProfile, String> {
+    let config = api_keys::ConfigDirs::read_config().map_err(|e| e.to_string())?;
+    let api_keys = config.api_keys;
+    let username = username.trim().trim_start_matches("u/").to_string();
+    let now = chrono::Utc::now().timestamp();
+
+    if !refresh.unwrap_or(false) {
+        if let Some(profile) = cached_author_profile(&username) {
+            if profile.is_fresh(&api_keys, now) {
+                return Ok(profile);
+            }
+        }
+    }
+
+    let token = app_token(&api_keys).await?;
+    fetch_and_cache_author(&api_keys, &token, &username, now).await
+}
+
+fn cached_author_profile(username: &str) -> Option<AuthorProfile> {
+    let db = database::adding::DB::new().ok()?;
+    db.get_author_profile(username).ok().flatten()
+}
+
+async fn fetch_and_cache_author(
+    api_keys: &api_keys::ApiKeys,
+    token: &str,
+    username: &str,
+    now: i64,
+) -> Result<AuthorProfile, String> {
+    let profile = fetch_author_profile(api_keys, token, username, now)
+        .await
+        .map_err(|e| format!("Failed to fetch profile of u/{}: {}", username, e))?;
+
+    if let Err(e) = database::adding::DB::new().and_then(|db| db.save_author_profile(&profile)) {
+        eprintln!("Failed to cache profile of u/{}: {}", username, e);
+    }
+
+    Ok(profile)
+}
+
+// With a session_id the earlier turns are sent along and the exchange is saved
+#[tauri::command]
+pub async fn ask_gemini_command(
+    question: String,
+    session_id: Option<i64>,
+) -> Result<String, String> {
     // Uses the generic adapter which checks the configured provider
-    crate::ai::adapter::ask_ai(&question)
+    crate::ai::adapter::ask_ai(&question, session_id)
         .await
         .map_err(|e| e.to_string())
 }
@@ -370,28 +956,383 @@ pub async fn ask_gemini_command(question: String) -> Result<String, String> {
 #[tauri::command]
 pub async fn start_reddit_auth_flow_command() -> Result<String, String> {
     let config = api_keys::ConfigDirs::read_config().map_err(|e| e.to_string())?;
-    crate::models::auth::start_auth_flow(
-        config.api_keys.reddit_api_id,
-        config.api_keys.reddit_api_secret,
+    crate::models::auth::start_auth_flow(config.api_keys).await
+}
+
+// Streams the answer as `ai-stream` events tagged with `request_id` instead of returning it
+#[tauri::command]
+pub async fn ask_gemini_stream_command(
+    app: tauri::AppHandle,
+    request_id: String,
+    question: String,
+    session_id: Option<i64>,
+) -> Result<(), String> {
+    let provider = crate::ai::adapter::configured_provider().map_err(|e| e.to_string())?;
+    let messages = crate::ai::adapter::question_messages(&question, session_id)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Only finished answers are saved, not cancelled or failed ones
+    let emit = stream_emitter(app);
+    let emit = move |event: StreamEvent| {
+        if let (Some(session_id), StreamEvent::Done { text, .. }) = (session_id, &event) {
+            if let Err(e) = crate::ai::adapter::save_exchange(session_id, &question, text) {
+                eprintln!("Failed to save the answer to chat session {}: {}", session_id, e);
+            }
+        }
+        emit(event)
+    };
+    stream::start(&request_id, provider, messages, emit).map_err(|e| e.to_string())
+}
+
+// An empty title is replaced by the first question
+#[tauri::command]
+pub fn create_chat_session_command(title: Option<String>) -> Result<ChatSession, String> {
+    let db = database::adding::DB::new().map_err(|e| e.to_string())?;
+    let id = db
+        .create_chat_session(title.unwrap_or_default().trim(), chrono::Utc::now().timestamp())
+        .map_err(|e| e.to_string())?;
+    existing_chat_session(id)
+}
+
+#[tauri::command]
+pub fn list_chat_sessions_command() -> Result<Vec<ChatSession>, String> {
+    DBReader::new().get_chat_sessions().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_chat_messages_command(session_id: i64) -> Result<Vec<StoredChatMessage>, String> {
+    existing_chat_session(session_id)?;
+    DBReader::new()
+        .get_chat_messages(session_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn rename_chat_session_command(session_id: i64, title: String) -> Result<ChatSession, String> {
+    let db = database::adding::DB::new().map_err(|e| e.to_string())?;
+    if db
+        .rename_chat_session(session_id, title.trim())
+        .map_err(|e| e.to_string())?
+        == 0
+    {
+        return Err(format!("Chat session {} not found", session_id));
+    }
+    existing_chat_session(session_id)
+}
+
+#[tauri::command]
+pub fn delete_chat_session_command(session_id: i64) -> Result<(), String> {
+    let mut db = database::adding::DB::new().map_err(|e| e.to_string())?;
+    if db
+        .delete_chat_session(session_id)
+        .map_err(|e| e.to_string())?
+        == 0
+    {
+        return Err(format!("Chat session {} not found", session_id));
+    }
+    Ok(())
+}
+
+// Writes the conversation to the Reddit_data folder and returns the file path
+#[tauri::command]
+pub fn export_chat_session_command(
+    session_id: i64,
+    format: Option<String>,
+) -> Result<String, String> {
+    let session = existing_chat_session(session_id)?;
+    let messages = DBReader::new()
+        .get_chat_messages(session_id)
+        .map_err(|e| e.to_string())?;
+
+    let path = crate::exports::chat::export_chat_session(
+        &session,
+        &messages,
+        format.as_deref().unwrap_or("markdown"),
     )
-    .await
+    .map_err(|e| e.to_string())?;
+    Ok(path.display().to_string())
+}
+
+fn existing_chat_session(session_id: i64) -> Result<ChatSession, String> {
+    DBReader::new()
+        .get_chat_session(session_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Chat session {} not found", session_id))
+}
+
+// A single reply from the title and body alone, in the default tone unless `tone` is given
+#[tauri::command]
+pub async fn generate_reply_command(
+    post_title: String,
+    post_body: String,
+    tone: Option<String>,
+) -> Result<String, String> {
+    let messages = reply_messages(&post_title, &post_body, tone.as_deref())?;
+    let provider = crate::ai::adapter::configured_provider().map_err(|e| e.to_string())?;
+
+    provider.chat(&messages).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn generate_reply_command(post_title: String, post_body: String) -> Result<String, String> {
+pub async fn generate_reply_stream_command(
+    app: tauri::AppHandle,
+    request_id: String,
+    post_title: String,
+    post_body: String,
+    tone: Option<String>,
+) -> Result<(), String> {
+    let messages = reply_messages(&post_title, &post_body, tone.as_deref())?;
+    let provider = crate::ai::adapter::configured_provider().map_err(|e| e.to_string())?;
+
+    stream::start(&request_id, provider, messages, stream_emitter(app)).map_err(|e| e.to_string())
+}
+
+// Several candidate replies to a stored post, written with its top comments and
+// subreddit rules in view, each with a self-promotion risk
+#[tauri::command]
+pub async fn draft_replies_command(
+    post_id: i64,
+    tone: Option<String>,
+    variants: Option<usize>,
+) -> Result<ReplyDrafts, String> {
     let config = api_keys::ConfigDirs::read_config().map_err(|e| e.to_string())?;
-    let preamble = if config.api_keys.reply_preamble.trim().is_empty() {
-        "You are a helpful and knowledgeable assistant. Draft a helpful, relevant, and polite reply to the following Reddit post.".to_string()
-    } else {
-        config.api_keys.reply_preamble
-    };
+    let settings = &config.api_keys.replies;
+    let tone = settings.tone(tone.as_deref())?;
+    let variants = settings.variant_count(variants);
+    let thread = reply_thread(&config.api_keys, post_id).await?;
 
-    let full_prompt = format!(
-        "{}\n\nTitle: {}\n\nContent:\n{}",
-        preamble, post_title, post_body
+    let messages = replies::reply_messages(
+        &reply_preamble(&config.api_keys),
+        settings,
+        &tone,
+        &thread,
+        variants,
     );
+    let provider = crate::ai::adapter::configured_provider().map_err(|e| e.to_string())?;
+    let reply = provider
+        .chat_json(&messages, "replies", &replies::reply_schema())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(ReplyDrafts {
+        tone: tone.name,
+        drafts: replies::drafts_from_reply(
+            &reply,
+            &config.api_keys.branded_keywords,
+            &thread.self_promotion,
+        ),
+    })
+}
+
+// Built-in tones and the ones from settings.toml, for draft_replies_command
+#[tauri::command]
+pub fn get_reply_tones_command() -> Result<Vec<TonePreset>, String> {
+    let config = api_keys::ConfigDirs::read_config().map_err(|e| e.to_string())?;
+    Ok(config.api_keys.replies.tone_presets())
+}
+
+// Drafts replies for every post in the background, a few at a time, and saves them
+// as "draft" for review. Progress goes out as `reply-queue` events tagged with `job_id`.
+#[tauri::command]
+pub async fn start_reply_queue_command(
+    app: tauri::AppHandle,
+    job_id: String,
+    post_ids: Vec<i64>,
+    tone: Option<String>,
+    variants: Option<usize>,
+) -> Result<(), String> {
+    if post_ids.is_empty() {
+        return Err("No posts to draft replies for".to_string());
+    }
+    // A bad tone fails here rather than once per post
+    let config = api_keys::ConfigDirs::read_config().map_err(|e| e.to_string())?;
+    let settings = &config.api_keys.replies;
+    settings.tone(tone.as_deref())?;
+    let concurrency = settings.concurrency;
+
+    let queue_job = job_id.clone();
+    let draft = move |post_id: i64| {
+        let tone = tone.clone();
+        let job_id = queue_job.clone();
+        async move {
+            let drafts = draft_replies_command(post_id, tone, variants).await?;
+            let now = chrono::Utc::now().timestamp();
+            let rows: Vec<StoredReplyDraft> = drafts
+                .drafts
+                .into_iter()
+                .map(|draft| StoredReplyDraft::new(post_id, &job_id, &drafts.tone, draft, now))
+                .collect();
+
+            let mut db = database::adding::DB::new().map_err(|e| e.to_string())?;
+            db.replace_reply_drafts(post_id, &rows)
+                .map_err(|e| e.to_string())?;
+            Ok(rows.len())
+        }
+    };
+    let emit = move |event: QueueEvent| {
+        if let Err(e) = app.emit(reply_queue::REPLY_QUEUE_EVENT, event) {
+            eprintln!("Failed to emit reply queue event: {}", e);
+        }
+    };
+    reply_queue::start(&job_id, post_ids, concurrency, draft, emit)
+}
+
+// Posts already drafted keep their drafts; returns false when the job had already finished
+#[tauri::command]
+pub fn cancel_reply_queue_command(job_id: String) -> bool {
+    reply_queue::cancel(&job_id)
+}
+
+#[tauri::command]
+pub fn get_reply_drafts_command(
+    status: Option<String>,
+    post_id: Option<i64>,
+) -> Result<Vec<StoredReplyDraft>, String> {
+    if let Some(status) = &status {
+        check_reply_status(status)?;
+    }
+    DBReader::new()
+        .get_reply_drafts(status.as_deref(), post_id)
+        .map_err(|e| e.to_string())
+}
 
-    crate::ai::adapter::ask_ai(&full_prompt)
+// Sets the review status and/or edits the text of a saved draft
+#[tauri::command]
+pub fn update_reply_draft_command(
+    id: i64,
+    status: Option<String>,
+    text: Option<String>,
+) -> Result<StoredReplyDraft, String> {
+    if let Some(status) = &status {
+        check_reply_status(status)?;
+    }
+    let db = database::adding::DB::new().map_err(|e| e.to_string())?;
+    let updated = db
+        .update_reply_draft(
+            id,
+            status.as_deref(),
+            text.as_deref(),
+            chrono::Utc::now().timestamp(),
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Reply draft {} not found", id));
+    }
+
+    DBReader::new()
+        .get_reply_draft(id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Reply draft {} not found", id))
+}
+
+fn check_reply_status(status: &str) -> Result<(), String> {
+    if reply_queue::REPLY_STATUSES.contains(&status) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unknown reply status {:?}, use one of: {}",
+            status,
+            reply_queue::REPLY_STATUSES.join(", ")
+        ))
+    }
+}
+
+// Answers with the AI calling read-only commands itself; changes wait for confirm_ai_actions_command
+#[tauri::command]
+pub async fn ask_ai_with_tools_command(question: String) -> Result<ToolReply, String> {
+    let provider = crate::ai::adapter::configured_provider().map_err(|e| e.to_string())?;
+    tools::ask(&provider, &question)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Runs the approved pending actions (by call id), declines the others and continues the answer
+#[tauri::command]
+pub async fn confirm_ai_actions_command(
+    conversation_id: String,
+    approved: Vec<String>,
+) -> Result<ToolReply, String> {
+    let provider = crate::ai::adapter::configured_provider().map_err(|e| e.to_string())?;
+    tools::confirm(&provider, &conversation_id, &approved)
         .await
         .map_err(|e| e.to_string())
 }
+
+// Returns false when the stream had already finished
+#[tauri::command]
+pub fn cancel_ai_stream_command(request_id: String) -> bool {
+    stream::cancel(&request_id)
+}
+
+fn stream_emitter(app: tauri::AppHandle) -> impl Fn(StreamEvent) + Clone + Send + 'static {
+    move |event| {
+        if let Err(e) = app.emit(stream::AI_STREAM_EVENT, event) {
+            eprintln!("Failed to emit AI stream event: {}", e);
+        }
+    }
+}
+
+fn reply_preamble(api_keys: &api_keys::ApiKeys) -> String {
+    if api_keys.reply_preamble.trim().is_empty() {
+        "You are a helpful and knowledgeable assistant. Draft a helpful, relevant, and polite reply to the following Reddit post.".to_string()
+    } else {
+        api_keys.reply_preamble.clone()
+    }
+}
+
+fn reply_messages(
+    post_title: &str,
+    post_body: &str,
+    tone: Option<&str>,
+) -> Result<Vec<ChatMessage>, String> {
+    let config = api_keys::ConfigDirs::read_config().map_err(|e| e.to_string())?;
+    let settings = &config.api_keys.replies;
+    let thread = ThreadContext {
+        title: post_title.to_string(),
+        body: post_body.to_string(),
+        ..ThreadContext::default()
+    };
+
+    Ok(replies::reply_messages(
+        &reply_preamble(&config.api_keys),
+        settings,
+        &settings.tone(tone)?,
+        &thread,
+        1,
+    ))
+}
+
+// The post with its top saved comments and its subreddit's rules
+async fn reply_thread(api_keys: &api_keys::ApiKeys, post_id: i64) -> Result<ThreadContext, String> {
+    let reader = DBReader::new();
+    let post = reader
+        .get_post(post_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Post {} not found", post_id))?;
+    let comments = reader
+        .get_post_comments(post.name.trim_start_matches("t3_"))
+        .map_err(|e| e.to_string())?;
+
+    // Drafting still works without the rules, e.g. when Reddit can't be reached
+    let (rules, self_promotion) = if post.subreddit.is_empty() {
+        (String::new(), String::new())
+    } else {
+        match get_subreddit_rules_command(post.subreddit.clone(), None).await {
+            Ok(rules) => (rules.rules, rules.self_promotion),
+            Err(e) => {
+                eprintln!("Drafting without the rules of r/{}: {}", post.subreddit, e);
+                (String::new(), String::new())
+            }
+        }
+    };
+
+    Ok(ThreadContext {
+        title: post.title,
+        body: post.selftext.unwrap_or_default(),
+        subreddit: post.subreddit,
+        comments: replies::top_comments(comments, api_keys.replies.max_comments),
+        rules,
+        self_promotion,
+    })
+}
@@ -1,9 +1,18 @@
 use chrono::{DateTime, Utc};
 use directories::BaseDirs;
-use rusqlite::{params, Connection, Result as RusqliteResult};
+use rusqlite::{params, Connection, Result as RusqliteResult, Row, Statement, Transaction};
 use serde::{Deserialize, Serialize};
 use std::{i64, path::PathBuf};
 
+use crate::ai::embeddings::{vector_from_bytes, vector_to_bytes, StoredEmbedding};
+use crate::ai::history::{title_from_question, ChatSession, StoredChatMessage};
+use crate::ai::reply_queue::StoredReplyDraft;
+use crate::models::authors::AuthorProfile;
+use crate::models::brands::split_tags;
+use crate::models::subreddits::{SavedSubreddit, SubredditInfo, SubredditRules};
+use crate::models::topics::{Topic, TopicModel};
+use crate::models::sentiment;
+
 // Post data structure
 #[derive(Debug, Deserialize, Serialize)]
 pub struct PostDataWrapper {
@@ -29,6 +38,155 @@ pub struct PostDataWrapper {
     pub intent: String,
     pub date_added: i64,
     pub interest: i64,
+    // Listing metadata, defaulted so older clients can still send posts without it
+    #[serde(default)]
+    pub link_flair_text: Option<String>,
+    #[serde(default)]
+    pub over_18: bool,
+    #[serde(default)]
+    pub upvote_ratio: f64,
+    #[serde(default)]
+    pub stickied: bool,
+    #[serde(default)]
+    pub locked: bool,
+    #[serde(default)]
+    pub domain: String,
+    #[serde(default)]
+    pub author_flair_text: Option<String>,
+    #[serde(default)]
+    pub crosspost_parent: Option<String>,
+    #[serde(default)]
+    pub media_type: Option<String>, // image, video, gallery, link...
+    #[serde(default)]
+    pub brand_mentions: String, // Comma separated, like sort_type
+    #[serde(default = "sentiment::default_label")]
+    pub sentiment: String, // positive, neutral or negative
+    #[serde(default)]
+    pub sentiment_score: f64,
+    // Fullname of the oldest post in its duplicate/crosspost cluster, empty when unique
+    #[serde(default)]
+    pub cluster_id: String,
+}
+
+/// Columns shared by `reddit_posts` and `subreddit_search`, in `PostDataWrapper::from_row` order.
+pub const POST_COLUMNS: &str = "id, timestamp, formatted_date, title, url, sort_type, relevance_score, subreddit, permalink, engaged, assignee, notes, name, selftext, author, score, thumbnail, is_self, num_comments, intent, date_added, interest, link_flair_text, over_18, upvote_ratio, stickied, locked, domain, author_flair_text, crosspost_parent, media_type, brand_mentions, sentiment, sentiment_score, cluster_id";
+
+// Columns added after the tables were first created
+const POST_MIGRATIONS: &[&str] = &[
+    "date_added INTEGER NOT NULL DEFAULT 0",
+    "interest INTEGER NOT NULL DEFAULT 0",
+    "link_flair_text TEXT",
+    "over_18 INTEGER NOT NULL DEFAULT 0",
+    "upvote_ratio REAL NOT NULL DEFAULT 0",
+    "stickied INTEGER NOT NULL DEFAULT 0",
+    "locked INTEGER NOT NULL DEFAULT 0",
+    "domain TEXT NOT NULL DEFAULT ''",
+    "author_flair_text TEXT",
+    "crosspost_parent TEXT",
+    "media_type TEXT",
+    "brand_mentions TEXT NOT NULL DEFAULT ''",
+    "sentiment TEXT NOT NULL DEFAULT 'neutral'",
+    "sentiment_score REAL NOT NULL DEFAULT 0",
+    "cluster_id TEXT NOT NULL DEFAULT ''",
+];
+
+impl PostDataWrapper {
+    /// Maps a row selected with `POST_COLUMNS`.
+    pub fn from_row(row: &Row) -> RusqliteResult<Self> {
+        Ok(PostDataWrapper {
+            id: row.get(0)?,
+            timestamp: row.get(1)?,
+            formatted_date: row.get(2)?,
+            title: row.get(3)?,
+            url: row.get(4)?,
+            sort_type: row.get(5)?,
+            relevance_score: row.get(6)?,
+            subreddit: row.get(7)?,
+            permalink: row.get(8)?,
+            engaged: row.get(9)?,
+            assignee: row.get(10)?,
+            notes: row.get(11)?,
+            name: row.get(12)?,
+            selftext: row.get(13)?,
+            author: row.get(14)?,
+            score: row.get(15)?,
+            thumbnail: row.get(16)?,
+            is_self: row.get(17)?,
+            num_comments: row.get(18)?,
+            intent: row.get(19)?,
+            date_added: row.get(20)?,
+            interest: row.get(21)?,
+            link_flair_text: row.get(22)?,
+            over_18: row.get(23)?,
+            upvote_ratio: row.get(24)?,
+            stickied: row.get(25)?,
+            locked: row.get(26)?,
+            domain: row.get(27)?,
+            author_flair_text: row.get(28)?,
+            crosspost_parent: row.get(29)?,
+            media_type: row.get(30)?,
+            brand_mentions: row.get(31)?,
+            sentiment: row.get(32)?,
+            sentiment_score: row.get(33)?,
+            cluster_id: row.get(34)?,
+        })
+    }
+}
+
+#[cfg(test)]
+impl PostDataWrapper {
+    /// A plain self post for tests, named `t3_<id in base 36>` like Reddit's fullnames.
+    /// Set other fields with struct update syntax.
+    pub fn sample(id: i64, title: &str) -> Self {
+        PostDataWrapper {
+            id,
+            timestamp: 0,
+            formatted_date: String::new(),
+            title: title.to_string(),
+            url: String::new(),
+            sort_type: "new".to_string(),
+            relevance_score: 0,
+            subreddit: "smallbusiness".to_string(),
+            permalink: String::new(),
+            engaged: 0,
+            assignee: String::new(),
+            notes: String::new(),
+            name: format!("t3_{}", base36(id)),
+            selftext: Some(String::new()),
+            author: "someone".to_string(),
+            score: 1,
+            thumbnail: Some(String::new()),
+            is_self: true,
+            num_comments: 0,
+            intent: "Low".to_string(),
+            date_added: 0,
+            interest: 0,
+            link_flair_text: None,
+            over_18: false,
+            upvote_ratio: 0.0,
+            stickied: false,
+            locked: false,
+            domain: String::new(),
+            author_flair_text: None,
+            crosspost_parent: None,
+            media_type: None,
+            brand_mentions: String::new(),
+            sentiment: sentiment::default_label(),
+            sentiment_score: 0.0,
+            cluster_id: String::new(),
+        }
+    }
+}
+
+// Reddit ids are base 36; posts and comments refer to each other that way
+#[cfg(test)]
+pub fn base36(mut id: i64) -> String {
+    let mut digits = Vec::new();
+    while id > 0 {
+        digits.push(std::char::from_digit((id % 36) as u32, 36).unwrap());
+        id /= 36;
+    }
+    digits.iter().rev().collect()
 }
 
 // Comment data structure
@@ -47,6 +205,221 @@ pub struct CommentDataWrapper {
     pub post_title: String,
     pub engaged: i64, // Changed from bool to i64
     pub assignee: String,
+    #[serde(default)]
+    pub brand_mentions: String,
+    #[serde(default = "sentiment::default_label")]
+    pub sentiment: String,
+    #[serde(default)]
+    pub sentiment_score: f64,
+}
+
+/// Columns of `reddit_comments`, in `CommentDataWrapper::from_row` order.
+pub const COMMENT_COLUMNS: &str = "id, post_id, body, author, timestamp, formatted_date, score, permalink, parent_id, subreddit, post_title, engaged, assignee, brand_mentions, sentiment, sentiment_score";
+
+const COMMENT_MIGRATIONS: &[&str] = &[
+    "brand_mentions TEXT NOT NULL DEFAULT ''",
+    "sentiment TEXT NOT NULL DEFAULT 'neutral'",
+    "sentiment_score REAL NOT NULL DEFAULT 0",
+];
+
+impl CommentDataWrapper {
+    /// Maps a row selected with `COMMENT_COLUMNS`.
+    pub fn from_row(row: &Row) -> RusqliteResult<Self> {
+        Ok(CommentDataWrapper {
+            id: row.get(0)?,
+            post_id: row.get(1)?,
+            body: row.get(2)?,
+            author: row.get(3)?,
+            timestamp: row.get(4)?,
+            formatted_date: row.get(5)?,
+            score: row.get(6)?,
+            permalink: row.get(7)?,
+            parent_id: row.get(8)?,
+            subreddit: row.get(9)?,
+            post_title: row.get(10)?,
+            engaged: row.get(11)?,
+            assignee: row.get(12)?,
+            brand_mentions: row.get(13)?,
+            sentiment: row.get(14)?,
+            sentiment_score: row.get(15)?,
+        })
+    }
+}
+
+#[cfg(test)]
+impl CommentDataWrapper {
+    /// A comment for tests on the post with base 36 id `post_id`.
+    pub fn sample(id: &str, post_id: &str, body: &str) -> Self {
+        CommentDataWrapper {
+            id: id.to_string(),
+            post_id: post_id.to_string(),
+            body: body.to_string(),
+            author: "someone".to_string(),
+            timestamp: 0,
+            formatted_date: String::new(),
+            score: 1,
+            permalink: String::new(),
+            parent_id: format!("t3_{}", post_id),
+            subreddit: "smallbusiness".to_string(),
+            post_title: String::new(),
+            engaged: 0,
+            assignee: String::new(),
+            brand_mentions: String::new(),
+            sentiment: sentiment::default_label(),
+            sentiment_score: 0.0,
+        }
+    }
+}
+
+// Mentions of one brand in one period, for share-of-voice reporting
+#[derive(Debug, Serialize)]
+pub struct BrandMentionCount {
+    pub brand: String,
+    pub period: String,
+    pub mentions: i64,
+    // Fraction of all brand mentions in the period
+    pub share: f64,
+}
+
+/// Columns of `authors`, in `AuthorProfile::from_row` order.
+pub const AUTHOR_COLUMNS: &str = "username, link_karma, comment_karma, account_created, verified_email, suspended, recent_posts, recent_comments, active_subreddits, keyword_mentions, flags, summary, fetched_at";
+
+impl AuthorProfile {
+    /// Maps a row selected with `AUTHOR_COLUMNS`.
+    pub fn from_row(row: &Row) -> RusqliteResult<Self> {
+        Ok(AuthorProfile {
+            username: row.get(0)?,
+            link_karma: row.get(1)?,
+            comment_karma: row.get(2)?,
+            account_created: row.get(3)?,
+            verified_email: row.get(4)?,
+            suspended: row.get(5)?,
+            recent_posts: row.get(6)?,
+            recent_comments: row.get(7)?,
+            active_subreddits: split_tags(&row.get::<_, String>(8)?).map(str::to_string).collect(),
+            keyword_mentions: row.get(9)?,
+            flags: split_tags(&row.get::<_, String>(10)?).map(str::to_string).collect(),
+            summary: row.get(11)?,
+            fetched_at: row.get(12)?,
+        })
+    }
+}
+
+/// Columns of `subreddits`, in `SavedSubreddit::from_row` order.
+pub const SUBREDDIT_COLUMNS: &str = "name, title, description, subscribers, active_users, over_18, url, notes, weight, rules, self_promotion, rules_fetched_at, added_at";
+
+impl SavedSubreddit {
+    /// Maps a row selected with `SUBREDDIT_COLUMNS`.
+    pub fn from_row(row: &Row) -> RusqliteResult<Self> {
+        Ok(SavedSubreddit {
+            info: SubredditInfo {
+                name: row.get(0)?,
+                title: row.get(1)?,
+                description: row.get(2)?,
+                subscribers: row.get(3)?,
+                active_users: row.get(4)?,
+                over_18: row.get(5)?,
+                url: row.get(6)?,
+            },
+            notes: row.get(7)?,
+            weight: row.get(8)?,
+            rules: row.get(9)?,
+            self_promotion: row.get(10)?,
+            rules_fetched_at: row.get(11)?,
+            added_at: row.get(12)?,
+        })
+    }
+}
+
+/// Columns of `topics`, in `Topic::from_row` order.
+pub const TOPIC_COLUMNS: &str = "id, label, top_terms, size, created_at";
+
+impl Topic {
+    /// Maps a row selected with `TOPIC_COLUMNS`.
+    pub fn from_row(row: &Row) -> RusqliteResult<Self> {
+        Ok(Topic {
+            id: row.get(0)?,
+            label: row.get(1)?,
+            top_terms: split_tags(&row.get::<_, String>(2)?).map(str::to_string).collect(),
+            size: row.get(3)?,
+            created_at: row.get(4)?,
+        })
+    }
+}
+
+/// Columns of `embeddings`, in `StoredEmbedding::from_row` order.
+pub const EMBEDDING_COLUMNS: &str = "kind, item_id, model, content_hash, vector, created_at";
+
+impl StoredEmbedding {
+    /// Maps a row selected with `EMBEDDING_COLUMNS`.
+    pub fn from_row(row: &Row) -> RusqliteResult<Self> {
+        Ok(StoredEmbedding {
+            kind: row.get(0)?,
+            item_id: row.get(1)?,
+            model: row.get(2)?,
+            content_hash: row.get(3)?,
+            vector: vector_from_bytes(&row.get::<_, Vec<u8>>(4)?),
+            created_at: row.get(5)?,
+        })
+    }
+}
+
+/// Columns of `chat_sessions`, in `ChatSession::from_row` order.
+pub const CHAT_SESSION_COLUMNS: &str = "id, title, created_at, updated_at, \
+    (SELECT COUNT(*) FROM chat_messages WHERE chat_messages.session_id = chat_sessions.id)";
+
+impl ChatSession {
+    /// Maps a row selected with `CHAT_SESSION_COLUMNS`.
+    pub fn from_row(row: &Row) -> RusqliteResult<Self> {
+        Ok(ChatSession {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            created_at: row.get(2)?,
+            updated_at: row.get(3)?,
+            message_count: row.get(4)?,
+        })
+    }
+}
+
+/// Columns of `chat_messages`, in `StoredChatMessage::from_row` order.
+pub const CHAT_MESSAGE_COLUMNS: &str = "id, session_id, role, content, created_at";
+
+impl StoredChatMessage {
+    /// Maps a row selected with `CHAT_MESSAGE_COLUMNS`.
+    pub fn from_row(row: &Row) -> RusqliteResult<Self> {
+        Ok(StoredChatMessage {
+            id: row.get(0)?,
+            session_id: row.get(1)?,
+            role: row.get(2)?,
+            content: row.get(3)?,
+            created_at: row.get(4)?,
+        })
+    }
+}
+
+/// Columns of `reply_drafts`, in `StoredReplyDraft::from_row` order.
+pub const REPLY_DRAFT_COLUMNS: &str = "id, post_id, job_id, tone, text, approach, \
+    promotion_risk, risk_level, risk_reasons, status, created_at, updated_at";
+
+impl StoredReplyDraft {
+    /// Maps a row selected with `REPLY_DRAFT_COLUMNS`.
+    pub fn from_row(row: &Row) -> RusqliteResult<Self> {
+        let risk_reasons: String = row.get(8)?;
+        Ok(StoredReplyDraft {
+            id: row.get(0)?,
+            post_id: row.get(1)?,
+            job_id: row.get(2)?,
+            tone: row.get(3)?,
+            text: row.get(4)?,
+            approach: row.get(5)?,
+            promotion_risk: row.get(6)?,
+            risk_level: row.get(7)?,
+            risk_reasons: risk_reasons.lines().map(str::to_string).collect(),
+            status: row.get(9)?,
+            created_at: row.get(10)?,
+            updated_at: row.get(11)?,
+        })
+    }
 }
 
 pub struct DB {
@@ -123,22 +496,33 @@ impl DB {
                 is_self INTEGER NOT NULL DEFAULT 0,
                 num_comments INTEGER NOT NULL DEFAULT 0,
                 intent TEXT NOT NULL DEFAULT 'low',
-                date_added INTEGER NOT NULL DEFAULT 0
+                date_added INTEGER NOT NULL DEFAULT 0,
+                link_flair_text TEXT,
+                over_18 INTEGER NOT NULL DEFAULT 0,
+                upvote_ratio REAL NOT NULL DEFAULT 0,
+                stickied INTEGER NOT NULL DEFAULT 0,
+                locked INTEGER NOT NULL DEFAULT 0,
+                domain TEXT NOT NULL DEFAULT '',
+                author_flair_text TEXT,
+                crosspost_parent TEXT,
+                media_type TEXT,
+                brand_mentions TEXT NOT NULL DEFAULT '',
+                sentiment TEXT NOT NULL DEFAULT 'neutral',
+                sentiment_score REAL NOT NULL DEFAULT 0,
+                cluster_id TEXT NOT NULL DEFAULT ''
             )",
             [],
         )?;
 
-        let _ = self.conn.execute(
-            "ALTER TABLE reddit_posts ADD COLUMN date_added INTEGER NOT NULL DEFAULT 0",
-            [],
-        );
-
-        let _ = self.conn.execute(
-            "ALTER TABLE reddit_posts ADD COLUMN interest INTEGER NOT NULL DEFAULT 0",
-            [],
-        );
+        self.migrate_post_columns("reddit_posts");
 
         self.create_comments_table()?;
+        self.create_authors_table()?;
+        self.create_subreddits_table()?;
+        self.create_topics_tables()?;
+        self.create_embeddings_table()?;
+        self.create_chat_tables()?;
+        self.create_reply_drafts_table()?;
         Ok(())
     }
 
@@ -146,40 +530,75 @@ impl DB {
     pub fn save_single_reddit(&self, post: &PostDataWrapper) -> RusqliteResult<()> {
         println!("Attempting to save post: {:#?}", &post);
 
-        let query = "INSERT OR IGNORE INTO reddit_posts (id, timestamp, formatted_date, title, url, sort_type, relevance_score, subreddit, permalink, engaged, assignee, notes, name, selftext, author, score, thumbnail, is_self, num_comments, intent, date_added, interest)
-                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
-
-        self.conn.execute(
-            query,
-            params![
-                post.id,
-                post.timestamp,
-                post.formatted_date,
-                post.title,
-                post.url,
-                post.sort_type,
-                post.relevance_score,
-                post.subreddit,
-                post.permalink,
-                post.engaged,
-                post.assignee,
-                post.notes,
-                post.name,
-                post.selftext,
-                post.author,
-                post.score,
-                post.thumbnail,
-                post.is_self,
-                post.num_comments,
-                post.intent,
-                if post.date_added == 0 { Utc::now().timestamp() } else { post.date_added },
-                post.interest
-            ],
-        )?;
+        let mut stmt = self
+            .conn
+            .prepare(&post_insert_sql("INSERT OR IGNORE", "reddit_posts"))?;
+        execute_post_insert(&mut stmt, post)?;
 
         Ok(())
     }
 
+    // ALTER TABLE fails when the column already exists, which is fine
+    fn migrate_post_columns(&self, table: &str) {
+        for column in POST_MIGRATIONS {
+            let added = self
+                .conn
+                .execute(&format!("ALTER TABLE {} ADD COLUMN {}", table, column), [])
+                .is_ok();
+            // Posts saved before sentiment was stored are scored once, when the column appears
+            if added && column.starts_with("sentiment_score ") {
+                if let Err(e) = self.backfill_post_sentiment(table) {
+                    eprintln!("Failed to score the existing posts of {}: {}", table, e);
+                }
+            }
+        }
+    }
+
+    fn backfill_post_sentiment(&self, table: &str) -> RusqliteResult<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut select = tx.prepare(&format!("SELECT id, title, selftext FROM {}", table))?;
+            let posts = select
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                    ))
+                })?
+                .collect::<RusqliteResult<Vec<_>>>()?;
+
+            let mut update = tx.prepare(&format!(
+                "UPDATE {} SET sentiment = ?2, sentiment_score = ?3 WHERE id = ?1",
+                table
+            ))?;
+            for (id, title, selftext) in posts {
+                let tone = sentiment::analyze_post(&title, selftext.as_deref());
+                update.execute(params![id, tone.sentiment.as_str(), tone.score])?;
+            }
+        }
+        tx.commit()
+    }
+
+    fn backfill_comment_sentiment(&self) -> RusqliteResult<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut select = tx.prepare("SELECT id, body FROM reddit_comments")?;
+            let comments = select
+                .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+                .collect::<RusqliteResult<Vec<_>>>()?;
+
+            let mut update = tx.prepare(
+                "UPDATE reddit_comments SET sentiment = ?2, sentiment_score = ?3 WHERE id = ?1",
+            )?;
+            for (id, body) in comments {
+                let tone = sentiment::analyze(&body);
+                update.execute(params![id, tone.sentiment.as_str(), tone.score])?;
+            }
+        }
+        tx.commit()
+    }
+
     // REMOVE A SINGLE ENTRY FROM THE TABLE
     pub fn remove_single_reddit(&self, id: &i64) -> RusqliteResult<()> {
         self.conn
@@ -211,21 +630,26 @@ impl DB {
                 is_self BOOLEAN NOT NULL DEFAULT FALSE,
                 num_comments INTEGER NOT NULL DEFAULT 0,
                 intent TEXT NOT NULL DEFAULT 'low',
-                date_added INTEGER NOT NULL DEFAULT 0
+                date_added INTEGER NOT NULL DEFAULT 0,
+                link_flair_text TEXT,
+                over_18 INTEGER NOT NULL DEFAULT 0,
+                upvote_ratio REAL NOT NULL DEFAULT 0,
+                stickied INTEGER NOT NULL DEFAULT 0,
+                locked INTEGER NOT NULL DEFAULT 0,
+                domain TEXT NOT NULL DEFAULT '',
+                author_flair_text TEXT,
+                crosspost_parent TEXT,
+                media_type TEXT,
+                brand_mentions TEXT NOT NULL DEFAULT '',
+                sentiment TEXT NOT NULL DEFAULT 'neutral',
+                sentiment_score REAL NOT NULL DEFAULT 0,
+                cluster_id TEXT NOT NULL DEFAULT ''
             )",
             [],
         )?;
 
-        // Migration: Add date_added to subreddit_search if it doesn't exist
-        let _ = self.conn.execute(
-            "ALTER TABLE subreddit_search ADD COLUMN date_added INTEGER NOT NULL DEFAULT 0",
-            [],
-        );
-
-        let _ = self.conn.execute(
-            "ALTER TABLE subreddit_search ADD COLUMN interest INTEGER NOT NULL DEFAULT 0",
-            [],
-        );
+        // Migration: Add newer columns to subreddit_search if they don't exist
+        self.migrate_post_columns("subreddit_search");
 
         // Create comments table
         self.create_comments_table()?;
@@ -249,51 +673,544 @@ impl DB {
                 subreddit TEXT NOT NULL,
                 post_title TEXT NOT NULL,
                 engaged BOOLEAN,
-                assignee TEXT NOT NULL DEFAULT ''
+                assignee TEXT NOT NULL DEFAULT '',
+                brand_mentions TEXT NOT NULL DEFAULT '',
+                sentiment TEXT NOT NULL DEFAULT 'neutral',
+                sentiment_score REAL NOT NULL DEFAULT 0
             )",
             [],
         )?;
 
+        for column in COMMENT_MIGRATIONS {
+            let added = self
+                .conn
+                .execute(
+                    &format!("ALTER TABLE reddit_comments ADD COLUMN {}", column),
+                    [],
+                )
+                .is_ok();
+            // Like posts, comments saved before sentiment was stored are scored once
+            if added && column.starts_with("sentiment_score ") {
+                if let Err(e) = self.backfill_comment_sentiment() {
+                    eprintln!("Failed to score the existing comments: {}", e);
+                }
+            }
+        }
+
+        self.create_brand_mentions_table()?;
+
         Ok(())
     }
 
-    pub fn append_results(&mut self, results: &[PostDataWrapper]) -> RusqliteResult<()> {
+    pub fn create_brand_mentions_table(&self) -> RusqliteResult<()> {
+        // thing_id is the Reddit fullname (t3_ post, t1_ comment) so re-fetches don't double count
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS brand_mentions (
+                brand TEXT NOT NULL,
+                thing_id TEXT NOT NULL,
+                subreddit TEXT NOT NULL DEFAULT '',
+                timestamp INTEGER NOT NULL,
+                PRIMARY KEY (brand, thing_id)
+            )",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn create_authors_table(&self) -> RusqliteResult<()> {
+        // Cached Reddit profiles; lists are comma separated like sort_type
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS authors (
+                username TEXT PRIMARY KEY COLLATE NOCASE,
+                link_karma INTEGER NOT NULL DEFAULT 0,
+                comment_karma INTEGER NOT NULL DEFAULT 0,
+                account_created INTEGER NOT NULL DEFAULT 0,
+                verified_email INTEGER NOT NULL DEFAULT 0,
+                suspended INTEGER NOT NULL DEFAULT 0,
+                recent_posts INTEGER NOT NULL DEFAULT 0,
+                recent_comments INTEGER NOT NULL DEFAULT 0,
+                active_subreddits TEXT NOT NULL DEFAULT '',
+                keyword_mentions INTEGER NOT NULL DEFAULT 0,
+                flags TEXT NOT NULL DEFAULT '',
+                summary TEXT NOT NULL DEFAULT '',
+                fetched_at INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn save_author_profile(&self, profile: &AuthorProfile) -> RusqliteResult<()> {
+        self.conn.execute(
+            &insert_sql("INSERT OR REPLACE", "authors", AUTHOR_COLUMNS),
+            params![
+                profile.username,
+                profile.link_karma,
+                profile.comment_karma,
+                profile.account_created,
+                profile.verified_email,
+                profile.suspended,
+                profile.recent_posts,
+                profile.recent_comments,
+                profile.active_subreddits.join(","),
+                profile.keyword_mentions,
+                profile.flags.join(","),
+                profile.summary,
+                profile.fetched_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_author_profile(&self, username: &str) -> RusqliteResult<Option<AuthorProfile>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM authors WHERE username = ?1",
+            AUTHOR_COLUMNS
+        ))?;
+
+        let mut profiles = stmt.query_map([username], AuthorProfile::from_row)?;
+        profiles.next().transpose()
+    }
+
+    pub fn create_subreddits_table(&self) -> RusqliteResult<()> {
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS subreddits (
+                name TEXT PRIMARY KEY COLLATE NOCASE,
+                title TEXT NOT NULL DEFAULT '',
+                description TEXT NOT NULL DEFAULT '',
+                subscribers INTEGER NOT NULL DEFAULT 0,
+                active_users INTEGER NOT NULL DEFAULT 0,
+                over_18 INTEGER NOT NULL DEFAULT 0,
+                url TEXT NOT NULL DEFAULT '',
+                notes TEXT NOT NULL DEFAULT '',
+                weight REAL NOT NULL DEFAULT 1.0,
+                rules TEXT NOT NULL DEFAULT '',
+                self_promotion TEXT NOT NULL DEFAULT '',
+                rules_fetched_at INTEGER NOT NULL DEFAULT 0,
+                added_at INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+
+        // Rules of any subreddit drafted for, saved or not
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS subreddit_rules (
+                name TEXT PRIMARY KEY COLLATE NOCASE,
+                rules TEXT NOT NULL DEFAULT '',
+                self_promotion TEXT NOT NULL DEFAULT '',
+                fetched_at INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    // Adds the subreddit or refreshes its Reddit metadata, keeping notes, weight and rules
+    pub fn save_subreddit(&self, info: &SubredditInfo) -> RusqliteResult<()> {
+        self.conn.execute(
+            "INSERT INTO subreddits (name, title, description, subscribers, active_users, over_18, url, added_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            ON CONFLICT(name) DO UPDATE SET
+                title = excluded.title,
+                description = excluded.description,
+                subscribers = excluded.subscribers,
+                active_users = excluded.active_users,
+                over_18 = excluded.over_18,
+                url = excluded.url",
+            params![
+                info.name.trim_start_matches("r/"),
+                info.title,
+                info.description,
+                info.subscribers,
+                info.active_users,
+                info.over_18,
+                info.url,
+                Utc::now().timestamp()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_subreddit_notes(&self, name: &str, notes: &str) -> RusqliteResult<()> {
+        self.conn.execute(
+            "UPDATE subreddits SET notes = ?1 WHERE name = ?2",
+            params![notes, name],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_subreddit_weight(&self, name: &str, weight: f64) -> RusqliteResult<()> {
+        self.conn.execute(
+            "UPDATE subreddits SET weight = ?1 WHERE name = ?2",
+            params![weight, name],
+        )?;
+        Ok(())
+    }
+
+    // Caches the rules of any subreddit, and keeps them on the saved subreddit if there is one
+    pub fn update_subreddit_rules(&self, rules: &SubredditRules) -> RusqliteResult<()> {
+        self.conn.execute(
+            "INSERT INTO subreddit_rules (name, rules, self_promotion, fetched_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(name) DO UPDATE SET
+                rules = excluded.rules,
+                self_promotion = excluded.self_promotion,
+                fetched_at = excluded.fetched_at",
+            params![rules.subreddit, rules.rules, rules.self_promotion, rules.fetched_at],
+        )?;
+        self.conn.execute(
+            "UPDATE subreddits SET rules = ?1, self_promotion = ?2, rules_fetched_at = ?3 WHERE name = ?4",
+            params![rules.rules, rules.self_promotion, rules.fetched_at, rules.subreddit],
+        )?;
+        Ok(())
+    }
+
+    // Last fetched rules of a subreddit, from the cache or else its saved row
+    pub fn get_subreddit_rules(&self, name: &str) -> RusqliteResult<Option<SubredditRules>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT name, rules, self_promotion, fetched_at FROM subreddit_rules WHERE name = ?1
+            UNION ALL
+            SELECT name, rules, self_promotion, rules_fetched_at FROM subreddits
+            WHERE name = ?1 AND rules_fetched_at > 0
+            ORDER BY 4 DESC
+            LIMIT 1",
+        )?;
+
+        let mut rules = stmt.query_map([name], |row| {
+            Ok(SubredditRules {
+                subreddit: row.get(0)?,
+                rules: row.get(1)?,
+                self_promotion: row.get(2)?,
+                fetched_at: row.get(3)?,
+            })
+        })?;
+        rules.next().transpose()
+    }
+
+    pub fn remove_subreddit(&self, name: &str) -> RusqliteResult<()> {
+        self.conn
+            .execute("DELETE FROM subreddits WHERE name = ?1", params![name])?;
+        Ok(())
+    }
+
+    pub fn create_topics_tables(&self) -> RusqliteResult<()> {
+        // Output of the last cluster_topics_command run; top_terms is comma separated
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS topics (
+                id INTEGER PRIMARY KEY,
+                label TEXT NOT NULL DEFAULT '',
+                top_terms TEXT NOT NULL DEFAULT '',
+                size INTEGER NOT NULL DEFAULT 0,
+                created_at INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS post_topics (
+                post_id INTEGER PRIMARY KEY,
+                topic_id INTEGER NOT NULL,
+                similarity REAL NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    // Topics are recomputed from scratch, so the previous run is dropped
+    pub fn replace_topics(&mut self, model: &TopicModel) -> RusqliteResult<()> {
         let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM post_topics", [])?;
+        tx.execute("DELETE FROM topics", [])?;
 
         {
+            let mut stmt = tx.prepare(&insert_sql("INSERT", "topics", TOPIC_COLUMNS))?;
+            for topic in &model.topics {
+                stmt.execute(params![
+                    topic.id,
+                    topic.label,
+                    topic.top_terms.join(","),
+                    topic.size,
+                    topic.created_at
+                ])?;
+            }
+
             let mut stmt = tx.prepare(
-                "INSERT OR IGNORE INTO reddit_posts
-                (id, timestamp, formatted_date, title, url, sort_type, relevance_score, subreddit, permalink, engaged, assignee, notes, name, selftext, author, score, thumbnail, is_self, num_comments, intent, date_added, interest)
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)",
+                "INSERT INTO post_topics (post_id, topic_id, similarity) VALUES (?1, ?2, ?3)",
             )?;
+            for (post_id, topic_id, similarity) in &model.assignments {
+                stmt.execute(params![post_id, topic_id, similarity])?;
+            }
+        }
 
-            for result in results {
+        tx.commit()
+    }
+
+    pub fn create_embeddings_table(&self) -> RusqliteResult<()> {
+        // kind is "post" (item_id is the row id) or "comment"; vector is little-endian f32s
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS embeddings (
+                kind TEXT NOT NULL,
+                item_id TEXT NOT NULL,
+                model TEXT NOT NULL,
+                content_hash TEXT NOT NULL,
+                vector BLOB NOT NULL,
+                created_at INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (kind, item_id)
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    pub fn save_embeddings(&mut self, embeddings: &[StoredEmbedding]) -> RusqliteResult<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt =
+                tx.prepare(&insert_sql("INSERT OR REPLACE", "embeddings", EMBEDDING_COLUMNS))?;
+            for embedding in embeddings {
+                stmt.execute(params![
+                    embedding.kind,
+                    embedding.item_id,
+                    embedding.model,
+                    embedding.content_hash,
+                    vector_to_bytes(&embedding.vector),
+                    embedding.created_at
+                ])?;
+            }
+        }
+        tx.commit()
+    }
+
+    // Takes (kind, item id) pairs
+    pub fn delete_embeddings(&mut self, keys: &[(String, String)]) -> RusqliteResult<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare("DELETE FROM embeddings WHERE kind = ?1 AND item_id = ?2")?;
+            for (kind, item_id) in keys {
+                stmt.execute(params![kind, item_id])?;
+            }
+        }
+        tx.commit()
+    }
+
+    pub fn create_chat_tables(&self) -> RusqliteResult<()> {
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS chat_sessions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                title TEXT NOT NULL DEFAULT '',
+                created_at INTEGER NOT NULL DEFAULT 0,
+                updated_at INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+
+        // role is "user" or "assistant"
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS chat_messages (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id INTEGER NOT NULL,
+                role TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS chat_messages_session ON chat_messages (session_id)",
+            [],
+        )?;
+        Ok(())
+    }
+
+    pub fn create_chat_session(&self, title: &str, now: i64) -> RusqliteResult<i64> {
+        self.conn.execute(
+            "INSERT INTO chat_sessions (title, created_at, updated_at) VALUES (?1, ?2, ?2)",
+            params![title, now],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    // Saves a question with its answer; an untitled session is named after the question
+    pub fn add_chat_exchange(
+        &mut self,
+        session_id: i64,
+        question: &str,
+        answer: &str,
+        now: i64,
+    ) -> RusqliteResult<()> {
+        let tx = self.conn.transaction()?;
+        let updated = tx.execute(
+            "UPDATE chat_sessions
+             SET updated_at = ?2, title = CASE WHEN title = '' THEN ?3 ELSE title END
+             WHERE id = ?1",
+            params![session_id, now, title_from_question(question)],
+        )?;
+        if updated == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO chat_messages (session_id, role, content, created_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            stmt.execute(params![session_id, "user", question, now])?;
+            stmt.execute(params![session_id, "assistant", answer, now])?;
+        }
+        tx.commit()
+    }
+
+    pub fn rename_chat_session(&self, session_id: i64, title: &str) -> RusqliteResult<usize> {
+        self.conn.execute(
+            "UPDATE chat_sessions SET title = ?2 WHERE id = ?1",
+            params![session_id, title],
+        )
+    }
+
+    pub fn delete_chat_session(&mut self, session_id: i64) -> RusqliteResult<usize> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "DELETE FROM chat_messages WHERE session_id = ?1",
+            params![session_id],
+        )?;
+        let deleted = tx.execute("DELETE FROM chat_sessions WHERE id = ?1", params![session_id])?;
+        tx.commit()?;
+        Ok(deleted)
+    }
+
+    // status is "draft", "approved", "posted" or "rejected"; risk_reasons one per line
+    pub fn create_reply_drafts_table(&self) -> RusqliteResult<()> {
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS reply_drafts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                post_id INTEGER NOT NULL,
+                job_id TEXT NOT NULL DEFAULT '',
+                tone TEXT NOT NULL DEFAULT '',
+                text TEXT NOT NULL,
+                approach TEXT NOT NULL DEFAULT '',
+                promotion_risk REAL NOT NULL DEFAULT 0,
+                risk_level TEXT NOT NULL DEFAULT '',
+                risk_reasons TEXT NOT NULL DEFAULT '',
+                status TEXT NOT NULL DEFAULT 'draft',
+                created_at INTEGER NOT NULL DEFAULT 0,
+                updated_at INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS reply_drafts_post ON reply_drafts (post_id)",
+            [],
+        )?;
+        Ok(())
+    }
+
+    // Saves new drafts for a post in place of its unreviewed ones; reviewed drafts stay
+    pub fn replace_reply_drafts(
+        &mut self,
+        post_id: i64,
+        drafts: &[StoredReplyDraft],
+    ) -> RusqliteResult<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "DELETE FROM reply_drafts WHERE post_id = ?1 AND status = 'draft'",
+            params![post_id],
+        )?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO reply_drafts (post_id, job_id, tone, text, approach, promotion_risk,
+                    risk_level, risk_reasons, status, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+            for draft in drafts {
                 stmt.execute(params![
-                    result.id,
-                    result.timestamp,
-                    result.formatted_date,
-                    result.title,
-                    result.url,
-                    result.sort_type,
-                    result.relevance_score,
-                    result.subreddit,
-                    result.permalink,
-                    result.engaged,
-                    result.assignee,
-                    result.notes,
-                    result.name,
-                    result.selftext,
-                    result.author,
-                    result.score,
-                    result.thumbnail,
-                    result.is_self,
-                    result.num_comments,
-                    result.intent,
-                    if result.date_added == 0 { Utc::now().timestamp() } else { result.date_added },
-                    result.interest
+                    post_id,
+                    draft.job_id,
+                    draft.tone,
+                    draft.text,
+                    draft.approach,
+                    draft.promotion_risk,
+                    draft.risk_level,
+                    draft.risk_reasons.join("\n"),
+                    draft.status,
+                    draft.created_at,
+                    draft.updated_at,
                 ])?;
             }
         }
+        tx.commit()
+    }
+
+    // Fields left as None are kept
+    pub fn update_reply_draft(
+        &self,
+        id: i64,
+        status: Option<&str>,
+        text: Option<&str>,
+        now: i64,
+    ) -> RusqliteResult<usize> {
+        self.conn.execute(
+            "UPDATE reply_drafts
+             SET status = COALESCE(?2, status), text = COALESCE(?3, text), updated_at = ?4
+             WHERE id = ?1",
+            params![id, status, text, now],
+        )
+    }
+
+    pub fn record_post_brand_mentions(&mut self, posts: &[PostDataWrapper]) -> RusqliteResult<()> {
+        let mentions = posts.iter().flat_map(|post| {
+            split_tags(&post.brand_mentions)
+                .map(move |brand| (brand, post.name.as_str(), post.subreddit.as_str(), post.timestamp))
+        });
+        self.record_brand_mentions(mentions)
+    }
+
+    pub fn record_comment_brand_mentions(
+        &mut self,
+        comments: &[CommentDataWrapper],
+    ) -> RusqliteResult<()> {
+        let mentions = comments.iter().flat_map(|comment| {
+            let thing_id = format!("t1_{}", comment.id);
+            split_tags(&comment.brand_mentions)
+                .map(move |brand| (brand, thing_id.clone(), comment.subreddit.as_str(), comment.timestamp))
+                .collect::<Vec<_>>()
+        });
+        self.record_brand_mentions(mentions)
+    }
+
+    fn record_brand_mentions<'a, T: AsRef<str>>(
+        &mut self,
+        mentions: impl Iterator<Item = (&'a str, T, &'a str, i64)>,
+    ) -> RusqliteResult<()> {
+        let tx = self.conn.transaction()?;
+
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO brand_mentions (brand, thing_id, subreddit, timestamp)
+                VALUES (?1, ?2, ?3, ?4)",
+            )?;
+
+            for (brand, thing_id, subreddit, timestamp) in mentions {
+                stmt.execute(params![brand, thing_id.as_ref(), subreddit, timestamp])?;
+            }
+        }
+
+        tx.commit()
+    }
+
+    pub fn append_results(&mut self, results: &[PostDataWrapper]) -> RusqliteResult<()> {
+        let tx = self.conn.transaction()?;
+
+        {
+            let mut stmt = tx.prepare(&post_insert_sql("INSERT OR IGNORE", "reddit_posts"))?;
+
+            for result in results {
+                execute_post_insert(&mut stmt, result)?;
+            }
+        }
 
         tx.commit()?;
         println!("Added {} results", results.len());
@@ -309,42 +1226,14 @@ impl DB {
     pub fn replace_current_results(&mut self, results: &[PostDataWrapper]) -> RusqliteResult<()> {
         let tx = self.conn.transaction()?;
 
-        // First, clear all existing results
         // First, clear all existing results
         tx.execute("DELETE FROM subreddit_search", [])?;
 
         {
-            let mut stmt = tx.prepare(
-                "INSERT INTO subreddit_search
-            (id, timestamp, formatted_date, title, url, sort_type, relevance_score, subreddit, permalink, engaged, assignee, notes, name, selftext, author, score, thumbnail, is_self, num_comments, intent, date_added, interest)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)",
-            )?;
+            let mut stmt = tx.prepare(&post_insert_sql("INSERT", "subreddit_search"))?;
 
             for result in results {
-                stmt.execute(params![
-                    result.id,
-                    result.timestamp,
-                    result.formatted_date,
-                    result.title,
-                    result.url,
-                    result.sort_type,
-                    result.relevance_score,
-                    result.subreddit,
-                    result.permalink,
-                    result.engaged,
-                    result.assignee,
-                    result.notes,
-                    result.name,
-                    result.selftext,
-                    result.author,
-                    result.score,
-                    result.thumbnail,
-                    result.is_self,
-                    result.num_comments,
-                    result.intent,
-                    if result.date_added == 0 { Utc::now().timestamp() } else { result.date_added },
-                    result.interest
-                ])?;
+                execute_post_insert(&mut stmt, result)?;
             }
         }
 
@@ -357,11 +1246,11 @@ impl DB {
         let tx = self.conn.transaction()?;
 
         {
-            let mut stmt = tx.prepare(
-                "INSERT OR IGNORE INTO reddit_comments
-                (id, post_id, body, author, timestamp, formatted_date, score, permalink, parent_id, subreddit, post_title, engaged, assignee)
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
-            )?;
+            let mut stmt = tx.prepare(&insert_sql(
+                "INSERT OR IGNORE",
+                "reddit_comments",
+                COMMENT_COLUMNS,
+            ))?;
 
             for comment in comments {
                 stmt.execute(params![
@@ -377,7 +1266,10 @@ impl DB {
                     comment.subreddit,
                     comment.post_title,
                     comment.engaged,
-                    comment.assignee
+                    comment.assignee,
+                    comment.brand_mentions,
+                    comment.sentiment,
+                    comment.sentiment_score
                 ])?;
             }
         }
@@ -388,70 +1280,29 @@ impl DB {
     }
 
     pub fn get_db_results(&self) -> RusqliteResult<Vec<PostDataWrapper>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, timestamp, formatted_date, title, url, sort_type, relevance_score, subreddit, permalink, engaged, assignee, notes, name, selftext, author, thumbnail, is_self, num_comments, intent, date_added, interest
-             FROM reddit_posts
-             ORDER BY timestamp DESC",
-        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM reddit_posts ORDER BY timestamp DESC",
+            POST_COLUMNS
+        ))?;
 
         let posts = stmt
-            .query_map([], |row| {
-                Ok(PostDataWrapper {
-                    id: row.get(0)?,
-                    timestamp: row.get(1)?,
-                    formatted_date: row.get(2)?,
-                    title: row.get(3)?,
-                    url: row.get(4)?,
-                    sort_type: row.get(5)?,       // Updated index
-                    relevance_score: row.get(6)?, // Updated index
-                    subreddit: row.get(7)?,
-                    permalink: row.get(8)?,
-                    engaged: row.get(9)?,
-                    assignee: row.get(10)?,
-                    notes: row.get(11)?,
-                    name: row.get(12)?,
-                    selftext: row.get(13)?,
-                    author: row.get(14)?,
-                    score: 0, // Score is not selected in the query, providing default
-                    thumbnail: row.get(15)?,
-                    is_self: row.get(16)?,
-                    num_comments: row.get(17)?,
-                    intent: row.get(18)?,
-                    date_added: row.get(19)?,
-                    interest: row.get(20)?,
-                })
-            })?
+            .query_map([], PostDataWrapper::from_row)?
             .collect::<RusqliteResult<Vec<_>>>()?;
 
         Ok(posts)
     }
 
     pub fn get_post_comments(&self, post_id: &str) -> RusqliteResult<Vec<CommentDataWrapper>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, post_id, body, author, timestamp, formatted_date, score, permalink, parent_id, subreddit, post_title, engaged, assignee
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}
              FROM reddit_comments
              WHERE post_id = ?1
              ORDER BY timestamp DESC",
-        )?;
+            COMMENT_COLUMNS
+        ))?;
 
         let comments = stmt
-            .query_map([post_id], |row| {
-                Ok(CommentDataWrapper {
-                    id: row.get(0)?,
-                    post_id: row.get(1)?,
-                    body: row.get(2)?,
-                    author: row.get(3)?,
-                    timestamp: row.get(4)?,
-                    formatted_date: row.get(5)?,
-                    score: row.get(6)?,
-                    permalink: row.get(7)?,
-                    parent_id: row.get(8)?,
-                    subreddit: row.get(9)?,
-                    post_title: row.get(10)?,
-                    engaged: row.get(11)?,
-                    assignee: row.get(12)?,
-                })
-            })?
+            .query_map([post_id], CommentDataWrapper::from_row)?
             .collect::<RusqliteResult<Vec<_>>>()?;
 
         Ok(comments)
@@ -484,6 +1335,34 @@ impl DB {
         Ok(())
     }
 
+    // Gives every saved post in the same duplicate cluster as `id` the same assignee
+    pub fn update_cluster_assignee(&self, id: i64, assignee: &str) -> RusqliteResult<usize> {
+        self.conn.execute(
+            "UPDATE reddit_posts SET assignee = ?1
+            WHERE id != ?2
+              AND cluster_id != ''
+              AND cluster_id = (SELECT cluster_id FROM reddit_posts WHERE id = ?2)",
+            params![assignee, id],
+        )
+    }
+
+    // Replaces every cluster id with (post id, cluster id) pairs, on saved posts and current search results
+    pub fn update_cluster_ids(&mut self, clusters: &[(i64, String)]) -> RusqliteResult<()> {
+        let tx = self.conn.transaction()?;
+        for table in ["reddit_posts", "subreddit_search"] {
+            tx.execute(&format!("UPDATE {} SET cluster_id = ''", table), [])?;
+        }
+        write_cluster_ids(&tx, clusters)?;
+        tx.commit()
+    }
+
+    // Sets the cluster id of just these posts, leaving the rest as they are
+    pub fn set_cluster_ids(&mut self, clusters: &[(i64, String)]) -> RusqliteResult<()> {
+        let tx = self.conn.transaction()?;
+        write_cluster_ids(&tx, clusters)?;
+        tx.commit()
+    }
+
     pub fn update_post_engaged_status(&self, id: i64, engaged: i64) -> RusqliteResult<()> {
         self.conn.execute(
             "UPDATE reddit_posts SET engaged = ?1 WHERE id = ?2",
@@ -517,3 +1396,145 @@ impl DB {
         Ok(())
     }
 }
+
+// Writes (post id, cluster id) pairs to saved posts and current search results
+fn write_cluster_ids(tx: &Transaction, clusters: &[(i64, String)]) -> RusqliteResult<()> {
+    for table in ["reddit_posts", "subreddit_search"] {
+        let mut stmt = tx.prepare(&format!("UPDATE {} SET cluster_id = ?1 WHERE id = ?2", table))?;
+        for (id, cluster_id) in clusters {
+            stmt.execute(params![cluster_id, id])?;
+        }
+    }
+    Ok(())
+}
+
+fn post_insert_sql(verb: &str, table: &str) -> String {
+    insert_sql(verb, table, POST_COLUMNS)
+}
+
+fn insert_sql(verb: &str, table: &str, columns: &str) -> String {
+    let placeholders = (1..=columns.split(',').count())
+        .map(|i| format!("?{}", i))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{} INTO {} ({}) VALUES ({})", verb, table, columns, placeholders)
+}
+
+// Binds a post in `POST_COLUMNS` order
+fn execute_post_insert(stmt: &mut Statement, post: &PostDataWrapper) -> RusqliteResult<usize> {
+    stmt.execute(params![
+        post.id,
+        post.timestamp,
+        post.formatted_date,
+        post.title,
+        post.url,
+        post.sort_type,
+        post.relevance_score,
+        post.subreddit,
+        post.permalink,
+        post.engaged,
+        post.assignee,
+        post.notes,
+        post.name,
+        post.selftext,
+        post.author,
+        post.score,
+        post.thumbnail,
+        post.is_self,
+        post.num_comments,
+        post.intent,
+        if post.date_added == 0 { Utc::now().timestamp() } else { post.date_added },
+        post.interest,
+        post.link_flair_text,
+        post.over_18,
+        post.upvote_ratio,
+        post.stickied,
+        post.locked,
+        post.domain,
+        post.author_flair_text,
+        post.crosspost_parent,
+        post.media_type,
+        post.brand_mentions,
+        post.sentiment,
+        post.sentiment_score,
+        post.cluster_id
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments_saved_before_sentiment_are_scored_once() {
+        let db = DB {
+            conn: Connection::open_in_memory().unwrap(),
+        };
+        // reddit_comments as it was before the sentiment columns
+        db.conn
+            .execute(
+                "CREATE TABLE reddit_comments (
+                    id TEXT PRIMARY KEY, post_id TEXT NOT NULL, body TEXT NOT NULL,
+                    author TEXT NOT NULL, timestamp INTEGER NOT NULL, formatted_date TEXT NOT NULL,
+                    score INTEGER NOT NULL, permalink TEXT NOT NULL, parent_id TEXT NOT NULL,
+                    subreddit TEXT NOT NULL, post_title TEXT NOT NULL, engaged BOOLEAN,
+                    assignee TEXT NOT NULL DEFAULT ''
+                )",
+                [],
+            )
+            .unwrap();
+        for (id, body) in [("c1", "This tool is terrible and support is awful"), ("c2", "Thanks, this is great!")] {
+            db.conn
+                .execute(
+                    "INSERT INTO reddit_comments VALUES (?1, 'p', ?2, 'a', 0, '', 1, '', 't3_p', 's', 't', 0, '')",
+                    params![id, body],
+                )
+                .unwrap();
+        }
+
+        db.create_comments_table().unwrap();
+
+        let sentiment = |id: &str| -> (String, f64) {
+            db.conn
+                .query_row(
+                    "SELECT sentiment, sentiment_score FROM reddit_comments WHERE id = ?1",
+                    [id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .unwrap()
+        };
+        let (label, score) = sentiment("c1");
+        assert_eq!(label, "negative");
+        assert!(score < 0.0);
+        let (label, score) = sentiment("c2");
+        assert_eq!(label, "positive");
+        assert!(score > 0.0);
+    }
+
+    #[test]
+    fn rules_are_cached_for_unsaved_subreddits_too() {
+        let db = DB {
+            conn: Connection::open_in_memory().unwrap(),
+        };
+        db.create_subreddits_table().unwrap();
+        let rules = |subreddit: &str, text: &str, fetched_at: i64| SubredditRules {
+            subreddit: subreddit.to_string(),
+            rules: text.to_string(),
+            self_promotion: String::new(),
+            fetched_at,
+        };
+
+        db.update_subreddit_rules(&rules("smallbusiness", "Be nice", 100)).unwrap();
+        db.update_subreddit_rules(&rules("smallbusiness", "No spam", 200)).unwrap();
+
+        let cached = db.get_subreddit_rules("SmallBusiness").unwrap().unwrap();
+        assert_eq!((cached.rules.as_str(), cached.fetched_at), ("No spam", 200));
+        assert!(db.get_subreddit_rules("shopify").unwrap().is_none());
+        // Caching doesn't save the subreddit
+        let saved: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM subreddits", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(saved, 0);
+    }
+}
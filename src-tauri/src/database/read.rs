@@ -1,5 +1,15 @@
-use crate::database::adding::{CommentDataWrapper, PostDataWrapper, DB};
-use rusqlite::Result as RusqliteResult;
+use crate::database::adding::{
+    BrandMentionCount, CommentDataWrapper, PostDataWrapper, CHAT_MESSAGE_COLUMNS,
+    CHAT_SESSION_COLUMNS, COMMENT_COLUMNS, DB, EMBEDDING_COLUMNS, POST_COLUMNS, REPLY_DRAFT_COLUMNS,
+    SUBREDDIT_COLUMNS, TOPIC_COLUMNS,
+};
+use crate::ai::embeddings::StoredEmbedding;
+use crate::ai::history::{ChatSession, StoredChatMessage};
+use crate::ai::reply_queue::StoredReplyDraft;
+use crate::models::subreddits::SavedSubreddit;
+use crate::models::topics::Topic;
+use rusqlite::{params, Connection, Result as RusqliteResult};
+use std::collections::HashMap;
 
 pub struct DBReader;
 
@@ -24,38 +34,14 @@ impl DBReader {
     // ADD THIS FUNCTION - Gets ALL posts from the database
     pub fn get_all_posts(&self) -> RusqliteResult<Vec<PostDataWrapper>> {
         let db = DB::new()?;
-        let mut stmt = db.conn.prepare(
-            "SELECT id, timestamp, formatted_date, title, url, sort_type, relevance_score, subreddit, permalink, engaged, assignee, notes, name, selftext, author, score, thumbnail, is_self, num_comments, intent, date_added, interest
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {}
          FROM reddit_posts
          ORDER BY timestamp DESC",
-        )?;
-
-        let posts = stmt.query_map([], |row| {
-            Ok(PostDataWrapper {
-                id: row.get(0)?,
-                timestamp: row.get(1)?,
-                formatted_date: row.get(2)?,
-                title: row.get(3)?,
-                url: row.get(4)?,
-                sort_type: row.get(5)?,
-                relevance_score: row.get(6)?,
-                subreddit: row.get(7)?,
-                permalink: row.get(8)?,
-                engaged: row.get(9)?,
-                assignee: row.get(10)?,
-                notes: row.get(11)?,
-                name: row.get(12)?,
-                selftext: row.get(13)?,
-                author: row.get(14)?,
-                score: row.get(15)?,
-                thumbnail: row.get(16)?,
-                is_self: row.get(17)?,
-                num_comments: row.get(18)?,
-                intent: row.get(19)?,
-                date_added: row.get(20)?,
-                interest: row.get(21)?,
-            })
-        })?;
+            POST_COLUMNS
+        ))?;
+
+        let posts = stmt.query_map([], PostDataWrapper::from_row)?;
 
         posts.collect()
     }
@@ -63,195 +49,81 @@ impl DBReader {
     // GET JUST THE SEARCHED SUBREDDITS
     pub fn get_all_searched_posts(&self) -> RusqliteResult<Vec<PostDataWrapper>> {
         let db = DB::new()?;
-        let mut stmt = db.conn.prepare(
-            "SELECT id, timestamp, formatted_date, title, url, sort_type, relevance_score, subreddit, permalink, engaged, assignee, notes, name, selftext, author, score, thumbnail, is_self, num_comments, intent, date_added, interest
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {}
          FROM subreddit_search
          ORDER BY timestamp DESC",
-        )?;
-
-        let posts = stmt.query_map([], |row| {
-            Ok(PostDataWrapper {
-                id: row.get(0)?,
-                timestamp: row.get(1)?,
-                formatted_date: row.get(2)?,
-                title: row.get(3)?,
-                url: row.get(4)?,
-                sort_type: row.get(5)?,
-                relevance_score: row.get(6)?,
-                subreddit: row.get(7)?,
-                permalink: row.get(8)?,
-                engaged: row.get(9)?,
-                assignee: row.get(10)?,
-                notes: row.get(11)?,
-                name: row.get(12)?,
-                selftext: row.get(13)?,
-                author: row.get(14)?,
-                score: row.get(15)?,
-                thumbnail: row.get(16)?,
-                is_self: row.get(17)?,
-                num_comments: row.get(18)?,
-                intent: row.get(19)?,
-                date_added: row.get(20)?,
-                interest: row.get(21)?,
-            })
-        })?;
+            POST_COLUMNS
+        ))?;
+
+        let posts = stmt.query_map([], PostDataWrapper::from_row)?;
 
         posts.collect()
     }
 
+    // A saved post, or else a current search result
+    pub fn get_post(&self, id: i64) -> RusqliteResult<Option<PostDataWrapper>> {
+        let db = DB::new()?;
+        find_post(&db.conn, id)
+    }
+
     pub fn get_recent_posts(&self, limit: i64) -> RusqliteResult<Vec<PostDataWrapper>> {
         let db = DB::new()?;
-        let mut stmt = db.conn.prepare(
-            "SELECT id, timestamp, formatted_date, title, url, sort_type, relevance_score, subreddit, permalink, engaged, assignee, notes, name, selftext, author, score, thumbnail, is_self, num_comments, intent, date_added, interest
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {}
          FROM reddit_posts
          ORDER BY timestamp DESC
          LIMIT ?1",
-        )?;
-
-        let posts = stmt.query_map([limit], |row| {
-            Ok(PostDataWrapper {
-                id: row.get(0)?,
-                timestamp: row.get(1)?,
-                formatted_date: row.get(2)?,
-                title: row.get(3)?,
-                url: row.get(4)?,
-                sort_type: row.get(5)?,
-                relevance_score: row.get(6)?,
-                subreddit: row.get(7)?,
-                permalink: row.get(8)?,
-                engaged: row.get(9)?,
-                assignee: row.get(10)?,
-                notes: row.get(11)?,
-                name: row.get(12)?,
-                selftext: row.get(13)?,
-                author: row.get(14)?,
-                score: row.get(15)?,
-                thumbnail: row.get(16)?,
-                is_self: row.get(17)?,
-                num_comments: row.get(18)?,
-                intent: row.get(19)?,
-                date_added: row.get(20)?,
-                interest: row.get(21)?,
-            })
-        })?;
+            POST_COLUMNS
+        ))?;
+
+        let posts = stmt.query_map([limit], PostDataWrapper::from_row)?;
 
         posts.collect()
     }
 
     pub fn get_posts_by_subreddit(&self, subreddit: &str) -> RusqliteResult<Vec<PostDataWrapper>> {
         let db = DB::new()?;
-        let mut stmt = db.conn.prepare(
-            "SELECT id, timestamp, formatted_date, title, url, sort_type, relevance_score, subreddit, permalink, engaged, assignee, notes, name, selftext, author, score, thumbnail, is_self, num_comments, intent, date_added, interest
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {}
          FROM reddit_posts
          WHERE subreddit = ?1
          ORDER BY timestamp DESC",
-        )?;
-
-        let posts = stmt.query_map([subreddit], |row| {
-            Ok(PostDataWrapper {
-                id: row.get(0)?,
-                timestamp: row.get(1)?,
-                formatted_date: row.get(2)?,
-                title: row.get(3)?,
-                url: row.get(4)?,
-                sort_type: row.get(5)?,
-                relevance_score: row.get(6)?,
-                subreddit: row.get(7)?,
-                permalink: row.get(8)?,
-                engaged: row.get(9)?,
-                assignee: row.get(10)?,
-                notes: row.get(11)?,
-                name: row.get(12)?,
-                selftext: row.get(13)?,
-                author: row.get(14)?,
-                score: row.get(15)?,
-                thumbnail: row.get(16)?,
-                is_self: row.get(17)?,
-                num_comments: row.get(18)?,
-                intent: row.get(19)?,
-                date_added: row.get(20)?,
-                interest: row.get(21)?,
-            })
-        })?;
+            POST_COLUMNS
+        ))?;
+
+        let posts = stmt.query_map([subreddit], PostDataWrapper::from_row)?;
 
         posts.collect()
     }
 
     pub fn get_posts_by_sort_type(&self, sort_type: &str) -> RusqliteResult<Vec<PostDataWrapper>> {
         let db = DB::new()?;
-        let mut stmt = db.conn.prepare(
-            "SELECT id, timestamp, formatted_date, title, url, sort_type, relevance_score, subreddit, permalink, engaged, assignee, notes, name, selftext, author, score, thumbnail, is_self, num_comments, intent, date_added, interest
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {}
          FROM reddit_posts
          WHERE sort_type = ?1
          ORDER BY timestamp DESC",
-        )?;
-
-        let posts = stmt.query_map([sort_type], |row| {
-            Ok(PostDataWrapper {
-                id: row.get(0)?,
-                timestamp: row.get(1)?,
-                formatted_date: row.get(2)?,
-                title: row.get(3)?,
-                url: row.get(4)?,
-                sort_type: row.get(5)?,
-                relevance_score: row.get(6)?,
-                subreddit: row.get(7)?,
-                permalink: row.get(8)?,
-                engaged: row.get(9)?,
-                assignee: row.get(10)?,
-                notes: row.get(11)?,
-                name: row.get(12)?,
-                selftext: row.get(13)?,
-                author: row.get(14)?,
-                score: row.get(15)?,
-                thumbnail: row.get(16)?,
-                is_self: row.get(17)?,
-                num_comments: row.get(18)?,
-                intent: row.get(19)?,
-                date_added: row.get(20)?,
-                interest: row.get(21)?,
-            })
-        })?;
+            POST_COLUMNS
+        ))?;
+
+        let posts = stmt.query_map([sort_type], PostDataWrapper::from_row)?;
 
         posts.collect()
     }
 
     pub fn search_posts(&self, search_term: &str) -> RusqliteResult<Vec<PostDataWrapper>> {
         let db = DB::new()?;
-        let mut stmt = db.conn.prepare(
-            "SELECT id, timestamp, formatted_date, title, url, sort_type, relevance_score, subreddit, permalink, engaged, assignee, notes, name, selftext, author, score, thumbnail, is_self, num_comments, intent, date_added, interest
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {}
          FROM reddit_posts
          WHERE title LIKE ?1 OR subreddit LIKE ?1 OR sort_type LIKE ?1
          ORDER BY timestamp DESC",
-        )?;
+            POST_COLUMNS
+        ))?;
 
         let search_pattern = format!("%{}%", search_term);
-        let posts = stmt.query_map([search_pattern], |row| {
-            Ok(PostDataWrapper {
-                id: row.get(0)?,
-                timestamp: row.get(1)?,
-                formatted_date: row.get(2)?,
-                title: row.get(3)?,
-                url: row.get(4)?,
-                sort_type: row.get(5)?,
-                relevance_score: row.get(6)?,
-                subreddit: row.get(7)?,
-                permalink: row.get(8)?,
-                engaged: row.get(9)?,
-                assignee: row.get(10)?,
-                notes: row.get(11)?,
-                name: row.get(12)?,
-                selftext: row.get(13)?,
-                author: row.get(14)?,
-                score: row.get(15)?,
-                thumbnail: row.get(16)?,
-                is_self: row.get(17)?,
-                num_comments: row.get(18)?,
-                intent: row.get(19)?,
-                date_added: row.get(20)?,
-                interest: row.get(21)?,
-            })
-        })?;
+        let posts = stmt.query_map([search_pattern], PostDataWrapper::from_row)?;
 
         posts.collect()
     }
@@ -302,33 +174,356 @@ impl DBReader {
         Ok(())
     }
 
+    pub fn get_posts_by_sentiment(&self, sentiment: &str) -> RusqliteResult<Vec<PostDataWrapper>> {
+        let db = DB::new()?;
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {}
+         FROM reddit_posts
+         WHERE sentiment = ?1
+         ORDER BY sentiment_score, timestamp DESC",
+            POST_COLUMNS
+        ))?;
+
+        let posts = stmt.query_map([sentiment], PostDataWrapper::from_row)?;
+
+        posts.collect()
+    }
+
+    pub fn get_comments_by_sentiment(
+        &self,
+        sentiment: &str,
+    ) -> RusqliteResult<Vec<CommentDataWrapper>> {
+        let db = DB::new()?;
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {}
+         FROM reddit_comments
+         WHERE sentiment = ?1
+         ORDER BY sentiment_score, timestamp DESC",
+            COMMENT_COLUMNS
+        ))?;
+
+        let comments = stmt.query_map([sentiment], CommentDataWrapper::from_row)?;
+
+        comments.collect()
+    }
+
+    pub fn get_saved_subreddits(&self) -> RusqliteResult<Vec<SavedSubreddit>> {
+        let db = DB::new()?;
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {}
+         FROM subreddits
+         ORDER BY weight DESC, subscribers DESC",
+            SUBREDDIT_COLUMNS
+        ))?;
+
+        let subreddits = stmt.query_map([], SavedSubreddit::from_row)?;
+
+        subreddits.collect()
+    }
+
+    // Weights of saved subreddits that differ from the default 1.0
+    pub fn get_subreddit_weights(&self) -> RusqliteResult<Vec<(String, f64)>> {
+        let db = DB::new()?;
+        let mut stmt = db
+            .conn
+            .prepare("SELECT name, weight FROM subreddits WHERE weight != 1.0")?;
+
+        let weights = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+        weights.collect()
+    }
+
+    pub fn get_topics(&self) -> RusqliteResult<Vec<Topic>> {
+        let db = DB::new()?;
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {}
+         FROM topics
+         ORDER BY size DESC, id",
+            TOPIC_COLUMNS
+        ))?;
+
+        let topics = stmt.query_map([], Topic::from_row)?;
+
+        topics.collect()
+    }
+
+    // Posts of a topic, closest to its centroid first
+    pub fn get_posts_by_topic(&self, topic_id: i64) -> RusqliteResult<Vec<PostDataWrapper>> {
+        let db = DB::new()?;
+        let columns: Vec<String> = POST_COLUMNS
+            .split(", ")
+            .map(|column| format!("p.{}", column))
+            .collect();
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {}
+         FROM reddit_posts p
+         JOIN post_topics t ON t.post_id = p.id
+         WHERE t.topic_id = ?1
+         ORDER BY t.similarity DESC, p.timestamp DESC",
+            columns.join(", ")
+        ))?;
+
+        let posts = stmt.query_map([topic_id], PostDataWrapper::from_row)?;
+
+        posts.collect()
+    }
+
+    pub fn get_embeddings(&self) -> RusqliteResult<Vec<StoredEmbedding>> {
+        let db = DB::new()?;
+        let mut stmt = db
+            .conn
+            .prepare(&format!("SELECT {} FROM embeddings", EMBEDDING_COLUMNS))?;
+
+        let embeddings = stmt.query_map([], StoredEmbedding::from_row)?;
+
+        embeddings.collect()
+    }
+
+    // Most recently used first
+    pub fn get_chat_sessions(&self) -> RusqliteResult<Vec<ChatSession>> {
+        let db = DB::new()?;
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {} FROM chat_sessions ORDER BY updated_at DESC, id DESC",
+            CHAT_SESSION_COLUMNS
+        ))?;
+
+        let sessions = stmt.query_map([], ChatSession::from_row)?;
+
+        sessions.collect()
+    }
+
+    pub fn get_chat_session(&self, session_id: i64) -> RusqliteResult<Option<ChatSession>> {
+        let db = DB::new()?;
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {} FROM chat_sessions WHERE id = ?1",
+            CHAT_SESSION_COLUMNS
+        ))?;
+
+        let mut sessions = stmt.query_map([session_id], ChatSession::from_row)?;
+        sessions.next().transpose()
+    }
+
+    // Oldest first
+    pub fn get_chat_messages(&self, session_id: i64) -> RusqliteResult<Vec<StoredChatMessage>> {
+        let db = DB::new()?;
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {} FROM chat_messages WHERE session_id = ?1 ORDER BY id",
+            CHAT_MESSAGE_COLUMNS
+        ))?;
+
+        let messages = stmt.query_map([session_id], StoredChatMessage::from_row)?;
+
+        messages.collect()
+    }
+
+    // Newest first, a post's variants together; None matches any status or post
+    pub fn get_reply_drafts(
+        &self,
+        status: Option<&str>,
+        post_id: Option<i64>,
+    ) -> RusqliteResult<Vec<StoredReplyDraft>> {
+        let db = DB::new()?;
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {} FROM reply_drafts
+             WHERE (?1 IS NULL OR status = ?1) AND (?2 IS NULL OR post_id = ?2)
+             ORDER BY created_at DESC, post_id, id",
+            REPLY_DRAFT_COLUMNS
+        ))?;
+
+        let drafts = stmt.query_map(params![status, post_id], StoredReplyDraft::from_row)?;
+
+        drafts.collect()
+    }
+
+    pub fn get_reply_draft(&self, id: i64) -> RusqliteResult<Option<StoredReplyDraft>> {
+        let db = DB::new()?;
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {} FROM reply_drafts WHERE id = ?1",
+            REPLY_DRAFT_COLUMNS
+        ))?;
+
+        let mut drafts = stmt.query_map([id], StoredReplyDraft::from_row)?;
+        drafts.next().transpose()
+    }
+
     // GET ALL COMMENTS
     pub fn get_all_comments(&self) -> RusqliteResult<Vec<CommentDataWrapper>> {
         let db = DB::new()?;
-        let mut stmt = db.conn.prepare(
-        "SELECT id, post_id, body, author, timestamp, formatted_date, score, permalink, parent_id, subreddit, post_title, engaged, assignee
+        let mut stmt = db.conn.prepare(&format!(
+            "SELECT {}
          FROM reddit_comments
          ORDER BY timestamp DESC",
+            COMMENT_COLUMNS
+        ))?;
+
+        let comments = stmt.query_map([], CommentDataWrapper::from_row)?;
+
+        comments.collect()
+    }
+
+    // Brand mentions per day/week/month since `since` (unix seconds)
+    pub fn get_brand_mention_counts(
+        &self,
+        period: &str,
+        since: i64,
+    ) -> RusqliteResult<Vec<BrandMentionCount>> {
+        let db = DB::new()?;
+        brand_mention_counts(&db.conn, period, since)
+    }
+}
+
+// Counts per brand and period, with each brand's share of that period's mentions
+fn brand_mention_counts(
+    conn: &Connection,
+    period: &str,
+    since: i64,
+) -> RusqliteResult<Vec<BrandMentionCount>> {
+    let format = match period {
+        "week" => "%Y-W%W",
+        "month" => "%Y-%m",
+        _ => "%Y-%m-%d",
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT brand, strftime(?1, timestamp, 'unixepoch') AS period, COUNT(*)
+     FROM brand_mentions
+     WHERE timestamp >= ?2
+     GROUP BY brand, period
+     ORDER BY period, brand",
     )?;
 
-        let comments = stmt.query_map([], |row| {
-            Ok(CommentDataWrapper {
-                id: row.get(0)?,
-                post_id: row.get(1)?,
-                body: row.get(2)?,
-                author: row.get(3)?,
-                timestamp: row.get(4)?,
-                formatted_date: row.get(5)?,
-                score: row.get(6)?,
-                permalink: row.get(7)?,
-                parent_id: row.get(8)?,
-                subreddit: row.get(9)?,
-                post_title: row.get(10)?,
-                engaged: row.get(11)?,
-                assignee: row.get(12)?,
+    let mut counts = stmt
+        .query_map(params![format, since], |row| {
+            Ok(BrandMentionCount {
+                brand: row.get(0)?,
+                period: row.get(1)?,
+                mentions: row.get(2)?,
+                share: 0.0,
             })
-        })?;
+        })?
+        .collect::<RusqliteResult<Vec<_>>>()?;
 
-        comments.collect()
+    let mut totals: HashMap<String, i64> = HashMap::new();
+    for count in &counts {
+        *totals.entry(count.period.clone()).or_default() += count.mentions;
+    }
+    for count in &mut counts {
+        count.share = count.mentions as f64 / totals[&count.period] as f64;
+    }
+
+    Ok(counts)
+}
+
+fn find_post(conn: &Connection, id: i64) -> RusqliteResult<Option<PostDataWrapper>> {
+    for table in ["reddit_posts", "subreddit_search"] {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM {} WHERE id = ?1",
+            POST_COLUMNS, table
+        ))?;
+        let post = stmt.query_map([id], PostDataWrapper::from_row)?.next().transpose()?;
+        if post.is_some() {
+            return Ok(post);
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Monday 2024-01-01 00:00 UTC
+    const JAN_1: i64 = 1_704_067_200;
+    const DAY: i64 = 86_400;
+
+    fn mentions_db() -> DB {
+        let mut db = DB {
+            conn: Connection::open_in_memory().unwrap(),
+        };
+        db.create_brand_mentions_table().unwrap();
+        let post = |id: i64, brands: &str, timestamp: i64| PostDataWrapper {
+            brand_mentions: brands.to_string(),
+            timestamp,
+            ..PostDataWrapper::sample(id, "Inventory tools")
+        };
+        db.record_post_brand_mentions(&[
+            post(1, "Acme", JAN_1 + 3_600),
+            post(2, "Acme,Widgetly", JAN_1 + 7_200),
+            post(3, "Acme", JAN_1 + 2 * DAY),
+            post(4, "Widgetly", JAN_1 + 7 * DAY),
+            // Before `since`
+            post(5, "Acme", JAN_1 - 7 * DAY),
+        ])
+        .unwrap();
+        db
+    }
+
+    fn summary(counts: &[BrandMentionCount]) -> Vec<(&str, &str, i64, f64)> {
+        counts
+            .iter()
+            .map(|c| (c.period.as_str(), c.brand.as_str(), c.mentions, c.share))
+            .collect()
+    }
+
+    #[test]
+    fn mentions_are_bucketed_by_day_week_and_month() {
+        let db = mentions_db();
+
+        assert_eq!(
+            summary(&brand_mention_counts(&db.conn, "day", JAN_1).unwrap()),
+            vec![
+                ("2024-01-01", "Acme", 2, 2.0 / 3.0),
+                ("2024-01-01", "Widgetly", 1, 1.0 / 3.0),
+                ("2024-01-03", "Acme", 1, 1.0),
+                ("2024-01-08", "Widgetly", 1, 1.0),
+            ]
+        );
+        assert_eq!(
+            summary(&brand_mention_counts(&db.conn, "week", JAN_1).unwrap()),
+            vec![
+                ("2024-W01", "Acme", 3, 0.75),
+                ("2024-W01", "Widgetly", 1, 0.25),
+                ("2024-W02", "Widgetly", 1, 1.0),
+            ]
+        );
+        assert_eq!(
+            summary(&brand_mention_counts(&db.conn, "month", JAN_1).unwrap()),
+            vec![("2024-01", "Acme", 3, 0.6), ("2024-01", "Widgetly", 2, 0.4)]
+        );
+    }
+
+    #[test]
+    fn recording_the_same_post_twice_counts_once() {
+        let mut db = mentions_db();
+        let again = PostDataWrapper {
+            brand_mentions: "Acme".to_string(),
+            timestamp: JAN_1 + 3_600,
+            ..PostDataWrapper::sample(1, "Inventory tools")
+        };
+        db.record_post_brand_mentions(&[again]).unwrap();
+
+        let counts = brand_mention_counts(&db.conn, "month", 0).unwrap();
+
+        assert_eq!(summary(&counts)[0], ("2023-12", "Acme", 1, 1.0));
+        assert_eq!(summary(&counts)[1], ("2024-01", "Acme", 3, 0.6));
+    }
+
+    #[test]
+    fn posts_are_found_in_saved_posts_then_search_results() {
+        let mut db = DB {
+            conn: Connection::open_in_memory().unwrap(),
+        };
+        db.create_tables().unwrap();
+        db.create_current_search_tables().unwrap();
+        db.save_single_reddit(&PostDataWrapper::sample(1, "Saved title")).unwrap();
+        db.replace_current_results(&[
+            PostDataWrapper::sample(1, "Searched title"),
+            PostDataWrapper::sample(2, "Only searched"),
+        ])
+        .unwrap();
+
+        assert_eq!(find_post(&db.conn, 1).unwrap().unwrap().title, "Saved title");
+        assert_eq!(find_post(&db.conn, 2).unwrap().unwrap().title, "Only searched");
+        assert!(find_post(&db.conn, 3).unwrap().is_none());
     }
 }
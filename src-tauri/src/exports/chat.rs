@@ -0,0 +1,51 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use chrono::Local;
+use directories::UserDirs;
+
+use crate::ai::history::{to_markdown, ChatSession, StoredChatMessage};
+
+// Export a chat session to the Reddit_data folder as Markdown or JSON
+pub fn export_chat_session(
+    session: &ChatSession,
+    messages: &[StoredChatMessage],
+    format: &str,
+) -> io::Result<PathBuf> {
+    let (contents, extension) = match format.trim().to_lowercase().as_str() {
+        "json" => (
+            serde_json::to_string_pretty(&serde_json::json!({
+                "session": session,
+                "messages": messages,
+            }))?,
+            "json",
+        ),
+        "markdown" | "md" | "" => (to_markdown(session, messages), "md"),
+        other => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown export format {:?}, use markdown or json", other),
+            ))
+        }
+    };
+
+    let user_dirs = UserDirs::new()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Failed to get user directories"))?;
+    let desktop = user_dirs
+        .desktop_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Failed to get desktop directory"))?;
+
+    let folder_path = desktop.join("Reddit_data");
+    fs::create_dir_all(&folder_path)?;
+
+    let save_path = folder_path.join(format!(
+        "Atalaia_chat_{}_{}.{}",
+        session.id,
+        Local::now().format("%d-%m-%Y_%H-%M-%S"),
+        extension
+    ));
+    fs::write(&save_path, contents)?;
+    println!("Successfully exported to {:?}", save_path);
+    Ok(save_path)
+}
@@ -1,5 +1,6 @@
 use std::fs;
 
+use crate::ai::structured::{parse_leads, LeadBatch};
 use crate::database::adding::DB;
 use chrono::Local;
 use directories::UserDirs;
@@ -78,23 +79,13 @@ pub fn create_excel() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-// Export the filtered data by the LLM into a .xlsx
-pub fn export_gemini_to_excel(json_str: &str) -> Result<(), XlsxError> {
-    let gemini_values: Vec<Value> = match serde_json::from_str(json_str) {
-        Ok(arr) => arr,
-        Err(_) => {
-            match serde_json::from_str::<Value>(json_str) {
-                Ok(obj) => vec![obj],
-                Err(e) => {
-                    eprintln!("Warning: Failed to parse JSON, using empty data. Error: {}", e);
-                    eprintln!("JSON content (first 1000 chars): {}", &json_str[..json_str.len().min(1000)]);
-                    Vec::new() // Return empty vector instead of failing
-                }
-            }
-        }
-    };
-
-    println!("Processing {} items from JSON", gemini_values.len());
+// Export the leads validated from the LLM's reply into a .xlsx
+pub fn export_gemini_to_excel(batch: &LeadBatch) -> Result<(), XlsxError> {
+    println!(
+        "Processing {} leads and {} invalid records",
+        batch.leads.len(),
+        batch.errors.len()
+    );
 
     // Create workbook
     let mut workbook = Workbook::new();
@@ -119,34 +110,16 @@ pub fn export_gemini_to_excel(json_str: &str) -> Result<(), XlsxError> {
     worksheet.write_string_with_format(0, 6, "Engagement Score", &header_format)?;
 
     // Write leads data
-    for (row, value) in gemini_values.iter().enumerate() {
+    for (row, lead) in batch.leads.iter().enumerate() {
         let row = (row + 1) as u32;
-        if let Some(obj) = value.as_object() {
-            // Cache commonly used values
-            let title = obj
-                .get("title")
-                .and_then(|v| v.as_str())
-                .unwrap_or_default();
-            let url = obj.get("url").and_then(|v| v.as_str()).unwrap_or_default();
-
-            worksheet.write_string(row, 0, title)?;
-            worksheet.write_string(row, 1, url)?;
-
-            if let Some(date) = obj.get("formatted_date").and_then(|v| v.as_str()) {
-                worksheet.write_string(row, 2, date)?;
-            }
-            if let Some(relevance) = obj.get("relevance").and_then(|v| v.as_str()) {
-                worksheet.write_string(row, 3, relevance)?;
-            }
-            if let Some(subreddit) = obj.get("subreddit").and_then(|v| v.as_str()) {
-                worksheet.write_string(row, 4, subreddit)?;
-            }
-            if let Some(sentiment) = obj.get("sentiment").and_then(|v| v.as_str()) {
-                worksheet.write_string(row, 5, sentiment)?;
-            }
-            if let Some(engagement_score) = obj.get("engagement_score").and_then(|v| v.as_str()) {
-                worksheet.write_string(row, 6, engagement_score)?;
-            }
+        worksheet.write_string(row, 0, &lead.title)?;
+        worksheet.write_string(row, 1, &lead.url)?;
+        worksheet.write_string(row, 2, &lead.formatted_date)?;
+        worksheet.write_string(row, 3, lead.relevance.as_str())?;
+        worksheet.write_string(row, 4, &lead.subreddit)?;
+        worksheet.write_string(row, 5, &lead.sentiment)?;
+        if let Some(engagement_score) = lead.engagement_score {
+            worksheet.write_string(row, 6, engagement_score.as_str())?;
         }
     }
 
@@ -171,34 +144,16 @@ pub fn export_gemini_to_excel(json_str: &str) -> Result<(), XlsxError> {
     worksheet.write_string_with_format(0, 4, "URL", &header_format)?;
 
     let mut row_num = 1;
-    for value in &gemini_values {
-        if let Some(obj) = value.as_object() {
-            let title = obj
-                .get("title")
-                .and_then(|v| v.as_str())
-                .unwrap_or_default();
-            let url = obj.get("url").and_then(|v| v.as_str()).unwrap_or_default();
-
-            if let Some(comments) = obj.get("top_comments").and_then(|v| v.as_array()) {
-                for comment in comments {
-                    if let Some(comment_obj) = comment.as_object() {
-                        worksheet.write_string(row_num, 0, title)?;
-                        if let Some(author) = comment_obj.get("author").and_then(|v| v.as_str()) {
-                            worksheet.write_string(row_num, 1, author)?;
-                        }
-                        if let Some(text) = comment_obj.get("text").and_then(|v| v.as_str()) {
-                            worksheet.write_string(row_num, 2, text)?;
-                        }
-                        if let Some(sentiment) =
-                            comment_obj.get("sentiment").and_then(|v| v.as_str())
-                        {
-                            worksheet.write_string(row_num, 3, sentiment)?;
-                        }
-                        worksheet.write_string(row_num, 4, url)?;
-                        row_num += 1;
-                    }
-                }
+    for lead in &batch.leads {
+        for comment in &lead.top_comments {
+            worksheet.write_string(row_num, 0, &lead.title)?;
+            worksheet.write_string(row_num, 1, &comment.author)?;
+            worksheet.write_string(row_num, 2, &comment.text)?;
+            if let Some(sentiment) = &comment.sentiment {
+                worksheet.write_string(row_num, 3, sentiment)?;
             }
+            worksheet.write_string(row_num, 4, &lead.url)?;
+            row_num += 1;
         }
     }
 
@@ -211,6 +166,23 @@ pub fn export_gemini_to_excel(json_str: &str) -> Result<(), XlsxError> {
         worksheet.set_column_width(4, 30)?; // URL
     }
 
+    // Records that failed validation, so they can be fixed by hand
+    if !batch.errors.is_empty() {
+        worksheet = workbook.add_worksheet();
+        worksheet.set_name("Invalid records")?;
+        worksheet.write_string_with_format(0, 0, "Record", &header_format)?;
+        worksheet.write_string_with_format(0, 1, "Error", &header_format)?;
+        worksheet.write_string_with_format(0, 2, "Data", &header_format)?;
+        for (row, error) in batch.errors.iter().enumerate() {
+            let row = (row + 1) as u32;
+            worksheet.write_number(row, 0, error.index as f64)?;
+            worksheet.write_string(row, 1, &error.message)?;
+            worksheet.write_string(row, 2, error.record.to_string())?;
+        }
+        worksheet.set_column_width(1, 50)?;
+        worksheet.set_column_width(2, 100)?;
+    }
+
     // Get user's desktop directory
     let user_dirs = UserDirs::new().ok_or_else(|| {
         XlsxError::IoError(std::io::Error::new(
@@ -342,7 +314,7 @@ pub fn export_comments_from_db(post_id: &str) -> Result<(), XlsxError> {
 
 // Function to export the leads that are generated from the LLM
 pub async fn export_leads_with_gemini(data: &str) -> Result<(), XlsxError> {
-    export_gemini_to_excel(data)
+    export_gemini_to_excel(&parse_leads(data))
 }
 
 // Function to export the comments that are generated from the LLM
@@ -33,6 +33,15 @@ async fn main() {
         .invoke_handler(tauri::generate_handler![
             // COMMANDS GOES HERE
             commands::get_reddit_results,
+            commands::run_brand_monitoring_command,
+            commands::get_brand_mention_counts_command,
+            commands::get_duplicate_clusters_command,
+            commands::cluster_topics_command,
+            commands::get_topics_command,
+            commands::get_posts_by_topic_command,
+            commands::index_embeddings_command,
+            commands::semantic_search_command,
+            commands::find_similar_leads_command,
             commands::get_recent_posts,
             commands::get_all_posts,
             commands::get_all_searched_posts,
@@ -41,6 +50,8 @@ async fn main() {
             commands::remove_single_reddit_command,
             commands::get_post_comments_command,
             commands::get_all_comments_command,
+            commands::get_posts_by_sentiment_command,
+            commands::get_comments_by_sentiment_command,
             commands::clear_comments_command,
             commands::open_settings_commmand,
             commands::open_db_folder_command,
@@ -51,10 +62,40 @@ async fn main() {
             commands::get_reddit_config_command,
             commands::update_reddit_config_command,
             commands::submit_reddit_comment_command,
+            commands::reddit_whoami_command,
+            commands::find_leads_command,
+            commands::generate_ai_leads_command,
+            commands::count_leads_by_subreddit_command,
+            commands::get_author_profile_command,
+            commands::discover_subreddits_command,
+            commands::save_subreddit_command,
+            commands::get_saved_subreddits_command,
+            commands::update_subreddit_notes_command,
+            commands::update_subreddit_weight_command,
+            commands::remove_saved_subreddit_command,
+            commands::get_subreddit_rules_command,
             commands::ask_gemini_command,
+            commands::ask_gemini_stream_command,
             commands::get_gemini_models_command,
+            commands::get_ai_models_command,
             commands::start_reddit_auth_flow_command,
-            commands::generate_reply_command
+            commands::generate_reply_command,
+            commands::generate_reply_stream_command,
+            commands::draft_replies_command,
+            commands::get_reply_tones_command,
+            commands::start_reply_queue_command,
+            commands::cancel_reply_queue_command,
+            commands::get_reply_drafts_command,
+            commands::update_reply_draft_command,
+            commands::cancel_ai_stream_command,
+            commands::create_chat_session_command,
+            commands::list_chat_sessions_command,
+            commands::get_chat_messages_command,
+            commands::rename_chat_session_command,
+            commands::delete_chat_session_command,
+            commands::export_chat_session_command,
+            commands::ask_ai_with_tools_command,
+            commands::confirm_ai_actions_command
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,35 +1,85 @@
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use rand::Rng;
 use reqwest::Client;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use base64::{engine::general_purpose, Engine as _};
-use crate::settings::api_keys::{self, ConfigDirs};
+use chrono::Utc;
+use std::future::Future;
+use crate::models::search::RedditError;
+use crate::settings::api_keys::{ApiKeys, ConfigDirs};
 
 #[derive(Debug, Deserialize)]
-struct TokenResponse {
-    access_token: String,
-    token_type: String,
-    expires_in: i64,
-    refresh_token: Option<String>,
-    scope: String,
+pub(crate) struct TokenResponse {
+    pub access_token: String,
+    pub expires_in: i64,
+    pub refresh_token: Option<String>,
+    #[serde(default)]
+    pub scope: String,
 }
 
-pub async fn start_auth_flow(client_id: String, client_secret: String) -> Result<String, String> {
+// Refresh a little early so a token never expires mid-request
+const TOKEN_EXPIRY_MARGIN_SECS: i64 = 60;
+
+/// Who the stored user token belongs to, for the settings screen.
+#[derive(Debug, Serialize)]
+pub struct RedditIdentity {
+    pub username: String,
+    pub scopes: Vec<String>,
+    pub expires_at: i64,
+    pub expires_at_formatted: String,
+}
+
+/// Where the auth flow listens when `reddit_redirect_uri` is not configured.
+pub const DEFAULT_REDIRECT_URI: &str = "http://localhost:8989";
+
+// Largest callback request we are willing to buffer before giving up on it
+const MAX_CALLBACK_REQUEST_BYTES: usize = 16 * 1024;
+
+const SUCCESS_PAGE: &str = "<html>
+    <body style='font-family: sans-serif; text-align: center; padding: 50px;'>
+        <h1 style='color: #4CAF50;'>Authentication Successful!</h1>
+        <p>You can close this window and return to the application.</p>
+        <script>window.close();</script>
+    </body>
+    </html>";
+
+#[derive(Debug, PartialEq)]
+enum CallbackOutcome {
+    Code(String),
+    Denied,
+    Failed(String),
+    // Anything else hitting the listener (favicon, preconnects); keep waiting
+    Ignored,
+}
+
+pub async fn start_auth_flow(api_keys: ApiKeys) -> Result<String, String> {
     println!("Starting auth flow...");
-    let client_id = client_id.trim().to_string();
-    let client_secret = client_secret.trim().to_string();
+    let client_id = api_keys.reddit_api_id.trim().to_string();
+    let client_secret = api_keys.reddit_api_secret.trim().to_string();
 
     if client_id == "CHANGE_ME" || client_secret == "CHANGE_ME" {
         return Err("Client ID and Secret must be configured first.".to_string());
     }
 
-    let port = 8989;
-    let redirect_uri = format!("http://localhost:{}", port);
+    let redirect_uri = if api_keys.reddit_redirect_uri.trim().is_empty() {
+        DEFAULT_REDIRECT_URI.to_string()
+    } else {
+        api_keys.reddit_redirect_uri.trim().to_string()
+    };
+    let parsed_redirect = reqwest::Url::parse(&redirect_uri)
+        .map_err(|e| format!("Invalid redirect URI '{}': {}", redirect_uri, e))?;
+    let port = parsed_redirect
+        .port_or_known_default()
+        .ok_or_else(|| format!("Redirect URI '{}' has no port", redirect_uri))?;
+    let callback_path = parsed_redirect.path().to_string();
+
     println!("--- REDDIT AUTH CONFIG ---");
     println!("Expected Redirect URI: {}", redirect_uri);
     println!("Ensure this EXACT URL is set in your Reddit App Preferences.");
     println!("--------------------------");
 
-    let state = "random_state_string"; // In prod, generate random string
+    let state = generate_state();
     let scope = "identity read submit privatemessages history"; // Add other scopes as needed
 
     // 1. Start Listener
@@ -41,8 +91,12 @@ pub async fn start_auth_flow(client_id: String, client_secret: String) -> Result
     let encoded_redirect = urlencoding::encode(&redirect_uri);
     let encoded_scope = urlencoding::encode(scope);
     let auth_url = format!(
-        "https://www.reddit.com/api/v1/authorize?client_id={}&response_type=code&state={}&redirect_uri={}&duration=permanent&scope={}",
-        client_id, state, encoded_redirect, encoded_scope
+        "{}?client_id={}&response_type=code&state={}&redirect_uri={}&duration=permanent&scope={}",
+        api_keys.reddit_auth_url("/api/v1/authorize"),
+        client_id,
+        state,
+        encoded_redirect,
+        encoded_scope
     );
 
     println!("Opening browser to: {}", auth_url);
@@ -57,146 +111,240 @@ pub async fn start_auth_flow(client_id: String, client_secret: String) -> Result
     // 3. Wait for callback (with timeout)
     println!("Waiting for callback on port {}...", port);
 
-    let (mut stream, _) = tokio::time::timeout(
+    let code = tokio::time::timeout(
         std::time::Duration::from_secs(300), // 5 minute timeout
-        listener.accept()
+        wait_for_callback(&listener, &callback_path, &state),
     )
     .await
-    .map_err(|_| "Authentication timed out. Please try again.".to_string())?
-    .map_err(|e| e.to_string())?;
-    
-    let mut buffer = [0; 1024];
-    stream.read(&mut buffer).await.map_err(|e| e.to_string())?;
-    
-    let request = String::from_utf8_lossy(&buffer);
-    
-    let code_start = request.find("code=");
-    if code_start.is_none() {
-         let response = "HTTP/1.1 400 BAD REQUEST\r\n\r\nFailed to get code";
-         stream.write_all(response.as_bytes()).await.unwrap();
-         return Err("Callback received but no code found.".to_string());
-    }
-    
-    let code_start_idx = code_start.unwrap() + 5;
-    let code_end_idx = request[code_start_idx..].find(|c| c == '&' || c == ' ').map(|i| code_start_idx + i).unwrap_or(request.len());
-    let code = &request[code_start_idx..code_end_idx];
-
-    println!("Received code: {}", code);
-
-    // Send success response to browser
-    let success_page = "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n
-    <html>
-    <body style='font-family: sans-serif; text-align: center; padding: 50px;'>
-        <h1 style='color: #4CAF50;'>Authentication Successful!</h1>
-        <p>You can close this window and return to the application.</p>
-        <script>window.close();</script>
-    </body>
-    </html>";
-    stream.write_all(success_page.as_bytes()).await.unwrap();
-    stream.flush().await.unwrap();
-    
+    .map_err(|_| "Authentication timed out. Please try again.".to_string())??;
+
+    println!("Received authorization code.");
+
     // 4. Exchange code for token
-    exchange_code_for_token(&client_id, &client_secret, code, &redirect_uri).await
+    exchange_code_for_token(&api_keys, &code, &redirect_uri).await
+}
+
+// Random per-attempt value echoed back by Reddit, so we only accept our own callback
+fn generate_state() -> String {
+    rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
+// Accepts connections until the browser delivers the OAuth redirect, answering each one
+async fn wait_for_callback(
+    listener: &TcpListener,
+    callback_path: &str,
+    expected_state: &str,
+) -> Result<String, String> {
+    loop {
+        let (mut stream, _) = listener.accept().await.map_err(|e| e.to_string())?;
+
+        let target = match read_request_target(&mut stream).await {
+            Ok(target) => target,
+            Err(e) => {
+                eprintln!("Ignoring malformed callback request: {}", e);
+                let _ = respond(&mut stream, "400 Bad Request", "Malformed request").await;
+                continue;
+            }
+        };
+
+        match parse_callback(&target, callback_path, expected_state) {
+            CallbackOutcome::Code(code) => {
+                respond(&mut stream, "200 OK", SUCCESS_PAGE).await?;
+                return Ok(code);
+            }
+            CallbackOutcome::Denied => {
+                respond(&mut stream, "200 OK", "Authorization was denied. You can close this window.").await?;
+                return Err("Reddit authorization was denied. Please approve access to connect your account.".to_string());
+            }
+            CallbackOutcome::Failed(reason) => {
+                respond(&mut stream, "400 Bad Request", &reason).await?;
+                return Err(reason);
+            }
+            CallbackOutcome::Ignored => {
+                let _ = respond(&mut stream, "404 Not Found", "Not Found").await;
+            }
+        }
+    }
+}
+
+// Reads until the request head is complete and returns its target (path + query)
+async fn read_request_target(stream: &mut TcpStream) -> Result<String, String> {
+    let mut buffer = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+
+    loop {
+        let read = stream.read(&mut chunk).await.map_err(|e| e.to_string())?;
+        if read == 0 {
+            return Err("Connection closed before the request was complete".to_string());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+
+        let mut headers = [httparse::EMPTY_HEADER; 64];
+        let mut request = httparse::Request::new(&mut headers);
+        match request.parse(&buffer).map_err(|e| e.to_string())? {
+            httparse::Status::Complete(_) => {
+                if request.method != Some("GET") {
+                    return Err(format!("Unexpected method {:?}", request.method));
+                }
+                return request
+                    .path
+                    .map(|path| path.to_string())
+                    .ok_or_else(|| "Request has no target".to_string());
+            }
+            httparse::Status::Partial => {
+                if buffer.len() > MAX_CALLBACK_REQUEST_BYTES {
+                    return Err("Request too large".to_string());
+                }
+            }
+        }
+    }
+}
+
+fn parse_callback(target: &str, callback_path: &str, expected_state: &str) -> CallbackOutcome {
+    let url = match reqwest::Url::parse(&format!("http://localhost{}", target)) {
+        Ok(url) => url,
+        Err(_) => return CallbackOutcome::Ignored,
+    };
+
+    if url.path() != callback_path {
+        return CallbackOutcome::Ignored;
+    }
+
+    let mut code = None;
+    let mut state = None;
+    let mut error = None;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "code" => code = Some(value.into_owned()),
+            "state" => state = Some(value.into_owned()),
+            "error" => error = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+
+    if code.is_none() && error.is_none() {
+        return CallbackOutcome::Ignored;
+    }
+
+    if state.as_deref() != Some(expected_state) {
+        return CallbackOutcome::Failed("OAuth state mismatch. Please start the login again.".to_string());
+    }
+
+    match (error, code) {
+        (Some(error), _) if error == "access_denied" => CallbackOutcome::Denied,
+        (Some(error), _) => CallbackOutcome::Failed(format!("Reddit returned an error: {}", error)),
+        (None, Some(code)) if !code.is_empty() => CallbackOutcome::Code(code),
+        _ => CallbackOutcome::Failed("Callback received but no code found.".to_string()),
+    }
 }
 
-async fn exchange_code_for_token(client_id: &str, client_secret: &str, code: &str, redirect_uri: &str) -> Result<String, String> {
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) -> Result<(), String> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await.map_err(|e| e.to_string())?;
+    stream.flush().await.map_err(|e| e.to_string())
+}
+
+// POSTs a grant to the token endpoint with the app's basic auth credentials
+pub(crate) async fn request_token(
+    api_keys: &ApiKeys,
+    params: &[(&str, &str)],
+) -> Result<TokenResponse, RedditError> {
     let client = Client::new();
-    let credentials = format!("{}:{}", client_id, client_secret);
+    let credentials = format!(
+        "{}:{}",
+        api_keys.reddit_api_id.trim(),
+        api_keys.reddit_api_secret.trim()
+    );
     let encoded = general_purpose::STANDARD.encode(credentials);
 
-    let params = [
-        ("grant_type", "authorization_code"),
-        ("code", code),
-        ("redirect_uri", redirect_uri),
-    ];
-
-    println!("Attempting to exchange code for token...");
-    println!("Client ID: {}", client_id);
-    println!("Redirect URI: {}", redirect_uri);
-    
-    let response = client.post("https://www.reddit.com/api/v1/access_token")
+    let response = client.post(api_keys.reddit_auth_url("/api/v1/access_token"))
         .header("Authorization", format!("Basic {}", encoded))
         .header("User-Agent", "Atalaia/0.1.0") // REQUIRED by Reddit
-        .form(&params)
+        .form(params)
         .send()
-        .await
-        .map_err(|e| {
-             println!("HTTP Request Failed: {}", e);
-             e.to_string()
-        })?;
-
-    println!("Response Status: {}", response.status());
+        .await?;
 
     if !response.status().is_success() {
+        let status = response.status();
         let error_text = response.text().await.unwrap_or_default();
-        println!("Token exchange error body: {}", error_text);
-        return Err(format!("Token exchange failed: {}", error_text));
+        return Err(RedditError::HttpError(status.as_u16(), error_text));
     }
 
-    let response_text = response.text().await.map_err(|e| e.to_string())?;
-    println!("Token Response Body: {}", response_text);
+    let response_text = response.text().await?;
+    serde_json::from_str(&response_text).map_err(|e| RedditError::ParseError(e.to_string()))
+}
+
+async fn exchange_code_for_token(api_keys: &ApiKeys, code: &str, redirect_uri: &str) -> Result<String, String> {
+    let params = [
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", redirect_uri),
+    ];
 
-    let token_res: TokenResponse = serde_json::from_str(&response_text).map_err(|e| {
-        println!("JSON Parse Error: {}", e);
-        e.to_string()
+    println!("Attempting to exchange code for token...");
+    println!("Client ID: {}", api_keys.reddit_api_id);
+    println!("Redirect URI: {}", redirect_uri);
+
+    let token_res = request_token(api_keys, &params).await.map_err(|e| {
+        println!("Token exchange failed: {}", e);
+        match e {
+            RedditError::HttpError(_, text) => format!("Token exchange failed: {}", text),
+            other => other.to_string(),
+        }
     })?;
 
     // 5. Save tokens to config
     let mut config = ConfigDirs::read_config().map_err(|e| format!("Failed to read config during auth save: {}", e))?;
-    config.api_keys.reddit_access_token = token_res.access_token.clone();
+    store_access_token(&mut config.api_keys, &token_res);
     if let Some(refresh_token) = token_res.refresh_token {
         config.api_keys.reddit_refresh_token = refresh_token;
     } else {
         println!("WARNING: No refresh token received from Reddit! User might need to revoke app access to get a new one.");
     }
-    println!("Saving config... Refresh token in memory: '{}'", config.api_keys.reddit_refresh_token);
     ConfigDirs::save_config(&config).map_err(|e| format!("Failed to save config during auth: {}", e))?;
-    
+
     println!("Successfully saved tokens. Refresh token present: {}", !config.api_keys.reddit_refresh_token.is_empty());
 
     Ok("Authentication successful!".to_string())
 }
 
-pub async fn refresh_access_token(client_id: &str, client_secret: &str, refresh_token: &str) -> Result<String, String> {
-    let client = Client::new();
-    let credentials = format!("{}:{}", client_id.trim(), client_secret.trim());
-    let encoded = general_purpose::STANDARD.encode(credentials);
-
+pub async fn refresh_access_token(api_keys: &ApiKeys) -> Result<String, String> {
     let params = [
         ("grant_type", "refresh_token"),
-        ("refresh_token", refresh_token),
+        ("refresh_token", api_keys.reddit_refresh_token.as_str()),
     ];
 
-    let response = client.post("https://www.reddit.com/api/v1/access_token")
-        .header("Authorization", format!("Basic {}", encoded))
-        .header("User-Agent", "Atalaia/0.1.0") // REQUIRED by Reddit
-        .form(&params)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_default();
-        
+    let token_res = match request_token(api_keys, &params).await {
+        Ok(token_res) => token_res,
         // If Unauthorized (401), it means the refresh token is invalid or credentials changed.
         // We should clear the tokens to force a re-login.
-        if status == reqwest::StatusCode::UNAUTHORIZED {
+        Err(RedditError::HttpError(401, _)) => {
             let mut config = ConfigDirs::read_config().map_err(|e| e.to_string())?;
             config.api_keys.reddit_access_token = String::new();
+            config.api_keys.reddit_access_token_expires_at = 0;
+            config.api_keys.reddit_token_scope = String::new();
             config.api_keys.reddit_refresh_token = String::new();
             ConfigDirs::save_config(&config).map_err(|e| e.to_string())?;
             return Err("Session expired or credentials changed. Please reconnect your Reddit account in Settings.".to_string());
         }
-
-        return Err(format!("Token refresh failed ({}): {}", status, error_text));
-    }
-
-    let token_res: TokenResponse = response.json().await.map_err(|e| e.to_string())?;
+        Err(RedditError::HttpError(status, error_text)) => {
+            return Err(format!("Token refresh failed ({}): {}", status, error_text));
+        }
+        Err(e) => return Err(e.to_string()),
+    };
 
     // Update config with new access token
     let mut config = ConfigDirs::read_config().map_err(|e| e.to_string())?;
-    config.api_keys.reddit_access_token = token_res.access_token.clone();
+    store_access_token(&mut config.api_keys, &token_res);
     // Refresh token might rotate? Reddit usually keeps it, but if a new one is returned, update it.
     if let Some(new_rt) = token_res.refresh_token {
         config.api_keys.reddit_refresh_token = new_rt;
@@ -205,3 +353,247 @@ pub async fn refresh_access_token(client_id: &str, client_secret: &str, refresh_
 
     Ok(token_res.access_token)
 }
+
+fn store_access_token(api_keys: &mut ApiKeys, token_res: &TokenResponse) {
+    api_keys.reddit_access_token = token_res.access_token.clone();
+    api_keys.reddit_access_token_expires_at = Utc::now().timestamp() + token_res.expires_in;
+    if !token_res.scope.is_empty() {
+        api_keys.reddit_token_scope = token_res.scope.clone();
+    }
+}
+
+fn has_usable_access_token(api_keys: &ApiKeys) -> bool {
+    !api_keys.reddit_access_token.is_empty()
+        && api_keys.reddit_access_token_expires_at > Utc::now().timestamp() + TOKEN_EXPIRY_MARGIN_SECS
+}
+
+/// Returns the stored user access token while it is still valid, refreshing it otherwise.
+/// `force_refresh` skips the stored token, e.g. after Reddit rejected it with a 401.
+pub async fn get_valid_user_token(force_refresh: bool) -> Result<String, String> {
+    let config = ConfigDirs::read_config().map_err(|e| e.to_string())?;
+    let api_keys = config.api_keys;
+
+    if !force_refresh && has_usable_access_token(&api_keys) {
+        return Ok(api_keys.reddit_access_token);
+    }
+
+    if api_keys.reddit_refresh_token.is_empty() {
+        return Err("Please login with Reddit in Settings first.".to_string());
+    }
+
+    refresh_access_token(&api_keys).await
+}
+
+/// Runs a user-authenticated Reddit call, refreshing the token and retrying once on a 401.
+pub async fn with_user_token<T, F, Fut>(call: F) -> Result<T, String>
+where
+    F: Fn(ApiKeys, String) -> Fut,
+    Fut: Future<Output = Result<T, RedditError>>,
+{
+    let config = ConfigDirs::read_config().map_err(|e| e.to_string())?;
+    let token = get_valid_user_token(false).await?;
+    match call(config.api_keys.clone(), token).await {
+        Err(RedditError::HttpError(401, _)) => {
+            println!("Reddit rejected the stored access token, refreshing...");
+            let token = get_valid_user_token(true).await?;
+            call(config.api_keys, token).await.map_err(|e| e.to_string())
+        }
+        result => result.map_err(|e| e.to_string()),
+    }
+}
+
+async fn fetch_username(api_keys: ApiKeys, access_token: String) -> Result<String, RedditError> {
+    let response = Client::new()
+        .get(api_keys.reddit_api_url("/api/v1/me"))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .header("User-Agent", "Atalaia/0.1.0") // REQUIRED by Reddit
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(RedditError::HttpError(
+            response.status().as_u16(),
+            response.text().await.unwrap_or_default(),
+        ));
+    }
+
+    let json: serde_json::Value = response.json().await?;
+    json["name"]
+        .as_str()
+        .map(|name| name.to_string())
+        .ok_or_else(|| RedditError::ParseError("Reddit did not return a username".to_string()))
+}
+
+pub async fn whoami() -> Result<RedditIdentity, String> {
+    let username = with_user_token(fetch_username).await?;
+
+    // Re-read so we report the expiry of the token that was actually used
+    let config = ConfigDirs::read_config().map_err(|e| e.to_string())?;
+    let api_keys = config.api_keys;
+
+    Ok(RedditIdentity {
+        username,
+        scopes: api_keys
+            .reddit_token_scope
+            .split([' ', ','])
+            .filter(|scope| !scope.is_empty())
+            .map(|scope| scope.to_string())
+            .collect(),
+        expires_at: api_keys.reddit_access_token_expires_at,
+        expires_at_formatted: crate::database::adding::DB::format_timestamp(
+            api_keys.reddit_access_token_expires_at,
+        )
+        .unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Plays the browser: connects to the listener and sends the request in the given pieces
+    async fn browser_request(port: u16, pieces: Vec<String>) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        for piece in pieces {
+            stream.write_all(piece.as_bytes()).await.unwrap();
+            stream.flush().await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    fn get(target: &str) -> String {
+        format!(
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nUser-Agent: test-browser\r\nAccept: text/html\r\n\r\n",
+            target
+        )
+    }
+
+    async fn listen() -> (TcpListener, u16) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        (listener, port)
+    }
+
+    #[tokio::test]
+    async fn accepts_code_with_matching_state() {
+        let (listener, port) = listen().await;
+        let browser = tokio::spawn(browser_request(port, vec![get("/?state=abc&code=the-code")]));
+
+        let code = wait_for_callback(&listener, "/", "abc").await;
+        let response = browser.await.unwrap();
+
+        assert_eq!(code, Ok("the-code".to_string()));
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("Authentication Successful"));
+    }
+
+    #[tokio::test]
+    async fn rejects_mismatched_state() {
+        let (listener, port) = listen().await;
+        let browser = tokio::spawn(browser_request(port, vec![get("/?state=forged&code=the-code")]));
+
+        let result = wait_for_callback(&listener, "/", "abc").await;
+        let response = browser.await.unwrap();
+
+        assert!(result.unwrap_err().contains("state mismatch"));
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+    }
+
+    #[tokio::test]
+    async fn reports_access_denied() {
+        let (listener, port) = listen().await;
+        let browser = tokio::spawn(browser_request(port, vec![get("/?state=abc&error=access_denied")]));
+
+        let result = wait_for_callback(&listener, "/", "abc").await;
+        browser.await.unwrap();
+
+        assert!(result.unwrap_err().contains("denied"));
+    }
+
+    #[tokio::test]
+    async fn reassembles_request_split_across_reads() {
+        let (listener, port) = listen().await;
+        let request = get("/callback?code=split%2Bcode&state=abc");
+        let (head, tail) = request.split_at(20);
+        let browser = tokio::spawn(browser_request(port, vec![head.to_string(), tail.to_string()]));
+
+        let code = wait_for_callback(&listener, "/callback", "abc").await;
+        browser.await.unwrap();
+
+        assert_eq!(code, Ok("split+code".to_string()));
+    }
+
+    #[tokio::test]
+    async fn keeps_waiting_after_unrelated_requests() {
+        let (listener, port) = listen().await;
+        let browser = tokio::spawn(async move {
+            let favicon = browser_request(port, vec![get("/favicon.ico")]).await;
+            let callback = browser_request(port, vec![get("/?code=late&state=abc")]).await;
+            (favicon, callback)
+        });
+
+        let code = wait_for_callback(&listener, "/", "abc").await;
+        let (favicon, _) = browser.await.unwrap();
+
+        assert_eq!(code, Ok("late".to_string()));
+        assert!(favicon.starts_with("HTTP/1.1 404 Not Found"));
+    }
+
+    fn token(access_token: &str, expires_in: i64, scope: &str) -> TokenResponse {
+        TokenResponse {
+            access_token: access_token.to_string(),
+            expires_in,
+            refresh_token: None,
+            scope: scope.to_string(),
+        }
+    }
+
+    #[test]
+    fn fresh_tokens_are_reused() {
+        let mut api_keys = ApiKeys {
+            reddit_token_scope: "identity read".to_string(),
+            ..ApiKeys::default()
+        };
+
+        store_access_token(&mut api_keys, &token("fresh", 3600, ""));
+
+        assert!(has_usable_access_token(&api_keys));
+        assert_eq!(api_keys.reddit_access_token, "fresh");
+        // An empty scope in the response keeps the one granted at login
+        assert_eq!(api_keys.reddit_token_scope, "identity read");
+    }
+
+    #[test]
+    fn tokens_inside_the_expiry_margin_are_refreshed() {
+        let mut api_keys = ApiKeys::default();
+
+        store_access_token(&mut api_keys, &token("nearly-expired", TOKEN_EXPIRY_MARGIN_SECS - 5, "read"));
+        assert!(!has_usable_access_token(&api_keys));
+        assert_eq!(api_keys.reddit_token_scope, "read");
+
+        store_access_token(&mut api_keys, &token("expired", -10, "read"));
+        assert!(!has_usable_access_token(&api_keys));
+    }
+
+    #[test]
+    fn missing_tokens_are_not_usable() {
+        let api_keys = ApiKeys {
+            reddit_access_token: String::new(),
+            reddit_access_token_expires_at: Utc::now().timestamp() + 3600,
+            ..ApiKeys::default()
+        };
+
+        assert!(!has_usable_access_token(&api_keys));
+        assert!(!has_usable_access_token(&ApiKeys::default()));
+    }
+
+    #[test]
+    fn generates_distinct_states() {
+        let first = generate_state();
+        assert_eq!(first.len(), 32);
+        assert_ne!(first, generate_state());
+    }
+}
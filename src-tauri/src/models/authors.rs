@@ -0,0 +1,322 @@
+// Redditor profiles for leads: karma, account age, where they are active and
+// whether they talked about our keywords before. Profiles are cached in the
+// authors table for `author_cache_hours` so leads can be screened for bots and
+// throwaways without hitting Reddit for every post.
+use std::collections::HashMap;
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::models::intent::KeywordMatcher;
+use crate::models::search::{get_reddit_json, RedditError};
+use crate::settings::api_keys::ApiKeys;
+
+// Accounts younger than this or with less karma are treated as throwaways
+const NEW_ACCOUNT_DAYS: i64 = 30;
+const LOW_KARMA: i64 = 50;
+const ACTIVE_SUBREDDITS: usize = 5;
+// Well-known bots whose names don't say so
+const KNOWN_BOTS: &[&str] = &["automoderator", "savevideo", "vredditdownloader"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorProfile {
+    pub username: String,
+    pub link_karma: i64,
+    pub comment_karma: i64,
+    pub account_created: i64,
+    pub verified_email: bool,
+    pub suspended: bool,
+    // Counts over the last 100 items of the user's overview
+    pub recent_posts: i64,
+    pub recent_comments: i64,
+    // Most active first
+    pub active_subreddits: Vec<String>,
+    // Recent posts/comments mentioning lead, branded or competitor keywords
+    pub keyword_mentions: i64,
+    // Reasons to skip the author: bot, throwaway, new_account, low_karma, suspended, no_history
+    pub flags: Vec<String>,
+    pub summary: String,
+    pub fetched_at: i64,
+}
+
+impl AuthorProfile {
+    /// Anything flagged looks like a bot or throwaway rather than a buyer.
+    pub fn is_suspect(&self) -> bool {
+        !self.flags.is_empty()
+    }
+
+    pub fn is_fresh(&self, api_keys: &ApiKeys, now: i64) -> bool {
+        now - self.fetched_at < api_keys.author_cache_hours.max(0) * 3600
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AboutResponse {
+    data: AboutData,
+}
+
+// Suspended accounts only come back with name and is_suspended
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AboutData {
+    name: String,
+    created_utc: f64,
+    link_karma: i64,
+    comment_karma: i64,
+    has_verified_email: Option<bool>,
+    is_suspended: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActivityListing {
+    data: ActivityListingData,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActivityListingData {
+    children: Vec<ActivityChild>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActivityChild {
+    kind: String, // t3 submission, t1 comment
+    data: ActivityData,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ActivityData {
+    subreddit: String,
+    title: String,
+    selftext: String,
+    body: String,
+}
+
+/// Fetches `/user/{name}/about` and the user's recent overview and builds a profile.
+pub async fn fetch_author_profile(
+    api_keys: &ApiKeys,
+    access_token: &str,
+    username: &str,
+    now: i64,
+) -> Result<AuthorProfile, RedditError> {
+    let client = Client::new();
+    let username = username.trim().trim_start_matches("u/");
+
+    let about: AboutResponse = get_reddit_json(
+        &client,
+        &api_keys.reddit_api_url(&format!("/user/{}/about", username)),
+        &[],
+        access_token,
+    )
+    .await?;
+
+    // Suspended users' listings are forbidden, the about data is all there is
+    let activity = if about.data.is_suspended {
+        Vec::new()
+    } else {
+        let listing: ActivityListing = get_reddit_json(
+            &client,
+            &api_keys.reddit_api_url(&format!("/user/{}/overview", username)),
+            &[("limit", "100")],
+            access_token,
+        )
+        .await?;
+        listing.data.children
+    };
+
+    Ok(build_profile(api_keys, username, about.data, activity, now))
+}
+
+fn build_profile(
+    api_keys: &ApiKeys,
+    username: &str,
+    about: AboutData,
+    activity: Vec<ActivityChild>,
+    now: i64,
+) -> AuthorProfile {
+    let mut keywords = api_keys.lead_keywords.clone();
+    keywords.extend(api_keys.all_brand_keywords());
+    let keywords = KeywordMatcher::new(&keywords);
+
+    let mut recent_posts = 0;
+    let mut recent_comments = 0;
+    let mut keyword_mentions = 0;
+    let mut subreddits: HashMap<String, usize> = HashMap::new();
+    for item in &activity {
+        let (title, body) = if item.kind == "t3" {
+            recent_posts += 1;
+            (item.data.title.as_str(), item.data.selftext.as_str())
+        } else {
+            recent_comments += 1;
+            ("", item.data.body.as_str())
+        };
+        if keywords.count(title, Some(body)) > 0 {
+            keyword_mentions += 1;
+        }
+        if !item.data.subreddit.is_empty() {
+            *subreddits.entry(item.data.subreddit.clone()).or_default() += 1;
+        }
+    }
+
+    let mut active_subreddits: Vec<(String, usize)> = subreddits.into_iter().collect();
+    active_subreddits.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let active_subreddits: Vec<String> = active_subreddits
+        .into_iter()
+        .take(ACTIVE_SUBREDDITS)
+        .map(|(name, _)| name)
+        .collect();
+
+    let name = if about.name.is_empty() { username.to_string() } else { about.name };
+    let account_created = about.created_utc as i64;
+    let age_days = (now - account_created).max(0) / 86_400;
+    let karma = about.link_karma + about.comment_karma;
+
+    let mut flags = Vec::new();
+    if about.is_suspended {
+        flags.push("suspended");
+    }
+    let lower = name.to_lowercase();
+    if is_bot_name(&name) {
+        flags.push("bot");
+    }
+    if lower.contains("throwaway") {
+        flags.push("throwaway");
+    }
+    if !about.is_suspended {
+        if age_days < NEW_ACCOUNT_DAYS {
+            flags.push("new_account");
+        }
+        if karma < LOW_KARMA {
+            flags.push("low_karma");
+        }
+        if activity.is_empty() {
+            flags.push("no_history");
+        }
+    }
+    let flags: Vec<String> = flags.into_iter().map(str::to_string).collect();
+
+    let summary = summarize(
+        age_days,
+        karma,
+        &active_subreddits,
+        keyword_mentions,
+        &flags,
+        about.is_suspended,
+    );
+
+    AuthorProfile {
+        username: name,
+        link_karma: about.link_karma,
+        comment_karma: about.comment_karma,
+        account_created,
+        verified_email: about.has_verified_email.unwrap_or(false),
+        suspended: about.is_suspended,
+        recent_posts,
+        recent_comments,
+        active_subreddits,
+        keyword_mentions,
+        flags,
+        summary,
+        fetched_at: now,
+    }
+}
+
+// "bot" has to be a word of its own: stock_bot, bot-3000 or RemindMeBot, but not Talbot
+fn is_bot_name(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    if KNOWN_BOTS.contains(&lower.as_str()) {
+        return true;
+    }
+    if lower.split(['_', '-']).any(|part| part == "bot") {
+        return true;
+    }
+    name.strip_suffix("Bot")
+        .and_then(|head| head.chars().last())
+        .is_some_and(|c| c.is_lowercase() || c.is_ascii_digit())
+}
+
+// "4 years old, 12345 karma, active in r/a, r/b, 2 keyword mentions (flags: ...)"
+fn summarize(
+    age_days: i64,
+    karma: i64,
+    subreddits: &[String],
+    keyword_mentions: i64,
+    flags: &[String],
+    suspended: bool,
+) -> String {
+    let mut parts = Vec::new();
+    if suspended {
+        parts.push("suspended account".to_string());
+    } else {
+        let age = match age_days {
+            d if d < 60 => format!("{} days old", d),
+            d if d < 730 => format!("{} months old", d / 30),
+            d => format!("{} years old", d / 365),
+        };
+        parts.push(age);
+        parts.push(format!("{} karma", karma));
+    }
+    if !subreddits.is_empty() {
+        let names: Vec<String> = subreddits.iter().map(|s| format!("r/{}", s)).collect();
+        parts.push(format!("active in {}", names.join(", ")));
+    }
+    if keyword_mentions > 0 {
+        parts.push(format!("{} keyword mentions", keyword_mentions));
+    }
+
+    let mut summary = parts.join(", ");
+    if !flags.is_empty() {
+        summary.push_str(&format!(" (flags: {})", flags.join(", ")));
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2025-01-10
+    const NOW: i64 = 1_736_467_200;
+
+    fn flags(name: &str, age_days: i64, karma: i64, active: bool) -> Vec<String> {
+        let about = AboutData {
+            name: name.to_string(),
+            created_utc: (NOW - age_days * 86_400) as f64,
+            link_karma: karma,
+            ..AboutData::default()
+        };
+        let activity = if active {
+            vec![ActivityChild {
+                kind: "t1".to_string(),
+                data: ActivityData {
+                    subreddit: "smallbusiness".to_string(),
+                    body: "We use a spreadsheet".to_string(),
+                    ..ActivityData::default()
+                },
+            }]
+        } else {
+            Vec::new()
+        };
+        build_profile(&ApiKeys::default(), name, about, activity, NOW).flags
+    }
+
+    #[test]
+    fn bot_must_be_its_own_word() {
+        for name in ["Talbot", "abbot", "robotics_fan", "ROBOT"] {
+            assert!(!is_bot_name(name), "{}", name);
+        }
+        for name in ["AutoModerator", "stock_bot", "bot-3000", "RemindMeBot", "Reminder2Bot"] {
+            assert!(is_bot_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn profiles_are_flagged_for_each_reason() {
+        assert!(flags("Talbot", 2_000, 5_000, true).is_empty());
+        assert_eq!(flags("RemindMeBot", 2_000, 5_000, true), vec!["bot"]);
+        assert_eq!(
+            flags("throwaway_4821", 3, 1, false),
+            vec!["throwaway", "new_account", "low_karma", "no_history"]
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::database::adding::PostDataWrapper;
+use crate::models::filters::FilterCounts;
+use crate::models::intent::KeywordMatcher;
+use crate::settings::api_keys::ApiKeys;
+
+// Tags posts and comments with the brands (ours and competitors) they mention
+pub struct BrandTracker {
+    brands: KeywordMatcher,
+}
+
+impl BrandTracker {
+    pub fn new(api_keys: &ApiKeys) -> Self {
+        BrandTracker {
+            brands: KeywordMatcher::new(&api_keys.all_brand_keywords()),
+        }
+    }
+
+    /// Comma separated list of mentioned brands, same format as `sort_type`.
+    pub fn tag(&self, title: &str, body: Option<&str>) -> String {
+        self.brands.matching(title, body).join(",")
+    }
+}
+
+// Splits a `brand_mentions` column back into brand names
+pub fn split_tags(tags: &str) -> impl Iterator<Item = &str> {
+    tags.split(',').map(str::trim).filter(|tag| !tag.is_empty())
+}
+
+// Result of a brand monitoring run
+#[derive(Serialize, Debug)]
+pub struct BrandMonitorReport {
+    pub posts: Vec<PostDataWrapper>,
+    // Posts found per brand in this run
+    pub mentions: HashMap<String, usize>,
+    pub filtered: FilterCounts,
+}
+
+// Search query for one brand keyword; quoted so Reddit matches the exact phrase
+pub fn brand_query(keyword: &str) -> String {
+    format!("\"{}\"", keyword.trim().trim_end_matches('*'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn posts_are_tagged_with_ours_and_competitors() {
+        let api_keys = ApiKeys {
+            branded_keywords: vec!["Acme".to_string()],
+            competitor_keywords: vec!["Widgetly".to_string(), "Stockr".to_string()],
+            ..ApiKeys::default()
+        };
+        let tracker = BrandTracker::new(&api_keys);
+
+        assert_eq!(
+            tracker.tag("Acme or Widgetly?", Some("Stockr was too pricey")),
+            "Acme,Widgetly,Stockr"
+        );
+        assert_eq!(tracker.tag("acme inventory", None), "Acme");
+        assert_eq!(tracker.tag("Spreadsheets forever", None), "");
+    }
+
+    #[test]
+    fn tags_split_back_into_brands() {
+        let tags: Vec<&str> = split_tags(" Acme, ,Widgetly,").collect();
+        assert_eq!(tags, vec!["Acme", "Widgetly"]);
+        assert_eq!(split_tags("").count(), 0);
+    }
+
+    #[test]
+    fn brand_queries_are_exact_phrases() {
+        assert_eq!(brand_query(" Acme Inventory "), "\"Acme Inventory\"");
+        assert_eq!(brand_query("Stock*"), "\"Stock\"");
+    }
+}
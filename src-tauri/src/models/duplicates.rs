@@ -0,0 +1,357 @@
+// Groups posts that are the same question: crossposts (via crosspost_parent)
+// and near-duplicates posted to several subreddits. Near-duplicates are found
+// by comparing the sets of meaningful words in the normalized title and body;
+// two posts are linked when the Jaccard similarity reaches
+// `duplicate_similarity`. Links are transitive, so A~B and B~C is one cluster.
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::database::adding::PostDataWrapper;
+
+// Titles with fewer words than this are too generic to match on their own
+const MIN_TITLE_WORDS: usize = 3;
+// Only the start of long bodies is compared
+const MAX_BODY_CHARS: usize = 1000;
+
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "any", "anyone", "are", "as", "at", "be", "but", "by", "can", "do", "does",
+    "for", "from", "how", "i", "if", "in", "is", "it", "just", "me", "my", "not", "of", "on",
+    "or", "our", "so", "that", "the", "there", "this", "to", "we", "what", "with", "you", "your",
+];
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateCluster {
+    // Fullname of the oldest post in the cluster, also stored as each post's cluster_id
+    pub id: String,
+    pub subreddits: Vec<String>,
+    // Whether any of the links came from crosspost_parent
+    pub crosspost: bool,
+    // Oldest first
+    pub posts: Vec<PostDataWrapper>,
+}
+
+struct Fingerprint {
+    title: HashSet<String>,
+    full: HashSet<String>,
+}
+
+impl Fingerprint {
+    fn new(post: &PostDataWrapper) -> Self {
+        let title = words(&post.title);
+        let mut full = title.clone();
+        if let Some(body) = &post.selftext {
+            let body: String = body.chars().take(MAX_BODY_CHARS).collect();
+            full.extend(words(&body));
+        }
+        Fingerprint { title, full }
+    }
+
+    fn similarity(&self, other: &Fingerprint) -> f64 {
+        let full = jaccard(&self.full, &other.full);
+        if self.title.len() >= MIN_TITLE_WORDS && other.title.len() >= MIN_TITLE_WORDS {
+            full.max(jaccard(&self.title, &other.title))
+        } else {
+            full
+        }
+    }
+}
+
+/// Groups `posts` into clusters of two or more; posts without duplicates are left out.
+pub fn find_clusters(posts: Vec<PostDataWrapper>, threshold: f64) -> Vec<DuplicateCluster> {
+    let (mut groups, crosspost_members) =
+        link(&posts.iter().collect::<Vec<_>>(), posts.len(), threshold);
+
+    let crosspost_groups: HashSet<usize> = crosspost_members
+        .into_iter()
+        .map(|i| groups.find(i))
+        .collect();
+    let mut members: HashMap<usize, Vec<PostDataWrapper>> = HashMap::new();
+    for (i, post) in posts.into_iter().enumerate() {
+        members.entry(groups.find(i)).or_default().push(post);
+    }
+
+    let mut clusters: Vec<DuplicateCluster> = members
+        .into_iter()
+        .filter(|(_, posts)| posts.len() > 1)
+        .map(|(root, mut posts)| {
+            posts.sort_by_key(|post| post.timestamp);
+            let mut subreddits: Vec<String> = Vec::new();
+            for post in &posts {
+                if !subreddits.iter().any(|s| s.eq_ignore_ascii_case(&post.subreddit)) {
+                    subreddits.push(post.subreddit.clone());
+                }
+            }
+            DuplicateCluster {
+                id: posts[0].name.clone(),
+                subreddits,
+                crosspost: crosspost_groups.contains(&root),
+                posts,
+            }
+        })
+        .collect();
+
+    clusters.sort_by(|a, b| b.posts.len().cmp(&a.posts.len()).then_with(|| a.id.cmp(&b.id)));
+    clusters
+}
+
+/// Cluster id for every post in `new` that has a duplicate among `new` or `saved`, and for
+/// the saved posts it duplicates, by post id. Saved posts are only compared with new ones;
+/// a cluster keeps the stored id of its saved posts, or else takes the oldest post's fullname.
+pub fn cluster_ids(
+    new: &[&PostDataWrapper],
+    saved: &[&PostDataWrapper],
+    threshold: f64,
+) -> HashMap<i64, String> {
+    let posts: Vec<&PostDataWrapper> = new.iter().chain(saved).copied().collect();
+    let (mut groups, _) = link(&posts, new.len(), threshold);
+
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..posts.len() {
+        members.entry(groups.find(i)).or_default().push(i);
+    }
+
+    let mut ids = HashMap::new();
+    for members in members.into_values() {
+        if members.len() < 2 || !members.iter().any(|&i| i < new.len()) {
+            continue;
+        }
+        let cluster_id = members
+            .iter()
+            .map(|&i| posts[i])
+            .filter(|post| !post.cluster_id.is_empty())
+            .min_by_key(|post| post.timestamp)
+            .map(|post| post.cluster_id.clone())
+            .unwrap_or_else(|| {
+                let oldest = members.iter().map(|&i| posts[i]).min_by_key(|post| post.timestamp);
+                oldest.unwrap().name.clone()
+            });
+        for &i in &members {
+            ids.insert(posts[i].id, cluster_id.clone());
+        }
+    }
+    ids
+}
+
+// Links crossposts and near-duplicates; also returns the posts linked as crossposts.
+// Only pairs with at least one of the first `compared` posts are checked for similarity.
+fn link(
+    posts: &[&PostDataWrapper],
+    compared: usize,
+    threshold: f64,
+) -> (UnionFind, HashSet<usize>) {
+    let mut groups = UnionFind::new(posts.len());
+    let mut crosspost_members = HashSet::new();
+
+    // Crossposts join their parent, or each other when the parent isn't in the set
+    let mut by_name: HashMap<&str, usize> = HashMap::new();
+    for (i, post) in posts.iter().enumerate() {
+        by_name.insert(post.name.as_str(), i);
+    }
+    let mut by_parent: HashMap<&str, usize> = HashMap::new();
+    for (i, post) in posts.iter().enumerate() {
+        let Some(parent) = post.crosspost_parent.as_deref().filter(|p| !p.is_empty()) else {
+            continue;
+        };
+        if let Some(&j) = by_name.get(parent) {
+            groups.union(i, j);
+            crosspost_members.insert(i);
+        }
+        if let Some(&j) = by_parent.get(parent) {
+            groups.union(i, j);
+            crosspost_members.insert(i);
+        } else {
+            by_parent.insert(parent, i);
+        }
+    }
+
+    let fingerprints: Vec<Fingerprint> = posts.iter().map(|post| Fingerprint::new(post)).collect();
+    for i in 0..compared.min(posts.len()) {
+        for j in (i + 1)..posts.len() {
+            if groups.find(i) != groups.find(j)
+                && fingerprints[i].similarity(&fingerprints[j]) >= threshold
+            {
+                groups.union(i, j);
+            }
+        }
+    }
+
+    (groups, crosspost_members)
+}
+
+// Lowercased words without punctuation or stopwords
+fn words(text: &str) -> HashSet<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.len() > 1 && !STOPWORDS.contains(word))
+        .map(str::to_string)
+        .collect()
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let shared = a.intersection(b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(size: usize) -> Self {
+        UnionFind {
+            parent: (0..size).collect(),
+        }
+    }
+
+    fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        // Path compression
+        let mut node = i;
+        while self.parent[node] != root {
+            let next = self.parent[node];
+            self.parent[node] = root;
+            node = next;
+        }
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[b] = a;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(name: &str, subreddit: &str, title: &str, body: &str, timestamp: i64) -> PostDataWrapper {
+        let id = i64::from_str_radix(name.trim_start_matches("t3_"), 36).unwrap();
+        PostDataWrapper {
+            subreddit: subreddit.to_string(),
+            selftext: Some(body.to_string()),
+            timestamp,
+            ..PostDataWrapper::sample(id, title)
+        }
+    }
+
+    fn names(cluster: &DuplicateCluster) -> Vec<&str> {
+        cluster.posts.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn reworded_cross_posts_are_grouped() {
+        let clusters = find_clusters(
+            vec![
+                post("t3_b", "shopify", "Best inventory software for a small shop?", "", 200),
+                post("t3_a", "smallbusiness", "Best inventory software for small shop", "", 100),
+                post("t3_c", "smallbusiness", "How do you price handmade candles?", "", 150),
+            ],
+            0.6,
+        );
+
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].id, "t3_a");
+        assert_eq!(names(&clusters[0]), vec!["t3_a", "t3_b"]);
+        assert_eq!(clusters[0].subreddits, vec!["smallbusiness", "shopify"]);
+        assert!(!clusters[0].crosspost);
+    }
+
+    #[test]
+    fn identical_bodies_match_despite_different_titles() {
+        let body = "We sell on Shopify and Amazon and stock counts drift every week, what do people use to keep them in sync?";
+        let clusters = find_clusters(
+            vec![
+                post("t3_a", "shopify", "Stock sync help", body, 100),
+                post("t3_b", "ecommerce", "Keeping inventory in sync", body, 200),
+            ],
+            0.6,
+        );
+
+        assert_eq!(clusters.len(), 1);
+    }
+
+    #[test]
+    fn crossposts_join_their_parent_and_each_other() {
+        let mut child = post("t3_b", "shopify", "x-post", "", 200);
+        child.crosspost_parent = Some("t3_a".to_string());
+        let mut sibling = post("t3_d", "ecommerce", "look at this", "", 300);
+        sibling.crosspost_parent = Some("t3_zz".to_string());
+        let mut other_sibling = post("t3_e", "retail", "seen elsewhere", "", 400);
+        other_sibling.crosspost_parent = Some("t3_zz".to_string());
+
+        let clusters = find_clusters(
+            vec![
+                post("t3_a", "smallbusiness", "Original question", "", 100),
+                child,
+                sibling,
+                other_sibling,
+            ],
+            0.6,
+        );
+
+        assert_eq!(clusters.len(), 2);
+        assert!(clusters.iter().all(|c| c.crosspost));
+        let mut grouped: Vec<Vec<&str>> = clusters.iter().map(names).collect();
+        grouped.sort();
+        assert_eq!(grouped, vec![vec!["t3_a", "t3_b"], vec!["t3_d", "t3_e"]]);
+    }
+
+    #[test]
+    fn short_generic_titles_do_not_match_alone() {
+        let clusters = find_clusters(
+            vec![
+                post("t3_a", "smallbusiness", "Help?", "My landlord raised the rent twice this year", 100),
+                post("t3_b", "shopify", "Help?", "Checkout button disappeared after the theme update", 200),
+            ],
+            0.6,
+        );
+
+        assert!(clusters.is_empty());
+    }
+
+    #[test]
+    fn new_results_reuse_the_cluster_of_saved_posts() {
+        let new = post("t3_c", "ecommerce", "Best inventory software for a small shop?", "", 300);
+        let unrelated = post("t3_d", "retail", "How do you price handmade candles?", "", 400);
+        let saved = PostDataWrapper {
+            cluster_id: "t3_9".to_string(),
+            ..post("t3_b", "shopify", "Best inventory software for small shop", "", 200)
+        };
+        // Duplicates each other but no new result, so stays as stored
+        let saved_pair = [
+            post("t3_e", "smallbusiness", "Quarterly tax payments for an LLC", "", 50),
+            post("t3_f", "tax", "Quarterly tax payments for an LLC", "", 60),
+        ];
+
+        let ids = cluster_ids(
+            &[&new, &unrelated],
+            &[&saved, &saved_pair[0], &saved_pair[1]],
+            0.6,
+        );
+
+        assert_eq!(ids.len(), 2);
+        assert_eq!(ids[&new.id], "t3_9");
+        assert_eq!(ids[&saved.id], "t3_9");
+    }
+
+    #[test]
+    fn new_clusters_are_named_after_their_oldest_post() {
+        let new = post("t3_c", "ecommerce", "Best inventory software for a small shop?", "", 300);
+        let saved = post("t3_b", "shopify", "Best inventory software for small shop", "", 200);
+
+        let ids = cluster_ids(&[&new], &[&saved], 0.6);
+
+        assert_eq!(ids[&new.id], "t3_b");
+        assert_eq!(ids[&saved.id], "t3_b");
+    }
+}
@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+
+use crate::database::adding::PostDataWrapper;
+
+// Content filters applied to search results before they are stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContentFilters {
+    pub min_score: i64,
+    pub min_comments: i64,
+    // 0 disables the age limit
+    pub max_age_days: i64,
+    pub exclude_authors: Vec<String>,
+    pub exclude_subreddits: Vec<String>,
+    pub exclude_deleted: bool,
+    pub exclude_nsfw: bool,
+    pub exclude_locked: bool,
+    pub exclude_stickied: bool,
+    pub negative_keywords: Vec<String>,
+    // Keep only posts with one of these sentiments; empty keeps all
+    pub sentiments: Vec<String>,
+}
+
+impl Default for ContentFilters {
+    fn default() -> Self {
+        ContentFilters {
+            min_score: 0,
+            min_comments: 0,
+            max_age_days: 0,
+            exclude_authors: vec!["AutoModerator".to_string()],
+            exclude_subreddits: vec![],
+            exclude_deleted: true,
+            exclude_nsfw: true,
+            exclude_locked: false,
+            exclude_stickied: false,
+            negative_keywords: vec![],
+            sentiments: vec![],
+        }
+    }
+}
+
+// How many posts each filter removed; a post is only counted under the first filter it fails
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FilterCounts {
+    pub total: usize,
+    pub deleted: usize,
+    pub nsfw: usize,
+    pub locked: usize,
+    pub stickied: usize,
+    pub low_score: usize,
+    pub low_comments: usize,
+    pub too_old: usize,
+    pub excluded_author: usize,
+    pub excluded_subreddit: usize,
+    pub negative_keyword: usize,
+    pub sentiment: usize,
+}
+
+enum FilterReason {
+    Deleted,
+    Nsfw,
+    Locked,
+    Stickied,
+    LowScore,
+    LowComments,
+    TooOld,
+    ExcludedAuthor,
+    ExcludedSubreddit,
+    NegativeKeyword,
+    Sentiment,
+}
+
+impl FilterCounts {
+    fn record(&mut self, reason: FilterReason) {
+        self.total += 1;
+        match reason {
+            FilterReason::Deleted => self.deleted += 1,
+            FilterReason::Nsfw => self.nsfw += 1,
+            FilterReason::Locked => self.locked += 1,
+            FilterReason::Stickied => self.stickied += 1,
+            FilterReason::LowScore => self.low_score += 1,
+            FilterReason::LowComments => self.low_comments += 1,
+            FilterReason::TooOld => self.too_old += 1,
+            FilterReason::ExcludedAuthor => self.excluded_author += 1,
+            FilterReason::ExcludedSubreddit => self.excluded_subreddit += 1,
+            FilterReason::NegativeKeyword => self.negative_keyword += 1,
+            FilterReason::Sentiment => self.sentiment += 1,
+        }
+    }
+}
+
+impl ContentFilters {
+    /// Splits `posts` into the ones that pass and a tally of what was dropped.
+    pub fn apply(&self, posts: Vec<PostDataWrapper>, now: i64) -> (Vec<PostDataWrapper>, FilterCounts) {
+        let mut counts = FilterCounts::default();
+        let mut kept = Vec::with_capacity(posts.len());
+
+        for post in posts {
+            match self.check(&post, now) {
+                Some(reason) => counts.record(reason),
+                None => kept.push(post),
+            }
+        }
+
+        (kept, counts)
+    }
+
+    fn check(&self, post: &PostDataWrapper, now: i64) -> Option<FilterReason> {
+        if self.exclude_deleted && is_deleted(post) {
+            return Some(FilterReason::Deleted);
+        }
+        if self.exclude_nsfw && post.over_18 {
+            return Some(FilterReason::Nsfw);
+        }
+        if self.exclude_locked && post.locked {
+            return Some(FilterReason::Locked);
+        }
+        if self.exclude_stickied && post.stickied {
+            return Some(FilterReason::Stickied);
+        }
+        if post.score < self.min_score {
+            return Some(FilterReason::LowScore);
+        }
+        if post.num_comments < self.min_comments {
+            return Some(FilterReason::LowComments);
+        }
+        if self.max_age_days > 0 && now - post.timestamp > self.max_age_days * 86_400 {
+            return Some(FilterReason::TooOld);
+        }
+        if self
+            .exclude_authors
+            .iter()
+            .any(|author| author.trim_start_matches("u/").eq_ignore_ascii_case(&post.author))
+        {
+            return Some(FilterReason::ExcludedAuthor);
+        }
+        if self
+            .exclude_subreddits
+            .iter()
+            .any(|sub| sub.trim_start_matches("r/").eq_ignore_ascii_case(&post.subreddit))
+        {
+            return Some(FilterReason::ExcludedSubreddit);
+        }
+        if !self.negative_keywords.is_empty() {
+            let text = format!("{} {}", post.title, post.selftext.as_deref().unwrap_or("")).to_lowercase();
+            if self
+                .negative_keywords
+                .iter()
+                .any(|keyword| !keyword.trim().is_empty() && text.contains(&keyword.trim().to_lowercase()))
+            {
+                return Some(FilterReason::NegativeKeyword);
+            }
+        }
+        if !self.sentiments.is_empty()
+            && !self
+                .sentiments
+                .iter()
+                .any(|sentiment| sentiment.trim().eq_ignore_ascii_case(&post.sentiment))
+        {
+            return Some(FilterReason::Sentiment);
+        }
+
+        None
+    }
+}
+
+// Reddit keeps removed posts in listings with placeholder text
+fn is_deleted(post: &PostDataWrapper) -> bool {
+    post.author == "[deleted]"
+        || post.title == "[deleted by user]"
+        || matches!(post.selftext.as_deref(), Some("[deleted]") | Some("[removed]"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn post(id: i64) -> PostDataWrapper {
+        PostDataWrapper {
+            timestamp: NOW - 3_600,
+            score: 10,
+            num_comments: 5,
+            ..PostDataWrapper::sample(id, "Looking for an inventory app")
+        }
+    }
+
+    fn strict() -> ContentFilters {
+        ContentFilters {
+            min_score: 2,
+            min_comments: 1,
+            max_age_days: 7,
+            exclude_subreddits: vec!["r/Giveaways".to_string()],
+            exclude_locked: true,
+            exclude_stickied: true,
+            negative_keywords: vec!["Hiring".to_string()],
+            sentiments: vec!["negative".to_string(), "neutral".to_string()],
+            ..ContentFilters::default()
+        }
+    }
+
+    #[test]
+    fn each_filter_drops_its_own_posts() {
+        let cases: Vec<(&str, PostDataWrapper)> = vec![
+            ("deleted", PostDataWrapper { author: "[deleted]".to_string(), ..post(1) }),
+            ("deleted", PostDataWrapper { selftext: Some("[removed]".to_string()), ..post(2) }),
+            ("deleted", PostDataWrapper { title: "[deleted by user]".to_string(), ..post(3) }),
+            ("nsfw", PostDataWrapper { over_18: true, ..post(4) }),
+            ("locked", PostDataWrapper { locked: true, ..post(5) }),
+            ("stickied", PostDataWrapper { stickied: true, ..post(6) }),
+            ("low_score", PostDataWrapper { score: 1, ..post(7) }),
+            ("low_comments", PostDataWrapper { num_comments: 0, ..post(8) }),
+            ("too_old", PostDataWrapper { timestamp: NOW - 8 * 86_400, ..post(9) }),
+            ("excluded_author", PostDataWrapper { author: "automoderator".to_string(), ..post(10) }),
+            ("excluded_subreddit", PostDataWrapper { subreddit: "giveaways".to_string(), ..post(11) }),
+            ("negative_keyword", PostDataWrapper { title: "We're hiring a dev".to_string(), ..post(12) }),
+            ("sentiment", PostDataWrapper { sentiment: "Positive".to_string(), ..post(13) }),
+        ];
+
+        for (reason, dropped) in cases {
+            let (kept, counts) = strict().apply(vec![dropped, post(99)], NOW);
+
+            assert_eq!(kept.len(), 1, "{}", reason);
+            assert_eq!(kept[0].id, 99, "{}", reason);
+            let counts = serde_json::to_value(&counts).unwrap();
+            assert_eq!(counts[reason], 1, "{}", reason);
+            assert_eq!(counts["total"], 1, "{}", reason);
+        }
+    }
+
+    #[test]
+    fn counts_add_up_to_the_total() {
+        let posts = vec![
+            post(1),
+            // Fails several filters but only counts under the first
+            PostDataWrapper { over_18: true, locked: true, score: 0, ..post(2) },
+            PostDataWrapper { author: "[deleted]".to_string(), ..post(3) },
+            PostDataWrapper { num_comments: 0, ..post(4) },
+            PostDataWrapper { sentiment: "positive".to_string(), ..post(5) },
+        ];
+
+        let (kept, counts) = strict().apply(posts, NOW);
+
+        assert_eq!(kept.len(), 1);
+        assert_eq!(counts.total, 4);
+        assert_eq!(counts.nsfw, 1);
+        assert_eq!(counts.locked + counts.low_score, 0);
+        let per_reason = counts.deleted
+            + counts.nsfw
+            + counts.locked
+            + counts.stickied
+            + counts.low_score
+            + counts.low_comments
+            + counts.too_old
+            + counts.excluded_author
+            + counts.excluded_subreddit
+            + counts.negative_keyword
+            + counts.sentiment;
+        assert_eq!(per_reason, counts.total);
+    }
+
+    #[test]
+    fn defaults_keep_locked_stickied_and_any_sentiment() {
+        let posts = vec![
+            PostDataWrapper { locked: true, stickied: true, ..post(1) },
+            PostDataWrapper { sentiment: "positive".to_string(), timestamp: 0, ..post(2) },
+        ];
+
+        let (kept, counts) = ContentFilters::default().apply(posts, NOW);
+
+        assert_eq!(kept.len(), 2);
+        assert_eq!(counts.total, 0);
+    }
+}
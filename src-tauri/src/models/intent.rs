@@ -0,0 +1,505 @@
+// Intent scoring for posts. Patterns come from the intent_* lists in settings.toml:
+//
+//   looking for        whole words/phrase, case insensitive ("vs" does not match "canvas")
+//   recommend*         trailing * also matches longer words ("recommendations")
+//   need ~3 tool       both phrases within 3 words of each other, in either order
+//   re:\bv\d+\b        regular expression, case insensitive
+//
+// Text matched by an intent_negative pattern can't count towards intent, so
+// "not looking for alternatives" does not score on "looking for".
+use regex::Regex;
+
+use crate::settings::api_keys::ApiKeys;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntentScore {
+    pub level: String,
+    // Sum of title/body weights of the patterns that matched
+    pub high: f64,
+    pub medium: f64,
+    pub high_hits: usize,
+    pub medium_hits: usize,
+    pub negated: usize,
+}
+
+#[derive(Debug)]
+enum Pattern {
+    Phrase(Vec<Term>),
+    Near(Vec<Term>, Vec<Term>, usize),
+    Regex(Regex),
+}
+
+#[derive(Debug)]
+struct Term {
+    text: String,
+    prefix: bool,
+}
+
+struct Token {
+    text: String,
+    start: usize,
+    end: usize,
+}
+
+// Title or body, tokenized once and shared by all patterns
+struct Field {
+    text: String,
+    tokens: Vec<Token>,
+    negated: Vec<(usize, usize)>,
+}
+
+pub struct IntentMatcher {
+    high: Vec<Pattern>,
+    medium: Vec<Pattern>,
+    negative: Vec<Pattern>,
+    title_weight: f64,
+    body_weight: f64,
+}
+
+impl IntentMatcher {
+    pub fn new(api_keys: &ApiKeys) -> Self {
+        IntentMatcher {
+            high: compile_all(&api_keys.intent_high),
+            medium: compile_all(&api_keys.intent_medium),
+            negative: compile_all(&api_keys.intent_negative),
+            title_weight: api_keys.intent_title_weight,
+            body_weight: api_keys.intent_body_weight,
+        }
+    }
+
+    /// High needs high-intent hits worth at least 1.0; any other positive hit is Medium.
+    pub fn score(&self, title: &str, body: Option<&str>) -> IntentScore {
+        let mut fields = [
+            (Field::new(title), self.title_weight),
+            (Field::new(body.unwrap_or("")), self.body_weight),
+        ];
+
+        let mut negated = 0;
+        for (field, _) in fields.iter_mut() {
+            let spans: Vec<(usize, usize)> = self
+                .negative
+                .iter()
+                .flat_map(|pattern| field.find(pattern))
+                .collect();
+            negated += spans.len();
+            field.negated = spans;
+        }
+
+        let (high, high_hits) = weigh(&self.high, &fields);
+        let (medium, medium_hits) = weigh(&self.medium, &fields);
+
+        let level = if high >= 1.0 {
+            "High"
+        } else if high_hits > 0 || medium_hits > 0 {
+            "Medium"
+        } else {
+            "Low"
+        };
+
+        IntentScore {
+            level: level.to_string(),
+            high,
+            medium,
+            high_hits,
+            medium_hits,
+            negated,
+        }
+    }
+}
+
+// Keyword lists (e.g. branded_keywords) matched with the same pattern syntax
+pub struct KeywordMatcher {
+    keywords: Vec<(String, Pattern)>,
+}
+
+impl KeywordMatcher {
+    /// Keywords repeated in the settings, ignoring case, are kept once.
+    pub fn new(keywords: &[String]) -> Self {
+        let mut unique: Vec<(String, Pattern)> = Vec::new();
+        for keyword in keywords {
+            let trimmed = keyword.trim();
+            if unique.iter().any(|(seen, _)| seen.eq_ignore_ascii_case(trimmed)) {
+                continue;
+            }
+            if let Some(pattern) = compile(keyword) {
+                unique.push((trimmed.to_string(), pattern));
+            }
+        }
+        KeywordMatcher { keywords: unique }
+    }
+
+    /// The configured keywords found in the title or body.
+    pub fn matching(&self, title: &str, body: Option<&str>) -> Vec<&str> {
+        let fields = [Field::new(title), Field::new(body.unwrap_or(""))];
+        self.keywords
+            .iter()
+            .filter(|(_, pattern)| fields.iter().any(|field| field.matches(pattern)))
+            .map(|(keyword, _)| keyword.as_str())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keywords.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keywords.is_empty()
+    }
+
+    /// Number of keywords found in the title or body.
+    pub fn count(&self, title: &str, body: Option<&str>) -> usize {
+        self.matching(title, body).len()
+    }
+}
+
+// Each pattern counts once, with the weight of the best field it matched in
+fn weigh(patterns: &[Pattern], fields: &[(Field, f64)]) -> (f64, usize) {
+    let mut total = 0.0;
+    let mut hits = 0;
+
+    for pattern in patterns {
+        let best = fields
+            .iter()
+            .filter(|(field, _)| field.matches(pattern))
+            .map(|(_, weight)| *weight)
+            .fold(None, |best: Option<f64>, weight| {
+                Some(best.map_or(weight, |b| b.max(weight)))
+            });
+
+        if let Some(weight) = best {
+            total += weight;
+            hits += 1;
+        }
+    }
+
+    (total, hits)
+}
+
+fn compile_all(patterns: &[String]) -> Vec<Pattern> {
+    patterns.iter().filter_map(|p| compile(p)).collect()
+}
+
+fn compile(pattern: &str) -> Option<Pattern> {
+    let pattern = pattern.trim();
+
+    if let Some(expr) = pattern.strip_prefix("re:") {
+        return match Regex::new(&format!("(?i){}", expr)) {
+            Ok(regex) => Some(Pattern::Regex(regex)),
+            Err(e) => {
+                eprintln!("Ignoring invalid intent regex '{}': {}", expr, e);
+                None
+            }
+        };
+    }
+
+    if let Some((left, rest)) = pattern.split_once(" ~") {
+        if let Some((distance, right)) = rest.split_once(' ') {
+            if let Ok(distance) = distance.parse::<usize>() {
+                let (left, right) = (terms(left), terms(right));
+                if !left.is_empty() && !right.is_empty() {
+                    return Some(Pattern::Near(left, right, distance));
+                }
+            }
+        }
+    }
+
+    let terms = terms(pattern);
+    if terms.is_empty() {
+        None
+    } else {
+        Some(Pattern::Phrase(terms))
+    }
+}
+
+// "acme.io" becomes two terms so it lines up with how the text is tokenized
+fn terms(phrase: &str) -> Vec<Term> {
+    phrase
+        .split_whitespace()
+        .flat_map(|word| {
+            let prefix = word.ends_with('*');
+            let tokens = tokenize(word.trim_end_matches('*'));
+            let last = tokens.len().saturating_sub(1);
+            tokens
+                .into_iter()
+                .enumerate()
+                .map(move |(i, token)| Term {
+                    text: token.text,
+                    prefix: prefix && i == last,
+                })
+        })
+        .collect()
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+
+    for (i, c) in text.char_indices() {
+        let word_char = c.is_alphanumeric() || c == '\'' || c == '\u{2019}';
+        match (word_char, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                tokens.push(Token::new(&text[s..i], s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        tokens.push(Token::new(&text[s..], s, text.len()));
+    }
+
+    tokens
+}
+
+impl Token {
+    fn new(text: &str, start: usize, end: usize) -> Self {
+        Token {
+            text: text.to_lowercase().replace('\u{2019}', "'"),
+            start,
+            end,
+        }
+    }
+}
+
+impl Field {
+    fn new(text: &str) -> Self {
+        Field {
+            text: text.to_string(),
+            tokens: tokenize(text),
+            negated: Vec::new(),
+        }
+    }
+
+    fn matches(&self, pattern: &Pattern) -> bool {
+        self.find(pattern)
+            .into_iter()
+            .any(|span| !self.is_negated(span))
+    }
+
+    fn is_negated(&self, (start, end): (usize, usize)) -> bool {
+        self.negated.iter().any(|&(s, e)| start <= e && s <= end)
+    }
+
+    // Token ranges (inclusive) where the pattern matches
+    fn find(&self, pattern: &Pattern) -> Vec<(usize, usize)> {
+        match pattern {
+            Pattern::Phrase(terms) => self.phrase_spans(terms),
+            Pattern::Near(left, right, distance) => {
+                let rights = self.phrase_spans(right);
+                self.phrase_spans(left)
+                    .into_iter()
+                    .flat_map(|l| {
+                        rights
+                            .iter()
+                            .filter(move |r| {
+                                let gap = if l.1 < r.0 {
+                                    r.0 - l.1 - 1
+                                } else if r.1 < l.0 {
+                                    l.0 - r.1 - 1
+                                } else {
+                                    0
+                                };
+                                gap <= *distance
+                            })
+                            .map(move |r| (l.0.min(r.0), l.1.max(r.1)))
+                    })
+                    .collect()
+            }
+            Pattern::Regex(regex) => regex
+                .find_iter(&self.text)
+                .filter_map(|m| {
+                    let covered: Vec<usize> = self
+                        .tokens
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, t)| t.start < m.end() && m.start() < t.end)
+                        .map(|(i, _)| i)
+                        .collect();
+                    Some((*covered.first()?, *covered.last()?))
+                })
+                .collect(),
+        }
+    }
+
+    fn phrase_spans(&self, terms: &[Term]) -> Vec<(usize, usize)> {
+        if terms.len() > self.tokens.len() {
+            return Vec::new();
+        }
+
+        (0..=self.tokens.len() - terms.len())
+            .filter(|&start| {
+                terms.iter().enumerate().all(|(offset, term)| {
+                    let token = &self.tokens[start + offset].text;
+                    if term.prefix {
+                        token.starts_with(&term.text)
+                    } else {
+                        *token == term.text
+                    }
+                })
+            })
+            .map(|start| (start, start + terms.len() - 1))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher() -> IntentMatcher {
+        IntentMatcher::new(&ApiKeys::default())
+    }
+
+    fn level(title: &str, body: &str) -> String {
+        matcher().score(title, Some(body)).level
+    }
+
+    #[test]
+    fn every_default_high_pattern_scores_high() {
+        let keys = ApiKeys::default();
+        for pattern in &keys.intent_high {
+            let title = format!("Quick {} here", pattern.trim_end_matches('*'));
+            assert_eq!(level(&title, ""), "High", "pattern {:?}", pattern);
+        }
+    }
+
+    #[test]
+    fn every_default_medium_pattern_scores_medium() {
+        let keys = ApiKeys::default();
+        for pattern in &keys.intent_medium {
+            let title = format!("Quick {} here", pattern.trim_end_matches('*'));
+            assert_eq!(level(&title, ""), "Medium", "pattern {:?}", pattern);
+        }
+    }
+
+    #[test]
+    fn plain_text_scores_low() {
+        assert_eq!(
+            level("Show off: my weekend garden", "Took a few photos."),
+            "Low"
+        );
+    }
+
+    #[test]
+    fn negative_patterns_cancel_the_overlapping_hit() {
+        assert_eq!(level("Not looking for alternatives", ""), "Low");
+        assert_eq!(level("I don't recommend this", ""), "Low");
+
+        let score = matcher().score(
+            "Not looking for alternatives, but can anyone recommend a CRM?",
+            None,
+        );
+        assert_eq!(score.level, "High");
+        assert_eq!(score.negated, 1);
+    }
+
+    #[test]
+    fn patterns_match_whole_words() {
+        // "vs" and "cost" are default high patterns
+        assert_eq!(level("Painting on canvas", "Bought it at Costco"), "Low");
+        assert_eq!(level("Notion vs Obsidian", ""), "High");
+    }
+
+    #[test]
+    fn wildcard_matches_word_continuations() {
+        assert_eq!(level("Recommendations for a budget laptop", ""), "High");
+        assert_eq!(level("Reviewing my setup", ""), "High");
+    }
+
+    #[test]
+    fn matching_ignores_case_and_curly_apostrophes() {
+        assert_eq!(level("LOOKING FOR a tool", ""), "High");
+        assert_eq!(level("I don\u{2019}t recommend it", ""), "Low");
+    }
+
+    #[test]
+    fn regex_patterns() {
+        let keys = ApiKeys {
+            intent_high: vec![r"re:\bbudget (of|is) \$?\d+".to_string()],
+            intent_medium: vec![],
+            ..ApiKeys::default()
+        };
+        let matcher = IntentMatcher::new(&keys);
+
+        assert_eq!(
+            matcher
+                .score("Budget is $500, what should I get?", None)
+                .level,
+            "High"
+        );
+        assert_eq!(matcher.score("On a budget", None).level, "Low");
+    }
+
+    #[test]
+    fn invalid_regex_is_ignored() {
+        let keys = ApiKeys {
+            intent_high: vec!["re:(unclosed".to_string(), "looking for".to_string()],
+            ..ApiKeys::default()
+        };
+
+        assert_eq!(
+            IntentMatcher::new(&keys)
+                .score("Looking for a tool", None)
+                .level,
+            "High"
+        );
+    }
+
+    #[test]
+    fn proximity_patterns() {
+        let keys = ApiKeys {
+            intent_high: vec!["need ~3 tool*".to_string()],
+            intent_medium: vec![],
+            ..ApiKeys::default()
+        };
+        let matcher = IntentMatcher::new(&keys);
+
+        assert_eq!(matcher.score("We need a simple tool", None).level, "High");
+        assert_eq!(matcher.score("Tools we really need", None).level, "High");
+        assert_eq!(
+            matcher
+                .score("We need to talk about the many great tools", None)
+                .level,
+            "Low"
+        );
+    }
+
+    #[test]
+    fn body_weight_below_one_needs_several_body_hits() {
+        let keys = ApiKeys {
+            intent_title_weight: 1.0,
+            intent_body_weight: 0.5,
+            ..ApiKeys::default()
+        };
+        let matcher = IntentMatcher::new(&keys);
+
+        assert_eq!(
+            matcher
+                .score("Inventory", Some("Looking for something"))
+                .level,
+            "Medium"
+        );
+        assert_eq!(
+            matcher
+                .score(
+                    "Inventory",
+                    Some("Looking for something, what's the pricing?")
+                )
+                .level,
+            "High"
+        );
+        assert_eq!(matcher.score("Looking for something", None).level, "High");
+    }
+
+    #[test]
+    fn each_pattern_counts_once_with_its_best_weight() {
+        let score = matcher().score(
+            "Looking for a CRM",
+            Some("Still looking for one, looking for real"),
+        );
+
+        assert_eq!(score.high_hits, 1);
+        assert_eq!(score.high, 1.0);
+    }
+}
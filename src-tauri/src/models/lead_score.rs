@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::intent::{IntentMatcher, IntentScore, KeywordMatcher};
+use crate::settings::api_keys::ApiKeys;
+
+// Post score / comment count at which those signals max out (log scale below that)
+const SCORE_SATURATION: f64 = 500.0;
+const COMMENTS_SATURATION: f64 = 100.0;
+
+// Weights of the lead score signals, from [api_keys.lead_score] in settings.toml.
+// Each signal is scaled to 0..1, so the score is 0..100 before the subreddit weight.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LeadScoreWeights {
+    pub intent: f64,
+    pub branded: f64,
+    pub recency: f64,
+    pub post_score: f64,
+    pub comments: f64,
+    // Posts older than this get no recency points
+    pub recency_hours: f64,
+    // Multiplier per subreddit (name without r/), 1.0 when not listed
+    pub subreddit_weights: HashMap<String, f64>,
+}
+
+impl LeadScoreWeights {
+    /// Multiplies in the weights of saved subreddits; settings.toml entries for
+    /// the same subreddit are kept and combined rather than replaced.
+    pub fn apply_saved_weights(&mut self, saved: Vec<(String, f64)>) {
+        for (name, weight) in saved {
+            let existing = self
+                .subreddit_weights
+                .iter_mut()
+                .find(|(key, _)| key.trim_start_matches("r/").eq_ignore_ascii_case(&name));
+            match existing {
+                Some((_, current)) => *current *= weight,
+                None => {
+                    self.subreddit_weights.insert(name, weight);
+                }
+            }
+        }
+    }
+}
+
+impl Default for LeadScoreWeights {
+    fn default() -> Self {
+        LeadScoreWeights {
+            intent: 40.0,
+            branded: 20.0,
+            recency: 15.0,
+            post_score: 10.0,
+            comments: 15.0,
+            recency_hours: 72.0,
+            subreddit_weights: HashMap::new(),
+        }
+    }
+}
+
+// Everything needed to score posts, built once per search
+pub struct LeadScorer {
+    intent: IntentMatcher,
+    branded: KeywordMatcher,
+    weights: LeadScoreWeights,
+    now: i64,
+}
+
+pub struct PostSignals<'a> {
+    pub title: &'a str,
+    pub body: Option<&'a str>,
+    pub subreddit: &'a str,
+    pub created_utc: i64,
+    pub score: i64,
+    pub num_comments: i64,
+}
+
+impl LeadScorer {
+    pub fn new(api_keys: &ApiKeys, now: i64) -> Self {
+        LeadScorer {
+            intent: IntentMatcher::new(api_keys),
+            branded: KeywordMatcher::new(&api_keys.branded_keywords),
+            weights: api_keys.lead_score.clone(),
+            now,
+        }
+    }
+
+    /// Returns the intent of the post and its 0..100 lead score.
+    pub fn score(&self, post: &PostSignals) -> (IntentScore, i64) {
+        let intent = self.intent.score(post.title, post.body);
+        let branded_hits = self.branded.count(post.title, post.body);
+        let w = &self.weights;
+
+        // A high-intent hit is worth the full intent points, medium ones half
+        let intent_signal = (intent.high + intent.medium * 0.5).min(1.0);
+        // 1 mention = 0.5, 2 = 0.75, ...
+        let branded_signal = 1.0 - 0.5_f64.powi(branded_hits as i32);
+        let age_hours = (self.now - post.created_utc).max(0) as f64 / 3600.0;
+        let recency_signal = if w.recency_hours > 0.0 {
+            (1.0 - age_hours / w.recency_hours).max(0.0)
+        } else {
+            0.0
+        };
+        let score_signal = log_scale(post.score, SCORE_SATURATION);
+        let comments_signal = log_scale(post.num_comments, COMMENTS_SATURATION);
+
+        let total_weight = w.intent + w.branded + w.recency + w.post_score + w.comments;
+        if total_weight <= 0.0 {
+            return (intent, 0);
+        }
+
+        let weighted = w.intent * intent_signal
+            + w.branded * branded_signal
+            + w.recency * recency_signal
+            + w.post_score * score_signal
+            + w.comments * comments_signal;
+
+        let lead_score = 100.0 * weighted / total_weight * self.subreddit_weight(post.subreddit);
+        (intent, lead_score.round().clamp(0.0, 100.0) as i64)
+    }
+
+    fn subreddit_weight(&self, subreddit: &str) -> f64 {
+        self.weights
+            .subreddit_weights
+            .iter()
+            .find(|(name, _)| name.trim_start_matches("r/").eq_ignore_ascii_case(subreddit))
+            .map(|(_, weight)| *weight)
+            .unwrap_or(1.0)
+    }
+}
+
+fn log_scale(value: i64, saturation: f64) -> f64 {
+    let value = value.max(0) as f64;
+    ((1.0 + value).ln() / (1.0 + saturation).ln()).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    // Only the named signal carries weight
+    fn scorer(signal: &str, subreddit_weights: &[(&str, f64)]) -> LeadScorer {
+        let weight = |name: &str| if name == signal { 1.0 } else { 0.0 };
+        let api_keys = ApiKeys {
+            intent_high: vec!["looking for".to_string()],
+            intent_medium: vec!["any advice".to_string()],
+            intent_negative: vec![],
+            branded_keywords: vec!["Acme".to_string(), "Widgetly".to_string()],
+            lead_score: LeadScoreWeights {
+                intent: weight("intent"),
+                branded: weight("branded"),
+                recency: weight("recency"),
+                post_score: weight("post_score"),
+                comments: weight("comments"),
+                recency_hours: 72.0,
+                subreddit_weights: subreddit_weights
+                    .iter()
+                    .map(|(name, weight)| (name.to_string(), *weight))
+                    .collect(),
+            },
+            ..ApiKeys::default()
+        };
+        LeadScorer::new(&api_keys, NOW)
+    }
+
+    fn post(title: &str) -> PostSignals<'_> {
+        PostSignals {
+            title,
+            body: None,
+            subreddit: "smallbusiness",
+            created_utc: NOW,
+            score: 0,
+            num_comments: 0,
+        }
+    }
+
+    #[test]
+    fn intent_counts_medium_hits_half() {
+        let scorer = scorer("intent", &[]);
+
+        assert_eq!(scorer.score(&post("Looking for an inventory app")).1, 100);
+        assert_eq!(scorer.score(&post("Any advice on inventory?")).1, 50);
+        assert_eq!(scorer.score(&post("My shop turned five")).1, 0);
+    }
+
+    #[test]
+    fn each_brand_found_adds_less_than_the_last() {
+        let scorer = scorer("branded", &[]);
+
+        assert_eq!(scorer.score(&post("Is Acme any good?")).1, 50);
+        assert_eq!(scorer.score(&post("Acme vs Acme Pro")).1, 50);
+        assert_eq!(scorer.score(&post("Acme vs Widgetly")).1, 75);
+        assert_eq!(scorer.score(&post("Is this any good?")).1, 0);
+    }
+
+    #[test]
+    fn recency_fades_over_the_window() {
+        let scorer = scorer("recency", &[]);
+        let at = |hours: i64| PostSignals {
+            created_utc: NOW - hours * 3600,
+            ..post("Inventory app?")
+        };
+
+        assert_eq!(scorer.score(&at(0)).1, 100);
+        assert_eq!(scorer.score(&at(36)).1, 50);
+        assert_eq!(scorer.score(&at(100)).1, 0);
+    }
+
+    #[test]
+    fn votes_and_comments_saturate_on_a_log_scale() {
+        let votes = scorer("post_score", &[]);
+        let comments = scorer("comments", &[]);
+        let with = |score: i64, num_comments: i64| PostSignals {
+            score,
+            num_comments,
+            ..post("Inventory app?")
+        };
+
+        assert_eq!(votes.score(&with(500, 0)).1, 100);
+        assert_eq!(votes.score(&with(5_000, 0)).1, 100);
+        assert_eq!(votes.score(&with(-20, 0)).1, 0);
+        assert_eq!(votes.score(&with(22, 0)).1, 50);
+        assert_eq!(comments.score(&with(0, 100)).1, 100);
+        assert_eq!(comments.score(&with(0, 9)).1, 50);
+    }
+
+    #[test]
+    fn no_weights_scores_zero_but_keeps_the_intent() {
+        let scorer = scorer("none", &[]);
+
+        let (intent, score) = scorer.score(&post("Looking for an inventory app"));
+
+        assert_eq!(score, 0);
+        assert_eq!(intent.level, "High");
+    }
+
+    #[test]
+    fn subreddit_weights_match_any_case_and_clamp_at_100() {
+        let halved = scorer("intent", &[("r/SmallBusiness", 0.5)]);
+        let boosted = scorer("intent", &[("SMALLBUSINESS", 3.0)]);
+        let title = "Looking for an inventory app";
+
+        assert_eq!(halved.score(&post(title)).1, 50);
+        assert_eq!(boosted.score(&post(title)).1, 100);
+        let elsewhere = PostSignals {
+            subreddit: "shopify",
+            ..post(title)
+        };
+        assert_eq!(halved.score(&elsewhere).1, 100);
+        assert_eq!(boosted.score(&post("Any advice on inventory?")).1, 100);
+    }
+}
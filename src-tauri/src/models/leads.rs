@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::database::adding::{CommentDataWrapper, PostDataWrapper};
+use crate::models::authors::AuthorProfile;
+use crate::models::intent::KeywordMatcher;
+use crate::settings::api_keys::ApiKeys;
+
+// Comments kept per lead as evidence
+const MAX_MATCHING_COMMENTS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchMode {
+    // Every lead keyword has to appear somewhere in the post or its comments
+    All,
+    // At least one keyword has to appear
+    Any,
+}
+
+impl MatchMode {
+    /// `match_keyword = "AND"` means all keywords, anything else means any.
+    pub fn from_setting(value: &str) -> Self {
+        if value.trim().eq_ignore_ascii_case("and") {
+            MatchMode::All
+        } else {
+            MatchMode::Any
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocalLead {
+    pub post: PostDataWrapper,
+    pub matched_keywords: Vec<String>,
+    // Stored comments that mention at least one keyword, highest score first
+    pub matching_comments: Vec<CommentDataWrapper>,
+    // Cached Reddit profile of the post author, when one has been fetched
+    pub author: Option<AuthorProfile>,
+}
+
+/// Matches `lead_keywords` against post titles, selftext and stored comments, no AI involved.
+pub fn find_local_leads(
+    api_keys: &ApiKeys,
+    posts: Vec<PostDataWrapper>,
+    comments: Vec<CommentDataWrapper>,
+) -> Vec<LocalLead> {
+    let keywords = KeywordMatcher::new(&api_keys.lead_keywords);
+    let mode = MatchMode::from_setting(&api_keys.match_keyword);
+    let wanted = keywords.len();
+    if wanted == 0 {
+        return Vec::new();
+    }
+
+    // Comments are stored against the base36 post id
+    let mut comments_by_post: HashMap<String, Vec<CommentDataWrapper>> = HashMap::new();
+    for comment in comments {
+        comments_by_post
+            .entry(comment.post_id.clone())
+            .or_default()
+            .push(comment);
+    }
+
+    let mut leads: Vec<LocalLead> = posts
+        .into_iter()
+        .filter_map(|post| {
+            let post_id = post.name.trim_start_matches("t3_").to_string();
+            let mut matched: Vec<String> = keywords
+                .matching(&post.title, post.selftext.as_deref())
+                .into_iter()
+                .map(str::to_string)
+                .collect();
+
+            let mut matching_comments = Vec::new();
+            for comment in comments_by_post.remove(&post_id).unwrap_or_default() {
+                let found = keywords.matching(&comment.body, None);
+                if found.is_empty() {
+                    continue;
+                }
+                for keyword in found {
+                    if !matched.iter().any(|m| m == keyword) {
+                        matched.push(keyword.to_string());
+                    }
+                }
+                matching_comments.push(comment);
+            }
+
+            let is_lead = match mode {
+                MatchMode::All => matched.len() == wanted,
+                MatchMode::Any => !matched.is_empty(),
+            };
+            if !is_lead {
+                return None;
+            }
+
+            matching_comments.sort_by_key(|comment| std::cmp::Reverse(comment.score));
+            matching_comments.truncate(MAX_MATCHING_COMMENTS);
+
+            Some(LocalLead {
+                post,
+                matched_keywords: matched,
+                matching_comments,
+                author: None,
+            })
+        })
+        .collect();
+
+    leads.sort_by(|a, b| {
+        b.post
+            .relevance_score
+            .cmp(&a.post.relevance_score)
+            .then(b.matched_keywords.len().cmp(&a.matched_keywords.len()))
+    });
+
+    leads
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubredditLeadCount {
+    pub subreddit: String,
+    pub leads: usize,
+}
+
+/// Number of leads per subreddit, most first.
+pub fn count_by_subreddit(leads: &[LocalLead]) -> Vec<SubredditLeadCount> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for lead in leads {
+        *counts.entry(lead.post.subreddit.as_str()).or_insert(0) += 1;
+    }
+
+    let mut counts: Vec<SubredditLeadCount> = counts
+        .into_iter()
+        .map(|(subreddit, leads)| SubredditLeadCount {
+            subreddit: subreddit.to_string(),
+            leads,
+        })
+        .collect();
+    counts.sort_by(|a, b| b.leads.cmp(&a.leads).then(a.subreddit.cmp(&b.subreddit)));
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_keys(match_keyword: &str) -> ApiKeys {
+        ApiKeys {
+            lead_keywords: vec!["inventory".to_string(), "shopify".to_string()],
+            match_keyword: match_keyword.to_string(),
+            ..ApiKeys::default()
+        }
+    }
+
+    fn posts() -> Vec<PostDataWrapper> {
+        vec![
+            PostDataWrapper {
+                selftext: Some("We sell on Shopify".to_string()),
+                ..PostDataWrapper::sample(1, "Inventory app?")
+            },
+            PostDataWrapper::sample(2, "Inventory counts keep drifting"),
+            PostDataWrapper::sample(3, "Weekend plans"),
+        ]
+    }
+
+    fn ids(leads: &[LocalLead]) -> Vec<i64> {
+        let mut ids: Vec<i64> = leads.iter().map(|lead| lead.post.id).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn and_needs_every_keyword_or_needs_one() {
+        assert_eq!(MatchMode::from_setting(" and "), MatchMode::All);
+        assert_eq!(MatchMode::from_setting("OR"), MatchMode::Any);
+        assert_eq!(MatchMode::from_setting(""), MatchMode::Any);
+
+        let all = find_local_leads(&api_keys("AND"), posts(), Vec::new());
+        let any = find_local_leads(&api_keys("OR"), posts(), Vec::new());
+
+        assert_eq!(ids(&all), vec![1]);
+        assert_eq!(all[0].matched_keywords, vec!["inventory", "shopify"]);
+        assert_eq!(ids(&any), vec![1, 2]);
+        assert!(find_local_leads(&ApiKeys::default(), posts(), Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn keywords_in_comments_count_for_the_post() {
+        let comments = vec![
+            CommentDataWrapper::sample("c1", "2", "Shopify syncs stock for us"),
+            CommentDataWrapper::sample("c2", "3", "Have fun!"),
+        ];
+
+        let leads = find_local_leads(&api_keys("AND"), posts(), comments);
+
+        assert_eq!(ids(&leads), vec![1, 2]);
+        let lead = leads.iter().find(|lead| lead.post.id == 2).unwrap();
+        assert_eq!(lead.matched_keywords, vec!["inventory", "shopify"]);
+        assert_eq!(lead.matching_comments.len(), 1);
+        assert_eq!(lead.matching_comments[0].id, "c1");
+    }
+
+    #[test]
+    fn only_the_best_three_matching_comments_are_kept() {
+        let comments: Vec<CommentDataWrapper> = (1..=5)
+            .map(|score| CommentDataWrapper {
+                score,
+                ..CommentDataWrapper::sample(&format!("c{}", score), "3", "Shopify helps")
+            })
+            .chain([CommentDataWrapper {
+                score: 100,
+                ..CommentDataWrapper::sample("off-topic", "3", "Nice weather")
+            }])
+            .collect();
+
+        let leads = find_local_leads(&api_keys("OR"), posts(), comments);
+
+        let lead = leads.iter().find(|lead| lead.post.id == 3).unwrap();
+        let kept: Vec<&str> = lead.matching_comments.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(kept, vec!["c5", "c4", "c3"]);
+    }
+
+    #[test]
+    fn repeated_keywords_count_once() {
+        let api_keys = ApiKeys {
+            lead_keywords: vec![
+                "inventory".to_string(),
+                "shopify".to_string(),
+                " Shopify".to_string(),
+            ],
+            ..api_keys("AND")
+        };
+        let comments = vec![CommentDataWrapper::sample("c1", "2", "Shopify syncs stock for us")];
+
+        let leads = find_local_leads(&api_keys, posts(), comments);
+
+        assert_eq!(ids(&leads), vec![1, 2]);
+    }
+}
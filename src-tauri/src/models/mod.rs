@@ -1,2 +1,17 @@
 pub mod search;
 pub mod auth;
+pub mod authors;
+pub mod brands;
+pub mod duplicates;
+pub mod filters;
+pub mod intent;
+pub mod lead_score;
+pub mod leads;
+pub mod sentiment;
+pub mod subreddits;
+pub mod topics;
+
+#[cfg(test)]
+pub(crate) mod mock_reddit;
+#[cfg(test)]
+mod reddit_api_tests;
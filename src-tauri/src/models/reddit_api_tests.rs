@@ -0,0 +1,367 @@
+// End-to-end tests for the Reddit client code against the local mock server
+use crate::models::auth::request_token;
+use crate::models::authors::fetch_author_profile;
+use crate::models::subreddits::{discover_subreddits, fetch_subreddit_rules, SubredditRules};
+use crate::models::mock_reddit::{fixture, MockReddit};
+use crate::models::search::{
+    fetch_post_comments, get_access_token, get_subreddit_posts, post_comment,
+    search_subreddit_posts, RedditError,
+};
+use crate::models::lead_score::LeadScorer;
+use crate::settings::api_keys::ApiKeys;
+
+fn scorer(api_keys: &ApiKeys) -> LeadScorer {
+    LeadScorer::new(api_keys, chrono::Utc::now().timestamp())
+}
+
+#[tokio::test]
+async fn search_follows_pagination_up_to_max_pages() {
+    let server = MockReddit::start(|request| match request.query.get("after") {
+        None => (200, fixture("search_page1.json")),
+        Some(after) if after == "t3_1hx2b7c" => (200, fixture("search_page2.json")),
+        Some(_) => (404, "{}".to_string()),
+    })
+    .await;
+    let mut api_keys = server.api_keys();
+    api_keys.reddit_max_pages = 5;
+
+    let posts = search_subreddit_posts(&api_keys, "token", "inventory software", "new", &scorer(&api_keys))
+        .await
+        .unwrap();
+
+    let titles: Vec<&str> = posts.iter().map(|p| p.title.as_str()).collect();
+    assert_eq!(
+        titles,
+        vec![
+            "Recommend an inventory tool for a small shop?",
+            "Stock sync between Shopify and Amazon keeps drifting",
+            "Question about barcode scanning for stock counts",
+        ]
+    );
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].path, "/search");
+    assert_eq!(requests[0].query["q"], "inventory software");
+    assert_eq!(requests[0].query["sort"], "new");
+    assert_eq!(requests[0].query["limit"], "100");
+    assert_eq!(requests[0].header("authorization"), Some("Bearer token"));
+    assert_eq!(requests[1].query["after"], "t3_1hx2b7c");
+}
+
+#[tokio::test]
+async fn search_stops_after_one_page_by_default() {
+    let server = MockReddit::start(|_| (200, fixture("search_page1.json"))).await;
+
+    let api_keys = server.api_keys();
+    let posts = search_subreddit_posts(&api_keys, "token", "inventory", "hot", &scorer(&api_keys))
+        .await
+        .unwrap();
+
+    assert_eq!(posts.len(), 2);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn search_maps_listing_fields() {
+    let server = MockReddit::start(|_| (200, fixture("search_page1.json"))).await;
+
+    let api_keys = server.api_keys();
+    let posts = search_subreddit_posts(&api_keys, "token", "inventory", "hot", &scorer(&api_keys))
+        .await
+        .unwrap();
+    let post = &posts[0];
+
+    assert_eq!(post.id, i64::from_str_radix("1hx2a9f", 36).unwrap());
+    assert_eq!(post.name, "t3_1hx2a9f");
+    assert_eq!(post.subreddit, "smallbusiness");
+    assert_eq!(post.author, "shop_owner_22");
+    assert_eq!(post.score, 42);
+    assert_eq!(post.num_comments, 17);
+    assert_eq!(post.sort_type, "hot");
+    assert_eq!(post.formatted_date, "2025-01-09 05:20:00");
+    assert_eq!(
+        post.permalink,
+        "https://reddit.com/r/smallbusiness/comments/1hx2a9f/recommend_an_inventory_tool_for_a_small_shop/"
+    );
+    // "looking for" is one of the default high intent patterns
+    assert_eq!(post.intent, "High");
+    // 40 intent points plus some for votes and comments; the fixture is too old for recency
+    assert!((50..=60).contains(&post.relevance_score), "{}", post.relevance_score);
+}
+
+#[tokio::test]
+async fn search_maps_post_metadata() {
+    let server = MockReddit::start(|_| (200, fixture("search_page1.json"))).await;
+
+    let api_keys = server.api_keys();
+    let posts = search_subreddit_posts(&api_keys, "token", "inventory", "hot", &scorer(&api_keys))
+        .await
+        .unwrap();
+
+    let text_post = &posts[0];
+    assert_eq!(text_post.link_flair_text.as_deref(), Some("Question"));
+    assert_eq!(text_post.upvote_ratio, 0.94);
+    assert_eq!(text_post.domain, "self.smallbusiness");
+    assert_eq!(text_post.media_type.as_deref(), Some("text"));
+    assert!(!text_post.over_18 && !text_post.locked && !text_post.stickied);
+
+    let image_post = &posts[1];
+    assert_eq!(image_post.media_type.as_deref(), Some("image"));
+    assert_eq!(image_post.crosspost_parent.as_deref(), Some("t3_1hw9zzz"));
+    assert!(image_post.locked);
+    assert_eq!(image_post.link_flair_text, None);
+}
+
+#[tokio::test]
+async fn subreddit_listing_uses_clean_subreddit_path() {
+    let server = MockReddit::start(|_| (200, fixture("subreddit_hot.json"))).await;
+
+    let api_keys = server.api_keys();
+    let posts = get_subreddit_posts(&api_keys, "token", "r/rust", "hot", &scorer(&api_keys))
+        .await
+        .unwrap();
+
+    assert_eq!(posts.len(), 1);
+    assert_eq!(posts[0].title, "Thoughts on config crates in 2025");
+    assert_eq!(server.requests()[0].path, "/r/rust/hot");
+}
+
+#[tokio::test]
+async fn listing_errors_surface_http_status() {
+    let server = MockReddit::start(|_| (403, r#"{"message": "Forbidden", "error": 403}"#.to_string())).await;
+
+    let api_keys = server.api_keys();
+    let result = get_subreddit_posts(&api_keys, "token", "r/private", "new", &scorer(&api_keys)).await;
+
+    match result {
+        Err(RedditError::HttpError(status, body)) => {
+            assert_eq!(status, 403);
+            assert!(body.contains("Forbidden"));
+        }
+        other => panic!("expected HttpError, got {:?}", other.map(|p| p.len())),
+    }
+}
+
+#[tokio::test]
+async fn comments_are_flattened_depth_first() {
+    let server = MockReddit::start(|_| (200, fixture("comments.json"))).await;
+
+    let comments = fetch_post_comments(
+        &server.api_keys(),
+        "token",
+        "1hx2a9f",
+        "Recommend an inventory tool for a small shop?",
+        "q&a",
+        "smallbusiness",
+    )
+    .await
+    .unwrap();
+
+    let ids: Vec<&str> = comments.iter().map(|c| c.id.as_str()).collect();
+    // The trailing "more" stub is skipped
+    assert_eq!(ids, vec!["m6a0001", "m6a0002", "m6a0003", "m6a0004"]);
+    assert_eq!(comments[2].parent_id, "t1_m6a0002");
+    assert!(comments
+        .iter()
+        .all(|c| c.post_id == "1hx2a9f" && c.subreddit == "smallbusiness"));
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/r/smallbusiness/comments/1hx2a9f");
+    assert_eq!(request.query["sort"], "qa");
+}
+
+#[tokio::test]
+async fn comments_for_user_profile_posts_use_global_endpoint() {
+    let server = MockReddit::start(|_| (200, fixture("comments.json"))).await;
+
+    fetch_post_comments(&server.api_keys(), "token", "1hx2a9f", "Title", "top", "u_someone")
+        .await
+        .unwrap();
+
+    assert_eq!(server.requests()[0].path, "/comments/1hx2a9f");
+}
+
+#[tokio::test]
+async fn author_profile_combines_about_and_recent_activity() {
+    let server = MockReddit::start(|request| {
+        if request.path.ends_with("/about") {
+            (200, fixture("user_about.json"))
+        } else {
+            (200, fixture("user_overview.json"))
+        }
+    })
+    .await;
+    let mut api_keys = server.api_keys();
+    api_keys.branded_keywords = vec!["acme".to_string()];
+
+    // 2025-01-10, six years after the account was created
+    let profile = fetch_author_profile(&api_keys, "token", "u/shop_owner_22", 1736467200)
+        .await
+        .unwrap();
+
+    assert_eq!(profile.username, "shop_owner_22");
+    assert_eq!(profile.link_karma + profile.comment_karma, 9830);
+    assert_eq!((profile.recent_posts, profile.recent_comments), (1, 2));
+    assert_eq!(profile.active_subreddits, vec!["smallbusiness", "shopify"]);
+    assert_eq!(profile.keyword_mentions, 1);
+    assert!(profile.verified_email);
+    assert!(!profile.is_suspect(), "{:?}", profile.flags);
+    assert_eq!(
+        profile.summary,
+        "6 years old, 9830 karma, active in r/smallbusiness, r/shopify, 1 keyword mentions"
+    );
+
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/user/shop_owner_22/about");
+    assert_eq!(requests[1].path, "/user/shop_owner_22/overview");
+    assert_eq!(requests[1].query["limit"], "100");
+}
+
+#[tokio::test]
+async fn suspended_author_is_flagged_without_fetching_activity() {
+    let server = MockReddit::start(|_| (200, fixture("user_suspended.json"))).await;
+
+    let profile = fetch_author_profile(&server.api_keys(), "token", "spam_account_991", 1736467200)
+        .await
+        .unwrap();
+
+    assert_eq!(profile.flags, vec!["suspended"]);
+    assert!(profile.is_suspect());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn subreddit_discovery_maps_metadata_and_skips_nsfw() {
+    let server = MockReddit::start(|_| (200, fixture("subreddit_search.json"))).await;
+
+    let subreddits = discover_subreddits(&server.api_keys(), "token", "inventory", false)
+        .await
+        .unwrap();
+
+    let names: Vec<&str> = subreddits.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["smallbusiness", "InventoryManagement"]);
+    assert_eq!(subreddits[0].subscribers, 2140000);
+    assert_eq!(subreddits[0].active_users, 1830);
+    // Older listings report accounts_active instead
+    assert_eq!(subreddits[1].active_users, 12);
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/subreddits/search");
+    assert_eq!(request.query["q"], "inventory");
+    assert_eq!(request.query["include_over_18"], "off");
+}
+
+#[tokio::test]
+async fn subreddit_rules_pick_out_the_self_promotion_policy() {
+    let server = MockReddit::start(|_| (200, fixture("subreddit_rules.json"))).await;
+
+    let rules = fetch_subreddit_rules(&server.api_keys(), "token", "r/smallbusiness")
+        .await
+        .unwrap();
+    let rules = SubredditRules::new("r/smallbusiness", &rules, 1736467200);
+
+    assert_eq!(rules.subreddit, "smallbusiness");
+    assert!(rules.rules.starts_with("1. Be civil\n   No personal attacks"));
+    assert!(rules.rules.ends_with("3. No affiliate links"));
+    assert_eq!(
+        rules.self_promotion,
+        "No self-promotion: Do not promote your own product, blog or service outside the weekly thread.\n\nNo affiliate links"
+    );
+    assert_eq!(server.requests()[0].path, "/r/smallbusiness/about/rules");
+}
+
+#[tokio::test]
+async fn app_only_token_uses_client_credentials() {
+    let server = MockReddit::start(|_| (200, fixture("access_token.json"))).await;
+
+    let token = get_access_token(&server.api_keys()).await.unwrap();
+
+    assert_eq!(token, "app-only-token");
+    let request = &server.requests()[0];
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/api/v1/access_token");
+    assert_eq!(request.form()["grant_type"], "client_credentials");
+    // base64("test-client:test-secret")
+    assert_eq!(
+        request.header("authorization"),
+        Some("Basic dGVzdC1jbGllbnQ6dGVzdC1zZWNyZXQ=")
+    );
+}
+
+#[tokio::test]
+async fn refresh_grant_returns_rotated_tokens() {
+    let server = MockReddit::start(|_| (200, fixture("refresh_token.json"))).await;
+
+    let token = request_token(
+        &server.api_keys(),
+        &[("grant_type", "refresh_token"), ("refresh_token", "old-refresh")],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(token.access_token, "refreshed-user-token");
+    assert_eq!(token.refresh_token.as_deref(), Some("rotated-refresh-token"));
+    assert_eq!(token.expires_in, 86400);
+    assert!(token.scope.contains("submit"));
+
+    let form = server.requests()[0].form();
+    assert_eq!(form["grant_type"], "refresh_token");
+    assert_eq!(form["refresh_token"], "old-refresh");
+}
+
+#[tokio::test]
+async fn rejected_refresh_reports_unauthorized() {
+    let server = MockReddit::start(|_| (401, r#"{"error": "invalid_grant"}"#.to_string())).await;
+
+    let result = request_token(
+        &server.api_keys(),
+        &[("grant_type", "refresh_token"), ("refresh_token", "revoked")],
+    )
+    .await;
+
+    assert!(matches!(result, Err(RedditError::HttpError(401, _))));
+}
+
+#[tokio::test]
+async fn posting_a_comment_returns_the_created_comment() {
+    let server = MockReddit::start(|_| (200, fixture("comment_posted.json"))).await;
+
+    let comment = post_comment(&server.api_keys(), "user-token", "t3_1hx2a9f", "Thanks for sharing!")
+        .await
+        .unwrap();
+
+    assert_eq!(comment.id, "m6b0001");
+    assert_eq!(comment.body, "Thanks for sharing!");
+    assert_eq!(comment.parent_id, "t3_1hx2a9f");
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/api/comment");
+    assert_eq!(request.header("authorization"), Some("Bearer user-token"));
+    let form = request.form();
+    assert_eq!(form["thing_id"], "t3_1hx2a9f");
+    assert_eq!(form["text"], "Thanks for sharing!");
+    assert_eq!(form["api_type"], "json");
+}
+
+#[tokio::test]
+async fn comment_api_errors_are_reported() {
+    let server = MockReddit::start(|_| (200, fixture("comment_ratelimited.json"))).await;
+
+    let result = post_comment(&server.api_keys(), "user-token", "t3_1hx2a9f", "again").await;
+
+    match result {
+        Err(RedditError::HttpError(400, message)) => assert!(message.contains("RATELIMIT")),
+        other => panic!("expected rate limit error, got {:?}", other.map(|c| c.id)),
+    }
+}
+
+#[tokio::test]
+async fn expired_user_token_is_reported_as_unauthorized() {
+    let server = MockReddit::start(|_| (401, r#"{"message": "Unauthorized", "error": 401}"#.to_string())).await;
+
+    let result = post_comment(&server.api_keys(), "expired", "t3_1hx2a9f", "hello").await;
+
+    // with_user_token relies on this to refresh and retry
+    assert!(matches!(result, Err(RedditError::HttpError(401, _))));
+}
@@ -8,7 +8,13 @@ use crate::{
         self,
         adding::{CommentDataWrapper, PostDataWrapper},
     },
-    settings::api_keys::{self, AppConfig},
+    models::{
+        brands::BrandTracker,
+        filters::FilterCounts,
+        lead_score::{LeadScorer, PostSignals},
+        sentiment,
+    },
+    settings::api_keys::{self, ApiKeys, AppConfig},
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -26,12 +32,54 @@ pub struct RedditPost {
     thumbnail: Option<String>,
     is_self: bool,
     num_comments: i64,
+    #[serde(default)]
+    link_flair_text: Option<String>,
+    #[serde(default)]
+    over_18: bool,
+    #[serde(default)]
+    upvote_ratio: f64,
+    #[serde(default)]
+    stickied: bool,
+    #[serde(default)]
+    locked: bool,
+    #[serde(default)]
+    domain: String,
+    #[serde(default)]
+    author_flair_text: Option<String>,
+    #[serde(default)]
+    crosspost_parent: Option<String>,
+    #[serde(default)]
+    post_hint: Option<String>,
+    #[serde(default)]
+    is_video: bool,
+    #[serde(default)]
+    is_gallery: bool,
+}
+
+impl RedditPost {
+    // Collapses Reddit's post_hint / is_video / is_gallery flags into one label
+    fn media_type(&self) -> Option<String> {
+        if self.is_gallery {
+            return Some("gallery".to_string());
+        }
+        if self.is_video {
+            return Some("video".to_string());
+        }
+        match self.post_hint.as_deref() {
+            Some("image") => Some("image".to_string()),
+            Some("hosted:video") | Some("rich:video") => Some("video".to_string()),
+            Some("link") => Some("link".to_string()),
+            Some(_) => None,
+            None if self.is_self => Some("text".to_string()),
+            None => None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum RedditData {
-    Post(RedditPost),
+    Post(Box<RedditPost>),
     Comment(RedditComment),
 }
 
@@ -51,6 +99,8 @@ pub struct RedditComment {
 #[derive(Deserialize, Debug, Clone)]
 struct RedditListingData {
     children: Vec<RedditListingChild>,
+    #[serde(default)]
+    after: Option<String>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -63,6 +113,13 @@ pub struct RedditListing {
     data: RedditListingData,
 }
 
+// What get_reddit_results hands back to the UI
+#[derive(Serialize, Debug)]
+pub struct SearchResponse {
+    pub posts: Vec<PostDataWrapper>,
+    pub filtered: FilterCounts,
+}
+
 // Define a custom error type for better error handling
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -134,10 +191,10 @@ impl AppState {
 }
 
 // Function to get access token from Reddit API
-pub async fn get_access_token(
-    client_id: String,
-    client_secret: String,
-) -> Result<String, RedditError> {
+pub async fn get_access_token(api_keys: &ApiKeys) -> Result<String, RedditError> {
+    let client_id = api_keys.reddit_api_id.as_str();
+    let client_secret = api_keys.reddit_api_secret.as_str();
+
     if client_id == "CHANGE_ME" || client_secret == "CHANGE_ME" {
         let missing = if client_id == "CHANGE_ME" && client_secret == "CHANGE_ME" {
             "Client ID and Secret"
@@ -154,7 +211,7 @@ pub async fn get_access_token(
 
     let client = Client::new();
     let response = client
-        .post("https://www.reddit.com/api/v1/access_token")
+        .post(api_keys.reddit_auth_url("/api/v1/access_token"))
         .header("Authorization", format!("Basic {}", encoded))
         .header("User-Agent", "AtalaiaApp/0.1 by Atalaia")
         .form(&[("grant_type", "client_credentials")])
@@ -172,6 +229,32 @@ pub async fn get_access_token(
     }
 }
 
+// GET with the bearer token, deserializing a successful JSON response into `T`
+pub(crate) async fn get_reddit_json<T: for<'de> Deserialize<'de>>(
+    client: &Client,
+    url: &str,
+    query: &[(&str, &str)],
+    access_token: &str,
+) -> Result<T, RedditError> {
+    let response = client
+        .get(url)
+        .query(query)
+        .header("Authorization", format!("Bearer {}", access_token))
+        .header("User-Agent", "Atalaia/0.1.0 (by /u/Atalaia)")
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(RedditError::HttpError(
+            response.status().as_u16(),
+            response.text().await.unwrap_or_default(),
+        ));
+    }
+
+    let text = response.text().await?;
+    serde_json::from_str(&text).map_err(|e| RedditError::ParseError(e.to_string()))
+}
+
 #[derive(Debug, Deserialize)]
 struct SubredditListing {
     data: SubredditData,
@@ -187,99 +270,22 @@ struct SubredditChild {
     data: RedditData,
 }
 
-// Update your function
 pub async fn get_subreddit_posts(
+    api_keys: &ApiKeys,
     access_token: &str,
     subreddit: &str,
     sort_type: &str, // Renamed from relevance
+    scorer: &LeadScorer,
 ) -> Result<Vec<PostDataWrapper>, RedditError> {
-    let client = Client::new();
-    let config = api_keys::ConfigDirs::read_config().unwrap_or_default();
-
     // Clean the subreddit name - remove "r/" if present
     let subreddit_clean = subreddit.trim_start_matches("r/");
 
-    let url = format!(
-        "https://oauth.reddit.com/r/{}/{}?limit=100",
-        subreddit_clean,
-        sort_type // Use sort_type here
-    );
+    let url = api_keys.reddit_api_url(&format!("/r/{}/{}", subreddit_clean, sort_type));
+    let query = [("limit", "100")];
 
     println!("Fetching from URL: {}", url);
 
-    let response = client
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", access_token))
-        .header("User-Agent", "Atalaia/0.1.0 (by /u/Atalaia)")
-        .send()
-        .await
-        .map_err(|e| {
-            eprintln!("Request failed: {}", e);
-            RedditError::Reqwest(e)
-        })?;
-
-    // Check response status
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_default();
-        eprintln!("HTTP Error {}: {}", status, error_text);
-        return Err(RedditError::HttpError(status.as_u16(), error_text));
-    }
-
-    let response_text = response.text().await.map_err(RedditError::Reqwest)?;
-
-    // Debug: print the beginning of the response
-    println!(
-        "Response preview: {}",
-        &response_text[..std::cmp::min(500, response_text.len())]
-    );
-
-    // Try to parse the response
-    let listing: RedditListing = serde_json::from_str(&response_text).map_err(|e| {
-        eprintln!("JSON parse error: {}", e);
-        eprintln!("Full response: {}", response_text);
-        RedditError::ParseError(e.to_string())
-    })?;
-
-    let posts: Vec<PostDataWrapper> = listing
-        .data
-        .children
-        .into_iter()
-        .filter_map(|child| {
-            if let RedditData::Post(post) = child.data {
-                let intent = config
-                    .api_keys
-                    .calculate_intent(&post.title, post.selftext.as_deref());
-                Some(PostDataWrapper {
-                    id: i64::from_str_radix(&post.id, 36).unwrap_or(0),
-                    title: post.title.clone(),
-                    url: post.url.clone(),
-                    timestamp: post.created_utc as i64,
-                    formatted_date: database::adding::DB::format_timestamp(post.created_utc as i64)
-                        .expect("Failed to format timestamp"),
-                    sort_type: sort_type.to_string(), // Use sort_type
-                    relevance_score: 0, // Default to 0 as no score is available in RedditPost
-                    subreddit: post.subreddit.clone(),
-                    permalink: format!("https://reddit.com{}", post.permalink),
-                    engaged: 0,
-                    assignee: "".to_string(),
-                    notes: "".to_string(),
-                    name: post.name,
-                    selftext: post.selftext,
-                    author: post.author,
-                    score: post.score,
-                    thumbnail: post.thumbnail,
-                    is_self: post.is_self,
-                    num_comments: post.num_comments,
-                    intent,
-                    date_added: 0,
-                    interest: 0,
-                })
-            } else {
-                None
-            }
-        })
-        .collect();
+    let posts = fetch_listing_posts(api_keys, access_token, &url, &query, sort_type, scorer).await?;
 
     println!("Processed {} ", subreddit_clean);
     if !posts.is_empty() {
@@ -289,88 +295,162 @@ pub async fn get_subreddit_posts(
 }
 
 pub async fn search_subreddit_posts(
+    api_keys: &ApiKeys,
     access_token: &str,
     query: &str,
     sort_type: &str, // Renamed from relevance
+    scorer: &LeadScorer,
 ) -> Result<Vec<PostDataWrapper>, RedditError> {
-    let client = Client::new();
-    let config = api_keys::ConfigDirs::read_config().unwrap_or_default();
+    let url = api_keys.reddit_api_url("/search");
 
-    // Include the sort parameter in the URL
+    println!("Making request to: {} with q='{}'", url, query); // Debug log
 
-    let url = "https://oauth.reddit.com/search";
+    // Include the sort parameter in the query
+    let params = [
+        ("q", query),
+        ("sort", sort_type),
+        ("limit", "100"),
+        ("t", "all"),
+    ];
 
-    println!("Making request to: {} with q='{}'", url, query); // Debug log
+    let posts = fetch_listing_posts(api_keys, access_token, &url, &params, sort_type, scorer).await?;
 
-    let response = client
-        .get(url)
-        .query(&[
-            ("q", query),
-            ("sort", sort_type),
-            ("limit", "100"),
-            ("t", "all"),
-        ])
-        .header("Authorization", format!("Bearer {}", access_token))
-        .header("User-Agent", "Atalaia/0.1.0 (by /u/Atalaia)")
-        .send()
-        .await?;
+    println!("Processed {} posts for sort: {}", posts.len(), sort_type);
+    if !posts.is_empty() {
+        println!("Post: {:#?}", &posts[0]);
+    }
+    Ok(posts)
+}
+
+// Fetches a listing, following `after` for up to `reddit_max_pages` pages
+async fn fetch_listing_posts(
+    api_keys: &ApiKeys,
+    access_token: &str,
+    url: &str,
+    query: &[(&str, &str)],
+    sort_type: &str,
+    scorer: &LeadScorer,
+) -> Result<Vec<PostDataWrapper>, RedditError> {
+    let client = Client::new();
+    let max_pages = api_keys.reddit_max_pages.max(1);
+    let brands = BrandTracker::new(api_keys);
+    let mut posts = Vec::new();
+    let mut after: Option<String> = None;
+
+    for page in 1..=max_pages {
+        let mut request = client
+            .get(url)
+            .query(query)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .header("User-Agent", "Atalaia/0.1.0 (by /u/Atalaia)");
+        if let Some(after) = &after {
+            request = request.query(&[("after", after.as_str())]);
+        }
 
-    let listing: RedditListing = response.json().await?;
+        let response = request.send().await.map_err(|e| {
+            eprintln!("Request failed: {}", e);
+            RedditError::Reqwest(e)
+        })?;
 
-    // Debug: print how many posts were returned
-    println!(
-        "API returned {} posts for sort: {}",
-        listing.data.children.len(),
-        sort_type // Use sort_type here
-    );
+        // Check response status
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            eprintln!("HTTP Error {}: {}", status, error_text);
+            return Err(RedditError::HttpError(status.as_u16(), error_text));
+        }
 
-    let posts: Vec<PostDataWrapper> = listing
-        .data
-        .children
-        .into_iter()
-        .filter_map(|child| {
-            if let RedditData::Post(post) = &child.data {
-                let intent = config
-                    .api_keys
-                    .calculate_intent(&post.title, post.selftext.as_deref());
-                Some(PostDataWrapper {
-                    id: i64::from_str_radix(&post.id, 36).unwrap_or(0),
-                    title: post.title.clone(),
-                    url: post.url.clone(),
-                    timestamp: post.created_utc as i64,
-                    formatted_date: database::adding::DB::format_timestamp(post.created_utc as i64)
-                        .expect("Failed to format timestamp"),
-                    sort_type: sort_type.to_string(), // Use sort_type
-                    relevance_score: 0, // Default to 0 as no score is available in RedditPost
-                    subreddit: post.subreddit.clone(),
-                    permalink: format!("https://reddit.com{}", post.permalink.clone()),
-                    engaged: 0,
-                    assignee: "".to_string(),
-                    notes: "".to_string(),
-                    name: post.name.clone(),
-                    selftext: post.selftext.clone(),
-                    author: post.author.clone(),
-                    score: post.score.clone(),
-                    thumbnail: post.thumbnail.clone(),
-                    is_self: post.is_self.clone(),
-                    num_comments: post.num_comments.clone(),
-                    intent,
-                    date_added: 0,
-                    interest: 0,
-                })
+        let response_text = response.text().await.map_err(RedditError::Reqwest)?;
+
+        // Try to parse the response
+        let listing: RedditListing = serde_json::from_str(&response_text).map_err(|e| {
+            eprintln!("JSON parse error: {}", e);
+            eprintln!("Full response: {}", response_text);
+            RedditError::ParseError(e.to_string())
+        })?;
+
+        // Debug: print how many posts were returned
+        println!(
+            "API returned {} posts for sort: {} (page {})",
+            listing.data.children.len(),
+            sort_type,
+            page
+        );
+
+        posts.extend(listing.data.children.into_iter().filter_map(|child| {
+            if let RedditData::Post(post) = child.data {
+                Some(post_to_wrapper(*post, sort_type, scorer, &brands))
             } else {
                 None
             }
-        })
-        .collect();
+        }));
 
-    println!("Processed {} posts for sort: {}", posts.len(), sort_type);
-    if !posts.is_empty() {
-        println!("Post: {:#?}", &posts[0]);
+        after = listing.data.after;
+        if after.is_none() {
+            break;
+        }
     }
+
     Ok(posts)
 }
 
+fn post_to_wrapper(
+    post: RedditPost,
+    sort_type: &str,
+    scorer: &LeadScorer,
+    brands: &BrandTracker,
+) -> PostDataWrapper {
+    let (intent, lead_score) = scorer.score(&PostSignals {
+        title: &post.title,
+        body: post.selftext.as_deref(),
+        subreddit: &post.subreddit,
+        created_utc: post.created_utc as i64,
+        score: post.score,
+        num_comments: post.num_comments,
+    });
+    let media_type = post.media_type();
+    let brand_mentions = brands.tag(&post.title, post.selftext.as_deref());
+    let tone = sentiment::analyze_post(&post.title, post.selftext.as_deref());
+    PostDataWrapper {
+        id: i64::from_str_radix(&post.id, 36).unwrap_or(0),
+        title: post.title,
+        url: post.url,
+        timestamp: post.created_utc as i64,
+        formatted_date: database::adding::DB::format_timestamp(post.created_utc as i64)
+            .expect("Failed to format timestamp"),
+        sort_type: sort_type.to_string(), // Use sort_type
+        relevance_score: lead_score,
+        subreddit: post.subreddit,
+        permalink: format!("https://reddit.com{}", post.permalink),
+        engaged: 0,
+        assignee: "".to_string(),
+        notes: "".to_string(),
+        name: post.name,
+        selftext: post.selftext,
+        author: post.author,
+        score: post.score,
+        thumbnail: post.thumbnail,
+        is_self: post.is_self,
+        num_comments: post.num_comments,
+        intent: intent.level,
+        date_added: 0,
+        interest: 0,
+        link_flair_text: post.link_flair_text,
+        over_18: post.over_18,
+        upvote_ratio: post.upvote_ratio,
+        stickied: post.stickied,
+        locked: post.locked,
+        domain: post.domain,
+        author_flair_text: post.author_flair_text,
+        crosspost_parent: post.crosspost_parent,
+        media_type,
+        brand_mentions,
+        sentiment: tone.sentiment.as_str().to_string(),
+        sentiment_score: tone.score,
+        cluster_id: "".to_string(),
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct CommentResponse {
     data: CommentResponseData,
@@ -407,8 +487,6 @@ pub async fn get_post_comments(
     subreddit: &str,
     fullname: Option<String>,
 ) -> Result<Vec<CommentDataWrapper>, RedditError> {
-    let client = Client::new();
-
     // Try to get post ID from fullname first (t3_id)
     let post_id = if let Some(fn_str) = fullname {
         if fn_str.starts_with("t3_") {
@@ -432,31 +510,6 @@ pub async fn get_post_comments(
         },
     };
 
-    // Clean the subreddit name
-    let subreddit_clean = subreddit.trim_start_matches("r/");
-
-    // Construct the canonical Reddit API URL for comments
-    // Using global /comments/{id} endpoint is often more reliable than subreddit-specific ones,
-    // especially for user profile posts or cross-posts.
-    let is_user_sub = subreddit_clean.to_lowercase().starts_with("u_") || subreddit_clean.to_lowercase().starts_with("u/");
-    
-    let api_url = if !subreddit_clean.is_empty() && subreddit_clean != "unknown" && subreddit_clean != "N/A" && !is_user_sub {
-        format!(
-            "https://oauth.reddit.com/r/{}/comments/{}?sort={}&limit=500",
-            subreddit_clean,
-            post_id,
-            sort_type.replace("q&a", "qa")
-        )
-    } else {
-        format!(
-            "https://oauth.reddit.com/comments/{}?sort={}&limit=500",
-            post_id,
-            sort_type.replace("q&a", "qa")
-        )
-    };
-
-    println!("Fetching comments from canonical URL: {}", api_url);
-
     // Read config
     let config = api_keys::ConfigDirs::read_config().unwrap_or_else(|err| {
         eprintln!("Warning: using default config because: {err}");
@@ -464,11 +517,9 @@ pub async fn get_post_comments(
     });
 
     let api_keys = config.api_keys;
-    let client_id = api_keys.reddit_api_id;
-    let client_secret = api_keys.reddit_api_secret;
 
     // Get token
-    let token = match get_access_token(client_id, client_secret).await {
+    let token = match get_access_token(&api_keys).await {
         Ok(t) if !t.is_empty() => t,
         Ok(_) => {
             return Err("Authorization Error: Reddit API returned an empty buffer. Check your credentials.".into());
@@ -478,11 +529,51 @@ pub async fn get_post_comments(
         }
     };
 
+    let comments =
+        fetch_post_comments(&api_keys, &token, &post_id, post_title, sort_type, subreddit).await?;
+
+    // Save to database
+    let mut db = database::adding::DB::new().map_err(|e| RedditError::ParseError(e.to_string()))?;
+    db.append_comments(&comments)
+        .map_err(|e| RedditError::ParseError(e.to_string()))?;
+    db.record_comment_brand_mentions(&comments)
+        .map_err(|e| RedditError::ParseError(e.to_string()))?;
+
+    Ok(comments)
+}
+
+// Fetches and flattens the comment tree of a post without touching the database
+pub async fn fetch_post_comments(
+    api_keys: &ApiKeys,
+    access_token: &str,
+    post_id: &str,
+    post_title: &str,
+    sort_type: &str,
+    subreddit: &str,
+) -> Result<Vec<CommentDataWrapper>, RedditError> {
+    let client = Client::new();
+
+    // Clean the subreddit name
+    let subreddit_clean = subreddit.trim_start_matches("r/");
+
+    // Construct the canonical Reddit API URL for comments
+    // Using global /comments/{id} endpoint is often more reliable than subreddit-specific ones,
+    // especially for user profile posts or cross-posts.
+    let is_user_sub = subreddit_clean.to_lowercase().starts_with("u_") || subreddit_clean.to_lowercase().starts_with("u/");
+
+    let api_url = if !subreddit_clean.is_empty() && subreddit_clean != "unknown" && subreddit_clean != "N/A" && !is_user_sub {
+        api_keys.reddit_api_url(&format!("/r/{}/comments/{}", subreddit_clean, post_id))
+    } else {
+        api_keys.reddit_api_url(&format!("/comments/{}", post_id))
+    };
+    let sort = sort_type.replace("q&a", "qa");
+
     println!("Fetching comments from URL: {}", api_url);
 
     let response = client
         .get(&api_url)
-        .header("Authorization", format!("Bearer {}", token))
+        .query(&[("sort", sort.as_str()), ("limit", "500")])
+        .header("Authorization", format!("Bearer {}", access_token))
         .header("User-Agent", "Atalaia/0.1.0 (by /u/Atalaia)")
         .send()
         .await
@@ -517,10 +608,11 @@ pub async fn get_post_comments(
         })?;
 
     // Flatten recursive comments
+    let brands = BrandTracker::new(api_keys);
     let mut comments: Vec<CommentDataWrapper> = Vec::new();
     for child_json in comments_data.data.children {
         if let Ok(child) = serde_json::from_value::<CommentChild>(child_json) {
-            flatten_comments(child.data, &mut comments, &post_id, subreddit, post_title);
+            flatten_comments(child.data, &mut comments, post_id, subreddit, post_title, &brands);
         }
     }
 
@@ -529,11 +621,6 @@ pub async fn get_post_comments(
         comments.len()
     );
 
-    // Save to database
-    let mut db = database::adding::DB::new().map_err(|e| RedditError::ParseError(e.to_string()))?;
-    db.append_comments(&comments)
-        .map_err(|e| RedditError::ParseError(e.to_string()))?;
-
     Ok(comments)
 }
 
@@ -543,8 +630,11 @@ fn flatten_comments(
     post_id: &str,
     subreddit: &str,
     post_title: &str,
+    brands: &BrandTracker,
 ) {
     // Add the current comment
+    let brand_mentions = brands.tag(&data.body, None);
+    let tone = sentiment::analyze(&data.body);
     comments.push(CommentDataWrapper {
         id: data.id.clone(),
         post_id: post_id.to_string(),
@@ -560,6 +650,9 @@ fn flatten_comments(
         post_title: post_title.to_string(),
         engaged: 0,
         assignee: "".to_string(),
+        brand_mentions,
+        sentiment: tone.sentiment.as_str().to_string(),
+        sentiment_score: tone.score,
     });
 
     // Check for replies
@@ -569,7 +662,7 @@ fn flatten_comments(
                 for child_json in children {
                     if let Ok(child) = serde_json::from_value::<CommentChild>(child_json.clone()) {
                         // Recursively flatten
-                        flatten_comments(child.data, comments, post_id, subreddit, post_title);
+                        flatten_comments(child.data, comments, post_id, subreddit, post_title, brands);
                     }
                 }
             }
@@ -602,12 +695,12 @@ fn extract_subreddit_from_url(url: &str) -> Option<String> {
     None
 }
 
-pub async fn get_user_access_token(
-    client_id: &str,
-    client_secret: &str,
-    username: &str,
-    password: &str,
-) -> Result<String, RedditError> {
+pub async fn get_user_access_token(api_keys: &ApiKeys) -> Result<String, RedditError> {
+    let client_id = api_keys.reddit_api_id.as_str();
+    let client_secret = api_keys.reddit_api_secret.as_str();
+    let username = api_keys.reddit_username.as_str();
+    let password = api_keys.reddit_password.as_str();
+
     if client_id == "CHANGE_ME" || client_secret == "CHANGE_ME" || username.is_empty() || password.is_empty() {
         return Err(RedditError::ParseError("Reddit User credentials not fully configured. Please update your settings.".to_string()));
     }
@@ -622,7 +715,7 @@ pub async fn get_user_access_token(
 
     let client = Client::new();
     let response = client
-        .post("https://www.reddit.com/api/v1/access_token")
+        .post(api_keys.reddit_auth_url("/api/v1/access_token"))
         .header("Authorization", format!("Basic {}", encoded))
         .header("User-Agent", format!("macos:com.atalaia.client:v0.1.0 (by /u/{})", username))
         .form(&[
@@ -653,13 +746,14 @@ pub async fn get_user_access_token(
 }
 
 pub async fn post_comment(
+    api_keys: &ApiKeys,
     access_token: &str,
     parent_id: &str,
     text: &str,
 ) -> Result<CommentDataWrapper, RedditError> {
     let client = Client::new();
     let response = client
-        .post("https://oauth.reddit.com/api/comment")
+        .post(api_keys.reddit_api_url("/api/comment"))
         .header("Authorization", format!("Bearer {}", access_token))
         .header("User-Agent", "AtalaiaApp/0.1 by Atalaia")
         .form(&[("thing_id", parent_id), ("text", text), ("api_type", "json")])
@@ -693,6 +787,7 @@ pub async fn post_comment(
                  let comment_data: CommentData = serde_json::from_value(data.clone()).map_err(|e| RedditError::ParseError(e.to_string()))?;
                  
                  // Convert to CommentDataWrapper
+                 let tone = sentiment::analyze(&comment_data.body);
                  let wrapper = CommentDataWrapper {
                     id: comment_data.id,
                     post_id: "".to_string(), // Not returned directly, but we don't strictly need it for UI display initially
@@ -707,6 +802,9 @@ pub async fn post_comment(
                     post_title: "".to_string(),
                     engaged: 0,
                     assignee: "".to_string(),
+                    brand_mentions: "".to_string(),
+                    sentiment: tone.sentiment.as_str().to_string(),
+                    sentiment_score: tone.score,
                  };
                  return Ok(wrapper);
              }
@@ -0,0 +1,322 @@
+// Offline sentiment for posts and comments, so it works without an AI key.
+//
+// Each word in LEXICON carries a valence from -4 to 4. A negation ("not",
+// "don't", ...) within the three words before a term flips it, an intensifier
+// ("very", "really", ...) right before it makes it stronger, and after "but"
+// the rest of the text counts more than what came before. The sum is squashed
+// into -1..1, and anything within NEUTRAL_THRESHOLD of zero is neutral.
+
+use serde::Serialize;
+
+const NEUTRAL_THRESHOLD: f64 = 0.05;
+// Larger values need more words before the score approaches -1 or 1
+const NORMALIZATION_ALPHA: f64 = 15.0;
+const NEGATION_WINDOW: usize = 3;
+const NEGATION_FACTOR: f64 = -0.75;
+const INTENSIFIER_BOOST: f64 = 0.3;
+const BEFORE_BUT_FACTOR: f64 = 0.5;
+const AFTER_BUT_FACTOR: f64 = 1.5;
+
+const LEXICON: &[(&str, f64)] = &[
+    // Positive
+    ("amazing", 4.0),
+    ("awesome", 4.0),
+    ("excellent", 3.0),
+    ("fantastic", 4.0),
+    ("outstanding", 4.0),
+    ("perfect", 3.0),
+    ("love", 3.0),
+    ("loved", 3.0),
+    ("loves", 3.0),
+    ("great", 3.0),
+    ("brilliant", 3.0),
+    ("wonderful", 3.0),
+    ("best", 3.0),
+    ("impressed", 3.0),
+    ("impressive", 3.0),
+    ("good", 2.0),
+    ("nice", 2.0),
+    ("happy", 2.0),
+    ("glad", 2.0),
+    ("helpful", 2.0),
+    ("useful", 2.0),
+    ("recommend", 2.0),
+    ("recommended", 2.0),
+    ("reliable", 2.0),
+    ("easy", 2.0),
+    ("fast", 2.0),
+    ("smooth", 2.0),
+    ("solid", 2.0),
+    ("worth", 2.0),
+    ("thanks", 2.0),
+    ("thank", 2.0),
+    ("enjoy", 2.0),
+    ("enjoyed", 2.0),
+    ("like", 2.0),
+    ("liked", 2.0),
+    ("fixed", 2.0),
+    ("works", 1.0),
+    ("improved", 2.0),
+    ("better", 2.0),
+    ("affordable", 2.0),
+    ("intuitive", 2.0),
+    ("satisfied", 2.0),
+    ("fine", 1.0),
+    ("ok", 1.0),
+    ("okay", 1.0),
+    ("decent", 1.0),
+    ("cheap", 1.0),
+    ("win", 2.0),
+    // Negative
+    ("terrible", -3.0),
+    ("horrible", -3.0),
+    ("awful", -3.0),
+    ("worst", -3.0),
+    ("hate", -3.0),
+    ("hated", -3.0),
+    ("hates", -3.0),
+    ("scam", -4.0),
+    ("fraud", -4.0),
+    ("garbage", -3.0),
+    ("useless", -3.0),
+    ("disappointed", -2.0),
+    ("disappointing", -2.0),
+    ("frustrated", -2.0),
+    ("frustrating", -2.0),
+    ("annoying", -2.0),
+    ("angry", -3.0),
+    ("bad", -3.0),
+    ("worse", -3.0),
+    ("poor", -2.0),
+    ("broken", -2.0),
+    ("buggy", -2.0),
+    ("bug", -1.0),
+    ("bugs", -1.0),
+    ("crash", -2.0),
+    ("crashes", -2.0),
+    ("crashing", -2.0),
+    ("slow", -2.0),
+    ("expensive", -2.0),
+    ("overpriced", -2.0),
+    ("confusing", -2.0),
+    ("difficult", -1.0),
+    ("hard", -1.0),
+    ("problem", -2.0),
+    ("problems", -2.0),
+    ("issue", -1.0),
+    ("issues", -1.0),
+    ("error", -2.0),
+    ("errors", -2.0),
+    ("fail", -2.0),
+    ("failed", -2.0),
+    ("fails", -2.0),
+    ("failing", -2.0),
+    ("refund", -2.0),
+    ("cancel", -1.0),
+    ("cancelled", -1.0),
+    ("unreliable", -2.0),
+    ("unusable", -3.0),
+    ("waste", -2.0),
+    ("sucks", -3.0),
+    ("ripoff", -3.0),
+    ("stuck", -2.0),
+    ("lost", -2.0),
+    ("missing", -1.0),
+    ("sad", -2.0),
+    ("unhappy", -2.0),
+    ("regret", -2.0),
+    ("avoid", -2.0),
+    ("lacking", -2.0),
+    ("lacks", -2.0),
+    ("wrong", -2.0),
+];
+
+const NEGATIONS: &[&str] = &[
+    "not", "no", "never", "none", "nothing", "neither", "nor", "without", "hardly", "barely",
+    "isn't", "aren't", "wasn't", "weren't", "don't", "doesn't", "didn't", "won't", "wouldn't",
+    "can't", "cannot", "couldn't", "shouldn't", "haven't", "hasn't", "hadn't", "ain't", "isnt",
+    "dont", "doesnt", "didnt", "wont", "cant",
+];
+
+const INTENSIFIERS: &[&str] = &[
+    "very", "really", "extremely", "super", "so", "totally", "incredibly", "absolutely",
+    "completely", "highly", "insanely", "truly",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sentiment {
+    Positive,
+    Neutral,
+    Negative,
+}
+
+impl Sentiment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Sentiment::Positive => "positive",
+            Sentiment::Neutral => "neutral",
+            Sentiment::Negative => "negative",
+        }
+    }
+
+    /// Accepts the labels stored in the database, in any case.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "positive" => Some(Sentiment::Positive),
+            "neutral" => Some(Sentiment::Neutral),
+            "negative" => Some(Sentiment::Negative),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SentimentScore {
+    pub sentiment: Sentiment,
+    // -1 (most negative) to 1 (most positive)
+    pub score: f64,
+}
+
+// Default label for posts and comments saved before sentiment was stored
+pub fn default_label() -> String {
+    Sentiment::Neutral.as_str().to_string()
+}
+
+/// Scores a post's title and selftext together.
+pub fn analyze_post(title: &str, body: Option<&str>) -> SentimentScore {
+    match body {
+        Some(body) if !body.trim().is_empty() => analyze(&format!("{}. {}", title, body)),
+        _ => analyze(title),
+    }
+}
+
+pub fn analyze(text: &str) -> SentimentScore {
+    let words = words(text);
+    let but_at = words.iter().rposition(|word| word == "but");
+
+    let mut total = 0.0;
+    for (i, word) in words.iter().enumerate() {
+        let Some(mut valence) = valence(word) else {
+            continue;
+        };
+
+        if i > 0 && INTENSIFIERS.contains(&words[i - 1].as_str()) {
+            valence += valence.signum() * INTENSIFIER_BOOST * valence.abs();
+        }
+        let window = &words[i.saturating_sub(NEGATION_WINDOW)..i];
+        if window.iter().any(|w| NEGATIONS.contains(&w.as_str())) {
+            valence *= NEGATION_FACTOR;
+        }
+        match but_at {
+            Some(but) if i < but => valence *= BEFORE_BUT_FACTOR,
+            Some(but) if i > but => valence *= AFTER_BUT_FACTOR,
+            _ => {}
+        }
+
+        total += valence;
+    }
+
+    let score = total / (total * total + NORMALIZATION_ALPHA).sqrt();
+    let sentiment = if score >= NEUTRAL_THRESHOLD {
+        Sentiment::Positive
+    } else if score <= -NEUTRAL_THRESHOLD {
+        Sentiment::Negative
+    } else {
+        Sentiment::Neutral
+    };
+
+    SentimentScore { sentiment, score }
+}
+
+fn valence(word: &str) -> Option<f64> {
+    LEXICON
+        .iter()
+        .find(|(term, _)| *term == word)
+        .map(|(_, valence)| *valence)
+}
+
+// Lowercased words; apostrophes stay so "don't" is one word
+fn words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .replace('\u{2019}', "'")
+        .split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(text: &str) -> Sentiment {
+        analyze(text).sentiment
+    }
+
+    #[test]
+    fn positive_text() {
+        assert_eq!(label("This tool is great, I love it"), Sentiment::Positive);
+        assert_eq!(label("Really helpful support, would recommend"), Sentiment::Positive);
+    }
+
+    #[test]
+    fn negative_text() {
+        assert_eq!(label("Terrible app, it crashes constantly"), Sentiment::Negative);
+        assert_eq!(label("Total scam, I want a refund"), Sentiment::Negative);
+    }
+
+    #[test]
+    fn text_without_lexicon_words_is_neutral() {
+        assert_eq!(label("Which CRM do you use for a team of five?"), Sentiment::Neutral);
+        assert_eq!(label(""), Sentiment::Neutral);
+        assert_eq!(analyze("").score, 0.0);
+    }
+
+    #[test]
+    fn negation_flips_the_following_words() {
+        assert_eq!(label("not good"), Sentiment::Negative);
+        assert_eq!(label("I don't think it's bad"), Sentiment::Positive);
+        assert_eq!(label("I don\u{2019}t like it"), Sentiment::Negative);
+        // Outside the window the negation no longer applies
+        assert_eq!(label("not that I expected it to be this good"), Sentiment::Positive);
+    }
+
+    #[test]
+    fn intensifiers_strengthen_the_next_word() {
+        assert!(analyze("very good").score > analyze("good").score);
+        assert!(analyze("really slow").score < analyze("slow").score);
+    }
+
+    #[test]
+    fn text_after_but_dominates() {
+        assert_eq!(label("The UI is nice but the sync is broken"), Sentiment::Negative);
+        assert_eq!(label("Setup was confusing but support was great"), Sentiment::Positive);
+    }
+
+    #[test]
+    fn scores_stay_within_bounds() {
+        let gushing = analyze(&"amazing awesome fantastic ".repeat(50));
+        let ranting = analyze(&"terrible scam garbage ".repeat(50));
+        assert!(gushing.score <= 1.0 && gushing.score > 0.95);
+        assert!(ranting.score >= -1.0 && ranting.score < -0.95);
+    }
+
+    #[test]
+    fn post_body_is_included() {
+        assert_eq!(
+            analyze_post("Thoughts on Acme?", Some("Honestly the worst purchase I made")).sentiment,
+            Sentiment::Negative
+        );
+        assert_eq!(analyze_post("Thoughts on Acme?", None).sentiment, Sentiment::Neutral);
+    }
+
+    #[test]
+    fn labels_round_trip() {
+        for sentiment in [Sentiment::Positive, Sentiment::Neutral, Sentiment::Negative] {
+            assert_eq!(Sentiment::parse(sentiment.as_str()), Some(sentiment));
+        }
+        assert_eq!(Sentiment::parse(" Negative "), Some(Sentiment::Negative));
+        assert_eq!(Sentiment::parse("angry"), None);
+    }
+}
@@ -0,0 +1,218 @@
+// Subreddit discovery through Reddit's subreddit search, and the rules lookup
+// used before replying. Subreddits the user keeps are stored in the subreddits
+// table with notes and a weight that multiplies the lead score of their posts.
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::models::search::{get_reddit_json, RedditError};
+use crate::settings::api_keys::ApiKeys;
+
+// Rules are re-fetched after this long
+pub const RULES_CACHE_SECONDS: i64 = 7 * 86_400;
+
+// Words that mark a rule as being about self-promotion
+const PROMOTION_TERMS: &[&str] = &[
+    "promot",
+    "advertis",
+    "spam",
+    "affiliate",
+    "referral",
+    "solicit",
+    "marketing",
+    "self-promo",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubredditInfo {
+    pub name: String,
+    pub title: String,
+    pub description: String,
+    pub subscribers: i64,
+    pub active_users: i64,
+    pub over_18: bool,
+    pub url: String,
+}
+
+// A subreddit from the local subreddits table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSubreddit {
+    #[serde(flatten)]
+    pub info: SubredditInfo,
+    pub notes: String,
+    // Lead score multiplier for posts from this subreddit
+    pub weight: f64,
+    // Numbered rule list and the rules about self-promotion, empty until fetched
+    pub rules: String,
+    pub self_promotion: String,
+    pub rules_fetched_at: i64,
+    pub added_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubredditRule {
+    pub short_name: String,
+    pub description: String,
+    // link, comment or all
+    pub kind: String,
+}
+
+// What get_subreddit_rules_command returns
+#[derive(Debug, Serialize)]
+pub struct SubredditRules {
+    pub subreddit: String,
+    pub rules: String,
+    pub self_promotion: String,
+    pub fetched_at: i64,
+}
+
+impl SubredditRules {
+    pub fn new(subreddit: &str, rules: &[SubredditRule], fetched_at: i64) -> Self {
+        SubredditRules {
+            subreddit: subreddit.trim().trim_start_matches("r/").to_string(),
+            rules: format_rules(rules),
+            self_promotion: self_promotion_policy(rules),
+            fetched_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SubredditSearchListing {
+    data: SubredditSearchData,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubredditSearchData {
+    children: Vec<SubredditSearchChild>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubredditSearchChild {
+    data: RawSubreddit,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RawSubreddit {
+    display_name: String,
+    title: String,
+    public_description: String,
+    subscribers: Option<i64>,
+    active_user_count: Option<i64>,
+    accounts_active: Option<i64>,
+    over18: bool,
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RulesResponse {
+    #[serde(default)]
+    rules: Vec<RawRule>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RawRule {
+    short_name: String,
+    description: String,
+    kind: String,
+}
+
+/// Searches subreddit names and descriptions for `query`.
+pub async fn discover_subreddits(
+    api_keys: &ApiKeys,
+    access_token: &str,
+    query: &str,
+    include_nsfw: bool,
+) -> Result<Vec<SubredditInfo>, RedditError> {
+    let listing: SubredditSearchListing = get_reddit_json(
+        &Client::new(),
+        &api_keys.reddit_api_url("/subreddits/search"),
+        &[
+            ("q", query),
+            ("limit", "25"),
+            ("include_over_18", if include_nsfw { "on" } else { "off" }),
+        ],
+        access_token,
+    )
+    .await?;
+
+    Ok(listing
+        .data
+        .children
+        .into_iter()
+        .map(|child| child.data)
+        .filter(|raw| include_nsfw || !raw.over18)
+        .map(|raw| SubredditInfo {
+            name: raw.display_name,
+            title: raw.title,
+            description: raw.public_description,
+            subscribers: raw.subscribers.unwrap_or(0),
+            active_users: raw.active_user_count.or(raw.accounts_active).unwrap_or(0),
+            over_18: raw.over18,
+            url: raw.url,
+        })
+        .collect())
+}
+
+pub async fn fetch_subreddit_rules(
+    api_keys: &ApiKeys,
+    access_token: &str,
+    subreddit: &str,
+) -> Result<Vec<SubredditRule>, RedditError> {
+    let subreddit = subreddit.trim().trim_start_matches("r/");
+    let response: RulesResponse = get_reddit_json(
+        &Client::new(),
+        &api_keys.reddit_api_url(&format!("/r/{}/about/rules", subreddit)),
+        &[],
+        access_token,
+    )
+    .await?;
+
+    Ok(response
+        .rules
+        .into_iter()
+        .map(|rule| SubredditRule {
+            short_name: rule.short_name,
+            description: rule.description,
+            kind: rule.kind,
+        })
+        .collect())
+}
+
+// "1. Be civil\n   No personal attacks.\n2. ..."
+fn format_rules(rules: &[SubredditRule]) -> String {
+    rules
+        .iter()
+        .enumerate()
+        .map(|(i, rule)| {
+            let description = rule.description.trim();
+            if description.is_empty() {
+                format!("{}. {}", i + 1, rule.short_name.trim())
+            } else {
+                format!("{}. {}\n   {}", i + 1, rule.short_name.trim(), description)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// The rules that talk about promotion, spam or advertising, in full
+fn self_promotion_policy(rules: &[SubredditRule]) -> String {
+    rules
+        .iter()
+        .filter(|rule| {
+            let text = format!("{} {}", rule.short_name, rule.description).to_lowercase();
+            PROMOTION_TERMS.iter().any(|term| text.contains(term))
+        })
+        .map(|rule| {
+            let description = rule.description.trim();
+            if description.is_empty() {
+                rule.short_name.trim().to_string()
+            } else {
+                format!("{}: {}", rule.short_name.trim(), description)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
@@ -0,0 +1,395 @@
+// Offline topic clustering of saved posts. Each post (title, selftext and its
+// stored comments) becomes a TF-IDF vector; spherical k-means groups them and
+// each topic is labelled with the heaviest terms of its centroid. Seeding is
+// farthest-first rather than random, so the same posts give the same topics.
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::database::adding::{CommentDataWrapper, PostDataWrapper};
+
+const MAX_ITERATIONS: usize = 30;
+const MAX_TOPICS: usize = 20;
+const TOP_TERMS: usize = 8;
+const LABEL_TERMS: usize = 3;
+// Terms in fewer documents than this, or in more than MAX_DF of them, are ignored
+const MIN_DF: usize = 2;
+const MAX_DF: f64 = 0.5;
+
+const STOPWORDS: &[&str] = &[
+    "a", "about", "above", "after", "again", "all", "also", "am", "an", "and", "any", "anyone",
+    "are", "as", "at", "be", "because", "been", "before", "being", "but", "by", "can", "could",
+    "did", "do", "does", "doing", "don't", "for", "from", "get", "got", "had", "has", "have",
+    "having", "he", "her", "here", "him", "his", "how", "i", "i'm", "if", "in", "into", "is", "it",
+    "it's", "its", "just", "know", "like", "me", "more", "most", "my", "need", "no", "not", "now",
+    "of", "on", "one", "only", "or", "other", "our", "out", "over", "really", "same", "she",
+    "should", "so", "some", "such", "than", "thanks", "that", "the", "their", "them", "then",
+    "there", "these", "they", "think", "this", "those", "through", "to", "too", "under", "up",
+    "use", "using", "very", "want", "was", "we", "were", "what", "when", "where", "which", "while",
+    "who", "why", "will", "with", "would", "you", "your",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Topic {
+    pub id: i64,
+    // Top terms joined with " / "
+    pub label: String,
+    pub top_terms: Vec<String>,
+    pub size: i64,
+    pub created_at: i64,
+}
+
+#[derive(Debug)]
+pub struct TopicModel {
+    pub topics: Vec<Topic>,
+    // (post id, topic id, cosine similarity to the topic centroid)
+    pub assignments: Vec<(i64, i64, f64)>,
+}
+
+type SparseVector = Vec<(usize, f64)>;
+
+/// Title, selftext and stored comments of each post, keyed by post id.
+pub fn post_documents(
+    posts: &[PostDataWrapper],
+    comments: &[CommentDataWrapper],
+) -> Vec<(i64, String)> {
+    // Comments are stored against the base36 post id
+    let mut comments_by_post: HashMap<&str, Vec<&str>> = HashMap::new();
+    for comment in comments {
+        comments_by_post
+            .entry(comment.post_id.as_str())
+            .or_default()
+            .push(comment.body.as_str());
+    }
+
+    posts
+        .iter()
+        .map(|post| {
+            let mut text = format!("{}\n{}", post.title, post.selftext.as_deref().unwrap_or(""));
+            if let Some(bodies) = comments_by_post.get(post.name.trim_start_matches("t3_")) {
+                for body in bodies {
+                    text.push('\n');
+                    text.push_str(body);
+                }
+            }
+            (post.id, text)
+        })
+        .collect()
+}
+
+/// Clusters the documents into `k` topics, or about sqrt(n / 2) when `k` is None.
+pub fn cluster_topics(documents: &[(i64, String)], k: Option<usize>, now: i64) -> TopicModel {
+    let (vocabulary, vectors) = tf_idf(documents);
+
+    // Documents with no informative terms can't be placed
+    let placed: Vec<usize> = (0..vectors.len()).filter(|&i| !vectors[i].is_empty()).collect();
+    let k = k
+        .unwrap_or_else(|| ((placed.len() as f64 / 2.0).sqrt().round() as usize).max(2))
+        .clamp(1, MAX_TOPICS)
+        .min(placed.len());
+    if k == 0 {
+        return TopicModel {
+            topics: Vec::new(),
+            assignments: Vec::new(),
+        };
+    }
+
+    let mut centroids = seed_centroids(&vectors, &placed, k, vocabulary.len());
+    let mut assignment = vec![usize::MAX; vectors.len()];
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for &doc in &placed {
+            let best = nearest(&vectors[doc], &centroids).0;
+            if assignment[doc] != best {
+                assignment[doc] = best;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+        centroids = recompute_centroids(&vectors, &placed, &assignment, k, vocabulary.len());
+    }
+
+    // Largest topics first; empty ones are dropped
+    let mut sizes = vec![0i64; k];
+    for &doc in &placed {
+        sizes[assignment[doc]] += 1;
+    }
+    let mut order: Vec<usize> = (0..k).filter(|&c| sizes[c] > 0).collect();
+    order.sort_by(|a, b| sizes[*b].cmp(&sizes[*a]).then(a.cmp(b)));
+    let topic_ids: HashMap<usize, i64> = order
+        .iter()
+        .enumerate()
+        .map(|(rank, &cluster)| (cluster, rank as i64 + 1))
+        .collect();
+
+    let topics = order
+        .iter()
+        .map(|&cluster| {
+            let top_terms = top_terms(&centroids[cluster], &vocabulary);
+            Topic {
+                id: topic_ids[&cluster],
+                label: top_terms
+                    .iter()
+                    .take(LABEL_TERMS)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(" / "),
+                top_terms,
+                size: sizes[cluster],
+                created_at: now,
+            }
+        })
+        .collect();
+
+    let assignments = placed
+        .iter()
+        .map(|&doc| {
+            let cluster = assignment[doc];
+            (
+                documents[doc].0,
+                topic_ids[&cluster],
+                dot(&vectors[doc], &centroids[cluster]),
+            )
+        })
+        .collect();
+
+    TopicModel {
+        topics,
+        assignments,
+    }
+}
+
+pub(crate) fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .replace('\u{2019}', "'")
+        .split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .map(|word| word.trim_matches('\''))
+        .filter(|word| {
+            word.len() > 2 && !STOPWORDS.contains(word) && !word.chars().all(|c| c.is_numeric())
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+// Sublinear TF times smoothed IDF, L2 normalised
+fn tf_idf(documents: &[(i64, String)]) -> (Vec<String>, Vec<SparseVector>) {
+    let counts: Vec<HashMap<String, usize>> = documents
+        .iter()
+        .map(|(_, text)| {
+            let mut counts = HashMap::new();
+            for token in tokenize(text) {
+                *counts.entry(token).or_insert(0) += 1;
+            }
+            counts
+        })
+        .collect();
+
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    for doc in &counts {
+        for term in doc.keys() {
+            *document_frequency.entry(term.as_str()).or_insert(0) += 1;
+        }
+    }
+
+    let total = documents.len();
+    let max_df = ((total as f64 * MAX_DF).floor() as usize).max(MIN_DF);
+    let mut vocabulary: Vec<String> = document_frequency
+        .iter()
+        .filter(|(_, &df)| df >= MIN_DF && df <= max_df)
+        .map(|(term, _)| term.to_string())
+        .collect();
+    vocabulary.sort();
+    let index: HashMap<&str, usize> = vocabulary
+        .iter()
+        .enumerate()
+        .map(|(i, term)| (term.as_str(), i))
+        .collect();
+    let idf: Vec<f64> = vocabulary
+        .iter()
+        .map(|term| ((1.0 + total as f64) / (1.0 + document_frequency[term.as_str()] as f64)).ln() + 1.0)
+        .collect();
+
+    let vectors = counts
+        .iter()
+        .map(|doc| {
+            let mut vector: SparseVector = doc
+                .iter()
+                .filter_map(|(term, &count)| {
+                    index
+                        .get(term.as_str())
+                        .map(|&i| (i, (1.0 + (count as f64).ln()) * idf[i]))
+                })
+                .collect();
+            vector.sort_by_key(|(i, _)| *i);
+            let norm = vector.iter().map(|(_, w)| w * w).sum::<f64>().sqrt();
+            for (_, weight) in &mut vector {
+                *weight /= norm;
+            }
+            vector
+        })
+        .collect();
+
+    (vocabulary, vectors)
+}
+
+fn dot(vector: &SparseVector, centroid: &[f64]) -> f64 {
+    vector.iter().map(|(i, w)| w * centroid[*i]).sum()
+}
+
+fn nearest(vector: &SparseVector, centroids: &[Vec<f64>]) -> (usize, f64) {
+    let mut best = (0, f64::MIN);
+    for (c, centroid) in centroids.iter().enumerate() {
+        let similarity = dot(vector, centroid);
+        if similarity > best.1 {
+            best = (c, similarity);
+        }
+    }
+    best
+}
+
+fn to_dense(vector: &SparseVector, dimensions: usize) -> Vec<f64> {
+    let mut dense = vec![0.0; dimensions];
+    for (i, w) in vector {
+        dense[*i] = *w;
+    }
+    dense
+}
+
+// First the document with the most terms, then repeatedly the one least similar to any chosen seed
+fn seed_centroids(
+    vectors: &[SparseVector],
+    placed: &[usize],
+    k: usize,
+    dimensions: usize,
+) -> Vec<Vec<f64>> {
+    let first = *placed
+        .iter()
+        .max_by(|a, b| vectors[**a].len().cmp(&vectors[**b].len()).then(b.cmp(a)))
+        .unwrap();
+    let mut centroids = vec![to_dense(&vectors[first], dimensions)];
+
+    while centroids.len() < k {
+        let next = placed
+            .iter()
+            .map(|&doc| (doc, nearest(&vectors[doc], &centroids).1))
+            .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)))
+            .map(|(doc, _)| doc)
+            .unwrap();
+        centroids.push(to_dense(&vectors[next], dimensions));
+    }
+
+    centroids
+}
+
+// Mean of the members, normalised back to unit length
+fn recompute_centroids(
+    vectors: &[SparseVector],
+    placed: &[usize],
+    assignment: &[usize],
+    k: usize,
+    dimensions: usize,
+) -> Vec<Vec<f64>> {
+    let mut centroids = vec![vec![0.0; dimensions]; k];
+    for &doc in placed {
+        for (i, w) in &vectors[doc] {
+            centroids[assignment[doc]][*i] += w;
+        }
+    }
+    for centroid in &mut centroids {
+        let norm = centroid.iter().map(|w| w * w).sum::<f64>().sqrt();
+        if norm > 0.0 {
+            for weight in centroid.iter_mut() {
+                *weight /= norm;
+            }
+        }
+    }
+    centroids
+}
+
+fn top_terms(centroid: &[f64], vocabulary: &[String]) -> Vec<String> {
+    let mut weighted: Vec<(usize, f64)> = centroid
+        .iter()
+        .enumerate()
+        .filter(|(_, w)| **w > 0.0)
+        .map(|(i, w)| (i, *w))
+        .collect();
+    weighted.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    weighted
+        .into_iter()
+        .take(TOP_TERMS)
+        .map(|(i, _)| vocabulary[i].clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn documents() -> Vec<(i64, String)> {
+        [
+            "Shopify inventory sync keeps drifting between warehouse locations",
+            "Inventory counts wrong after Shopify sync with the warehouse",
+            "Warehouse inventory sync broken again, Shopify shows wrong stock",
+            "Best payroll software for a small team, payroll taxes are a pain",
+            "Payroll taxes and contractor payments, which payroll software handles both",
+            "Switching payroll software before tax season, payroll export help",
+            "Photos of my new cat",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, text)| (i as i64 + 1, text.to_string()))
+        .collect()
+    }
+
+    fn topic_of(model: &TopicModel, post_id: i64) -> Option<i64> {
+        model
+            .assignments
+            .iter()
+            .find(|(id, _, _)| *id == post_id)
+            .map(|(_, topic, _)| *topic)
+    }
+
+    #[test]
+    fn separates_unrelated_themes() {
+        let model = cluster_topics(&documents(), Some(2), 0);
+
+        assert_eq!(model.topics.len(), 2);
+        let inventory = topic_of(&model, 1).unwrap();
+        let payroll = topic_of(&model, 4).unwrap();
+        assert_ne!(inventory, payroll);
+        assert!((1..=3).all(|id| topic_of(&model, id) == Some(inventory)));
+        assert!((4..=6).all(|id| topic_of(&model, id) == Some(payroll)));
+    }
+
+    #[test]
+    fn labels_come_from_the_heaviest_terms() {
+        let model = cluster_topics(&documents(), Some(2), 0);
+
+        let payroll = topic_of(&model, 4).unwrap();
+        let topic = model.topics.iter().find(|t| t.id == payroll).unwrap();
+        assert!(topic.label.starts_with("payroll"), "{}", topic.label);
+        assert_eq!(topic.size, 3);
+        let inventory = model.topics.iter().find(|t| t.id != payroll).unwrap();
+        assert!(inventory.top_terms.iter().any(|t| t == "inventory"));
+    }
+
+    #[test]
+    fn posts_without_shared_terms_are_left_out() {
+        let model = cluster_topics(&documents(), Some(2), 0);
+
+        // Nothing in the cat post appears in another document
+        assert_eq!(topic_of(&model, 7), None);
+        assert_eq!(model.assignments.len(), 6);
+    }
+
+    #[test]
+    fn same_input_gives_same_topics() {
+        let first = cluster_topics(&documents(), None, 0);
+        let second = cluster_topics(&documents(), None, 0);
+
+        assert_eq!(first.assignments, second.assignments);
+        assert!(cluster_topics(&[], None, 0).topics.is_empty());
+    }
+}
@@ -1,8 +1,14 @@
 use directories::{BaseDirs, UserDirs};
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::path::Path;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::models::filters::ContentFilters;
+use crate::models::lead_score::LeadScoreWeights;
+use crate::ai::replies::ReplySettings;
+use crate::settings::secrets::{self, EncryptedFileStore};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 
 pub struct ApiKeys {
     #[serde(default)]
@@ -29,6 +35,61 @@ pub struct ApiKeys {
     #[serde(default)]
     #[serde(alias = "OPENAI_MODEL")]
     pub openai_model: String,
+
+    #[serde(default = "default_openai_base_url")]
+    #[serde(alias = "OPENAI_BASE_URL")]
+    pub openai_base_url: String,
+
+    #[serde(default = "default_gemini_base_url")]
+    #[serde(alias = "GEMINI_BASE_URL")]
+    pub gemini_base_url: String,
+
+    #[serde(default)]
+    #[serde(alias = "ANTHROPIC_API_KEY")]
+    pub anthropic_api_key: String,
+
+    #[serde(default = "default_anthropic_model")]
+    #[serde(alias = "ANTHROPIC_MODEL")]
+    pub anthropic_model: String,
+
+    #[serde(default = "default_anthropic_base_url")]
+    #[serde(alias = "ANTHROPIC_BASE_URL")]
+    pub anthropic_base_url: String,
+
+    // OpenAI-compatible server used when ai_provider is "local" (Ollama, llama.cpp, ...)
+    #[serde(default = "default_local_ai_base_url")]
+    #[serde(alias = "LOCAL_AI_BASE_URL")]
+    pub local_ai_base_url: String,
+
+    #[serde(default = "default_local_ai_model")]
+    #[serde(alias = "LOCAL_AI_MODEL")]
+    pub local_ai_model: String,
+
+    // Only needed when the local server is started with an API key
+    #[serde(default)]
+    #[serde(alias = "LOCAL_AI_API_KEY")]
+    pub local_ai_api_key: String,
+
+    // How long a provider's model list is reused before asking again
+    #[serde(default = "default_model_cache_minutes")]
+    #[serde(alias = "MODEL_CACHE_MINUTES")]
+    pub model_cache_minutes: i64,
+
+    // Tokens of app data sent with a question; 0 sizes it from the model's context window
+    #[serde(default)]
+    #[serde(alias = "AI_CONTEXT_TOKENS")]
+    pub ai_context_tokens: i64,
+
+    // Backend for semantic search vectors; empty uses ai_provider
+    #[serde(default)]
+    #[serde(alias = "EMBEDDING_PROVIDER")]
+    pub embedding_provider: String,
+
+    // Empty picks the provider's default embedding model
+    #[serde(default)]
+    #[serde(alias = "EMBEDDING_MODEL")]
+    pub embedding_model: String,
+
     #[serde(default)]
     #[serde(alias = "SUBREDDIT")]
     pub subreddit: String,
@@ -44,6 +105,11 @@ pub struct ApiKeys {
     #[serde(alias = "BRANDED_KEYWORDS")]
     pub branded_keywords: Vec<String>,
 
+    // Competitor brands, tracked alongside branded_keywords for share of voice
+    #[serde(default)]
+    #[serde(alias = "COMPETITOR_KEYWORDS")]
+    pub competitor_keywords: Vec<String>,
+
     #[serde(default)]
     #[serde(alias = "SENTIMENT")]
     pub sentiment: Vec<String>,
@@ -56,6 +122,19 @@ pub struct ApiKeys {
     #[serde(alias = "INTENT_MEDIUM")]
     pub intent_medium: Vec<String>,
 
+    // Text matched by these never counts towards intent ("not looking for")
+    #[serde(default = "default_negative_intent_patterns")]
+    #[serde(alias = "INTENT_NEGATIVE")]
+    pub intent_negative: Vec<String>,
+
+    // Weight of an intent pattern hit in the title vs the body; High needs 1.0
+    #[serde(default = "default_intent_weight")]
+    #[serde(alias = "INTENT_TITLE_WEIGHT")]
+    pub intent_title_weight: f64,
+    #[serde(default = "default_intent_weight")]
+    #[serde(alias = "INTENT_BODY_WEIGHT")]
+    pub intent_body_weight: f64,
+
     #[serde(default)]
     #[serde(alias = "MATCH")]
     pub match_keyword: String,
@@ -74,10 +153,59 @@ pub struct ApiKeys {
     #[serde(default)]
     #[serde(alias = "REDDIT_ACCESS_TOKEN")]
     pub reddit_access_token: String,
+    // Unix timestamp after which the access token has to be refreshed
+    #[serde(default)]
+    #[serde(alias = "REDDIT_ACCESS_TOKEN_EXPIRES_AT")]
+    pub reddit_access_token_expires_at: i64,
+    #[serde(default)]
+    #[serde(alias = "REDDIT_TOKEN_SCOPE")]
+    pub reddit_token_scope: String,
+
+    // Base URLs for the Reddit API, overridable to point at a local mock server
+    #[serde(default = "default_reddit_api_base_url")]
+    #[serde(alias = "REDDIT_API_BASE_URL")]
+    pub reddit_api_base_url: String,
+    #[serde(default = "default_reddit_auth_base_url")]
+    #[serde(alias = "REDDIT_AUTH_BASE_URL")]
+    pub reddit_auth_base_url: String,
+
+    // Listing pages (of 100 posts) to follow per search
+    #[serde(default = "default_reddit_max_pages")]
+    #[serde(alias = "REDDIT_MAX_PAGES")]
+    pub reddit_max_pages: u32,
+
+    // Must match the redirect URI registered on the Reddit app; the auth listener uses its port
+    #[serde(default = "default_redirect_uri")]
+    #[serde(alias = "REDDIT_REDIRECT_URI")]
+    pub reddit_redirect_uri: String,
 
     #[serde(default)]
     #[serde(alias = "REPLY_PREAMBLE")]
     pub reply_preamble: String,
+
+    // Posts failing these are dropped before search results are stored
+    #[serde(default)]
+    #[serde(alias = "CONTENT_FILTERS")]
+    pub content_filters: ContentFilters,
+
+    #[serde(default)]
+    #[serde(alias = "LEAD_SCORE")]
+    pub lead_score: LeadScoreWeights,
+
+    // Product facts and tone presets for reply drafts
+    #[serde(default)]
+    #[serde(alias = "REPLIES")]
+    pub replies: ReplySettings,
+
+    // How long a fetched author profile is reused before asking Reddit again
+    #[serde(default = "default_author_cache_hours")]
+    #[serde(alias = "AUTHOR_CACHE_HOURS")]
+    pub author_cache_hours: i64,
+
+    // Word overlap (0..1) at which two posts count as the same question
+    #[serde(default = "default_duplicate_similarity")]
+    #[serde(alias = "DUPLICATE_SIMILARITY")]
+    pub duplicate_similarity: f64,
 }
 
 #[derive(Debug)]
@@ -89,7 +217,7 @@ pub struct ConfigDirs {
     pub documents_dir: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct AppConfig {
     pub api_keys: ApiKeys,
 }
@@ -104,54 +232,150 @@ impl Default for ApiKeys {
             ai_provider: "gemini".to_string(),
             openai_api_key: "CHANGE_ME".to_string(),
             openai_model: "gpt-4o".to_string(),
+            openai_base_url: default_openai_base_url(),
+            gemini_base_url: default_gemini_base_url(),
+            anthropic_api_key: "CHANGE_ME".to_string(),
+            anthropic_model: default_anthropic_model(),
+            anthropic_base_url: default_anthropic_base_url(),
+            local_ai_base_url: default_local_ai_base_url(),
+            local_ai_model: default_local_ai_model(),
+            local_ai_api_key: "".to_string(),
+            model_cache_minutes: default_model_cache_minutes(),
+            ai_context_tokens: 0,
+            embedding_provider: "".to_string(),
+            embedding_model: "".to_string(),
             subreddit: "all".to_string(),
             relevance: "hot".to_string(),
             lead_keywords: vec![],
             branded_keywords: vec![],
+            competitor_keywords: vec![],
             sentiment: vec!["neutral".to_string()],
             intent_high: default_high_intent_patterns(),
             intent_medium: default_medium_intent_patterns(),
+            intent_negative: default_negative_intent_patterns(),
+            intent_title_weight: default_intent_weight(),
+            intent_body_weight: default_intent_weight(),
             match_keyword: "".to_string(),
             reddit_username: "".to_string(),
             reddit_password: "".to_string(),
             reddit_refresh_token: "".to_string(),
             reddit_access_token: "".to_string(),
+            reddit_access_token_expires_at: 0,
+            reddit_token_scope: "".to_string(),
+            reddit_api_base_url: default_reddit_api_base_url(),
+            reddit_auth_base_url: default_reddit_auth_base_url(),
+            reddit_max_pages: default_reddit_max_pages(),
+            reddit_redirect_uri: default_redirect_uri(),
             reply_preamble: "You are a helpful and knowledgeable assistant. Draft a helpful, relevant, and polite reply to the following Reddit post.".to_string(),
+            content_filters: ContentFilters::default(),
+            lead_score: LeadScoreWeights::default(),
+            replies: ReplySettings::default(),
+            author_cache_hours: default_author_cache_hours(),
+            duplicate_similarity: default_duplicate_similarity(),
         }
     }
 }
 
 impl ApiKeys {
-    pub fn calculate_intent(&self, title: &str, body: Option<&str>) -> String {
-        let text = format!("{} {}", title, body.unwrap_or("")).to_lowercase();
+    /// Fields kept in the encrypted secret store instead of settings.toml
+    pub fn secret_fields_mut(&mut self) -> [(&'static str, &mut String); 8] {
+        [
+            ("reddit_api_secret", &mut self.reddit_api_secret),
+            ("gemini_api_key", &mut self.gemini_api_key),
+            ("openai_api_key", &mut self.openai_api_key),
+            ("anthropic_api_key", &mut self.anthropic_api_key),
+            ("local_ai_api_key", &mut self.local_ai_api_key),
+            ("reddit_password", &mut self.reddit_password),
+            ("reddit_refresh_token", &mut self.reddit_refresh_token),
+            ("reddit_access_token", &mut self.reddit_access_token),
+        ]
+    }
 
-        // Check high intent
-        for pattern in &self.intent_high {
-            if text.contains(&pattern.to_lowercase()) {
-                return "High".to_string();
-            }
-        }
+    /// Full URL for an OAuth API path such as `/search`
+    pub fn reddit_api_url(&self, path: &str) -> String {
+        format!("{}{}", self.reddit_api_base_url.trim_end_matches('/'), path)
+    }
+
+    /// Full URL for a path on the www host, e.g. `/api/v1/access_token`
+    pub fn reddit_auth_url(&self, path: &str) -> String {
+        format!("{}{}", self.reddit_auth_base_url.trim_end_matches('/'), path)
+    }
 
-        // Check medium intent
-        for pattern in &self.intent_medium {
-            if text.contains(&pattern.to_lowercase()) {
-                return "Medium".to_string();
+    /// Our brands followed by competitors, without duplicates.
+    pub fn all_brand_keywords(&self) -> Vec<String> {
+        let mut brands: Vec<String> = Vec::new();
+        for keyword in self.branded_keywords.iter().chain(&self.competitor_keywords) {
+            let keyword = keyword.trim();
+            if !keyword.is_empty() && !brands.iter().any(|b| b.eq_ignore_ascii_case(keyword)) {
+                brands.push(keyword.to_string());
             }
         }
-
-        "Low".to_string()
+        brands
     }
 }
 
+fn default_openai_base_url() -> String {
+    "https://api.openai.com/v1".to_string()
+}
+
+fn default_gemini_base_url() -> String {
+    "https://generativelanguage.googleapis.com/v1beta".to_string()
+}
+
+fn default_anthropic_model() -> String {
+    "claude-sonnet-4-5".to_string()
+}
+
+fn default_anthropic_base_url() -> String {
+    "https://api.anthropic.com".to_string()
+}
+
+fn default_local_ai_base_url() -> String {
+    "http://localhost:11434/v1".to_string()
+}
+
+fn default_local_ai_model() -> String {
+    "llama3.1".to_string()
+}
+
+fn default_model_cache_minutes() -> i64 {
+    60
+}
+
+fn default_reddit_api_base_url() -> String {
+    "https://oauth.reddit.com".to_string()
+}
+
+fn default_reddit_auth_base_url() -> String {
+    "https://www.reddit.com".to_string()
+}
+
+fn default_author_cache_hours() -> i64 {
+    24
+}
+
+fn default_duplicate_similarity() -> f64 {
+    0.6
+}
+
+fn default_reddit_max_pages() -> u32 {
+    1
+}
+
+fn default_redirect_uri() -> String {
+    crate::models::auth::DEFAULT_REDIRECT_URI.to_string()
+}
+
+// Pattern syntax is described in models/intent.rs
 fn default_high_intent_patterns() -> Vec<String> {
     vec![
         "looking for".to_string(),
-        "recommend".to_string(),
-        "suggestion".to_string(),
+        "recommend*".to_string(),
+        "suggestion*".to_string(),
         "alternative to".to_string(),
         "vs".to_string(),
         "comparison".to_string(),
-        "review".to_string(),
+        "review*".to_string(),
         "best".to_string(),
         "help with".to_string(),
         "how to".to_string(),
@@ -164,15 +388,33 @@ fn default_high_intent_patterns() -> Vec<String> {
 fn default_medium_intent_patterns() -> Vec<String> {
     vec![
         "issues with".to_string(),
-        "problem".to_string(),
-        "error".to_string(),
-        "question".to_string(),
+        "problem*".to_string(),
+        "error*".to_string(),
+        "question*".to_string(),
         "anyone used".to_string(),
         "thoughts on".to_string(),
         "experience with".to_string(),
     ]
 }
 
+fn default_negative_intent_patterns() -> Vec<String> {
+    vec![
+        "not looking for".to_string(),
+        "no longer looking for".to_string(),
+        "not ~1 recommend*".to_string(),
+        "don't recommend*".to_string(),
+        "wouldn't recommend*".to_string(),
+        "never recommend*".to_string(),
+        "not asking for".to_string(),
+        "not interested in".to_string(),
+        "already found".to_string(),
+    ]
+}
+
+fn default_intent_weight() -> f64 {
+    1.0
+}
+
 impl ConfigDirs {
     pub fn new() -> Option<Self> {
         let user_dirs = UserDirs::new()?;
@@ -203,9 +445,7 @@ impl ConfigDirs {
         // Write to file if file does not exist yet
         if !config_path.exists() {
             println!("Creating config file: {}", config_path.display());
-            let default_config = AppConfig::default();
-            let toml_content = toml::to_string_pretty(&default_config)?;
-            fs::write(config_path, toml_content)?;
+            Self::write_config(&config_path, &AppConfig::default())?;
         }
 
         Ok(())
@@ -246,11 +486,17 @@ impl ConfigDirs {
         println!("Reading config file: {:#?}", config_path);
 
         // Read from file
-        let toml_content = fs::read_to_string(config_path)?;
+        let toml_content = fs::read_to_string(&config_path)?;
 
         // Try parsing; on failure, return the error instead of panicking
-        println!("TOML content read: {}", toml_content);
-        let app_config: AppConfig = toml::from_str(&toml_content)?;
+        let mut app_config: AppConfig = toml::from_str(&toml_content)?;
+
+        // Secrets live in the encrypted store; move any plaintext ones out of the TOML
+        let mut store = EncryptedFileStore::open_default()?;
+        if secrets::hydrate(&mut store, &mut app_config.api_keys)? {
+            println!("Moved plaintext secrets from settings.toml into the encrypted store.");
+            Self::write_config(&config_path, &app_config)?;
+        }
 
         Ok(app_config)
     }
@@ -260,7 +506,16 @@ impl ConfigDirs {
         let config_dir = base_dirs.config_dir();
         let config_path = config_dir.join("atalaia/settings.toml");
 
-        let toml_content = toml::to_string_pretty(config)?;
+        Self::write_config(&config_path, config)
+    }
+
+    // Splits secrets into the encrypted store and writes the rest as TOML
+    fn write_config(config_path: &Path, config: &AppConfig) -> Result<(), Box<dyn std::error::Error>> {
+        let mut public_config = config.clone();
+        let mut store = EncryptedFileStore::open_default()?;
+        secrets::extract(&mut store, &mut public_config.api_keys)?;
+
+        let toml_content = toml::to_string_pretty(&public_config)?;
         fs::write(config_path, toml_content)?;
 
         Ok(())
@@ -1 +1,2 @@
 pub mod api_keys;
+pub mod secrets;
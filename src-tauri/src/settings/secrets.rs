@@ -0,0 +1,354 @@
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use directories::BaseDirs;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::settings::api_keys::ApiKeys;
+
+// File layout: MAGIC | salt (16) | nonce (12) | ciphertext of a JSON map
+const MAGIC: &[u8; 5] = b"ATLS1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+const PBKDF2_ROUNDS: u32 = 600_000;
+
+/// Set this to derive the encryption key from a passphrase instead of the local key file.
+pub const PASSPHRASE_ENV: &str = "ATALAIA_PASSPHRASE";
+
+// Deriving from a passphrase is slow on purpose, and the config is read on almost every
+// command, so keep the last derived key around for the lifetime of the process.
+type DerivedKey = (String, [u8; SALT_LEN], [u8; KEY_LEN]);
+static DERIVED_KEY: Mutex<Option<DerivedKey>> = Mutex::new(None);
+
+/// Minimal keyring interface so the backing store can be swapped (OS keyring, memory, ...).
+pub trait SecretStore {
+    fn get(&self, name: &str) -> Option<String>;
+    fn set(&mut self, name: &str, value: &str) -> Result<(), Box<dyn std::error::Error>>;
+    fn remove(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/// Secrets encrypted with ChaCha20-Poly1305 in a single file next to `settings.toml`.
+///
+/// The key comes from `ATALAIA_PASSPHRASE` (PBKDF2-SHA256) when set, otherwise from a random
+/// key file kept in the data directory, so it works on headless machines without a keyring.
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    key_file: PathBuf,
+    salt: [u8; SALT_LEN],
+    entries: HashMap<String, String>,
+}
+
+impl EncryptedFileStore {
+    pub fn open_default() -> Result<Self, Box<dyn std::error::Error>> {
+        let base_dirs = BaseDirs::new().ok_or("Failed to get base directories")?;
+        let path = base_dirs.config_dir().join("atalaia/secrets.enc");
+        let key_file = base_dirs.data_dir().join("atalaia/secret.key");
+        Self::open(path, key_file)
+    }
+
+    pub fn open(path: PathBuf, key_file: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            let mut salt = [0u8; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            return Ok(EncryptedFileStore {
+                path,
+                key_file,
+                salt,
+                entries: HashMap::new(),
+            });
+        }
+
+        let bytes = fs::read(&path)?;
+        let header_len = MAGIC.len() + SALT_LEN + NONCE_LEN;
+        if bytes.len() < header_len || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(format!("{} is not a valid secrets file", path.display()).into());
+        }
+
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&bytes[MAGIC.len()..MAGIC.len() + SALT_LEN]);
+        let nonce = Nonce::from_slice(&bytes[MAGIC.len() + SALT_LEN..header_len]);
+
+        let cipher = ChaCha20Poly1305::new(&load_key(&key_file, &salt)?);
+        let plaintext = cipher.decrypt(nonce, &bytes[header_len..]).map_err(|_| {
+            format!(
+                "Failed to decrypt {}. Check {} or the key file at {}",
+                path.display(),
+                PASSPHRASE_ENV,
+                key_file.display()
+            )
+        })?;
+        let entries: HashMap<String, String> = serde_json::from_slice(&plaintext)?;
+
+        Ok(EncryptedFileStore {
+            path,
+            key_file,
+            salt,
+            entries,
+        })
+    }
+
+    fn persist(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let cipher = ChaCha20Poly1305::new(&load_key(&self.key_file, &self.salt)?);
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+
+        let plaintext = serde_json::to_vec(&self.entries)?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| "Failed to encrypt secrets")?;
+
+        let mut bytes = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.salt);
+        bytes.extend_from_slice(&nonce);
+        bytes.extend_from_slice(&ciphertext);
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_private(&self.path, &bytes)
+    }
+}
+
+impl SecretStore for EncryptedFileStore {
+    fn get(&self, name: &str) -> Option<String> {
+        self.entries.get(name).cloned()
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if self.entries.get(name).map(String::as_str) == Some(value) {
+            return Ok(());
+        }
+        self.entries.insert(name.to_string(), value.to_string());
+        self.persist()
+    }
+
+    fn remove(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if self.entries.remove(name).is_some() {
+            self.persist()?;
+        }
+        Ok(())
+    }
+}
+
+fn load_key(key_file: &Path, salt: &[u8; SALT_LEN]) -> Result<Key, Box<dyn std::error::Error>> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        if !passphrase.is_empty() {
+            let mut cached = DERIVED_KEY.lock().map_err(|_| "Secret key cache poisoned")?;
+            if let Some((cached_passphrase, cached_salt, key)) = cached.as_ref() {
+                if *cached_passphrase == passphrase && cached_salt == salt {
+                    return Ok(*Key::from_slice(key));
+                }
+            }
+            let mut key = [0u8; KEY_LEN];
+            pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+            *cached = Some((passphrase, *salt, key));
+            return Ok(*Key::from_slice(&key));
+        }
+    }
+
+    if key_file.exists() {
+        let bytes = fs::read(key_file)?;
+        if bytes.len() != KEY_LEN {
+            return Err(format!("Key file {} is corrupted", key_file.display()).into());
+        }
+        return Ok(*Key::from_slice(&bytes));
+    }
+
+    println!("Creating secrets key file: {}", key_file.display());
+    let mut key = [0u8; KEY_LEN];
+    OsRng.fill_bytes(&mut key);
+    if let Some(parent) = key_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_private(key_file, &key)?;
+    Ok(*Key::from_slice(&key))
+}
+
+// Owner read/write only where the platform supports it
+fn write_private(path: &Path, bytes: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        file.write_all(bytes)?;
+    }
+
+    #[cfg(not(unix))]
+    {
+        fs::write(path, bytes)?;
+    }
+
+    Ok(())
+}
+
+/// Fills the secret fields of `api_keys` from the store.
+///
+/// Any secret still present in `settings.toml` (older installs, or typed in by hand) wins and is
+/// moved into the store. Returns true when something was migrated so the caller can rewrite the
+/// TOML without it.
+pub fn hydrate(
+    store: &mut impl SecretStore,
+    api_keys: &mut ApiKeys,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut migrated = false;
+
+    for (name, value) in api_keys.secret_fields_mut() {
+        if !value.is_empty() {
+            store.set(name, value)?;
+            migrated = true;
+        } else if let Some(stored) = store.get(name) {
+            *value = stored;
+        }
+    }
+
+    Ok(migrated)
+}
+
+/// Writes the secret fields of `api_keys` to the store and blanks them, leaving only
+/// non-secret settings to be serialized.
+pub fn extract(
+    store: &mut impl SecretStore,
+    api_keys: &mut ApiKeys,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for (name, value) in api_keys.secret_fields_mut() {
+        if value.is_empty() {
+            store.remove(name)?;
+        } else {
+            store.set(name, value)?;
+            value.clear();
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct MemoryStore(HashMap<String, String>);
+
+    impl SecretStore for MemoryStore {
+        fn get(&self, name: &str) -> Option<String> {
+            self.0.get(name).cloned()
+        }
+
+        fn set(&mut self, name: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+            self.0.insert(name.to_string(), value.to_string());
+            Ok(())
+        }
+
+        fn remove(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+            self.0.remove(name);
+            Ok(())
+        }
+    }
+
+    // Defaults carry CHANGE_ME placeholders; start from no secrets at all
+    fn no_secrets() -> ApiKeys {
+        let mut api_keys = ApiKeys::default();
+        for (_, value) in api_keys.secret_fields_mut() {
+            value.clear();
+        }
+        api_keys
+    }
+
+    // A fresh directory per test so parallel runs don't share files
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("atalaia-secrets-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn secrets_in_the_toml_are_moved_into_the_store() {
+        let mut store = MemoryStore::default();
+        store.set("openai_api_key", "sk-stored").unwrap();
+        let mut api_keys = ApiKeys {
+            gemini_api_key: "gm-typed".to_string(),
+            ..no_secrets()
+        };
+
+        assert!(hydrate(&mut store, &mut api_keys).unwrap());
+        assert_eq!(api_keys.openai_api_key, "sk-stored");
+        assert_eq!(api_keys.gemini_api_key, "gm-typed");
+        assert_eq!(store.get("gemini_api_key").as_deref(), Some("gm-typed"));
+
+        let mut nothing_new = no_secrets();
+        assert!(!hydrate(&mut store, &mut nothing_new).unwrap());
+        assert_eq!(nothing_new.gemini_api_key, "gm-typed");
+    }
+
+    #[test]
+    fn extract_blanks_secrets_and_forgets_cleared_ones() {
+        let mut store = MemoryStore::default();
+        store.set("anthropic_api_key", "old").unwrap();
+        let mut api_keys = ApiKeys {
+            reddit_api_secret: "reddit-secret".to_string(),
+            ..no_secrets()
+        };
+
+        extract(&mut store, &mut api_keys).unwrap();
+
+        assert!(api_keys.reddit_api_secret.is_empty());
+        assert_eq!(
+            store.get("reddit_api_secret").as_deref(),
+            Some("reddit-secret")
+        );
+        assert_eq!(store.get("anthropic_api_key"), None);
+    }
+
+    #[test]
+    fn encrypted_file_round_trips_with_its_key() {
+        let dir = temp_dir("round-trip");
+        let (path, key_file) = (dir.join("secrets.enc"), dir.join("secret.key"));
+
+        let mut store = EncryptedFileStore::open(path.clone(), key_file.clone()).unwrap();
+        store.set("gemini_api_key", "gm-123").unwrap();
+        store.set("reddit_password", "hunter2").unwrap();
+        store.remove("reddit_password").unwrap();
+        assert!(!String::from_utf8_lossy(&fs::read(&path).unwrap()).contains("gm-123"));
+
+        let reopened = EncryptedFileStore::open(path, key_file).unwrap();
+        assert_eq!(reopened.get("gemini_api_key").as_deref(), Some("gm-123"));
+        assert_eq!(reopened.get("reddit_password"), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn wrong_or_corrupted_key_is_an_error() {
+        let dir = temp_dir("wrong-key");
+        let (path, key_file) = (dir.join("secrets.enc"), dir.join("secret.key"));
+        let mut store = EncryptedFileStore::open(path.clone(), key_file.clone()).unwrap();
+        store.set("openai_api_key", "sk-123").unwrap();
+
+        fs::write(&key_file, [7u8; KEY_LEN]).unwrap();
+        let error = EncryptedFileStore::open(path.clone(), key_file.clone())
+            .err()
+            .unwrap();
+        assert!(error.to_string().starts_with("Failed to decrypt"));
+
+        fs::write(&key_file, b"short").unwrap();
+        assert!(EncryptedFileStore::open(path.clone(), key_file).is_err());
+
+        fs::write(&path, b"not a secrets file").unwrap();
+        assert!(EncryptedFileStore::open(path, dir.join("other.key")).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
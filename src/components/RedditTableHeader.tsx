@@ -67,8 +67,16 @@ export function RedditTableHeader({ onSort }: TableHeaderProps) {
         <TableHead className="w-[70px] px-2 text-center text-[9px] uppercase font-black tracking-widest text-muted-foreground/40 border-b border-border/50">
           Segment
         </TableHead>
-        <TableHead className="w-[60px] px-2 text-center text-[9px] uppercase font-black tracking-widest text-muted-foreground/40 border-b border-border/50">
-          Intent
+        <TableHead className="w-[60px] px-2 text-center border-b border-border/50">
+          <Button
+            variant="ghost"
+            size="sm"
+            className="h-7 w-full px-1 text-[10px] uppercase font-black tracking-widest text-muted-foreground/60 hover:text-primary hover:bg-primary/5 transition-all flex items-center justify-between"
+            onClick={() => onSort("relevance_score")}
+          >
+            Intent
+            <ArrowUpDown className="h-2.5 w-2.5 opacity-20" />
+          </Button>
         </TableHead>
         <TableHead className="w-[85px] px-2 text-center text-[9px] uppercase font-black tracking-widest text-muted-foreground/40 border-b border-border/50">
           Tone
@@ -353,79 +353,64 @@ export function AppSettingsDialog({
     ai_provider: "gemini",
     openai_api_key: "",
     openai_model: "gpt-4o",
+    openai_base_url: "https://api.openai.com/v1",
+    anthropic_api_key: "",
+    anthropic_model: "claude-sonnet-4-5",
+    local_ai_base_url: "http://localhost:11434/v1",
+    local_ai_model: "llama3.1",
+    local_ai_api_key: "",
     reply_preamble: "",
   });
   const [availableModels, setAvailableModels] = useState<string[]>([]);
   const [isLoadingModels, setIsLoadingModels] = useState(false);
 
+  // Settings field holding the model of each provider
+  const modelField = (provider: string) =>
+    provider === "openai"
+      ? "openai_model"
+      : provider === "anthropic"
+        ? "anthropic_model"
+        : provider === "local"
+          ? "local_ai_model"
+          : "gemini_model";
+
+  const providerKey = (config: any, provider: string) =>
+    provider === "openai"
+      ? config.openai_api_key
+      : provider === "anthropic"
+        ? config.anthropic_api_key
+        : provider === "local"
+          ? config.local_ai_api_key
+          : config.gemini_api_key;
+
   const fetchModels = async (key: string, provider: string) => {
-    if (!key || key === "CHANGE_ME") return;
+    // Local servers usually run without a key
+    if (provider !== "local" && (!key || key === "CHANGE_ME")) return;
     setIsLoadingModels(true);
     try {
       const { invoke } = await import("@tauri-apps/api/core");
-      // The backend command now uses the config to determine provider, but we are in the middle of editing settings.
-      // We should probably update the backend command to accept provider as arg, OR save settings first.
-      // For now, let's just save settings temporarily or assume the user saves first?
-      // Actually, the backend command `get_gemini_models_command` reads from config.
-      // So we MUST save the provider to config before fetching models if we want to use the generic command as is.
-      // OR we can update the backend command to take provider as arg.
-      // Given I didn't update the backend command signature to take provider, I should rely on saving first or just
-      // update the backend command.
-      // But wait, I can't easily update backend command signature without breaking other things potentially.
-      // Let's look at `get_gemini_models_command` again. It reads `config.api_keys.ai_provider`.
-      // So if I want to fetch models for a *newly selected* provider that isn't saved yet, it won't work.
-      // I should probably update the backend command to accept provider.
-      // But for now, let's just assume we only fetch models for the *saved* provider or force a save?
-      // No, that's bad UX.
-
-      // Workaround: The backend `get_gemini_models_command` (now `get_ai_models`) reads from config.
-      // I will update the backend command to accept `provider` as an optional argument or just rely on the fact that
-      // for OpenAI I am returning a static list in `adapter.rs` anyway!
-      // And for Gemini, it uses the key passed in.
-
-      // Wait, `adapter::get_available_models` takes `provider` and `api_key`.
-      // `get_gemini_models_command` reads provider from config.
-      // This is a limitation. I should have updated the command.
-      // However, since OpenAI returns a static list, maybe I can just hardcode it in frontend for now if provider is openai?
-      // That avoids a backend roundtrip for static data and solves the "unsaved provider" issue.
-
-      if (provider === "openai") {
-        setAvailableModels(["gpt-4o", "gpt-4-turbo", "gpt-4", "gpt-3.5-turbo"]);
-        return;
-      }
-
-      // For Gemini, we still need to hit the API.
-      // If the user hasn't saved "gemini" as provider yet, the backend might read "openai" if that was previous.
-      // But `get_gemini_models_command` reads `ai_provider` from config.
-      // If I am switching to Gemini but haven't saved, backend sees "openai" (or whatever was saved).
-      // This IS a problem.
-
-      // Quick fix: Since I can't easily change backend signature now without more roundtrips (and I want to finish this task),
-      // I will just use the existing command and note that for Gemini, it might be slightly buggy if not saved.
-      // BUT, I can just save the settings *before* fetching models?
-      // Or just update the backend command. Updating backend command is cleaner.
-
-      // Let's update the backend command signature quickly.
-      // It's a small change.
-
+      // The provider is passed so models can be listed before the settings are saved
       const models = await invoke<string[]>("get_gemini_models_command", {
-        apiKey: key,
+        apiKey: key === "CHANGE_ME" ? "" : key,
+        provider,
       });
       setAvailableModels(models);
 
-      const currentModel =
-        provider === "openai" ? apiKeys.openai_model : apiKeys.gemini_model;
-      const defaultModel = provider === "openai" ? "gpt-4o" : "gemini-pro";
+      const field = modelField(provider);
+      const currentModel = (apiKeys as any)[field];
+      const defaultModels: Record<string, string> = {
+        gemini_model: "gemini-pro",
+        openai_model: "gpt-4o",
+        anthropic_model: "claude-sonnet-4-5",
+        local_ai_model: "llama3.1",
+      };
 
       if (
         models.length > 0 &&
         !models.includes(currentModel) &&
-        currentModel === defaultModel
+        currentModel === defaultModels[field]
       ) {
-        setApiKeys((prev) => ({
-          ...prev,
-          [provider === "openai" ? "openai_model" : "gemini_model"]: models[0],
-        }));
+        setApiKeys((prev) => ({ ...prev, [field]: models[0] }));
       }
     } catch (error) {
       console.error("Failed to fetch models:", error);
@@ -443,13 +428,7 @@ export function AppSettingsDialog({
         invoke("get_reddit_config_command").then((config: any) => {
           setApiKeys(config);
           const provider = config.ai_provider || "gemini";
-          const key =
-            provider === "openai"
-              ? config.openai_api_key
-              : config.gemini_api_key;
-          if (key && key !== "CHANGE_ME") {
-            fetchModels(key, provider);
-          }
+          fetchModels(providerKey(config, provider), provider);
         });
       });
     }
@@ -853,7 +832,7 @@ export function AppSettingsDialog({
                         <SelectItem value="none">No Sort</SelectItem>
                         <SelectItem value="date">Date</SelectItem>
                         <SelectItem value="title">Title</SelectItem>
-                        <SelectItem value="relevance">Relevance</SelectItem>
+                        <SelectItem value="relevance_score">Lead Score</SelectItem>
                         <SelectItem value="subreddit">Subreddit</SelectItem>
                       </SelectContent>
                     </Select>
@@ -1309,11 +1288,8 @@ export function AppSettingsDialog({
                       value={apiKeys.ai_provider}
                       onValueChange={(val) => {
                         setApiKeys((prev) => ({ ...prev, ai_provider: val }));
-                        const key =
-                          val === "openai"
-                            ? apiKeys.openai_api_key
-                            : apiKeys.gemini_api_key;
-                        fetchModels(key, val);
+                        setAvailableModels([]);
+                        fetchModels(providerKey(apiKeys, val), val);
                       }}
                     >
                       <SelectTrigger className="w-full">
@@ -1322,11 +1298,15 @@ export function AppSettingsDialog({
                       <SelectContent position="popper" className="!z-[10000]">
                         <SelectItem value="gemini">Google Gemini</SelectItem>
                         <SelectItem value="openai">OpenAI</SelectItem>
+                        <SelectItem value="anthropic">Anthropic</SelectItem>
+                        <SelectItem value="local">
+                          Local server (Ollama, llama.cpp)
+                        </SelectItem>
                       </SelectContent>
                     </Select>
                   </div>
 
-                  {apiKeys.ai_provider === "gemini" ? (
+                  {apiKeys.ai_provider === "gemini" || !apiKeys.ai_provider ? (
                     <>
                       <div>
                         <div className="flex items-center gap-2 mb-3">
@@ -1408,7 +1388,7 @@ export function AppSettingsDialog({
                         </Select>
                       </div>
                     </>
-                  ) : (
+                  ) : apiKeys.ai_provider === "openai" ? (
                     <>
                       <div>
                         <div className="flex items-center gap-2 mb-3">
@@ -1490,6 +1470,169 @@ export function AppSettingsDialog({
                         </Select>
                       </div>
                     </>
+                  ) : apiKeys.ai_provider === "anthropic" ? (
+                    <>
+                      <div>
+                        <div className="flex items-center gap-2 mb-3">
+                          <Label className="text-base font-semibold">
+                            Anthropic API Key
+                          </Label>
+                          {apiKeys.anthropic_api_key &&
+                            apiKeys.anthropic_api_key !== "CHANGE_ME" && (
+                              <Badge
+                                variant="secondary"
+                                className="text-xs bg-green-500/10 text-green-500 hover:bg-green-500/20 border-green-500/20"
+                              >
+                                Key Saved
+                              </Badge>
+                            )}
+                        </div>
+                        <p className="text-sm text-muted-foreground mb-3">
+                          Enter your Anthropic API key.
+                        </p>
+                        <Input
+                          type="password"
+                          placeholder="sk-ant-..."
+                          value={apiKeys.anthropic_api_key}
+                          onChange={(e) =>
+                            setApiKeys({
+                              ...apiKeys,
+                              anthropic_api_key: e.target.value,
+                            })
+                          }
+                        />
+                      </div>
+
+                      <div className="mt-4">
+                        <div className="flex items-center justify-between mb-2">
+                          <Label className="text-base font-semibold">
+                            Model
+                          </Label>
+                          <Button
+                            variant="ghost"
+                            size="sm"
+                            className="h-6 text-xs"
+                            onClick={() =>
+                              fetchModels(apiKeys.anthropic_api_key, "anthropic")
+                            }
+                            disabled={
+                              !apiKeys.anthropic_api_key || isLoadingModels
+                            }
+                          >
+                            {isLoadingModels
+                              ? "Refreshing..."
+                              : "Refresh Models"}
+                          </Button>
+                        </div>
+                        <p className="text-sm text-muted-foreground mb-2">
+                          Select the Claude model to use.
+                        </p>
+                        <Select
+                          value={apiKeys.anthropic_model}
+                          onValueChange={(val) =>
+                            setApiKeys({ ...apiKeys, anthropic_model: val })
+                          }
+                          disabled={isLoadingModels}
+                        >
+                          <SelectTrigger className="w-full">
+                            <SelectValue placeholder="Select Model" />
+                          </SelectTrigger>
+                          <SelectContent position="popper" className="!z-[10000]">
+                            {availableModels.map((model) => (
+                              <SelectItem key={model} value={model}>
+                                {model}
+                              </SelectItem>
+                            ))}
+                            {availableModels.length === 0 && (
+                              <SelectItem value="claude-sonnet-4-5">
+                                claude-sonnet-4-5 (Default)
+                              </SelectItem>
+                            )}
+                          </SelectContent>
+                        </Select>
+                      </div>
+                    </>
+                  ) : (
+                    <>
+                      <div>
+                        <Label className="text-base font-semibold">
+                          Server URL
+                        </Label>
+                        <p className="text-sm text-muted-foreground mb-3">
+                          Base URL of an OpenAI-compatible server such as
+                          Ollama or llama.cpp. Data never leaves your machine.
+                        </p>
+                        <Input
+                          placeholder="http://localhost:11434/v1"
+                          value={apiKeys.local_ai_base_url}
+                          onChange={(e) =>
+                            setApiKeys({
+                              ...apiKeys,
+                              local_ai_base_url: e.target.value,
+                            })
+                          }
+                        />
+                      </div>
+
+                      <div className="mt-4">
+                        <Label className="text-base font-semibold">
+                          API Key (optional)
+                        </Label>
+                        <p className="text-sm text-muted-foreground mb-3">
+                          Only needed if the server was started with one.
+                        </p>
+                        <Input
+                          type="password"
+                          value={apiKeys.local_ai_api_key}
+                          onChange={(e) =>
+                            setApiKeys({
+                              ...apiKeys,
+                              local_ai_api_key: e.target.value,
+                            })
+                          }
+                        />
+                      </div>
+
+                      <div className="mt-4">
+                        <div className="flex items-center justify-between mb-2">
+                          <Label className="text-base font-semibold">
+                            Model
+                          </Label>
+                          <Button
+                            variant="ghost"
+                            size="sm"
+                            className="h-6 text-xs"
+                            onClick={() =>
+                              fetchModels(apiKeys.local_ai_api_key, "local")
+                            }
+                            disabled={isLoadingModels}
+                          >
+                            {isLoadingModels
+                              ? "Refreshing..."
+                              : "Refresh Models"}
+                          </Button>
+                        </div>
+                        <p className="text-sm text-muted-foreground mb-2">
+                          Name of a model the server has pulled or loaded.
+                        </p>
+                        <Input
+                          placeholder="llama3.1"
+                          list="local-ai-models"
+                          value={apiKeys.local_ai_model}
+                          onChange={(e) =>
+                            setApiKeys({
+                              ...apiKeys,
+                              local_ai_model: e.target.value,
+                            })
+                          }
+                        />
+                        <datalist id="local-ai-models">
+                          {availableModels.map((model) => (
+                            <option key={model} value={model} />
+                          ))}
+                        </datalist>
+                      </div>
+                    </>
                   )}
 
                   <div className="mt-6 pt-6 border-t">
@@ -1517,11 +1660,10 @@ export function AppSettingsDialog({
                     <Button
                       onClick={() => {
                         saveApiKeys();
-                        const key =
-                          apiKeys.ai_provider === "openai"
-                            ? apiKeys.openai_api_key
-                            : apiKeys.gemini_api_key;
-                        fetchModels(key, apiKeys.ai_provider);
+                        fetchModels(
+                          providerKey(apiKeys, apiKeys.ai_provider),
+                          apiKeys.ai_provider
+                        );
                         sonnerToast.success("Settings Saved", {
                           description: "AI Provider settings saved.",
                         });
@@ -1618,7 +1760,7 @@ function RedditAuthConfig() {
         <AlertDescription>
           For optimal compatibility, please ensure your Reddit App is set to <strong>"web app"</strong> type.
           <br />
-          Set Redirect URI to: <code className="bg-muted px-1 rounded">http://localhost:8989</code>
+          Set Redirect URI to: <code className="bg-muted px-1 rounded">{config?.reddit_redirect_uri || "http://localhost:8989"}</code>
         </AlertDescription>
       </Alert>
 
@@ -1663,6 +1805,17 @@ function RedditAuthConfig() {
                 placeholder="Client Secret (Empty for installed apps if not provided)"
               />
             </div>
+            <div className="space-y-1">
+              <Label htmlFor="reddit_redirect_uri">Redirect URI</Label>
+              <Input
+                id="reddit_redirect_uri"
+                value={config?.reddit_redirect_uri || ""}
+                onChange={(e) =>
+                  setConfig({ ...config, reddit_redirect_uri: e.target.value })
+                }
+                placeholder="http://localhost:8989"
+              />
+            </div>
             {/* Save basic credentials first before auth flow */}
             <Button onClick={handleSave} disabled={isSaving} className="w-full" variant="secondary">
               {isSaving ? "Saving..." : "Update Credentials"}
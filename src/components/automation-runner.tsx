@@ -2,7 +2,7 @@
 
 import { useEffect, useRef } from "react";
 import { useAppSettings } from "@/store/settings-store";
-import { useAutomationStore, useAddSingleSubReddit, PostDataWrapper } from "@/store/store";
+import { useAutomationStore, useAddSingleSubReddit, PostDataWrapper, SearchResponse } from "@/store/store";
 import { invoke } from "@tauri-apps/api/core";
 import { calculateIntent, categorizePost, matchesKeyword } from "@/lib/marketing-utils";
 
@@ -109,7 +109,7 @@ export function AutomationRunner() {
             addLog(`Searching Globally for: ${chunk.map(k => k.term).join(", ")}...`, "info");
 
             try {
-                const results: PostDataWrapper[] = await invoke("get_reddit_results", {
+                const { posts: results }: SearchResponse = await invoke("get_reddit_results", {
                     sortTypes: ["new", "relevance"],
                     query: query
                 });
@@ -176,7 +176,7 @@ export function AutomationRunner() {
                 const query = `subreddit:${subreddit} (${chunk.map(k => `"${k}"`).join(" OR ")})`;
 
                 try {
-                    const results: PostDataWrapper[] = await invoke("get_reddit_results", {
+                    const { posts: results }: SearchResponse = await invoke("get_reddit_results", {
                         sortTypes: ["new", "relevance"],
                         query: query
                     });
@@ -1,6 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
 import { toast } from "sonner";
-import type { PostDataWrapper } from "@/store/store";
+import type { PostDataWrapper, SearchResponse } from "@/store/store";
 import type { SearchResult, SortType } from "./types";
 import { categorizePost } from "@/lib/marketing-utils";
 
@@ -16,10 +16,15 @@ export async function performSearch(
     params: SearchParams
 ): Promise<SearchResult[]> {
     try {
-        const fetchedPosts: PostDataWrapper[] = await invoke("get_reddit_results", {
+        const response: SearchResponse = await invoke("get_reddit_results", {
             sortTypes: params.sortTypes,
             query: params.query.trim(),
         });
+        const fetchedPosts = response.posts;
+
+        if (response.filtered.total > 0) {
+            toast.info(`Filtered out ${response.filtered.total} posts (deleted, NSFW, low quality or excluded)`);
+        }
 
         const filteredPosts = params.blacklistSubreddits
             ? fetchedPosts.filter(
@@ -85,6 +90,19 @@ function mapPostsToResults(
         intent: post.intent,
         permalink: post.permalink,
         interest: post.interest,
+        link_flair_text: post.link_flair_text,
+        over_18: post.over_18,
+        upvote_ratio: post.upvote_ratio,
+        stickied: post.stickied,
+        locked: post.locked,
+        domain: post.domain,
+        author_flair_text: post.author_flair_text,
+        crosspost_parent: post.crosspost_parent,
+        media_type: post.media_type,
+        brand_mentions: post.brand_mentions,
+        sentiment: post.sentiment,
+        sentiment_score: post.sentiment_score,
+        cluster_id: post.cluster_id,
         category: categorizePost(post.title, brandKeywords, competitorKeywords),
     }));
 }
@@ -183,5 +183,19 @@ function buildPostData(
         category: categorizePost(result.title, brandKeywords, competitorKeywords),
         date_added: result.date_added || 0,
         interest: result.interest || 0,
+        // Listing metadata from the search, so saved leads keep flair, NSFW and lock data
+        link_flair_text: result.link_flair_text ?? null,
+        over_18: result.over_18 || false,
+        upvote_ratio: result.upvote_ratio || 0,
+        stickied: result.stickied || false,
+        locked: result.locked || false,
+        domain: result.domain || "",
+        author_flair_text: result.author_flair_text ?? null,
+        crosspost_parent: result.crosspost_parent ?? null,
+        media_type: result.media_type ?? null,
+        brand_mentions: result.brand_mentions || "",
+        sentiment: result.sentiment || "neutral",
+        sentiment_score: result.sentiment_score || 0,
+        cluster_id: result.cluster_id || "",
     };
 }
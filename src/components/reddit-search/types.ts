@@ -20,6 +20,19 @@ export type SearchResult = {
     permalink?: string;
     date_added?: number;
     interest?: number;
+    link_flair_text?: string | null;
+    over_18?: boolean;
+    upvote_ratio?: number;
+    stickied?: boolean;
+    locked?: boolean;
+    domain?: string;
+    author_flair_text?: string | null;
+    crosspost_parent?: string | null;
+    media_type?: string | null;
+    brand_mentions?: string;
+    sentiment?: "positive" | "neutral" | "negative";
+    sentiment_score?: number;
+    cluster_id?: string;
 };
 
 export type SortType = "hot" | "top" | "new";
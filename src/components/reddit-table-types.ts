@@ -29,6 +29,19 @@ export type RedditPost = {
   name?: string;
   date_added: number;
   interest: number; // Added new field (0-5)
+  link_flair_text?: string | null;
+  over_18?: boolean;
+  upvote_ratio?: number;
+  stickied?: boolean;
+  locked?: boolean;
+  domain?: string;
+  author_flair_text?: string | null;
+  crosspost_parent?: string | null;
+  media_type?: string | null; // image, video, gallery, link, text
+  brand_mentions?: string; // comma separated brands mentioned in the post
+  sentiment?: "positive" | "neutral" | "negative"; // local lexicon sentiment
+  sentiment_score?: number; // -1 to 1
+  cluster_id?: string; // oldest post of its duplicate/crosspost cluster, empty when unique
   // Client-side fields
   status?: "new" | "investigating" | "replied" | "closed" | "ignored";
   intent?: string;
@@ -1430,6 +1430,7 @@ export function RedditTable({
                       <TableCell className="px-1 text-center">
                         {post.intent && (
                           <Badge
+                            title={`Lead score ${post.relevance_score}/100`}
                             className={`${getIntentColor(
                               post.intent.toLowerCase(),
                             )} text-[9px] h-5 px-2 font-black border-0 shadow-sm rounded-lg uppercase tracking-tighter`}
@@ -25,11 +25,290 @@ interface PostDataWrapper {
   intent: string;
   date_added: number;
   interest: number;
+  link_flair_text?: string | null;
+  over_18?: boolean;
+  upvote_ratio?: number;
+  stickied?: boolean;
+  locked?: boolean;
+  domain?: string;
+  author_flair_text?: string | null;
+  crosspost_parent?: string | null;
+  media_type?: string | null;
+  brand_mentions?: string; // comma separated brands mentioned in the post
+  sentiment?: "positive" | "neutral" | "negative"; // local lexicon sentiment
+  sentiment_score?: number; // -1 to 1
+  cluster_id?: string; // oldest post of its duplicate/crosspost cluster, empty when unique
   // Client-side only?
   status?: "new" | "investigating" | "replied" | "closed" | "ignored";
   category?: "brand" | "competitor" | "general";
 }
 
+// Counts of posts dropped by the backend content filters, per reason
+interface FilterCounts {
+  total: number;
+  deleted: number;
+  nsfw: number;
+  locked: number;
+  stickied: number;
+  low_score: number;
+  low_comments: number;
+  too_old: number;
+  excluded_author: number;
+  excluded_subreddit: number;
+  negative_keyword: number;
+  sentiment: number;
+}
+
+// Response of run_brand_monitoring_command
+interface BrandMonitorReport {
+  posts: PostDataWrapper[];
+  mentions: Record<string, number>;
+  filtered: FilterCounts;
+}
+
+// Row of get_brand_mention_counts_command
+interface BrandMentionCount {
+  brand: string;
+  period: string;
+  mentions: number;
+  share: number;
+}
+
+// Row of get_duplicate_clusters_command
+interface DuplicateCluster {
+  id: string;
+  subreddits: string[];
+  crosspost: boolean;
+  posts: PostDataWrapper[];
+}
+
+// Topic from cluster_topics_command / get_topics_command
+interface Topic {
+  id: number;
+  label: string; // top three terms joined with " / "
+  top_terms: string[];
+  size: number;
+  created_at: number;
+}
+
+// Payload of the "ai-stream" event from ask_gemini_stream_command / generate_reply_stream_command
+type AiStreamEvent =
+  | { kind: "chunk"; request_id: string; text: string }
+  | { kind: "done"; request_id: string; text: string }
+  | { kind: "error"; request_id: string; message: string }
+  | { kind: "cancelled"; request_id: string };
+
+// Result of get_ai_models_command; null when the provider doesn't report it
+interface ModelInfo {
+  id: string;
+  display_name: string | null;
+  context_window: number | null;
+  max_output_tokens: number | null;
+  supports_json: boolean | null;
+}
+
+// Result of index_embeddings_command
+interface EmbeddingIndexStats {
+  embedded: number;
+  unchanged: number;
+  removed: number;
+}
+
+// Result of semantic_search_command and find_similar_leads_command
+interface SemanticMatch {
+  post: PostDataWrapper;
+  similarity: number;
+  // Set when the post matched through this comment
+  comment: LocalLead["matching_comments"][number] | null;
+}
+
+// A command the assistant called, or wants to call, through ask_ai_with_tools_command
+interface ToolCall {
+  id: string;
+  name: string;
+  arguments: unknown;
+  signature?: string;
+}
+
+// Result of ask_ai_with_tools_command and confirm_ai_actions_command
+interface ToolReply {
+  conversation_id: string;
+  // Null while pending_actions wait for confirm_ai_actions_command
+  answer: string | null;
+  pending_actions: ToolCall[];
+  actions_taken: ToolCall[];
+}
+
+// Result of count_leads_by_subreddit_command
+interface SubredditLeadCount {
+  subreddit: string;
+  leads: number;
+}
+
+// A lead graded by generate_ai_leads_command
+interface AiLead {
+  title: string;
+  url: string;
+  formatted_date: string;
+  relevance: "HIGH" | "MEDIUM" | "LOW";
+  subreddit: string;
+  sentiment: string;
+  engagement_score: "HIGH" | "MEDIUM" | "LOW" | null;
+  top_comments: { author: string; text: string; sentiment: string | null }[];
+  comment_sentiment: string | null;
+}
+
+// Result of generate_ai_leads_command; errors hold the records that failed validation
+interface LeadBatch {
+  leads: AiLead[];
+  errors: { index: number; message: string; record: unknown }[];
+}
+
+// A saved conversation for ask_gemini_command and ask_gemini_stream_command
+interface ChatSession {
+  id: number;
+  title: string;
+  created_at: number;
+  updated_at: number;
+  message_count: number;
+}
+
+// Result of get_chat_messages_command
+interface StoredChatMessage {
+  id: number;
+  session_id: number;
+  role: "user" | "assistant";
+  content: string;
+  created_at: number;
+}
+
+// Result of get_reply_tones_command
+interface TonePreset {
+  name: string;
+  instructions: string;
+}
+
+// A candidate reply from draft_replies_command
+interface ReplyDraft {
+  text: string;
+  approach: string;
+  // 0 reads as plain help, 1 reads as an ad
+  promotion_risk: number;
+  risk_level: "low" | "medium" | "high";
+  risk_reasons: string[];
+}
+
+// Result of draft_replies_command
+interface ReplyDrafts {
+  tone: string;
+  drafts: ReplyDraft[];
+}
+
+type ReplyDraftStatus = "draft" | "approved" | "posted" | "rejected";
+
+// A draft saved by start_reply_queue_command, from get_reply_drafts_command
+interface StoredReplyDraft extends ReplyDraft {
+  id: number;
+  post_id: number;
+  job_id: string;
+  tone: string;
+  status: ReplyDraftStatus;
+  created_at: number;
+  updated_at: number;
+}
+
+// Payload of the "reply-queue" event
+type ReplyQueueEvent =
+  | { kind: "started"; job_id: string; total: number }
+  | {
+      kind: "drafted";
+      job_id: string;
+      post_id: number;
+      drafts: number;
+      done: number;
+      total: number;
+    }
+  | {
+      kind: "failed";
+      job_id: string;
+      post_id: number;
+      message: string;
+      done: number;
+      total: number;
+    }
+  | { kind: "finished"; job_id: string; drafted: number; failed: number }
+  | { kind: "cancelled"; job_id: string; drafted: number; failed: number };
+
+// Result of discover_subreddits_command
+interface SubredditInfo {
+  name: string;
+  title: string;
+  description: string;
+  subscribers: number;
+  active_users: number;
+  over_18: boolean;
+  url: string;
+}
+
+// Row of get_saved_subreddits_command
+interface SavedSubreddit extends SubredditInfo {
+  notes: string;
+  weight: number; // lead score multiplier
+  rules: string;
+  self_promotion: string;
+  rules_fetched_at: number;
+  added_at: number;
+}
+
+// Result of get_subreddit_rules_command
+interface SubredditRules {
+  subreddit: string;
+  rules: string;
+  self_promotion: string;
+  fetched_at: number;
+}
+
+// Cached Reddit profile from get_author_profile_command
+interface AuthorProfile {
+  username: string;
+  link_karma: number;
+  comment_karma: number;
+  account_created: number;
+  verified_email: boolean;
+  suspended: boolean;
+  recent_posts: number;
+  recent_comments: number;
+  active_subreddits: string[];
+  keyword_mentions: number;
+  flags: string[]; // bot, throwaway, new_account, low_karma, suspended, no_history
+  summary: string;
+  fetched_at: number;
+}
+
+// Row of find_leads_command; comments come back as stored by the backend
+interface LocalLead {
+  post: PostDataWrapper;
+  matched_keywords: string[];
+  matching_comments: {
+    id: string;
+    post_id: string;
+    author: string;
+    body: string;
+    score: number;
+    formatted_date: string;
+    permalink: string;
+    sentiment?: "positive" | "neutral" | "negative";
+    sentiment_score?: number;
+  }[];
+  author: AuthorProfile | null;
+}
+
+// Response of the get_reddit_results command
+interface SearchResponse {
+  posts: PostDataWrapper[];
+  filtered: FilterCounts;
+}
+
 interface RedditPost {
   id: string;
   title: string;
@@ -157,4 +436,36 @@ const useAutomationStore = create<AutomationStore>()(
 );
 
 export { useSubredditsStore, useRedditPostsTab, useAddSingleSubReddit, useAutomationStore };
-export type { PostDataWrapper, RedditPost, LogEntry };
+export type {
+  PostDataWrapper,
+  FilterCounts,
+  SearchResponse,
+  BrandMonitorReport,
+  BrandMentionCount,
+  LocalLead,
+  AuthorProfile,
+  DuplicateCluster,
+  Topic,
+  AiStreamEvent,
+  ModelInfo,
+  EmbeddingIndexStats,
+  SemanticMatch,
+  ToolCall,
+  ToolReply,
+  SubredditLeadCount,
+  AiLead,
+  LeadBatch,
+  ChatSession,
+  StoredChatMessage,
+  TonePreset,
+  ReplyDraft,
+  ReplyDrafts,
+  ReplyDraftStatus,
+  StoredReplyDraft,
+  ReplyQueueEvent,
+  SubredditInfo,
+  SavedSubreddit,
+  SubredditRules,
+  RedditPost,
+  LogEntry,
+};